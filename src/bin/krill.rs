@@ -1,13 +1,14 @@
 extern crate krill;
 
-use std::sync::Arc;
+use std::{path::PathBuf, sync::Arc};
 
 use clap::{App, Arg};
 use log::error;
 
 use krill::{
+    commons::util::file,
     constants::{KRILL_DEFAULT_CONFIG_FILE, KRILL_SERVER_APP, KRILL_VERSION},
-    daemon::{config::Config, http::server},
+    daemon::{backup::KrillBackup, config::Config, http::server},
 };
 
 #[tokio::main]
@@ -25,6 +26,13 @@ async fn main() {
                 ))
                 .required(false),
         )
+        .arg(
+            Arg::with_name("restore")
+                .long("restore")
+                .value_name("FILE")
+                .help("Restore a backup made with 'krillc admin backup' into empty storage, and exit")
+                .required(false),
+        )
         .get_matches();
 
     let config_file = matches
@@ -33,6 +41,14 @@ async fn main() {
 
     match Config::create(config_file, false) {
         Ok(config) => {
+            if let Some(backup_file) = matches.value_of("restore") {
+                if let Err(e) = restore(&config, backup_file) {
+                    eprintln!("Could not restore backup: {}", e);
+                    ::std::process::exit(1);
+                }
+                return;
+            }
+
             if let Err(e) = server::start_krill_daemon(Arc::new(config)).await
             {
                 error!("Krill failed to start: {}", e);
@@ -45,3 +61,19 @@ async fn main() {
         }
     }
 }
+
+fn restore(config: &Config, backup_file: &str) -> Result<(), String> {
+    let bytes =
+        file::read(&PathBuf::from(backup_file)).map_err(|e| e.to_string())?;
+    let backup: KrillBackup =
+        serde_json::from_slice(&bytes).map_err(|e| e.to_string())?;
+
+    backup.restore(config).map_err(|e| e.to_string())?;
+
+    println!(
+        "Restored backup made by Krill version {} at {}",
+        backup.manifest().krill_version(),
+        backup.manifest().created().to_rfc3339()
+    );
+    Ok(())
+}
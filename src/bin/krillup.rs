@@ -7,10 +7,14 @@ use krill::{
     constants::{KRILL_DEFAULT_CONFIG_FILE, KRILL_UP_APP, KRILL_VERSION},
     daemon::{
         config::{Config, LogType},
+        encryption::{
+            decrypt_storage, enable_storage_encryption, encrypt_storage,
+        },
         properties::PropertiesManager,
     },
     upgrades::{
-        data_migration::migrate, prepare_upgrade_data_migrations, UpgradeMode,
+        data_migration::migrate, data_verification::verify,
+        prepare_upgrade_data_migrations, UpgradeMode,
     },
 };
 use url::Url;
@@ -25,6 +29,12 @@ fn main() {
         }
         Ok(mode) => match mode {
             KrillUpMode::Prepare { config } => {
+                if let Err(e) = enable_storage_encryption(&config) {
+                    eprintln!("*** Error Preparing Data Migration ***");
+                    eprintln!("{}", e);
+                    ::std::process::exit(1);
+                }
+
                 let properties_manager = match PropertiesManager::create(
                     &config.storage_uri,
                     config.use_history_cache,
@@ -80,6 +90,48 @@ fn main() {
                     ::std::process::exit(1);
                 }
             }
+            KrillUpMode::Verify { config } => {
+                if let Err(e) = enable_storage_encryption(&config) {
+                    eprintln!("*** Error Verifying DATA ***");
+                    eprintln!("{}", e);
+                    ::std::process::exit(1);
+                }
+
+                match verify(&config) {
+                    Err(e) => {
+                        eprintln!("*** Error Verifying DATA ***");
+                        eprintln!("{}", e);
+                        ::std::process::exit(1);
+                    }
+                    Ok(report) => {
+                        print!("{}", report);
+                        if !report.is_ok() {
+                            eprintln!("*** Issues found in DATA ***");
+                            ::std::process::exit(1);
+                        }
+                    }
+                }
+            }
+            KrillUpMode::Encrypt { config } => match encrypt_storage(&config)
+            {
+                Err(e) => {
+                    eprintln!("*** Error Encrypting DATA ***");
+                    eprintln!("{}", e);
+                    eprintln!();
+                    eprintln!("Note that values which were already encrypted remain encrypted, you can run this command again.");
+                    ::std::process::exit(1);
+                }
+                Ok(count) => info!("Encrypted {} values.", count),
+            },
+            KrillUpMode::Decrypt { config } => match decrypt_storage(&config)
+            {
+                Err(e) => {
+                    eprintln!("*** Error Decrypting DATA ***");
+                    eprintln!("{}", e);
+                    ::std::process::exit(1);
+                }
+                Ok(count) => info!("Decrypted {} values. Remove [storage_encryption] from the config file before starting Krill.", count),
+            },
         },
     }
 }
@@ -93,11 +145,27 @@ fn make_matches<'a>() -> ArgMatches<'a> {
     app = app.subcommand(prep_sub);
 
     let mut migrate_sub = SubCommand::with_name("migrate")
-        .about("Migrate Krill data to a different storage. Stop Krill before running this tool to ensure data does not change during migration. The original data in the storage defined in the config file is not modified. If your current data is for an older version of Krill, this tool will attempt to upgrade it. After successful migration, you can reconfigure Krill to use the new data storage and restart it.");
+        .about("Migrate Krill data to a different storage. Stop Krill before running this tool to ensure data does not change during migration, this tool refuses to run if it finds that Krill is still running. The original data in the storage defined in the config file, or given with --from, is not modified. The number of keys and the versions of all stored entities are checked after copying. If your current data is for an older version of Krill, this tool will attempt to upgrade it. After successful migration, you can reconfigure Krill to use the new data storage and restart it.");
     migrate_sub = add_config_arg(migrate_sub);
+    migrate_sub = add_source_storage_arg(migrate_sub);
     migrate_sub = add_new_storage_arg(migrate_sub);
     app = app.subcommand(migrate_sub);
 
+    let mut verify_sub = SubCommand::with_name("verify")
+        .about("Verify the consistency of the Krill data. The commands for every CA and other stored entity are replayed and compared to the stored snapshot, and the sequence of commands and publication server changes is checked for gaps and data that cannot be parsed. Any issues are reported per entity, and this tool will exit with an error if issues were found. The data is not modified.");
+    verify_sub = add_config_arg(verify_sub);
+    app = app.subcommand(verify_sub);
+
+    let mut encrypt_sub = SubCommand::with_name("encrypt")
+        .about("Encrypt the Krill data using the [storage_encryption] settings from the config file. Stop Krill before running this tool. A new data key is created if needed, and any values that are not encrypted yet are encrypted. After successful encryption, you can start Krill with the same config file.");
+    encrypt_sub = add_config_arg(encrypt_sub);
+    app = app.subcommand(encrypt_sub);
+
+    let mut decrypt_sub = SubCommand::with_name("decrypt")
+        .about("Decrypt the Krill data using the [storage_encryption] settings from the config file, and remove the data key. Stop Krill before running this tool. After successful decryption, remove [storage_encryption] from the config file before starting Krill.");
+    decrypt_sub = add_config_arg(decrypt_sub);
+    app = app.subcommand(decrypt_sub);
+
     app.get_matches()
 }
 
@@ -112,11 +180,23 @@ fn add_config_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
     )
 }
 
+fn add_source_storage_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+    app.arg(
+        Arg::with_name("from")
+            .short("f")
+            .long("from")
+            .value_name("URL")
+            .help("Override the source storage URI string (default: the storage_uri from the config file).")
+            .required(false),
+    )
+}
+
 fn add_new_storage_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
     app.arg(
-        Arg::with_name("target")
+        Arg::with_name("to")
             .short("t")
-            .long("target")
+            .long("to")
+            .alias("target")
             .value_name("URL")
             .help("Provide the target storage URI string. E.g. local:///var/lib/krill or postgres://postgres@localhost/postgres.")
             .required(true),
@@ -129,18 +209,33 @@ fn parse_matches(matches: ArgMatches) -> Result<KrillUpMode, String> {
         Ok(KrillUpMode::Prepare { config })
     } else if let Some(m) = matches.subcommand_matches("migrate") {
         let target_str =
-            m.value_of("target").ok_or("--target missing".to_string())?;
-        let target = Url::parse(target_str).map_err(|e| {
-            format!("cannot parse url: {}. Error: {}", target_str, e)
-        })?;
+            m.value_of("to").ok_or("--to missing".to_string())?;
+        let target = parse_storage_uri(target_str)?;
 
-        let config = parse_config(m)?;
+        let mut config = parse_config(m)?;
+        if let Some(source_str) = m.value_of("from") {
+            config.storage_uri = parse_storage_uri(source_str)?;
+        }
         Ok(KrillUpMode::Migrate { config, target })
+    } else if let Some(m) = matches.subcommand_matches("verify") {
+        let config = parse_config(m)?;
+        Ok(KrillUpMode::Verify { config })
+    } else if let Some(m) = matches.subcommand_matches("encrypt") {
+        let config = parse_config(m)?;
+        Ok(KrillUpMode::Encrypt { config })
+    } else if let Some(m) = matches.subcommand_matches("decrypt") {
+        let config = parse_config(m)?;
+        Ok(KrillUpMode::Decrypt { config })
     } else {
         Err("Cannot parse arguments. Use --help.".to_string())
     }
 }
 
+fn parse_storage_uri(s: &str) -> Result<Url, String> {
+    Url::parse(s)
+        .map_err(|e| format!("cannot parse url: {}. Error: {}", s, e))
+}
+
 fn parse_config(m: &ArgMatches) -> Result<Config, String> {
     let config_file =
         m.value_of("config").unwrap_or(KRILL_DEFAULT_CONFIG_FILE);
@@ -157,4 +252,7 @@ fn parse_config(m: &ArgMatches) -> Result<Config, String> {
 enum KrillUpMode {
     Prepare { config: Config },
     Migrate { config: Config, target: Url },
+    Verify { config: Config },
+    Encrypt { config: Config },
+    Decrypt { config: Config },
 }
@@ -7,25 +7,31 @@ use rpki::ca::idexchange;
 use crate::{
     cli::{
         options::{
-            BulkCaCommand, CaCommand, Command, KrillInitDetails, Options,
-            PubServerCommand,
+            AdminCommand, BulkCaCommand, CaCommand, Command,
+            KrillInitDetails, Options, PubServerCommand, TaskCommand,
+            WebhookCommand,
         },
         report::{ApiResponse, ReportError},
     },
     commons::{
         api::{
             AllCertAuthIssues, ApiRepositoryContact, AspaDefinitionUpdates,
-            BgpSecDefinitionUpdates, CaRepoDetails, CertAuthIssues,
-            ChildCaInfo, ChildrenConnectionStats, ParentCaContact,
-            ParentStatuses, PublisherDetails, PublisherList, RepoStatus,
-            Token,
+            AuditCriteria, AuditFormat, BgpSecDefinitionUpdates,
+            CaRepoDetails, CertAuthIssues, ChildCaInfo,
+            ChildrenConnectionStats, CommandDryRun, ExpiryWindow,
+            GhostbusterDefinitionUpdates, ParentCaContact, ParentStatuses,
+            PublisherDetails, PublisherList, RepoStatus, Token,
         },
         bgp::BgpAnalysisAdvice,
         error::KrillIoError,
         util::{file, httpclient},
     },
     constants::KRILL_CLI_API_ENV,
-    daemon::config::Config,
+    daemon::{
+        backup::KrillBackup,
+        ca::{SignedChecklist, SignedGeofeed},
+        config::Config,
+    },
 };
 
 #[cfg(feature = "multi-user")]
@@ -60,6 +66,29 @@ async fn post_empty(
         .map_err(Error::HttpClientError)
 }
 
+async fn post_empty_with_response<T: DeserializeOwned>(
+    server: &idexchange::ServiceUri,
+    token: &Token,
+    path: &str,
+) -> Result<T, Error> {
+    let uri = resolve_uri(server, path);
+    httpclient::post_empty_with_response(&uri, Some(token))
+        .await
+        .map_err(Error::HttpClientError)
+}
+
+async fn post_empty_if_match(
+    server: &idexchange::ServiceUri,
+    token: &Token,
+    path: &str,
+    version: u64,
+) -> Result<(), Error> {
+    let uri = resolve_uri(server, path);
+    httpclient::post_empty_if_match(&uri, version, Some(token))
+        .await
+        .map_err(Error::HttpClientError)
+}
+
 async fn post_json(
     server: &idexchange::ServiceUri,
     token: &Token,
@@ -146,6 +175,11 @@ impl KrillClient {
         match options.command {
             Command::Health => client.health().await,
             Command::Info => client.info().await,
+            Command::Expiry(within) => client.expiry(within).await,
+            Command::Audit(crit, format) => client.audit(crit, format).await,
+            Command::Webhooks(cmd) => client.webhooks(cmd).await,
+            Command::Tasks(cmd) => client.tasks(cmd).await,
+            Command::Admin(cmd) => client.admin(cmd).await,
             Command::Bulk(cmd) => client.bulk(cmd).await,
             Command::CertAuth(cmd) => client.certauth(cmd).await,
             Command::PubServer(cmd) => client.publishers(cmd).await,
@@ -174,6 +208,134 @@ impl KrillClient {
         Ok(ApiResponse::Info(info))
     }
 
+    async fn expiry(
+        &self,
+        within: Option<ExpiryWindow>,
+    ) -> Result<ApiResponse, Error> {
+        let uri = match within {
+            Some(within) => format!("api/v1/expiry?within={}", within),
+            None => "api/v1/expiry".to_string(),
+        };
+        let report = httpclient::get_json(
+            &resolve_uri(&self.server, &uri),
+            Some(&self.token),
+        )
+        .await?;
+        Ok(ApiResponse::ObjectExpiry(report))
+    }
+
+    async fn audit(
+        &self,
+        crit: AuditCriteria,
+        format: AuditFormat,
+    ) -> Result<ApiResponse, Error> {
+        let mut query = url::form_urlencoded::Serializer::new(String::new());
+        if let Some(actor) = &crit.actor {
+            query.append_pair("actor", actor);
+        }
+        if let Some(after) = crit.after {
+            query.append_pair("after", &after.to_rfc3339());
+        }
+        if let Some(before) = crit.before {
+            query.append_pair("before", &before.to_rfc3339());
+        }
+        if let Some(command) = &crit.command {
+            query.append_pair("command", command);
+        }
+        if let Some(handle) = &crit.handle {
+            query.append_pair("handle", handle.as_str());
+        }
+        query.append_pair("format", &format.to_string());
+
+        let uri = format!("api/v1/audit?{}", query.finish());
+        let log = httpclient::get_text(
+            &resolve_uri(&self.server, &uri),
+            Some(&self.token),
+        )
+        .await?;
+        Ok(ApiResponse::GenericBody(log))
+    }
+
+    async fn webhooks(
+        &self,
+        command: WebhookCommand,
+    ) -> Result<ApiResponse, Error> {
+        match command {
+            WebhookCommand::List => {
+                let list =
+                    get_json(&self.server, &self.token, "api/v1/webhooks")
+                        .await?;
+                Ok(ApiResponse::Webhooks(list))
+            }
+            WebhookCommand::Add(definition) => {
+                post_json(
+                    &self.server,
+                    &self.token,
+                    "api/v1/webhooks",
+                    definition,
+                )
+                .await?;
+                Ok(ApiResponse::Empty)
+            }
+            WebhookCommand::Remove(name) => {
+                let uri = format!("api/v1/webhooks/{}", name);
+                delete(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
+            WebhookCommand::Test(name) => {
+                let uri = format!("api/v1/webhooks/{}/test", name);
+                post_empty(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
+        }
+    }
+
+    async fn tasks(
+        &self,
+        command: TaskCommand,
+    ) -> Result<ApiResponse, Error> {
+        match command {
+            TaskCommand::List => {
+                let list =
+                    get_json(&self.server, &self.token, "api/v1/tasks")
+                        .await?;
+                Ok(ApiResponse::Tasks(list))
+            }
+            TaskCommand::Run(task) => {
+                post_json(
+                    &self.server,
+                    &self.token,
+                    "api/v1/tasks/run",
+                    task,
+                )
+                .await?;
+                Ok(ApiResponse::Empty)
+            }
+        }
+    }
+
+    async fn admin(
+        &self,
+        command: AdminCommand,
+    ) -> Result<ApiResponse, Error> {
+        match command {
+            AdminCommand::Backup(out) => {
+                let backup: KrillBackup = get_json(
+                    &self.server,
+                    &self.token,
+                    "api/v1/admin/backup",
+                )
+                .await?;
+
+                // Cannot fail, the backup was just deserialized from json.
+                let json = serde_json::to_vec(&backup).unwrap();
+                file::save(&json, &out)?;
+
+                Ok(ApiResponse::Backup(backup.manifest().clone()))
+            }
+        }
+    }
+
     async fn bulk(
         &self,
         command: BulkCaCommand,
@@ -403,6 +565,29 @@ impl KrillClient {
                 post_empty(&self.server, &self.token, &uri).await?;
                 Ok(ApiResponse::Empty)
             }
+            CaCommand::KeyRollEmergency(handle, rcn) => {
+                let uri = match rcn {
+                    Some(rcn) => format!(
+                        "api/v1/cas/{}/keys/roll_emergency/{}",
+                        handle, rcn
+                    ),
+                    None => {
+                        format!("api/v1/cas/{}/keys/roll_emergency", handle)
+                    }
+                };
+                post_empty(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
+            CaCommand::KeyRollPolicySet(handle, policy) => {
+                let uri = format!("api/v1/cas/{}/keys/policy", handle);
+                post_json(&self.server, &self.token, &uri, policy).await?;
+                Ok(ApiResponse::Empty)
+            }
+            CaCommand::KeyRollPolicyRemove(handle) => {
+                let uri = format!("api/v1/cas/{}/keys/policy", handle);
+                delete(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::Empty)
+            }
 
             CaCommand::RouteAuthorizationsList(handle) => {
                 let uri = format!("api/v1/cas/{}/routes", handle);
@@ -497,6 +682,28 @@ impl KrillClient {
                 Ok(ApiResponse::Empty)
             }
 
+            CaCommand::GhostbustersList(handle) => {
+                let uri = format!("api/v1/cas/{}/ghostbusters", handle);
+                let list = get_json(&self.server, &self.token, &uri).await?;
+                Ok(ApiResponse::GhostbusterDefinitions(list))
+            }
+
+            CaCommand::GhostbustersAdd(handle, addition) => {
+                let uri = format!("api/v1/cas/{}/ghostbusters", handle);
+                let update =
+                    GhostbusterDefinitionUpdates::new(vec![addition], vec![]);
+                post_json(&self.server, &self.token, &uri, update).await?;
+                Ok(ApiResponse::Empty)
+            }
+
+            CaCommand::GhostbustersRemove(handle, removal) => {
+                let uri = format!("api/v1/cas/{}/ghostbusters", handle);
+                let update =
+                    GhostbusterDefinitionUpdates::new(vec![], vec![removal]);
+                post_json(&self.server, &self.token, &uri, update).await?;
+                Ok(ApiResponse::Empty)
+            }
+
             CaCommand::AspasList(handle) => {
                 let uri = format!("api/v1/cas/{}/aspas", handle);
                 let aspas = get_json(&self.server, &self.token, &uri).await?;
@@ -533,6 +740,14 @@ impl KrillClient {
                 Ok(ApiResponse::CertAuthInfo(ca_info))
             }
 
+            CaCommand::ShowAt(handle, point) => {
+                let uri = format!("api/v1/cas/{}?at={}", handle, point);
+                let ca_info =
+                    get_json(&self.server, &self.token, &uri).await?;
+
+                Ok(ApiResponse::HistoricCertAuthInfo(ca_info))
+            }
+
             CaCommand::ShowHistoryCommands(handle, options) => {
                 let uri = format!(
                     "api/v1/cas/{}/history/commands/{}",
@@ -554,6 +769,43 @@ impl KrillClient {
                 Ok(ApiResponse::CertAuthAction(action))
             }
 
+            CaCommand::HistoryRevertDryRun(handle, reverted) => {
+                let uri = format!(
+                    "api/v1/cas/{}/history/revert/{}?dry_run=true",
+                    handle, reverted
+                );
+                let dry_run =
+                    post_empty_with_response(&self.server, &self.token, &uri)
+                        .await?;
+
+                Ok(ApiResponse::CertAuthDryRun(dry_run))
+            }
+
+            CaCommand::HistoryRevert(handle, reverted) => {
+                // Get a preview first and only apply the revert if the CA
+                // did not change in the meantime, so that what is shown is
+                // what was done.
+                let uri = format!(
+                    "api/v1/cas/{}/history/revert/{}",
+                    handle, reverted
+                );
+                let preview: CommandDryRun = post_empty_with_response(
+                    &self.server,
+                    &self.token,
+                    &format!("{}?dry_run=true", uri),
+                )
+                .await?;
+                post_empty_if_match(
+                    &self.server,
+                    &self.token,
+                    &uri,
+                    preview.version(),
+                )
+                .await?;
+
+                Ok(ApiResponse::CertAuthDryRun(preview))
+            }
+
             CaCommand::Issues(ca_opt) => match ca_opt {
                 Some(ca) => {
                     let uri = format!("api/v1/cas/{}/issues", ca);
@@ -572,6 +824,44 @@ impl KrillClient {
                 }
             },
 
+            CaCommand::RscSign(ca, request, out) => {
+                let uri = format!("api/v1/cas/{}/rsc", ca);
+                let rsc: SignedChecklist = post_json_with_response(
+                    &self.server,
+                    &self.token,
+                    &uri,
+                    request,
+                )
+                .await?;
+
+                match out {
+                    None => Ok(ApiResponse::Rsc(rsc)),
+                    Some(out) => {
+                        file::save(rsc.as_ref(), &out)?;
+                        Ok(ApiResponse::Empty)
+                    }
+                }
+            }
+
+            CaCommand::GeofeedSign(ca, request, out) => {
+                let uri = format!("api/v1/cas/{}/geofeed", ca);
+                let signed: SignedGeofeed = post_json_with_response(
+                    &self.server,
+                    &self.token,
+                    &uri,
+                    request,
+                )
+                .await?;
+
+                match out {
+                    None => Ok(ApiResponse::Geofeed(signed)),
+                    Some(out) => {
+                        file::save(signed.geofeed().as_bytes(), &out)?;
+                        Ok(ApiResponse::Empty)
+                    }
+                }
+            }
+
             CaCommand::RtaList(ca) => {
                 let uri = format!("api/v1/cas/{}/rta/", ca);
                 let list = get_json(&self.server, &self.token, &uri).await?;
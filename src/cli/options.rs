@@ -9,13 +9,17 @@ use std::{
 
 use bytes::Bytes;
 use clap::{App, Arg, ArgMatches, SubCommand};
+use url::Url;
 
 use rpki::{
     ca::{
         csr::BgpsecCsr,
         idcert::IdCert,
         idexchange,
-        idexchange::{CaHandle, ChildHandle, ParentHandle, PublisherHandle},
+        idexchange::{
+            CaHandle, ChildHandle, MyHandle, ParentHandle, PublisherHandle,
+        },
+        provisioning::ResourceClassName,
     },
     crypto::KeyIdentifier,
     repository::{
@@ -31,20 +35,27 @@ use crate::{
     commons::{
         api::{
             self, import::ImportChild, AddChildRequest, AspaDefinition,
-            AspaDefinitionFormatError, AspaProvidersUpdate,
-            AuthorizationFmtError, BgpSecAsnKey, BgpSecDefinition,
-            CertAuthInit, CustomerAsn, ParentCaReq, ProviderAsn,
-            PublicationServerUris, RepoFileDeleteCriteria, RoaConfiguration,
+            AspaDefinitionFormatError, AspaProvidersUpdate, AuditCriteria,
+            AuditFormat, AuthorizationFmtError, BgpSecAsnKey,
+            BgpSecDefinition, CertAuthInit, CustomerAsn, ExpiryWindow,
+            GhostbusterDefinition, GhostbusterName, KeyRollPolicy,
+            ParentCaReq, PointInTime, ProviderAsn, PublicationServerUris,
+            RepoFileDeleteCriteria, RoaConfiguration,
             RoaConfigurationUpdates, RoaPayload, RtaName, Token,
-            UpdateChildRequest,
+            UpdateChildRequest, WebhookDefinition, WebhookEventType,
+            WebhookName,
         },
         crypto::SignSupport,
         error::KrillIoError,
         util::file,
     },
     constants::*,
-    daemon::ca::{
-        ResourceTaggedAttestation, RtaContentRequest, RtaPrepareRequest,
+    daemon::{
+        ca::{
+            GeofeedSignRequest, ResourceTaggedAttestation, RscChecklistItem,
+            RscSignRequest, RtaContentRequest, RtaPrepareRequest,
+        },
+        mq::TaskRunRequest,
     },
 };
 
@@ -357,6 +368,14 @@ impl Options {
         sub = GeneralArgs::add_args(sub);
         sub = Self::add_my_ca_arg(sub);
 
+        sub = sub.arg(
+            Arg::with_name("at")
+                .long("at")
+                .help("Show the CA as it was at this version, or date/time in RFC 3339 format, e.g. 2020-04-09T19:37:02Z")
+                .value_name("<version or RFC 3339 DateTime>")
+                .required(false),
+        );
+
         app.subcommand(sub)
     }
 
@@ -424,12 +443,39 @@ impl Options {
         app.subcommand(sub)
     }
 
+    fn make_cas_history_revert_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("revert").about(
+            "Revert the ROA, ASPA or BGPSec definition changes of a command",
+        );
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("command")
+                .long("command")
+                .value_name("<version>")
+                .help("The version of the command as shown in 'history commands'")
+                .required(true),
+        );
+
+        sub = sub.arg(
+            Arg::with_name("dryrun")
+                .long("dryrun")
+                .help("Only show what reverting the command would do")
+                .required(false),
+        );
+
+        app.subcommand(sub)
+    }
+
     fn make_cas_show_history_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub = SubCommand::with_name("history")
             .about("Show the history of a CA");
 
         sub = Self::make_cas_show_history_list_sc(sub);
         sub = Self::make_cas_show_history_details_sc(sub);
+        sub = Self::make_cas_history_revert_sc(sub);
 
         app.subcommand(sub)
     }
@@ -674,12 +720,85 @@ impl Options {
         app.subcommand(sub)
     }
 
+    fn make_cas_keyroll_emergency_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("emergency").about(
+            "Replace a compromised key immediately, skipping the staging period",
+        );
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("rcn")
+                .long("rcn")
+                .value_name("name")
+                .help("The resource class of the compromised key. Default: all resource classes")
+                .required(false),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_keyroll_policy_set_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("set")
+            .about("Set the policy for automatic key rolls for a CA");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("roll_after_days")
+                    .long("roll-after-days")
+                    .value_name("days")
+                    .help("Roll keys once they have been in use for this many days. E.g. 365")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("activate_after_hours")
+                    .long("activate-after-hours")
+                    .value_name("hours")
+                    .help("Activate new keys after this staging period in hours. Default: 24 (RFC 6489)")
+                    .required(false),
+            );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_keyroll_policy_remove_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("remove")
+            .about("Remove the policy for automatic key rolls for a CA");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_keyroll_policy_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("policy")
+            .about("Manage the policy for automatic key rolls for a CA");
+
+        sub = Self::make_cas_keyroll_policy_set_sc(sub);
+        sub = Self::make_cas_keyroll_policy_remove_sc(sub);
+
+        app.subcommand(sub)
+    }
+
     fn make_cas_keyroll_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub = SubCommand::with_name("keyroll")
-            .about("Perform a manual key rollover for a CA");
+            .about("Perform a manual key rollover for a CA, or manage automatic key rolls");
 
         sub = Self::make_cas_keyroll_init_sc(sub);
         sub = Self::make_cas_keyroll_activate_sc(sub);
+        sub = Self::make_cas_keyroll_emergency_sc(sub);
+        sub = Self::make_cas_keyroll_policy_sc(sub);
 
         app.subcommand(sub)
     }
@@ -890,6 +1009,103 @@ impl Options {
         app.subcommand(sub)
     }
 
+    fn make_cas_ghostbusters_list_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("list")
+            .about("Show current Ghostbuster configurations");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_ghostbusters_add_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("add")
+            .about("Add or replace a Ghostbuster configuration");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("name")
+                    .long("name")
+                    .value_name("name")
+                    .help("The name for the Ghostbuster record. E.g. noc")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("full_name")
+                    .long("full-name")
+                    .value_name("text")
+                    .help("The full name (vCard FN) of the contact")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("org")
+                    .long("org")
+                    .value_name("text")
+                    .help("The organization (vCard ORG) of the contact")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("address")
+                    .long("address")
+                    .value_name("text")
+                    .help("The postal address (vCard ADR) of the contact")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("tel")
+                    .long("tel")
+                    .value_name("text")
+                    .help("The telephone number (vCard TEL) of the contact")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("email")
+                    .long("email")
+                    .value_name("text")
+                    .help("The email address (vCard EMAIL) of the contact")
+                    .required(false),
+            );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_ghostbusters_remove_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("remove")
+            .about("Remove a Ghostbuster configuration");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("name")
+                .long("name")
+                .value_name("name")
+                .help("The name of the Ghostbuster record to remove")
+                .required(true),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_ghostbusters_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("ghostbusters")
+            .about("Manage Ghostbuster records (RFC 6493)");
+
+        sub = Self::make_cas_ghostbusters_list_sc(sub);
+        sub = Self::make_cas_ghostbusters_add_sc(sub);
+        sub = Self::make_cas_ghostbusters_remove_sc(sub);
+
+        app.subcommand(sub)
+    }
+
     fn make_cas_aspas_add_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub = SubCommand::with_name("add")
             .about("Add or replace an ASPA configuration");
@@ -1213,6 +1429,99 @@ impl Options {
         app.subcommand(sub)
     }
 
+    fn make_cas_geofeed_sign_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("sign")
+            .about("Sign a geofeed file with the CA's RPKI key (RFC 9632)");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = sub.arg(
+            Arg::with_name("geofeed")
+                .long("geofeed")
+                .short("g")
+                .value_name("path")
+                .help("The geofeed CSV file to sign")
+                .required(true),
+        );
+
+        sub = sub.arg(
+            Arg::with_name("days")
+                .long("days")
+                .short("d")
+                .value_name("number of days")
+                .help(
+                    "Validity time of the signature in days, defaults to 365",
+                )
+                .required(false),
+        );
+
+        sub = sub.arg(
+            Arg::with_name("out")
+                .long("out")
+                .short("o")
+                .value_name("path")
+                .help("File to write the signed geofeed to")
+                .required(false),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_geofeed_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("geofeed")
+            .about("Sign geofeed files (RFC 9632)");
+        sub = Self::make_cas_geofeed_sign_sc(sub);
+        app.subcommand(sub)
+    }
+
+    fn make_cas_rsc_sign_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("sign")
+            .about("Sign an RPKI Signed Checklist for one or more files");
+
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_my_ca_arg(sub);
+
+        sub = Self::add_resource_args(sub);
+
+        sub = sub.arg(
+            Arg::with_name("days")
+                .long("days")
+                .short("d")
+                .value_name("number of days")
+                .help("Validity time of the RSC in days, defaults to 7")
+                .required(false),
+        );
+
+        sub = sub.arg(
+            Arg::with_name("file")
+                .long("file")
+                .value_name("path")
+                .multiple(true)
+                .number_of_values(1)
+                .help("File to include on the checklist, can be repeated")
+                .required(true),
+        );
+
+        sub = sub.arg(
+            Arg::with_name("out")
+                .long("out")
+                .short("o")
+                .value_name("path")
+                .help("File to write the DER encoded RSC to")
+                .required(false),
+        );
+
+        app.subcommand(sub)
+    }
+
+    fn make_cas_rsc_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("rsc")
+            .about("Sign RPKI Signed Checklists (RFC 9323)");
+        sub = Self::make_cas_rsc_sign_sc(sub);
+        app.subcommand(sub)
+    }
+
     #[cfg(feature = "rta")]
     fn make_cas_rta_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub = SubCommand::with_name("rta")
@@ -1262,6 +1571,191 @@ impl Options {
         app.subcommand(info)
     }
 
+    fn make_expiry_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut expiry = SubCommand::with_name("expiry").about(
+            "Show the expiry times of signed objects of all CAs, and the TA",
+        );
+        expiry = GeneralArgs::add_args(expiry);
+        expiry = expiry.arg(
+            Arg::with_name("within")
+                .value_name("period")
+                .long("within")
+                .help("Only show objects expiring within this period, e.g. 7d (units: s, m, h, d, w)")
+                .required(false),
+        );
+        app.subcommand(expiry)
+    }
+
+    fn make_audit_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut audit = SubCommand::with_name("audit").about(
+            "Export the commands for all CAs, the publication server and the TA proxy",
+        );
+        audit = GeneralArgs::add_args(audit);
+        audit = audit
+            .arg(
+                Arg::with_name("actor")
+                    .long("actor")
+                    .value_name("name")
+                    .help("Only include commands by this actor")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("after")
+                    .long("after")
+                    .value_name("RFC 3339 DateTime")
+                    .help("Only include commands at or after this time")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("before")
+                    .long("before")
+                    .value_name("RFC 3339 DateTime")
+                    .help("Only include commands at or before this time")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("command")
+                    .long("command")
+                    .value_name("type")
+                    .help("Only include commands of this type, e.g. cmd-ca-roas-updated")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("handle")
+                    .long("handle")
+                    .value_name("name")
+                    .help("Only include commands for the CA, or other entity, with this name")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("csv")
+                    .long("csv")
+                    .help("Export as CSV instead of JSON Lines")
+                    .required(false),
+            );
+        app.subcommand(audit)
+    }
+
+    fn make_webhooks_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("webhooks")
+            .about("Manage webhooks notified of CA events and issues");
+
+        let mut list =
+            SubCommand::with_name("list").about("List all webhooks");
+        list = GeneralArgs::add_args(list);
+
+        let mut add = SubCommand::with_name("add").about("Add a webhook");
+        add = GeneralArgs::add_args(add);
+        add = Self::add_webhook_name_arg(add);
+        add = add
+            .arg(
+                Arg::with_name("url")
+                    .long("url")
+                    .value_name("url")
+                    .help("The URL that notifications are POSTed to")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("secret")
+                    .long("secret")
+                    .value_name("secret")
+                    .help("The shared secret used to sign notifications")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("event")
+                    .long("event")
+                    .value_name("event type")
+                    .help("Only notify these event types, default all. Use: parent_sync_failed, repo_sync_failed, child_suspended, key_roll_started, key_roll_finished, roas_updated")
+                    .multiple(true)
+                    .required(false),
+            );
+
+        let mut remove =
+            SubCommand::with_name("remove").about("Remove a webhook");
+        remove = GeneralArgs::add_args(remove);
+        remove = Self::add_webhook_name_arg(remove);
+
+        let mut test = SubCommand::with_name("test")
+            .about("Send a test notification to a webhook");
+        test = GeneralArgs::add_args(test);
+        test = Self::add_webhook_name_arg(test);
+
+        sub = sub
+            .subcommand(list)
+            .subcommand(add)
+            .subcommand(remove)
+            .subcommand(test);
+
+        app.subcommand(sub)
+    }
+
+    fn make_tasks_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("tasks")
+            .about("Show and trigger the background tasks of this server");
+
+        let mut list = SubCommand::with_name("list")
+            .about("List queued tasks and the outcome of their last run");
+        list = GeneralArgs::add_args(list);
+
+        let mut run =
+            SubCommand::with_name("run").about("Schedule a task to run now");
+        run = GeneralArgs::add_args(run);
+        run = Self::add_my_ca_arg(run);
+        run = run
+            .arg(
+                Arg::with_name("task")
+                    .value_name("task")
+                    .help("The task to run. Tasks for a CA need --ca, sync_parent also needs --parent")
+                    .possible_values(TaskRunRequest::RUNNABLE_TASKS)
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("parent")
+                    .long("parent")
+                    .short("p")
+                    .value_name("name")
+                    .help("The parent to synchronise with, for sync_parent")
+                    .required(false),
+            );
+
+        sub = sub.subcommand(list).subcommand(run);
+
+        app.subcommand(sub)
+    }
+
+    fn make_admin_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("admin")
+            .about("Manage the Krill instance as a whole");
+
+        let mut backup = SubCommand::with_name("backup").about(
+            "Save a backup of all data, restore it with 'krill --restore'",
+        );
+        backup = GeneralArgs::add_args(backup);
+        backup = backup.arg(
+            Arg::with_name("output")
+                .long("output")
+                .short("o")
+                .value_name("path")
+                .help("The file to save the backup to")
+                .required(true),
+        );
+
+        sub = sub.subcommand(backup);
+
+        app.subcommand(sub)
+    }
+
+    fn add_webhook_name_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        app.arg(
+            Arg::with_name("name")
+                .long("name")
+                .value_name("name")
+                .help("The name of the webhook")
+                .required(true),
+        )
+    }
+
     fn make_publishers_list_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
         let mut sub =
             SubCommand::with_name("list").about("List all publishers");
@@ -1474,10 +1968,13 @@ impl Options {
         app = Self::make_cas_keyroll_sc(app);
         app = Self::make_cas_routes_sc(app);
         app = Self::make_cas_bgpsec_sc(app);
+        app = Self::make_cas_ghostbusters_sc(app);
         app = Self::make_cas_repo_sc(app);
         app = Self::make_cas_issues_sc(app);
         app = Self::make_pubserver_sc(app);
         app = Self::make_cas_aspas_sc(app);
+        app = Self::make_cas_rsc_sc(app);
+        app = Self::make_cas_geofeed_sc(app);
 
         #[cfg(feature = "rta")]
         {
@@ -1488,6 +1985,16 @@ impl Options {
 
         app = Self::make_info_sc(app);
 
+        app = Self::make_expiry_sc(app);
+
+        app = Self::make_audit_sc(app);
+
+        app = Self::make_webhooks_sc(app);
+
+        app = Self::make_tasks_sc(app);
+
+        app = Self::make_admin_sc(app);
+
         app = Self::make_bulk_sc(app);
 
         app.get_matches()
@@ -1658,7 +2165,14 @@ impl Options {
         let general_args = GeneralArgs::from_matches(matches)?;
         let my_ca = Self::parse_my_ca(matches)?;
 
-        let command = Command::CertAuth(CaCommand::Show(my_ca));
+        let command = match matches.value_of("at") {
+            Some(at) => {
+                let point = PointInTime::from_str(at)
+                    .map_err(|e| Error::general(&e))?;
+                Command::CertAuth(CaCommand::ShowAt(my_ca, point))
+            }
+            None => Command::CertAuth(CaCommand::Show(my_ca)),
+        };
         Ok(Options::make(general_args, command))
     }
 
@@ -1717,8 +2231,26 @@ impl Options {
             options.before = Some(time);
         }
 
-        let command =
-            Command::CertAuth(CaCommand::ShowHistoryCommands(my_ca, options));
+        let command =
+            Command::CertAuth(CaCommand::ShowHistoryCommands(my_ca, options));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_history_revert(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+        let command = matches.value_of("command").unwrap();
+        let version = u64::from_str(command).map_err(|e| {
+            Error::general(&format!("invalid command version: {}", e))
+        })?;
+
+        let command = if matches.is_present("dryrun") {
+            Command::CertAuth(CaCommand::HistoryRevertDryRun(my_ca, version))
+        } else {
+            Command::CertAuth(CaCommand::HistoryRevert(my_ca, version))
+        };
         Ok(Options::make(general_args, command))
     }
 
@@ -1729,6 +2261,8 @@ impl Options {
             Self::parse_matches_cas_history_commands(m)
         } else if let Some(m) = matches.subcommand_matches("details") {
             Self::parse_matches_cas_history_details(m)
+        } else if let Some(m) = matches.subcommand_matches("revert") {
+            Self::parse_matches_cas_history_revert(m)
         } else {
             Err(Error::UnrecognizedSubCommand)
         }
@@ -2008,6 +2542,72 @@ impl Options {
         Ok(Options::make(general_args, command))
     }
 
+    fn parse_matches_cas_keyroll_emergency(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+        let rcn = matches.value_of("rcn").map(ResourceClassName::from);
+
+        let command =
+            Command::CertAuth(CaCommand::KeyRollEmergency(my_ca, rcn));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_keyroll_policy_set(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let roll_after_days = matches.value_of("roll_after_days").unwrap();
+        let roll_after_days =
+            u32::from_str(roll_after_days).map_err(|e| {
+                Error::general(&format!("invalid number of days: {}", e))
+            })?;
+
+        let activate_after_hours =
+            match matches.value_of("activate_after_hours") {
+                Some(hours) => u32::from_str(hours).map_err(|e| {
+                    Error::general(&format!("invalid number of hours: {}", e))
+                })?,
+                None => KEYROLL_ACTIVATE_AFTER_HOURS_DEFAULT,
+            };
+
+        let policy =
+            KeyRollPolicy::new(roll_after_days, activate_after_hours);
+
+        let command =
+            Command::CertAuth(CaCommand::KeyRollPolicySet(my_ca, policy));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_keyroll_policy_remove(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command =
+            Command::CertAuth(CaCommand::KeyRollPolicyRemove(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_keyroll_policy(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("set") {
+            Self::parse_matches_cas_keyroll_policy_set(m)
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            Self::parse_matches_cas_keyroll_policy_remove(m)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
     fn parse_matches_cas_keyroll(
         matches: &ArgMatches,
     ) -> Result<Options, Error> {
@@ -2015,6 +2615,10 @@ impl Options {
             Self::parse_matches_cas_keyroll_init(m)
         } else if let Some(m) = matches.subcommand_matches("activate") {
             Self::parse_matches_cas_keyroll_activate(m)
+        } else if let Some(m) = matches.subcommand_matches("emergency") {
+            Self::parse_matches_cas_keyroll_emergency(m)
+        } else if let Some(m) = matches.subcommand_matches("policy") {
+            Self::parse_matches_cas_keyroll_policy(m)
         } else {
             Err(Error::UnrecognizedSubCommand)
         }
@@ -2253,6 +2857,77 @@ impl Options {
         }
     }
 
+    fn parse_matches_cas_ghostbusters_list(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let command = Command::CertAuth(CaCommand::GhostbustersList(my_ca));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_ghostbuster_name(
+        matches: &ArgMatches,
+    ) -> Result<GhostbusterName, Error> {
+        let name_str = matches.value_of("name").unwrap();
+        GhostbusterName::from_str(name_str)
+            .map_err(|e| Error::GeneralArgumentError(e.to_string()))
+    }
+
+    fn parse_matches_cas_ghostbusters_add(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let name = Self::parse_ghostbuster_name(matches)?;
+        let full_name = matches.value_of("full_name").unwrap().to_string();
+        let org = matches.value_of("org").map(|s| s.to_string());
+        let address = matches.value_of("address").map(|s| s.to_string());
+        let tel = matches.value_of("tel").map(|s| s.to_string());
+        let email = matches.value_of("email").map(|s| s.to_string());
+
+        let definition = GhostbusterDefinition::new(
+            name, full_name, org, address, tel, email,
+        );
+        definition.verify().map_err(Error::GeneralArgumentError)?;
+
+        let command =
+            Command::CertAuth(CaCommand::GhostbustersAdd(my_ca, definition));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_ghostbusters_remove(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let my_ca = Self::parse_my_ca(matches)?;
+
+        let name = Self::parse_ghostbuster_name(matches)?;
+
+        let command =
+            Command::CertAuth(CaCommand::GhostbustersRemove(my_ca, name));
+
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_ghostbusters(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("list") {
+            Self::parse_matches_cas_ghostbusters_list(m)
+        } else if let Some(m) = matches.subcommand_matches("add") {
+            Self::parse_matches_cas_ghostbusters_add(m)
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            Self::parse_matches_cas_ghostbusters_remove(m)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
     fn parse_matches_cas_aspas_add(
         matches: &ArgMatches,
     ) -> Result<Options, Error> {
@@ -2449,6 +3124,144 @@ impl Options {
         Ok(Options::make(general, command))
     }
 
+    fn parse_matches_cas_geofeed_sign(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let ca = Self::parse_my_ca(matches)?;
+
+        let days = match matches.value_of("days") {
+            None => 365,
+            Some(days) => i64::from_str(days).map_err(|e| {
+                Error::GeneralArgumentError(format!(
+                    "Invalid number of days: {}",
+                    e
+                ))
+            })?,
+        };
+        let validity = SignSupport::sign_validity_days(days);
+
+        let in_file = matches.value_of("geofeed").unwrap();
+        let in_file = PathBuf::from_str(in_file).map_err(|_| {
+            Error::GeneralArgumentError(format!(
+                "Invalid filename: {}",
+                in_file
+            ))
+        })?;
+
+        let bytes = file::read(&in_file).map_err(|e| {
+            Error::GeneralArgumentError(format!(
+                "Can't read file '{}', error: {}",
+                in_file.to_string_lossy(),
+                e,
+            ))
+        })?;
+        let geofeed = String::from_utf8(bytes.to_vec()).map_err(|_| {
+            Error::GeneralArgumentError(format!(
+                "File '{}' is not valid UTF-8",
+                in_file.to_string_lossy()
+            ))
+        })?;
+
+        let out_file = match matches.value_of("out") {
+            None => None,
+            Some(out_file) => {
+                Some(PathBuf::from_str(out_file).map_err(|_| {
+                    Error::GeneralArgumentError(format!(
+                        "Invalid filename: {}",
+                        out_file
+                    ))
+                })?)
+            }
+        };
+
+        let request = GeofeedSignRequest::new(geofeed, validity);
+        let command =
+            Command::CertAuth(CaCommand::GeofeedSign(ca, request, out_file));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_geofeed(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("sign") {
+            Self::parse_matches_cas_geofeed_sign(m)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
+    fn parse_matches_cas_rsc_sign(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let ca = Self::parse_my_ca(matches)?;
+
+        let days = match matches.value_of("days") {
+            None => 7,
+            Some(days) => i64::from_str(days).map_err(|e| {
+                Error::GeneralArgumentError(format!(
+                    "Invalid number of days: {}",
+                    e
+                ))
+            })?,
+        };
+        let validity = SignSupport::sign_validity_days(days);
+
+        let resources = Self::parse_resource_args(matches)?
+            .ok_or_else(|| Error::general("You must specify at least one of --ipv4, --ipv6 or --asn"))?;
+
+        let mut checklist = vec![];
+        for in_file in matches.values_of("file").unwrap() {
+            let in_file = PathBuf::from_str(in_file).map_err(|_| {
+                Error::GeneralArgumentError(format!(
+                    "Invalid filename: {}",
+                    in_file
+                ))
+            })?;
+
+            let content = file::read(&in_file).map_err(|e| {
+                Error::GeneralArgumentError(format!(
+                    "Can't read file '{}', error: {}",
+                    in_file.to_string_lossy(),
+                    e,
+                ))
+            })?;
+
+            let file_name = in_file
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            checklist.push(RscChecklistItem::for_file(&file_name, &content));
+        }
+
+        let out_file = match matches.value_of("out") {
+            None => None,
+            Some(out_file) => {
+                Some(PathBuf::from_str(out_file).map_err(|_| {
+                    Error::GeneralArgumentError(format!(
+                        "Invalid filename: {}",
+                        out_file
+                    ))
+                })?)
+            }
+        };
+
+        let request = RscSignRequest::new(resources, validity, checklist);
+        let command =
+            Command::CertAuth(CaCommand::RscSign(ca, request, out_file));
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_cas_rsc(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("sign") {
+            Self::parse_matches_cas_rsc_sign(m)
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
     fn parse_matches_cas_rta_list(
         matches: &ArgMatches,
     ) -> Result<Options, Error> {
@@ -2654,6 +3467,155 @@ impl Options {
         Ok(Options::make(general_args, command))
     }
 
+    fn parse_matches_expiry(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+        let within = match matches.value_of("within") {
+            Some(within) => Some(
+                ExpiryWindow::from_str(within)
+                    .map_err(|e| Error::general(&e.to_string()))?,
+            ),
+            None => None,
+        };
+        let command = Command::Expiry(within);
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_audit(matches: &ArgMatches) -> Result<Options, Error> {
+        let general_args = GeneralArgs::from_matches(matches)?;
+
+        let parse_time = |name: &str| -> Result<Option<Time>, Error> {
+            match matches.value_of(name) {
+                Some(time) => Time::from_str(time).map(Some).map_err(|e| {
+                    Error::general(&format!("invalid date format: {}", e))
+                }),
+                None => Ok(None),
+            }
+        };
+
+        let handle = match matches.value_of("handle") {
+            Some(handle) => Some(
+                MyHandle::from_str(handle)
+                    .map_err(|_| Error::InvalidHandle)?,
+            ),
+            None => None,
+        };
+
+        let crit = AuditCriteria {
+            actor: matches.value_of("actor").map(|s| s.to_string()),
+            after: parse_time("after")?,
+            before: parse_time("before")?,
+            command: matches.value_of("command").map(|s| s.to_string()),
+            handle,
+        };
+
+        let format = if matches.is_present("csv") {
+            AuditFormat::Csv
+        } else {
+            AuditFormat::JsonLines
+        };
+
+        let command = Command::Audit(crit, format);
+        Ok(Options::make(general_args, command))
+    }
+
+    fn parse_matches_webhooks(
+        matches: &ArgMatches,
+    ) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("list") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let command = Command::Webhooks(WebhookCommand::List);
+            Ok(Options::make(general_args, command))
+        } else if let Some(m) = matches.subcommand_matches("add") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let name = Self::parse_webhook_name_arg(m)?;
+            let url =
+                Url::parse(m.value_of("url").unwrap()).map_err(|e| {
+                    Error::general(&format!("Invalid webhook URL: {}", e))
+                })?;
+            let secret = m.value_of("secret").unwrap().to_string();
+
+            let mut events = vec![];
+            if let Some(event_strs) = m.values_of("event") {
+                for event_str in event_strs {
+                    let event = WebhookEventType::from_str(event_str)
+                        .map_err(|e| Error::general(&e))?;
+                    events.push(event);
+                }
+            }
+
+            let definition =
+                WebhookDefinition::new(name, url, secret, events);
+            let command = Command::Webhooks(WebhookCommand::Add(definition));
+            Ok(Options::make(general_args, command))
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let name = Self::parse_webhook_name_arg(m)?;
+            let command = Command::Webhooks(WebhookCommand::Remove(name));
+            Ok(Options::make(general_args, command))
+        } else if let Some(m) = matches.subcommand_matches("test") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let name = Self::parse_webhook_name_arg(m)?;
+            let command = Command::Webhooks(WebhookCommand::Test(name));
+            Ok(Options::make(general_args, command))
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
+    fn parse_matches_tasks(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("list") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let command = Command::Tasks(TaskCommand::List);
+            Ok(Options::make(general_args, command))
+        } else if let Some(m) = matches.subcommand_matches("run") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let task = Self::parse_task_arg(m)?;
+            let command = Command::Tasks(TaskCommand::Run(task));
+            Ok(Options::make(general_args, command))
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
+    fn parse_task_arg(matches: &ArgMatches) -> Result<TaskRunRequest, Error> {
+        let task = matches.value_of("task").unwrap().to_string();
+        // The CA is only needed for some tasks, the server checks this.
+        let ca = if matches.value_of(KRILL_CLI_MY_CA_ARG).is_some()
+            || env::var(KRILL_CLI_MY_CA_ENV).is_ok()
+        {
+            Some(Self::parse_my_ca(matches)?)
+        } else {
+            None
+        };
+        let parent = match matches.value_of("parent") {
+            Some(parent_str) => Some(
+                ParentHandle::from_str(parent_str)
+                    .map_err(|_| Error::InvalidHandle)?,
+            ),
+            None => None,
+        };
+        Ok(TaskRunRequest::new(task, ca, parent))
+    }
+
+    fn parse_matches_admin(matches: &ArgMatches) -> Result<Options, Error> {
+        if let Some(m) = matches.subcommand_matches("backup") {
+            let general_args = GeneralArgs::from_matches(m)?;
+            let output = PathBuf::from(m.value_of("output").unwrap());
+            let command = Command::Admin(AdminCommand::Backup(output));
+            Ok(Options::make(general_args, command))
+        } else {
+            Err(Error::UnrecognizedSubCommand)
+        }
+    }
+
+    fn parse_webhook_name_arg(
+        matches: &ArgMatches,
+    ) -> Result<WebhookName, Error> {
+        let name_str = matches.value_of("name").unwrap();
+        WebhookName::from_str(name_str)
+            .map_err(|e| Error::general(&e.to_string()))
+    }
+
     fn parse_publisher_arg(
         matches: &ArgMatches,
     ) -> Result<PublisherHandle, Error> {
@@ -2888,10 +3850,16 @@ impl Options {
             Self::parse_matches_cas_bgpsec(m)
         } else if let Some(m) = matches.subcommand_matches("aspas") {
             Self::parse_matches_cas_aspas(m)
+        } else if let Some(m) = matches.subcommand_matches("ghostbusters") {
+            Self::parse_matches_cas_ghostbusters(m)
         } else if let Some(m) = matches.subcommand_matches("repo") {
             Self::parse_matches_cas_repo(m)
         } else if let Some(m) = matches.subcommand_matches("issues") {
             Self::parse_matches_cas_issues(m)
+        } else if let Some(m) = matches.subcommand_matches("rsc") {
+            Self::parse_matches_cas_rsc(m)
+        } else if let Some(m) = matches.subcommand_matches("geofeed") {
+            Self::parse_matches_cas_geofeed(m)
         } else if let Some(m) = matches.subcommand_matches("rta") {
             Self::parse_matches_cas_rta(m)
         } else if let Some(m) = matches.subcommand_matches("bulk") {
@@ -2900,6 +3868,16 @@ impl Options {
             Self::parse_matches_health(m)
         } else if let Some(m) = matches.subcommand_matches("info") {
             Self::parse_matches_info(m)
+        } else if let Some(m) = matches.subcommand_matches("expiry") {
+            Self::parse_matches_expiry(m)
+        } else if let Some(m) = matches.subcommand_matches("audit") {
+            Self::parse_matches_audit(m)
+        } else if let Some(m) = matches.subcommand_matches("webhooks") {
+            Self::parse_matches_webhooks(m)
+        } else if let Some(m) = matches.subcommand_matches("tasks") {
+            Self::parse_matches_tasks(m)
+        } else if let Some(m) = matches.subcommand_matches("admin") {
+            Self::parse_matches_admin(m)
         } else if let Some(m) = matches.subcommand_matches("pubserver") {
             Self::parse_matches_pubserver(m)
         } else {
@@ -2919,6 +3897,11 @@ pub enum Command {
     NotSet,
     Health,
     Info,
+    Expiry(Option<ExpiryWindow>),
+    Audit(AuditCriteria, AuditFormat),
+    Webhooks(WebhookCommand),
+    Tasks(TaskCommand),
+    Admin(AdminCommand),
     Bulk(BulkCaCommand),
     CertAuth(CaCommand),
     PubServer(PubServerCommand),
@@ -2963,6 +3946,9 @@ pub enum CaCommand {
     // Key Management
     KeyRollInit(CaHandle),
     KeyRollActivate(CaHandle),
+    KeyRollEmergency(CaHandle, Option<ResourceClassName>),
+    KeyRollPolicySet(CaHandle, KeyRollPolicy),
+    KeyRollPolicyRemove(CaHandle),
 
     // Authorizations
     RouteAuthorizationsList(CaHandle),
@@ -2983,12 +3969,26 @@ pub enum CaCommand {
     BgpSecAdd(CaHandle, BgpSecDefinition),
     BgpSecRemove(CaHandle, BgpSecAsnKey),
 
+    // Ghostbusters
+    GhostbustersList(CaHandle),
+    GhostbustersAdd(CaHandle, GhostbusterDefinition),
+    GhostbustersRemove(CaHandle, GhostbusterName),
+
     // Show details for this CA
     Show(CaHandle),
+    ShowAt(CaHandle, PointInTime),
     ShowHistoryCommands(CaHandle, HistoryOptions),
     ShowHistoryDetails(CaHandle, String),
+    HistoryRevert(CaHandle, u64),
+    HistoryRevertDryRun(CaHandle, u64),
     Issues(Option<CaHandle>),
 
+    // RSC
+    RscSign(CaHandle, RscSignRequest, Option<PathBuf>),
+
+    // Geofeed
+    GeofeedSign(CaHandle, GeofeedSignRequest, Option<PathBuf>),
+
     // RTA
     RtaList(CaHandle),
     RtaShow(CaHandle, RtaName, Option<PathBuf>),
@@ -3043,6 +4043,26 @@ impl HistoryOptions {
     }
 }
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WebhookCommand {
+    List,
+    Add(WebhookDefinition),
+    Remove(WebhookName),
+    Test(WebhookName),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[allow(clippy::large_enum_variant)]
+pub enum TaskCommand {
+    List,
+    Run(TaskRunRequest),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AdminCommand {
+    Backup(PathBuf),
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum BulkCaCommand {
     Refresh,
@@ -11,18 +11,24 @@ use crate::{
             import::ExportChild, AllCertAuthIssues, AspaDefinitionList,
             BgpSecCsrInfoList, CaCommandDetails, CaRepoDetails, CertAuthInfo,
             CertAuthIssues, CertAuthList, ChildCaInfo,
-            ChildrenConnectionStats, CommandHistory, ConfiguredRoas,
-            IdCertInfo, ParentCaContact, ParentStatuses, PublisherDetails,
-            PublisherList, RepoStatus, RepositoryContact, RtaList,
-            RtaPrepResponse, ServerInfo,
+            ChildrenConnectionStats, CommandDryRun, CommandHistory,
+            ConfiguredRoas, GhostbusterDefinitionList, HistoricCertAuthInfo,
+            IdCertInfo, ObjectExpiryReport, ParentCaContact, ParentStatuses,
+            PublisherDetails, PublisherList, RepoStatus, RepositoryContact,
+            RtaList, RtaPrepResponse, ServerInfo, WebhookList,
         },
         bgp::{BgpAnalysisAdvice, BgpAnalysisReport, BgpAnalysisSuggestion},
     },
-    daemon::ca::ResourceTaggedAttestation,
+    daemon::{
+        backup::BackupManifest,
+        ca::{ResourceTaggedAttestation, SignedChecklist, SignedGeofeed},
+        mq::TaskList,
+    },
     pubd::RepoStats,
     ta::{
-        TrustAnchorProxySignerExchanges, TrustAnchorSignedRequest,
-        TrustAnchorSignedResponse, TrustAnchorSignerInfo,
+        TaKeyRoll, TrustAnchorChild, TrustAnchorProxySignerExchanges,
+        TrustAnchorSignedRequest, TrustAnchorSignedResponse,
+        TrustAnchorSignerInfo, TrustAnchorSignerResponseDiff,
     },
 };
 
@@ -34,10 +40,16 @@ use crate::{
 pub enum ApiResponse {
     Health,
     Info(ServerInfo),
+    ObjectExpiry(ObjectExpiryReport),
+    Webhooks(WebhookList),
+    Tasks(TaskList),
+    Backup(BackupManifest),
 
     CertAuthInfo(CertAuthInfo),
+    HistoricCertAuthInfo(HistoricCertAuthInfo),
     CertAuthHistory(CommandHistory),
     CertAuthAction(CaCommandDetails),
+    CertAuthDryRun(CommandDryRun),
     CertAuths(CertAuthList),
 
     // ROA related
@@ -52,6 +64,9 @@ pub enum ApiResponse {
     // BGPSec related
     BgpSecDefinitions(BgpSecCsrInfoList),
 
+    // Ghostbuster related
+    GhostbusterDefinitions(GhostbusterDefinitionList),
+
     ParentCaContact(ParentCaContact),
     ParentStatuses(ParentStatuses),
 
@@ -77,6 +92,8 @@ pub enum ApiResponse {
     RtaList(RtaList),
     RtaMultiPrep(RtaPrepResponse),
     Rta(ResourceTaggedAttestation),
+    Rsc(SignedChecklist),
+    Geofeed(SignedGeofeed),
 
     Empty, // Typically a successful post just gets an empty 200 response
     GenericBody(String), /* For when the server echos Json to a
@@ -94,16 +111,30 @@ impl ApiResponse {
             match self {
                 ApiResponse::Health => Ok(None),
                 ApiResponse::Info(info) => Ok(Some(info.report(fmt)?)),
+                ApiResponse::ObjectExpiry(report) => {
+                    Ok(Some(report.report(fmt)?))
+                }
+                ApiResponse::Webhooks(list) => Ok(Some(list.report(fmt)?)),
+                ApiResponse::Tasks(list) => Ok(Some(list.report(fmt)?)),
+                ApiResponse::Backup(manifest) => {
+                    Ok(Some(manifest.report(fmt)?))
+                }
                 ApiResponse::CertAuths(list) => Ok(Some(list.report(fmt)?)),
                 ApiResponse::CertAuthInfo(info) => {
                     Ok(Some(info.report(fmt)?))
                 }
+                ApiResponse::HistoricCertAuthInfo(info) => {
+                    Ok(Some(info.report(fmt)?))
+                }
                 ApiResponse::CertAuthHistory(history) => {
                     Ok(Some(history.report(fmt)?))
                 }
                 ApiResponse::CertAuthAction(details) => {
                     Ok(Some(details.report(fmt)?))
                 }
+                ApiResponse::CertAuthDryRun(dry_run) => {
+                    Ok(Some(dry_run.report(fmt)?))
+                }
                 ApiResponse::CertAuthIssues(issues) => {
                     Ok(Some(issues.report(fmt)?))
                 }
@@ -128,6 +159,9 @@ impl ApiResponse {
                 ApiResponse::BgpSecDefinitions(definitions) => {
                     Ok(Some(definitions.report(fmt)?))
                 }
+                ApiResponse::GhostbusterDefinitions(definitions) => {
+                    Ok(Some(definitions.report(fmt)?))
+                }
                 ApiResponse::ParentCaContact(contact) => {
                     Ok(Some(contact.report(fmt)?))
                 }
@@ -167,6 +201,10 @@ impl ApiResponse {
                     Ok(Some(status.report(fmt)?))
                 }
                 ApiResponse::Rta(rta) => Ok(Some(rta.report(fmt)?)),
+                ApiResponse::Rsc(rsc) => Ok(Some(rsc.report(fmt)?)),
+                ApiResponse::Geofeed(geofeed) => {
+                    Ok(Some(geofeed.report(fmt)?))
+                }
                 ApiResponse::RtaList(list) => Ok(Some(list.report(fmt)?)),
                 ApiResponse::RtaMultiPrep(res) => Ok(Some(res.report(fmt)?)),
                 ApiResponse::GenericBody(body) => Ok(Some(body.clone())),
@@ -245,6 +283,7 @@ pub trait Report: Serialize + ToString {
 
 impl Report for CertAuthList {}
 impl Report for CertAuthInfo {}
+impl Report for HistoricCertAuthInfo {}
 impl Report for IdCertInfo {}
 impl Report for RepositoryContact {}
 
@@ -256,6 +295,7 @@ impl Report for ParentStatuses {}
 
 impl Report for CommandHistory {}
 impl Report for CaCommandDetails {}
+impl Report for CommandDryRun {}
 
 impl Report for PublisherList {}
 
@@ -298,6 +338,8 @@ impl Report for AspaDefinitionList {}
 
 impl Report for BgpSecCsrInfoList {}
 
+impl Report for GhostbusterDefinitionList {}
+
 impl Report for CaRepoDetails {}
 impl Report for RepoStatus {}
 
@@ -307,11 +349,24 @@ impl Report for AllCertAuthIssues {}
 
 impl Report for ServerInfo {}
 
+impl Report for ObjectExpiryReport {}
+
+impl Report for WebhookList {}
+
+impl Report for TaskList {}
+
+impl Report for BackupManifest {}
+
 impl Report for ResourceTaggedAttestation {}
+impl Report for SignedChecklist {}
+impl Report for SignedGeofeed {}
 impl Report for RtaList {}
 impl Report for RtaPrepResponse {}
 
 impl Report for TrustAnchorSignerInfo {}
 impl Report for TrustAnchorSignedRequest {}
 impl Report for TrustAnchorSignedResponse {}
+impl Report for TrustAnchorSignerResponseDiff {}
 impl Report for TrustAnchorProxySignerExchanges {}
+impl Report for TaKeyRoll {}
+impl Report for TrustAnchorChild {}
@@ -32,10 +32,12 @@ use crate::{
         KRILL_VERSION,
     },
     ta::{
-        self, Config, TrustAnchorHandle, TrustAnchorProxySignerExchanges,
+        self, Config, TaCertReissueRequest, TaKeyRoll, TrustAnchorChild,
+        TrustAnchorHandle, TrustAnchorProxySignerExchanges,
         TrustAnchorSignedRequest, TrustAnchorSignedResponse,
         TrustAnchorSigner, TrustAnchorSignerCommand, TrustAnchorSignerInfo,
         TrustAnchorSignerInitCommand, TrustAnchorSignerInitCommandDetails,
+        TrustAnchorSignerResponseDiff,
     },
 };
 
@@ -158,6 +160,8 @@ pub enum ProxyCommandDetails {
     SignerProcessResponse(TrustAnchorSignedResponse),
     ChildAdd(AddChildRequest),
     ChildResponse(ChildHandle),
+    ChildShow(ChildHandle),
+    ChildRemove(ChildHandle),
 }
 
 #[derive(Debug)]
@@ -175,9 +179,16 @@ pub enum SignerCommandDetails {
     ProcessRequest {
         signed_request: TrustAnchorSignedRequest,
         ta_mft_number_override: Option<u64>,
+        dry_run: bool,
     },
     ShowLastResponse,
     ShowExchanges,
+    KeyRollInit {
+        tal_https: Vec<uri::Https>,
+        tal_rsync: uri::Rsync,
+    },
+    KeyRollShow,
+    Reissue(TaCertReissueRequest),
 }
 
 #[derive(Debug)]
@@ -346,6 +357,8 @@ impl TrustAnchorClientCommand {
             .about("Manage children under the TA proxy");
         sub = Self::make_proxy_children_add_sc(sub);
         sub = Self::make_proxy_children_response_sc(sub);
+        sub = Self::make_proxy_children_show_sc(sub);
+        sub = Self::make_proxy_children_remove_sc(sub);
         app.subcommand(sub)
     }
 
@@ -397,6 +410,23 @@ impl TrustAnchorClientCommand {
         app.subcommand(sub)
     }
 
+    fn make_proxy_children_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show")
+            .about("Show a child, including any pending removal.");
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_child_arg(sub);
+        app.subcommand(sub)
+    }
+
+    fn make_proxy_children_remove_sc<'a, 'b>(
+        app: App<'a, 'b>,
+    ) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("remove").about("Remove a child. Its certificates are revoked by the signer in the next exchange, after which the child is removed.");
+        sub = GeneralArgs::add_args(sub);
+        sub = Self::add_child_arg(sub);
+        app.subcommand(sub)
+    }
+
     //-- Sub Commands Signer
 
     fn make_signer_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
@@ -408,6 +438,8 @@ impl TrustAnchorClientCommand {
         sub = Self::make_signer_process_sc(sub);
         sub = Self::make_signer_last_sc(sub);
         sub = Self::make_signer_exchanges_sc(sub);
+        sub = Self::make_signer_keyroll_sc(sub);
+        sub = Self::make_signer_reissue_sc(sub);
 
         app.subcommand(sub)
     }
@@ -495,6 +527,12 @@ impl TrustAnchorClientCommand {
                     .value_name("number")
                     .help("[OPTIONAL] Override the next manifest number (defaults to last + 1)")
                     .required(false),
+            )
+            .arg(
+                Arg::with_name("dry_run")
+                    .long("dry-run")
+                    .help("[OPTIONAL] Show what the response would change, without saving it")
+                    .required(false),
             );
         app.subcommand(sub)
     }
@@ -515,6 +553,106 @@ impl TrustAnchorClientCommand {
         app.subcommand(sub)
     }
 
+    fn make_signer_keyroll_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("keyroll")
+            .about("Manage a Trust Anchor key roll (RFC 9691)");
+
+        sub = Self::make_signer_keyroll_init_sc(sub);
+        sub = Self::make_signer_keyroll_show_sc(sub);
+
+        app.subcommand(sub)
+    }
+
+    fn make_signer_keyroll_init_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("init").about(
+            "Create a successor key. It is published with the next proxy request.",
+        );
+        sub = Self::add_config_arg(sub);
+        sub = Self::add_format_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("tal_rsync")
+                    .long("tal_rsync")
+                    .value_name("Rsync URI")
+                    .help("Used for successor TA certificate on TAL and AIA")
+                    .required(true),
+            )
+            .arg(
+                Arg::with_name("tal_https")
+                    .long("tal_https")
+                    .value_name("HTTPS URI")
+                    .help("Used for successor TAL. Multiple allowed.")
+                    .multiple(true)
+                    .required(true),
+            );
+
+        app.subcommand(sub)
+    }
+
+    fn make_signer_keyroll_show_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("show")
+            .about("Show the key roll in progress");
+        sub = Self::add_config_arg(sub);
+        sub = Self::add_format_arg(sub);
+        app.subcommand(sub)
+    }
+
+    fn make_signer_reissue_sc<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
+        let mut sub = SubCommand::with_name("reissue").about(
+            "Re-issue the TA certificate. Children are updated with the next proxy request.",
+        );
+        sub = Self::add_config_arg(sub);
+        sub = Self::add_format_arg(sub);
+
+        sub = sub
+            .arg(
+                Arg::with_name("tal_rsync")
+                    .long("tal_rsync")
+                    .value_name("Rsync URI")
+                    .help("[OPTIONAL] New rsync URI for the TA certificate on the TAL and AIA")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("tal_https")
+                    .long("tal_https")
+                    .value_name("HTTPS URI")
+                    .help("[OPTIONAL] New HTTPS URIs for the TAL. Multiple allowed.")
+                    .multiple(true)
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("asn")
+                    .value_name("asn resources")
+                    .long("asn")
+                    .help("[OPTIONAL] The ASN resources for the TA certificate")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("ipv4")
+                    .value_name("IPv4 resources")
+                    .long("ipv4")
+                    .help("[OPTIONAL] The IPv4 resources for the TA certificate")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("ipv6")
+                    .value_name("IPv6 resources")
+                    .long("ipv6")
+                    .help("[OPTIONAL] The IPv6 resources for the TA certificate")
+                    .required(false),
+            )
+            .arg(
+                Arg::with_name("validity_years")
+                    .long("validity_years")
+                    .value_name("number")
+                    .help("[OPTIONAL] Validity of the TA certificate in years (defaults to the configured value)")
+                    .required(false),
+            );
+
+        app.subcommand(sub)
+    }
+
     //-- Arguments
 
     fn add_config_arg<'a, 'b>(app: App<'a, 'b>) -> App<'a, 'b> {
@@ -749,6 +887,10 @@ impl TrustAnchorClientCommand {
             Self::parse_matches_proxy_children_add(m)
         } else if let Some(m) = matches.subcommand_matches("response") {
             Self::parse_matches_proxy_children_response(m)
+        } else if let Some(m) = matches.subcommand_matches("show") {
+            Self::parse_matches_proxy_children_show(m)
+        } else if let Some(m) = matches.subcommand_matches("remove") {
+            Self::parse_matches_proxy_children_remove(m)
         } else {
             Err(TaClientError::UnrecognizedMatch)
         }
@@ -794,6 +936,32 @@ impl TrustAnchorClientCommand {
         }))
     }
 
+    fn parse_matches_proxy_children_show(
+        matches: &ArgMatches,
+    ) -> Result<Self, TaClientError> {
+        let general = GeneralArgs::from_matches(matches)
+            .map_err(|e| TaClientError::Other(e.to_string()))?;
+        let child = Self::parse_child_arg(matches)?;
+
+        Ok(TrustAnchorClientCommand::Proxy(ProxyCommand {
+            general,
+            details: ProxyCommandDetails::ChildShow(child),
+        }))
+    }
+
+    fn parse_matches_proxy_children_remove(
+        matches: &ArgMatches,
+    ) -> Result<Self, TaClientError> {
+        let general = GeneralArgs::from_matches(matches)
+            .map_err(|e| TaClientError::Other(e.to_string()))?;
+        let child = Self::parse_child_arg(matches)?;
+
+        Ok(TrustAnchorClientCommand::Proxy(ProxyCommand {
+            general,
+            details: ProxyCommandDetails::ChildRemove(child),
+        }))
+    }
+
     fn parse_child_arg(
         matches: &ArgMatches,
     ) -> Result<ChildHandle, TaClientError> {
@@ -841,11 +1009,154 @@ impl TrustAnchorClientCommand {
             Self::parse_matches_signer_last_response(m)
         } else if let Some(m) = matches.subcommand_matches("exchanges") {
             Self::parse_matches_signer_exchanges(m)
+        } else if let Some(m) = matches.subcommand_matches("keyroll") {
+            Self::parse_matches_signer_keyroll(m)
+        } else if let Some(m) = matches.subcommand_matches("reissue") {
+            Self::parse_matches_signer_reissue(m)
+        } else {
+            Err(TaClientError::UnrecognizedMatch)
+        }
+    }
+
+    fn parse_matches_signer_keyroll(
+        matches: &ArgMatches,
+    ) -> Result<Self, TaClientError> {
+        if let Some(m) = matches.subcommand_matches("init") {
+            Self::parse_matches_signer_keyroll_init(m)
+        } else if let Some(m) = matches.subcommand_matches("show") {
+            Self::parse_matches_signer_keyroll_show(m)
         } else {
             Err(TaClientError::UnrecognizedMatch)
         }
     }
 
+    fn parse_matches_signer_keyroll_init(
+        matches: &ArgMatches,
+    ) -> Result<Self, TaClientError> {
+        let config = Self::parse_config(matches)?;
+        let format = Self::parse_format(matches)?;
+
+        let tal_https = Self::parse_tal_https(matches)?;
+        let tal_rsync = Self::parse_tal_rsync(matches)?;
+
+        Ok(TrustAnchorClientCommand::Signer(SignerCommand {
+            config,
+            format,
+            details: SignerCommandDetails::KeyRollInit {
+                tal_https,
+                tal_rsync,
+            },
+        }))
+    }
+
+    fn parse_matches_signer_keyroll_show(
+        matches: &ArgMatches,
+    ) -> Result<Self, TaClientError> {
+        let config = Self::parse_config(matches)?;
+        let format = Self::parse_format(matches)?;
+
+        Ok(TrustAnchorClientCommand::Signer(SignerCommand {
+            config,
+            format,
+            details: SignerCommandDetails::KeyRollShow,
+        }))
+    }
+
+    fn parse_matches_signer_reissue(
+        matches: &ArgMatches,
+    ) -> Result<Self, TaClientError> {
+        let config = Self::parse_config(matches)?;
+        let format = Self::parse_format(matches)?;
+
+        let tal_https = if matches.is_present("tal_https") {
+            Some(Self::parse_tal_https(matches)?)
+        } else {
+            None
+        };
+
+        let tal_rsync = if matches.is_present("tal_rsync") {
+            Some(Self::parse_tal_rsync(matches)?)
+        } else {
+            None
+        };
+
+        // If any resource type is specified, then the TA certificate will
+        // get exactly the specified resources.
+        let resources = {
+            let asn = matches.value_of("asn");
+            let ipv4 = matches.value_of("ipv4");
+            let ipv6 = matches.value_of("ipv6");
+
+            if asn.is_some() || ipv4.is_some() || ipv6.is_some() {
+                Some(
+                    ResourceSet::from_strs(
+                        asn.unwrap_or(""),
+                        ipv4.unwrap_or(""),
+                        ipv6.unwrap_or(""),
+                    )
+                    .map_err(|e| {
+                        TaClientError::Other(format!(
+                            "Cannot parse resources: {}",
+                            e
+                        ))
+                    })?,
+                )
+            } else {
+                None
+            }
+        };
+
+        let validity_years = if let Some(years) =
+            matches.value_of("validity_years")
+        {
+            let years = i32::from_str(years)
+                .ok()
+                .filter(|years| *years > 0)
+                .ok_or_else(|| {
+                    TaClientError::other("Invalid validity years, must be >0")
+                })?;
+            Some(years)
+        } else {
+            None
+        };
+
+        Ok(TrustAnchorClientCommand::Signer(SignerCommand {
+            config,
+            format,
+            details: SignerCommandDetails::Reissue(TaCertReissueRequest {
+                resources,
+                tal_https,
+                tal_rsync,
+                validity_years,
+            }),
+        }))
+    }
+
+    fn parse_tal_https(
+        matches: &ArgMatches,
+    ) -> Result<Vec<uri::Https>, TaClientError> {
+        let uri_strs = matches.values_of("tal_https").unwrap();
+        let mut uris = vec![];
+        for uri_str in uri_strs {
+            uris.push(uri::Https::from_str(uri_str).map_err(|_| {
+                TaClientError::Other(format!(
+                    "Invalid HTTPS URI: {}",
+                    uri_str
+                ))
+            })?);
+        }
+        Ok(uris)
+    }
+
+    fn parse_tal_rsync(
+        matches: &ArgMatches,
+    ) -> Result<uri::Rsync, TaClientError> {
+        let rsync_str = matches.value_of("tal_rsync").unwrap();
+        uri::Rsync::from_str(rsync_str).map_err(|_| {
+            TaClientError::Other(format!("Invalid rsync uri: {}", rsync_str))
+        })
+    }
+
     fn parse_matches_signer_init(
         matches: &ArgMatches,
     ) -> Result<Self, TaClientError> {
@@ -862,29 +1173,8 @@ impl TrustAnchorClientCommand {
             repo_contact.into()
         };
 
-        let tal_https = {
-            let uri_strs = matches.values_of("tal_https").unwrap();
-            let mut uris = vec![];
-            for uri_str in uri_strs {
-                uris.push(uri::Https::from_str(uri_str).map_err(|_| {
-                    TaClientError::Other(format!(
-                        "Invalid HTTPS URI: {}",
-                        uri_str
-                    ))
-                })?);
-            }
-            uris
-        };
-
-        let tal_rsync = {
-            let rsync_str = matches.value_of("tal_rsync").unwrap();
-            uri::Rsync::from_str(rsync_str).map_err(|_| {
-                TaClientError::Other(format!(
-                    "Invalid rsync uri: {}",
-                    rsync_str
-                ))
-            })?
-        };
+        let tal_https = Self::parse_tal_https(matches)?;
+        let tal_rsync = Self::parse_tal_rsync(matches)?;
 
         let ta_mft_nr_override = if let Some(number) =
             matches.value_of("initial_manifest_number")
@@ -966,6 +1256,7 @@ impl TrustAnchorClientCommand {
             details: SignerCommandDetails::ProcessRequest {
                 signed_request,
                 ta_mft_number_override,
+                dry_run: matches.is_present("dry_run"),
             },
         }))
     }
@@ -1109,6 +1400,17 @@ impl TrustAnchorClient {
                             response,
                         ))
                     }
+                    ProxyCommandDetails::ChildShow(child) => {
+                        let uri_path =
+                            format!("api/v1/ta/proxy/children/{}", child);
+                        let child = client.get_json(&uri_path).await?;
+                        Ok(TrustAnchorClientApiResponse::ProxyChild(child))
+                    }
+                    ProxyCommandDetails::ChildRemove(child) => {
+                        let uri_path =
+                            format!("api/v1/ta/proxy/children/{}", child);
+                        client.delete(&uri_path).await
+                    }
                 }
             }
             TrustAnchorClientCommand::Signer(signer_command) => {
@@ -1123,14 +1425,36 @@ impl TrustAnchorClient {
                     SignerCommandDetails::ProcessRequest {
                         signed_request,
                         ta_mft_number_override,
-                    } => signer_manager
-                        .process(signed_request, ta_mft_number_override),
+                        dry_run,
+                    } => {
+                        if dry_run {
+                            signer_manager.process_dry_run(
+                                signed_request,
+                                ta_mft_number_override,
+                            )
+                        } else {
+                            signer_manager.process(
+                                signed_request,
+                                ta_mft_number_override,
+                            )
+                        }
+                    }
                     SignerCommandDetails::ShowLastResponse => {
                         signer_manager.show_last_response()
                     }
                     SignerCommandDetails::ShowExchanges => {
                         signer_manager.show_exchanges()
                     }
+                    SignerCommandDetails::KeyRollInit {
+                        tal_https,
+                        tal_rsync,
+                    } => signer_manager.key_roll_init(tal_https, tal_rsync),
+                    SignerCommandDetails::KeyRollShow => {
+                        signer_manager.key_roll_show()
+                    }
+                    SignerCommandDetails::Reissue(request) => {
+                        signer_manager.reissue(request)
+                    }
                 }
             }
         }
@@ -1144,9 +1468,12 @@ pub enum TrustAnchorClientApiResponse {
     RepositoryContact(RepositoryContact),
     TrustAnchorProxySignerInfo(TrustAnchorSignerInfo),
     ParentResponse(idexchange::ParentResponse),
+    ProxyChild(TrustAnchorChild),
     SignerRequest(TrustAnchorSignedRequest),
     SignerResponse(TrustAnchorSignedResponse),
+    SignerResponseDiff(TrustAnchorSignerResponseDiff),
     ProxySignerExchanges(TrustAnchorProxySignerExchanges),
+    KeyRoll(TaKeyRoll),
     Empty,
 }
 
@@ -1171,6 +1498,9 @@ impl TrustAnchorClientApiResponse {
                 TrustAnchorClientApiResponse::TrustAnchorProxySignerInfo(
                     info,
                 ) => info.report(fmt).map(Some),
+                TrustAnchorClientApiResponse::ProxyChild(child) => {
+                    child.report(fmt).map(Some)
+                }
                 TrustAnchorClientApiResponse::ParentResponse(response) => {
                     response.report(fmt).map(Some)
                 }
@@ -1183,6 +1513,12 @@ impl TrustAnchorClientApiResponse {
                 TrustAnchorClientApiResponse::ProxySignerExchanges(
                     exchanges,
                 ) => exchanges.report(fmt).map(Some),
+                TrustAnchorClientApiResponse::SignerResponseDiff(diff) => {
+                    diff.report(fmt).map(Some)
+                }
+                TrustAnchorClientApiResponse::KeyRoll(key_roll) => {
+                    key_roll.report(fmt).map(Some)
+                }
                 TrustAnchorClientApiResponse::Empty => Ok(None),
             }
         }
@@ -1257,6 +1593,17 @@ impl ProxyClient {
             .map_err(TaClientError::HttpClientError)
     }
 
+    async fn delete(
+        &self,
+        path: &str,
+    ) -> Result<TrustAnchorClientApiResponse, TaClientError> {
+        let uri = self.resolve_uri(path);
+        httpclient::delete(&uri, Some(&self.token))
+            .await
+            .map(|_| TrustAnchorClientApiResponse::Empty)
+            .map_err(TaClientError::HttpClientError)
+    }
+
     async fn get_json<T: DeserializeOwned>(
         &self,
         path: &str,
@@ -1361,6 +1708,21 @@ impl TrustAnchorSignerManager {
         self.show_last_response()
     }
 
+    fn process_dry_run(
+        &self,
+        signed_request: TrustAnchorSignedRequest,
+        ta_mft_number_override: Option<u64>,
+    ) -> Result<TrustAnchorClientApiResponse, TaClientError> {
+        let diff = self.get_signer()?.dry_run_signer_request(
+            signed_request,
+            self.config.timing_config,
+            ta_mft_number_override,
+            &self.signer,
+        )?;
+
+        Ok(TrustAnchorClientApiResponse::SignerResponseDiff(diff))
+    }
+
     fn show_last_response(
         &self,
     ) -> Result<TrustAnchorClientApiResponse, TaClientError> {
@@ -1393,6 +1755,53 @@ impl TrustAnchorSignerManager {
         ))
     }
 
+    fn key_roll_init(
+        &self,
+        tal_https: Vec<uri::Https>,
+        tal_rsync: uri::Rsync,
+    ) -> Result<TrustAnchorClientApiResponse, TaClientError> {
+        let cmd = TrustAnchorSignerCommand::make_key_roll_init_command(
+            &self.ta_handle,
+            tal_https,
+            tal_rsync,
+            self.config.timing_config,
+            self.signer.clone(),
+            &self.actor,
+        );
+        self.store.command(cmd)?;
+
+        self.key_roll_show()
+    }
+
+    fn key_roll_show(
+        &self,
+    ) -> Result<TrustAnchorClientApiResponse, TaClientError> {
+        self.get_signer()?
+            .get_key_roll()
+            .map(|key_roll| {
+                TrustAnchorClientApiResponse::KeyRoll(key_roll.clone())
+            })
+            .ok_or_else(|| {
+                TaClientError::KrillError(KrillError::TaKeyRollNotInProgress)
+            })
+    }
+
+    fn reissue(
+        &self,
+        request: TaCertReissueRequest,
+    ) -> Result<TrustAnchorClientApiResponse, TaClientError> {
+        let cmd = TrustAnchorSignerCommand::make_reissue_ta_cert_command(
+            &self.ta_handle,
+            request,
+            self.config.timing_config,
+            self.signer.clone(),
+            &self.actor,
+        );
+        self.store.command(cmd)?;
+
+        self.show()
+    }
+
     fn get_signer(&self) -> Result<Arc<TrustAnchorSigner>, TaClientError> {
         if self.store.has(&self.ta_handle)? {
             self.store
@@ -689,13 +689,24 @@ impl fmt::Display for UpdateChildRequest {
 pub struct ServerInfo {
     version: String,
     started: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    node: Option<String>,
+    #[serde(default)]
+    role: NodeRole,
 }
 
 impl ServerInfo {
-    pub fn new(version: &str, started: Timestamp) -> Self {
+    pub fn new(
+        version: &str,
+        started: Timestamp,
+        node: Option<String>,
+        role: NodeRole,
+    ) -> Self {
         ServerInfo {
             version: version.to_string(),
             started,
+            node,
+            role,
         }
     }
 
@@ -706,6 +717,14 @@ impl ServerInfo {
     pub fn started(&self) -> Timestamp {
         self.started
     }
+
+    pub fn node(&self) -> Option<&String> {
+        self.node.as_ref()
+    }
+
+    pub fn role(&self) -> NodeRole {
+        self.role
+    }
 }
 
 impl fmt::Display for ServerInfo {
@@ -715,7 +734,49 @@ impl fmt::Display for ServerInfo {
             "Version: {}\nStarted: {}",
             self.version(),
             self.started.to_rfc3339()
-        )
+        )?;
+        if let Some(node) = &self.node {
+            write!(f, "\nNode: {}\nRole: {}", node, self.role)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ NodeRole ------------------------------------------------------
+
+/// The role of a Krill node in an active/passive set up.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeRole {
+    /// High availability is not configured, this is the only node.
+    #[default]
+    Single,
+
+    /// This node holds the lease, it runs background tasks and
+    /// accepts updates.
+    Leader,
+
+    /// Another node holds the lease, this node only serves read-only
+    /// requests.
+    Standby,
+}
+
+impl NodeRole {
+    /// Returns whether this node may run tasks and write to storage.
+    pub fn is_active(self) -> bool {
+        !matches!(self, NodeRole::Standby)
+    }
+}
+
+impl fmt::Display for NodeRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NodeRole::Single => write!(f, "single"),
+            NodeRole::Leader => write!(f, "leader"),
+            NodeRole::Standby => write!(f, "standby"),
+        }
     }
 }
 
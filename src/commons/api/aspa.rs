@@ -63,6 +63,10 @@ impl AspaDefinitionList {
     pub fn new(definitions: Vec<AspaDefinition>) -> Self {
         AspaDefinitionList(definitions)
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
 }
 
 impl fmt::Display for AspaDefinitionList {
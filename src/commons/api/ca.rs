@@ -56,7 +56,7 @@ use crate::{
     daemon::ca::RoaPayloadJsonMapKey,
 };
 
-use super::{rrdp, BgpSecAsnKey};
+use super::{rrdp, BgpSecAsnKey, GhostbusterName};
 
 //------------ IdCertInfo ----------------------------------------------------
 
@@ -607,6 +607,10 @@ impl ObjectName {
             format!("ROUTER-{:08X}-{}.cer", asn.into_u32(), key).into(),
         )
     }
+
+    pub fn ghostbuster(name: &GhostbusterName) -> Self {
+        ObjectName(format!("{}.gbr", name).into())
+    }
 }
 
 impl From<&Cert> for ObjectName {
@@ -1632,7 +1636,16 @@ impl ChildExchange {
 /// A wrapper for unix timestamps with second precision, with some convenient
 /// stuff.
 #[derive(
-    Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize,
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
 )]
 pub struct Timestamp(i64);
 
@@ -1752,6 +1765,57 @@ impl ops::SubAssign<Duration> for Timestamp {
     }
 }
 
+//------------ KeyRollPolicy -------------------------------------------------
+
+/// Describes when a CA should automatically roll its keys.
+///
+/// A key roll is initiated for a resource class when its current key has
+/// been in use for longer than `roll_after_days`. The new key is activated
+/// once it has been certified for at least `activate_after_hours`, i.e. the
+/// staging period described in RFC 6489, which should be at least 24 hours.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct KeyRollPolicy {
+    roll_after_days: u32,
+    activate_after_hours: u32,
+}
+
+impl KeyRollPolicy {
+    pub fn new(roll_after_days: u32, activate_after_hours: u32) -> Self {
+        KeyRollPolicy {
+            roll_after_days,
+            activate_after_hours,
+        }
+    }
+
+    pub fn roll_after_days(&self) -> u32 {
+        self.roll_after_days
+    }
+
+    pub fn activate_after_hours(&self) -> u32 {
+        self.activate_after_hours
+    }
+
+    /// The maximum age of a current key before it should be rolled.
+    pub fn roll_after(&self) -> Duration {
+        Duration::days(self.roll_after_days.into())
+    }
+
+    /// The staging period for a new key before it should be activated.
+    pub fn activate_after(&self) -> Duration {
+        Duration::hours(self.activate_after_hours.into())
+    }
+}
+
+impl fmt::Display for KeyRollPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "roll keys after {} days, activate new keys after {} hours",
+            self.roll_after_days, self.activate_after_hours
+        )
+    }
+}
+
 //------------ CertAuthInfo --------------------------------------------------
 
 /// This type represents the details of a CertAuth that need
@@ -1766,9 +1830,14 @@ pub struct CertAuthInfo {
     resource_classes: HashMap<ResourceClassName, ResourceClassInfo>,
     children: Vec<ChildHandle>,
     suspended_children: Vec<ChildHandle>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    keyroll_policy: Option<KeyRollPolicy>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    next_keyroll: Option<Timestamp>,
 }
 
 impl CertAuthInfo {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         handle: CaHandle,
         id_cert: IdCertInfo,
@@ -1777,6 +1846,8 @@ impl CertAuthInfo {
         resource_classes: HashMap<ResourceClassName, ResourceClassInfo>,
         children: Vec<ChildHandle>,
         suspended_children: Vec<ChildHandle>,
+        keyroll_policy: Option<KeyRollPolicy>,
+        next_keyroll: Option<Timestamp>,
     ) -> Self {
         let parents = parents.into_keys().map(ParentInfo::new).collect();
 
@@ -1798,6 +1869,8 @@ impl CertAuthInfo {
             resource_classes,
             children,
             suspended_children,
+            keyroll_policy,
+            next_keyroll,
         }
     }
 
@@ -1834,6 +1907,15 @@ impl CertAuthInfo {
     pub fn suspended_children(&self) -> &Vec<ChildHandle> {
         &self.suspended_children
     }
+
+    pub fn keyroll_policy(&self) -> Option<&KeyRollPolicy> {
+        self.keyroll_policy.as_ref()
+    }
+
+    /// The time at which the next automatic key roll is planned, if any.
+    pub fn next_keyroll(&self) -> Option<Timestamp> {
+        self.next_keyroll
+    }
 }
 
 impl fmt::Display for CertAuthInfo {
@@ -1886,6 +1968,17 @@ impl fmt::Display for CertAuthInfo {
             writeln!(f, "{}", rc.keys())?;
         }
 
+        match self.keyroll_policy() {
+            Some(policy) => {
+                writeln!(f, "Key roll policy: {}", policy)?;
+                if let Some(next) = self.next_keyroll() {
+                    writeln!(f, "Next key roll:   {}", next.to_rfc3339())?;
+                }
+            }
+            None => writeln!(f, "Key roll policy: <none>")?,
+        }
+        writeln!(f)?;
+
         writeln!(f, "Children:")?;
         if !self.children().is_empty() {
             for child_handle in self.children() {
@@ -2205,6 +2298,8 @@ pub struct CertAuthStats {
     roa_count: usize,
     child_count: usize,
     bgp_stats: BgpStats,
+    #[serde(default)]
+    key_ages: HashMap<ResourceClassName, i64>,
 }
 
 impl CertAuthStats {
@@ -2212,11 +2307,13 @@ impl CertAuthStats {
         roa_count: usize,
         child_count: usize,
         bgp_stats: BgpStats,
+        key_ages: HashMap<ResourceClassName, i64>,
     ) -> Self {
         CertAuthStats {
             roa_count,
             child_count,
             bgp_stats,
+            key_ages,
         }
     }
 
@@ -2231,6 +2328,11 @@ impl CertAuthStats {
     pub fn bgp_stats(&self) -> &BgpStats {
         &self.bgp_stats
     }
+
+    /// The age in seconds of the current key in each resource class.
+    pub fn key_ages(&self) -> &HashMap<ResourceClassName, i64> {
+        &self.key_ages
+    }
 }
 
 //------------ BgpStats ------------------------------------------------------
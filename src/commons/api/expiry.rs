@@ -0,0 +1,373 @@
+//! Reporting on the expiry of signed objects.
+//!
+//! Objects which are not re-issued in time, e.g. because a parent or the
+//! repository is unreachable, will become invalid. This report lists all
+//! objects signed by Krill CAs and the Trust Anchor, with the times at
+//! which they expire, so that this can be monitored.
+
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use chrono::Duration;
+use rpki::{ca::idexchange::CaHandle, repository::x509::Time, uri};
+
+use super::ObjectName;
+
+//------------ ExpiryWindow ------------------------------------------------
+
+/// A period of time from now, used to select the objects that will expire
+/// within it. Expressed as a number followed by a unit: 's' (seconds), 'm'
+/// (minutes), 'h' (hours), 'd' (days) or 'w' (weeks), e.g. "7d".
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ExpiryWindow {
+    number: i64,
+    unit: char,
+}
+
+impl ExpiryWindow {
+    // Keeps the resulting time well within the supported range.
+    const MAX_NUMBER: i64 = 1_000_000;
+
+    pub fn duration(&self) -> Duration {
+        match self.unit {
+            's' => Duration::seconds(self.number),
+            'm' => Duration::minutes(self.number),
+            'h' => Duration::hours(self.number),
+            'd' => Duration::days(self.number),
+            _ => Duration::weeks(self.number),
+        }
+    }
+
+    /// Returns the time at the end of this window, starting now.
+    pub fn end(&self) -> Time {
+        Time::now() + self.duration()
+    }
+}
+
+impl FromStr for ExpiryWindow {
+    type Err = ExpiryWindowFmtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unit = s
+            .chars()
+            .last()
+            .filter(|c| ['s', 'm', 'h', 'd', 'w'].contains(c))
+            .ok_or_else(|| ExpiryWindowFmtError(s.to_string()))?;
+
+        let number = i64::from_str(&s[..s.len() - 1])
+            .ok()
+            .filter(|n| (0..=Self::MAX_NUMBER).contains(n))
+            .ok_or_else(|| ExpiryWindowFmtError(s.to_string()))?;
+
+        Ok(ExpiryWindow { number, unit })
+    }
+}
+
+impl fmt::Display for ExpiryWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.number, self.unit)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExpiryWindowFmtError(String);
+
+impl std::error::Error for ExpiryWindowFmtError {}
+
+impl fmt::Display for ExpiryWindowFmtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid period '{}'. Use a number followed by s, m, h, d or w, e.g. '7d'",
+            self.0
+        )
+    }
+}
+
+//------------ ExpiringObjectType ------------------------------------------
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Deserialize,
+    Eq,
+    Hash,
+    Ord,
+    PartialEq,
+    PartialOrd,
+    Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiringObjectType {
+    /// The TA certificate.
+    TaCert,
+    /// A certificate received by a CA from its parent.
+    ReceivedCert,
+    Manifest,
+    Crl,
+    Roa,
+    Aspa,
+    BgpsecCert,
+    Ghostbuster,
+    /// A certificate issued to a child CA.
+    ChildCert,
+    /// A signed TAL, published by the TA during a key roll.
+    SignedTal,
+    Other,
+}
+
+impl ExpiringObjectType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ExpiringObjectType::TaCert => "ta_cert",
+            ExpiringObjectType::ReceivedCert => "received_cert",
+            ExpiringObjectType::Manifest => "manifest",
+            ExpiringObjectType::Crl => "crl",
+            ExpiringObjectType::Roa => "roa",
+            ExpiringObjectType::Aspa => "aspa",
+            ExpiringObjectType::BgpsecCert => "bgpsec_cert",
+            ExpiringObjectType::Ghostbuster => "ghostbuster",
+            ExpiringObjectType::ChildCert => "child_cert",
+            ExpiringObjectType::SignedTal => "signed_tal",
+            ExpiringObjectType::Other => "other",
+        }
+    }
+}
+
+/// Derives the type of a published object from its name. We use the file
+/// name extensions, and the name prefix that we use for BGPsec router
+/// certificates to tell them apart from certificates issued to children.
+impl From<&ObjectName> for ExpiringObjectType {
+    fn from(name: &ObjectName) -> Self {
+        let name: &str = name.as_ref();
+        if name.ends_with(".mft") {
+            ExpiringObjectType::Manifest
+        } else if name.ends_with(".crl") {
+            ExpiringObjectType::Crl
+        } else if name.ends_with(".roa") {
+            ExpiringObjectType::Roa
+        } else if name.ends_with(".asa") {
+            ExpiringObjectType::Aspa
+        } else if name.ends_with(".gbr") {
+            ExpiringObjectType::Ghostbuster
+        } else if name.ends_with(".tak") {
+            ExpiringObjectType::SignedTal
+        } else if name.ends_with(".cer") && name.starts_with("ROUTER-") {
+            ExpiringObjectType::BgpsecCert
+        } else if name.ends_with(".cer") {
+            ExpiringObjectType::ChildCert
+        } else {
+            ExpiringObjectType::Other
+        }
+    }
+}
+
+impl fmt::Display for ExpiringObjectType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+//------------ ExpiringObject ----------------------------------------------
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ExpiringObject {
+    pub ca: CaHandle,
+    pub object_type: ExpiringObjectType,
+    pub uri: uri::Rsync,
+    pub not_after: Time,
+    /// The next update time, for manifests and CRLs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_update: Option<Time>,
+}
+
+impl ExpiringObject {
+    pub fn new(
+        ca: CaHandle,
+        object_type: ExpiringObjectType,
+        uri: uri::Rsync,
+        not_after: Time,
+        next_update: Option<Time>,
+    ) -> Self {
+        ExpiringObject {
+            ca,
+            object_type,
+            uri,
+            not_after,
+            next_update,
+        }
+    }
+
+    /// Returns the first moment that this object needs to be replaced:
+    /// its next update time if it has one, or else its not after time.
+    pub fn expires(&self) -> Time {
+        match self.next_update {
+            Some(next_update) if next_update < self.not_after => next_update,
+            _ => self.not_after,
+        }
+    }
+}
+
+//------------ ObjectExpiryReport ------------------------------------------
+
+/// Lists signed objects, ordered by the time that they expire.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ObjectExpiryReport {
+    objects: Vec<ExpiringObject>,
+}
+
+impl ObjectExpiryReport {
+    pub fn add(&mut self, object: ExpiringObject) {
+        self.objects.push(object);
+    }
+
+    pub fn objects(&self) -> &Vec<ExpiringObject> {
+        &self.objects
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Only keep the objects which expire before the end of the window.
+    pub fn retain_within(&mut self, window: ExpiryWindow) {
+        let end = window.end();
+        self.objects.retain(|object| object.expires() < end);
+    }
+
+    /// Returns the earliest not after time, and the earliest next update
+    /// time if any, of the objects of each CA by object type. This keeps
+    /// the number of metrics independent of the number of objects.
+    pub fn earliest(
+        &self,
+    ) -> BTreeMap<(String, ExpiringObjectType), (Time, Option<Time>)> {
+        let mut earliest: BTreeMap<_, (Time, Option<Time>)> = BTreeMap::new();
+        for object in &self.objects {
+            let key = (object.ca.to_string(), object.object_type);
+            let entry = earliest
+                .entry(key)
+                .or_insert((object.not_after, object.next_update));
+            entry.0 = entry.0.min(object.not_after);
+            entry.1 = match (entry.1, object.next_update) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+        }
+        earliest
+    }
+
+    /// Orders the objects by the time they expire, earliest first.
+    pub fn sort(&mut self) {
+        self.objects.sort_by(|a, b| {
+            a.expires()
+                .cmp(&b.expires())
+                .then_with(|| a.ca.as_str().cmp(b.ca.as_str()))
+                .then_with(|| a.uri.as_str().cmp(b.uri.as_str()))
+        });
+    }
+}
+
+impl fmt::Display for ObjectExpiryReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.objects.is_empty() {
+            writeln!(f, "no expiring objects found")
+        } else {
+            for object in &self.objects {
+                write!(
+                    f,
+                    "{} {} {} not after: {}",
+                    object.ca,
+                    object.object_type,
+                    object.uri,
+                    object.not_after.to_rfc3339()
+                )?;
+                if let Some(next_update) = object.next_update {
+                    write!(f, " next update: {}", next_update.to_rfc3339())?;
+                }
+                writeln!(f)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+//------------ Tests --------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_expiry_window() {
+        let window = ExpiryWindow::from_str("7d").unwrap();
+        assert_eq!(window.duration(), Duration::days(7));
+        assert_eq!(window.to_string(), "7d");
+
+        assert_eq!(
+            ExpiryWindow::from_str("36h").unwrap().duration(),
+            Duration::hours(36)
+        );
+
+        assert!(ExpiryWindow::from_str("").is_err());
+        assert!(ExpiryWindow::from_str("7").is_err());
+        assert!(ExpiryWindow::from_str("d").is_err());
+        assert!(ExpiryWindow::from_str("-1d").is_err());
+        assert!(ExpiryWindow::from_str("7y").is_err());
+    }
+
+    #[test]
+    fn earliest_per_ca_and_type() {
+        fn time(secs: i64) -> Time {
+            Time::from(crate::commons::api::Timestamp::new(secs))
+        }
+        let ca = CaHandle::from_str("CA").unwrap();
+        let object =
+            |name: &str, not_after: i64, next_update: Option<i64>| {
+                ExpiringObject::new(
+                    ca.clone(),
+                    ExpiringObjectType::from(&ObjectName::from(name)),
+                    uri::Rsync::from_str(&format!(
+                        "rsync://localhost/repo/{}",
+                        name
+                    ))
+                    .unwrap(),
+                    time(not_after),
+                    next_update.map(time),
+                )
+            };
+
+        let mut report = ObjectExpiryReport::default();
+        report.add(object("AS1.roa", 300, None));
+        report.add(object("AS2.roa", 200, None));
+        report.add(object("AS3.asa", 400, None));
+        report.add(object("0.mft", 500, Some(100)));
+
+        let earliest = report.earliest();
+        assert_eq!(earliest.len(), 3);
+        assert_eq!(
+            earliest[&("CA".to_string(), ExpiringObjectType::Roa)],
+            (time(200), None)
+        );
+        assert_eq!(
+            earliest[&("CA".to_string(), ExpiringObjectType::Manifest)],
+            (time(500), Some(time(100)))
+        );
+    }
+
+    #[test]
+    fn object_type_from_name() {
+        let check = |name: &str, expected: ExpiringObjectType| {
+            assert_eq!(
+                ExpiringObjectType::from(&ObjectName::from(name)),
+                expected
+            )
+        };
+
+        check("AS65000.roa", ExpiringObjectType::Roa);
+        check("AS65000.asa", ExpiringObjectType::Aspa);
+        check("abuse.gbr", ExpiringObjectType::Ghostbuster);
+        check("ROUTER-0000FDE8-ABCD.cer", ExpiringObjectType::BgpsecCert);
+        check("ABCD.cer", ExpiringObjectType::ChildCert);
+        check("ABCD.tak", ExpiringObjectType::SignedTal);
+    }
+}
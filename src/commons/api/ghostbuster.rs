@@ -0,0 +1,409 @@
+//! Ghostbuster records, see RFC 6493.
+//!
+//! A Ghostbuster record is a signed object containing a vCard with contact
+//! details for the operator of a CA. It helps relying party operators find
+//! out who to contact when they see problems with objects published by the
+//! CA.
+
+use std::{fmt, str::FromStr};
+
+use bytes::Bytes;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::ObjectName;
+
+//------------ GhostbusterName ---------------------------------------------
+
+/// The name of a Ghostbuster record in a CA.
+///
+/// A CA may publish multiple Ghostbuster records, e.g. one for its NOC and
+/// one for abuse reports. The name is used to identify the record in the
+/// API and CLI, and to derive the file name for the published object. So
+/// we only allow alphanumeric characters, '-' and '_'.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct GhostbusterName(String);
+
+impl GhostbusterName {
+    const MAX_LEN: usize = 64;
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for GhostbusterName {
+    type Err = GhostbusterNameFmtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty()
+            || s.len() > Self::MAX_LEN
+            || !s
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            Err(GhostbusterNameFmtError(s.to_string()))
+        } else {
+            Ok(GhostbusterName(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for GhostbusterName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// We use GhostbusterName as (JSON) map keys, and we want to make sure
+/// that names are always validated when deserialized.
+impl Serialize for GhostbusterName {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for GhostbusterName {
+    fn deserialize<D>(d: D) -> Result<GhostbusterName, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(d)?;
+        GhostbusterName::from_str(&string).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GhostbusterNameFmtError(String);
+
+impl std::error::Error for GhostbusterNameFmtError {}
+
+impl fmt::Display for GhostbusterNameFmtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid Ghostbuster name '{}'. Use 1 to {} alphanumeric characters, '-' or '_'",
+            self.0,
+            GhostbusterName::MAX_LEN
+        )
+    }
+}
+
+//------------ GhostbusterDefinition ---------------------------------------
+
+/// The contact details for a Ghostbuster record.
+///
+/// These map onto the vCard properties described in section 5 of RFC 6493.
+/// The full name (FN) is required, and at least one of the address (ADR),
+/// telephone (TEL) or email (EMAIL) properties must be present.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GhostbusterDefinition {
+    name: GhostbusterName,
+    full_name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    org: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    address: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    tel: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    email: Option<String>,
+}
+
+impl GhostbusterDefinition {
+    pub fn new(
+        name: GhostbusterName,
+        full_name: String,
+        org: Option<String>,
+        address: Option<String>,
+        tel: Option<String>,
+        email: Option<String>,
+    ) -> Self {
+        GhostbusterDefinition {
+            name,
+            full_name,
+            org,
+            address,
+            tel,
+            email,
+        }
+    }
+
+    pub fn name(&self) -> &GhostbusterName {
+        &self.name
+    }
+
+    pub fn full_name(&self) -> &str {
+        &self.full_name
+    }
+
+    pub fn org(&self) -> Option<&str> {
+        self.org.as_deref()
+    }
+
+    pub fn address(&self) -> Option<&str> {
+        self.address.as_deref()
+    }
+
+    pub fn tel(&self) -> Option<&str> {
+        self.tel.as_deref()
+    }
+
+    pub fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+
+    pub fn object_name(&self) -> ObjectName {
+        ObjectName::ghostbuster(&self.name)
+    }
+
+    /// Verifies that this definition can be used for a Ghostbuster record.
+    /// Returns a message describing the problem if it cannot.
+    pub fn verify(&self) -> Result<(), String> {
+        if self.full_name.trim().is_empty() {
+            return Err("the full name must not be empty".to_string());
+        }
+
+        let is_set =
+            |v: &Option<String>| v.iter().any(|v| !v.trim().is_empty());
+
+        if !is_set(&self.address)
+            && !is_set(&self.tel)
+            && !is_set(&self.email)
+        {
+            return Err(
+                "at least one of address, telephone or email is required"
+                    .to_string(),
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Returns the vCard (version 4.0) content for the Ghostbuster record
+    /// as described in section 5 of RFC 6493.
+    pub fn to_vcard(&self) -> Bytes {
+        let mut vcard = String::new();
+
+        push_vcard_line(&mut vcard, "BEGIN:VCARD");
+        push_vcard_line(&mut vcard, "VERSION:4.0");
+        push_vcard_line(
+            &mut vcard,
+            &format!("FN:{}", vcard_escape(&self.full_name)),
+        );
+        if let Some(org) = &self.org {
+            push_vcard_line(
+                &mut vcard,
+                &format!("ORG:{}", vcard_escape(org)),
+            );
+        }
+        if let Some(address) = &self.address {
+            // ADR is a structured property, we put the full address in
+            // the street address component.
+            push_vcard_line(
+                &mut vcard,
+                &format!("ADR:;;{};;;;", vcard_escape(address)),
+            );
+        }
+        if let Some(tel) = &self.tel {
+            push_vcard_line(
+                &mut vcard,
+                &format!("TEL:{}", vcard_escape(tel)),
+            );
+        }
+        if let Some(email) = &self.email {
+            push_vcard_line(
+                &mut vcard,
+                &format!("EMAIL:{}", vcard_escape(email)),
+            );
+        }
+        push_vcard_line(&mut vcard, "END:VCARD");
+
+        Bytes::from(vcard)
+    }
+}
+
+impl fmt::Display for GhostbusterDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.full_name)?;
+        if let Some(org) = &self.org {
+            write!(f, ", org: {}", org)?;
+        }
+        if let Some(address) = &self.address {
+            write!(f, ", address: {}", address)?;
+        }
+        if let Some(tel) = &self.tel {
+            write!(f, ", tel: {}", tel)?;
+        }
+        if let Some(email) = &self.email {
+            write!(f, ", email: {}", email)?;
+        }
+        Ok(())
+    }
+}
+
+/// Escapes a text value as described in section 3.4 of RFC 6350.
+fn vcard_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ',' => escaped.push_str("\\,"),
+            ';' => escaped.push_str("\\;"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Adds a content line to the vCard. Lines are folded so that they do not
+/// exceed 75 octets, and terminated with CRLF as described in section 3.2
+/// of RFC 6350.
+fn push_vcard_line(vcard: &mut String, line: &str) {
+    const MAX_OCTETS: usize = 75;
+
+    let mut line_len = 0;
+    for c in line.chars() {
+        if line_len + c.len_utf8() > MAX_OCTETS {
+            vcard.push_str("\r\n ");
+            line_len = 1;
+        }
+        vcard.push(c);
+        line_len += c.len_utf8();
+    }
+    vcard.push_str("\r\n");
+}
+
+//------------ GhostbusterDefinitionUpdates --------------------------------
+
+/// Contains Ghostbuster definition updates sent to the API.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GhostbusterDefinitionUpdates {
+    add_or_replace: Vec<GhostbusterDefinition>,
+    remove: Vec<GhostbusterName>,
+}
+
+impl GhostbusterDefinitionUpdates {
+    pub fn new(
+        add_or_replace: Vec<GhostbusterDefinition>,
+        remove: Vec<GhostbusterName>,
+    ) -> Self {
+        GhostbusterDefinitionUpdates {
+            add_or_replace,
+            remove,
+        }
+    }
+
+    pub fn unpack(
+        self,
+    ) -> (Vec<GhostbusterDefinition>, Vec<GhostbusterName>) {
+        (self.add_or_replace, self.remove)
+    }
+}
+
+impl fmt::Display for GhostbusterDefinitionUpdates {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Update Ghostbuster definitions:")?;
+        if !self.add_or_replace.is_empty() {
+            write!(f, " add or replace:")?;
+            for definition in &self.add_or_replace {
+                write!(f, " {}", definition.name())?;
+            }
+        }
+        if !self.remove.is_empty() {
+            write!(f, " remove:")?;
+            for name in &self.remove {
+                write!(f, " {}", name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//------------ GhostbusterDefinitionList -----------------------------------
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GhostbusterDefinitionList(Vec<GhostbusterDefinition>);
+
+impl GhostbusterDefinitionList {
+    pub fn new(definitions: Vec<GhostbusterDefinition>) -> Self {
+        GhostbusterDefinitionList(definitions)
+    }
+
+    pub fn unpack(self) -> Vec<GhostbusterDefinition> {
+        self.0
+    }
+}
+
+impl fmt::Display for GhostbusterDefinitionList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for def in self.0.iter() {
+            writeln!(f, "{}", def)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(address: Option<&str>) -> GhostbusterDefinition {
+        GhostbusterDefinition::new(
+            GhostbusterName::from_str("noc").unwrap(),
+            "Example NOC".to_string(),
+            Some("Example, Inc.".to_string()),
+            address.map(|s| s.to_string()),
+            None,
+            Some("noc@example.com".to_string()),
+        )
+    }
+
+    #[test]
+    fn ghostbuster_name_from_str() {
+        assert!(GhostbusterName::from_str("noc-1_a").is_ok());
+        assert!(GhostbusterName::from_str("").is_err());
+        assert!(GhostbusterName::from_str("../noc").is_err());
+        assert!(GhostbusterName::from_str("noc.gbr").is_err());
+    }
+
+    #[test]
+    fn ghostbuster_vcard() {
+        let vcard = definition(Some("Main Street 1\nAmsterdam")).to_vcard();
+        let expected = "BEGIN:VCARD\r\n\
+                        VERSION:4.0\r\n\
+                        FN:Example NOC\r\n\
+                        ORG:Example\\, Inc.\r\n\
+                        ADR:;;Main Street 1\\nAmsterdam;;;;\r\n\
+                        EMAIL:noc@example.com\r\n\
+                        END:VCARD\r\n";
+        assert_eq!(expected.as_bytes(), vcard.as_ref());
+    }
+
+    #[test]
+    fn ghostbuster_vcard_folds_long_lines() {
+        let long = "a".repeat(200);
+        let vcard = definition(Some(&long)).to_vcard();
+        let vcard = std::str::from_utf8(vcard.as_ref()).unwrap();
+        assert!(vcard.split("\r\n").all(|line| line.len() <= 75));
+    }
+
+    #[test]
+    fn ghostbuster_verify() {
+        assert!(definition(None).verify().is_ok());
+
+        let no_contact = GhostbusterDefinition::new(
+            GhostbusterName::from_str("noc").unwrap(),
+            "Example NOC".to_string(),
+            None,
+            None,
+            None,
+            None,
+        );
+        assert!(no_contact.verify().is_err());
+    }
+}
@@ -1,4 +1,8 @@
-use std::{collections::BTreeMap, fmt};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+};
 
 use chrono::{DateTime, SecondsFormat};
 
@@ -14,13 +18,16 @@ use rpki::{
     crypto::KeyIdentifier,
     repository::{resources::ResourceSet, x509::Time},
     rrdp::Hash,
+    uri,
 };
 
 use crate::{
     commons::{
         api::{
-            ArgKey, ArgVal, AspaProvidersUpdate, CustomerAsn, Message,
-            RoaConfigurationUpdates, RtaName, StorableParentContact,
+            ArgKey, ArgVal, AspaDefinitionList, AspaProvidersUpdate,
+            CertAuthInfo, ChildCaInfo, ConfiguredRoa, CustomerAsn,
+            IssuedCertificate, Message, RoaConfigurationUpdates, RtaName,
+            StorableParentContact, Timestamp,
         },
         eventsourcing::{
             Event, InitEvent, StoredCommand, StoredEffect,
@@ -31,7 +38,8 @@ use crate::{
 };
 
 use super::{
-    AspaDefinitionUpdates, ResourceClassNameMapping, ResourceSetSummary,
+    AspaDefinitionUpdates, GhostbusterDefinitionUpdates, KeyRollPolicy,
+    ResourceClassNameMapping, ResourceSetSummary,
 };
 
 //------------ CommandHistory ------------------------------------------------
@@ -41,6 +49,8 @@ pub struct CommandHistory {
     offset: usize,
     total: usize,
     commands: Vec<CommandHistoryRecord>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    archived: Option<ArchivedCommands>,
 }
 
 impl CommandHistory {
@@ -53,9 +63,18 @@ impl CommandHistory {
             offset,
             total,
             commands,
+            archived: None,
         }
     }
 
+    pub fn with_archived(
+        mut self,
+        archived: Option<ArchivedCommands>,
+    ) -> Self {
+        self.archived = archived;
+        self
+    }
+
     pub fn offset(&self) -> usize {
         self.offset
     }
@@ -67,10 +86,20 @@ impl CommandHistory {
     pub fn commands(&self) -> &Vec<CommandHistoryRecord> {
         &self.commands
     }
+
+    /// Returns a summary of older commands that were archived, and which
+    /// are not included in this history.
+    pub fn archived(&self) -> Option<&ArchivedCommands> {
+        self.archived.as_ref()
+    }
 }
 
 impl fmt::Display for CommandHistory {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(archived) = &self.archived {
+            writeln!(f, "{}", archived)?;
+        }
+
         writeln!(f, "time::command::version::success")?;
 
         for command in self.commands() {
@@ -95,6 +124,51 @@ impl fmt::Display for CommandHistory {
     }
 }
 
+//------------ ArchivedCommands ----------------------------------------------
+
+/// A summary of the commands for an aggregate that were moved to the
+/// archive. These are the oldest commands, following the init command.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ArchivedCommands {
+    count: u64,
+    last_version: u64,
+    last_time: Timestamp,
+}
+
+impl ArchivedCommands {
+    pub fn new(count: u64, last_version: u64, last_time: Timestamp) -> Self {
+        ArchivedCommands {
+            count,
+            last_version,
+            last_time,
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn last_version(&self) -> u64 {
+        self.last_version
+    }
+
+    pub fn last_time(&self) -> Timestamp {
+        self.last_time
+    }
+}
+
+impl fmt::Display for ArchivedCommands {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} older commands up to version {} ({}) were archived",
+            self.count,
+            self.last_version,
+            self.last_time.to_rfc3339()
+        )
+    }
+}
+
 //------------ CommandHistoryRecord ------------------------------------------
 
 /// A description of a command that was processed, and the events / or error
@@ -353,6 +427,567 @@ impl Default for CommandHistoryCriteria {
     }
 }
 
+//------------ AuditCriteria -------------------------------------------------
+
+/// Used to select the commands to include in the audit log. Unlike
+/// [`CommandHistoryCriteria`] this is not limited to a single aggregate.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AuditCriteria {
+    pub actor: Option<String>,
+    pub after: Option<Time>,
+    pub before: Option<Time>,
+    pub command: Option<String>,
+    pub handle: Option<MyHandle>,
+}
+
+impl AuditCriteria {
+    pub fn matches(&self, record: &CommandHistoryRecord) -> bool {
+        self.actor
+            .as_ref()
+            .map_or(true, |actor| &record.actor == actor)
+            && self.after.map_or(true, |after| {
+                record.timestamp >= after.timestamp_millis()
+            })
+            && self.before.map_or(true, |before| {
+                record.timestamp <= before.timestamp_millis()
+            })
+            && self
+                .command
+                .as_ref()
+                .map_or(true, |label| &record.summary.label == label)
+            && self.matches_handle(&record.handle)
+    }
+
+    pub fn matches_handle(&self, handle: &MyHandle) -> bool {
+        self.handle.as_ref().map_or(true, |h| h == handle)
+    }
+}
+
+//------------ AuditSource ---------------------------------------------------
+
+/// The kind of aggregate that a command in the audit log was sent to.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSource {
+    Ca,
+    Pubd,
+    TaProxy,
+}
+
+impl fmt::Display for AuditSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuditSource::Ca => write!(f, "ca"),
+            AuditSource::Pubd => write!(f, "pubd"),
+            AuditSource::TaProxy => write!(f, "ta_proxy"),
+        }
+    }
+}
+
+//------------ AuditRecord ---------------------------------------------------
+
+/// A command in the audit log, i.e. a [`CommandHistoryRecord`] together
+/// with the kind of aggregate that it was sent to.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct AuditRecord {
+    pub time: Time,
+    pub source: AuditSource,
+    pub handle: MyHandle,
+    pub version: u64,
+    pub actor: String,
+    pub command: String,
+    pub message: Message,
+    pub args: BTreeMap<ArgKey, ArgVal>,
+    pub result: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub error: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new(source: AuditSource, record: CommandHistoryRecord) -> Self {
+        let time = record.time();
+        let (result, error) = match record.effect {
+            CommandHistoryResult::Init() => ("init", None),
+            CommandHistoryResult::Ok() => ("ok", None),
+            CommandHistoryResult::Error(msg) => ("error", Some(msg)),
+        };
+
+        AuditRecord {
+            time,
+            source,
+            handle: record.handle,
+            version: record.version,
+            actor: record.actor,
+            command: record.summary.label,
+            message: record.summary.msg,
+            args: record.summary.args,
+            result: result.to_string(),
+            error,
+        }
+    }
+
+    const CSV_HEADER: &'static str =
+        "time,source,handle,version,actor,command,message,result,error";
+
+    fn csv_line(&self) -> String {
+        [
+            self.time.to_rfc3339_opts(SecondsFormat::Secs, true),
+            self.source.to_string(),
+            self.handle.to_string(),
+            self.version.to_string(),
+            self.actor.clone(),
+            self.command.clone(),
+            self.message.clone(),
+            self.result.clone(),
+            self.error.clone().unwrap_or_default(),
+        ]
+        .iter()
+        .map(|field| Self::csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+
+    /// Quotes a CSV field if needed, as described in RFC 4180.
+    fn csv_field(field: &str) -> String {
+        if field.contains([',', '"', '\r', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+}
+
+//------------ AuditLog ------------------------------------------------------
+
+/// The commands for all aggregates in chronological order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AuditLog {
+    records: Vec<AuditRecord>,
+}
+
+impl AuditLog {
+    pub fn add(
+        &mut self,
+        source: AuditSource,
+        records: Vec<CommandHistoryRecord>,
+    ) {
+        self.records.extend(
+            records
+                .into_iter()
+                .map(|record| AuditRecord::new(source, record)),
+        );
+    }
+
+    /// Orders the records by time, and then by aggregate and version so
+    /// that commands processed in the same second keep their order.
+    pub fn sort(&mut self) {
+        self.records.sort_by(|a, b| {
+            (a.time, a.source, a.handle.as_str(), a.version).cmp(&(
+                b.time,
+                b.source,
+                b.handle.as_str(),
+                b.version,
+            ))
+        });
+    }
+
+    pub fn records(&self) -> &Vec<AuditRecord> {
+        &self.records
+    }
+
+    pub fn to_format(&self, format: AuditFormat) -> String {
+        let mut res = String::new();
+        match format {
+            AuditFormat::JsonLines => {
+                for record in &self.records {
+                    // Cannot fail, all fields serialize to json.
+                    res.push_str(&serde_json::to_string(record).unwrap());
+                    res.push('\n');
+                }
+            }
+            AuditFormat::Csv => {
+                res.push_str(AuditRecord::CSV_HEADER);
+                res.push_str("\r\n");
+                for record in &self.records {
+                    res.push_str(&record.csv_line());
+                    res.push_str("\r\n");
+                }
+            }
+        }
+        res
+    }
+}
+
+//------------ AuditFormat ---------------------------------------------------
+
+/// The format in which the audit log is exported.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum AuditFormat {
+    /// One JSON object per line, see https://jsonlines.org/
+    #[default]
+    JsonLines,
+    Csv,
+}
+
+impl FromStr for AuditFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(AuditFormat::JsonLines),
+            "csv" => Ok(AuditFormat::Csv),
+            _ => Err(format!("expected 'jsonl' or 'csv', got: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for AuditFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuditFormat::JsonLines => write!(f, "jsonl"),
+            AuditFormat::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+//------------ CommandDryRun -------------------------------------------------
+
+/// The outcome of processing one or more commands without storing them.
+///
+/// Contains the events that the commands would produce, and the changes to
+/// certificates and published objects that would follow from them.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct CommandDryRun {
+    /// The version that the commands were processed against.
+    version: u64,
+
+    events: Vec<DryRunEvent>,
+
+    /// Certificates that would be issued to children.
+    issued: Vec<DryRunCertificate>,
+
+    /// Keys of child certificates that would be revoked.
+    revoked: Vec<KeyIdentifier>,
+
+    /// Keys that would be revoked by parents.
+    revocation_requests: Vec<RevocationRequest>,
+
+    /// Objects that would be published, or updated.
+    published: Vec<uri::Rsync>,
+
+    /// Objects that would be withdrawn.
+    withdrawn: Vec<uri::Rsync>,
+}
+
+impl CommandDryRun {
+    pub fn new(version: u64) -> Self {
+        CommandDryRun {
+            version,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_event<E: Event>(&mut self, event: &E) {
+        self.events.push(DryRunEvent {
+            msg: event.to_string(),
+            details: serde_json::to_value(event).unwrap_or_default(),
+        });
+    }
+
+    pub fn add_issued(&mut self, cert: &IssuedCertificate) {
+        self.issued.push(DryRunCertificate {
+            key: cert.key_identifier(),
+            uri: cert.uri().clone(),
+            resources: cert.resources().clone(),
+        });
+    }
+
+    pub fn add_revoked(&mut self, key: KeyIdentifier) {
+        self.revoked.push(key);
+    }
+
+    pub fn add_revocation_request(&mut self, request: RevocationRequest) {
+        self.revocation_requests.push(request);
+    }
+
+    pub fn add_published(&mut self, uri: uri::Rsync) {
+        self.published.push(uri);
+    }
+
+    pub fn add_withdrawn(&mut self, uri: uri::Rsync) {
+        self.withdrawn.push(uri);
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn events(&self) -> &Vec<DryRunEvent> {
+        &self.events
+    }
+
+    pub fn issued(&self) -> &Vec<DryRunCertificate> {
+        &self.issued
+    }
+
+    pub fn revoked(&self) -> &Vec<KeyIdentifier> {
+        &self.revoked
+    }
+
+    pub fn revocation_requests(&self) -> &Vec<RevocationRequest> {
+        &self.revocation_requests
+    }
+
+    pub fn published(&self) -> &Vec<uri::Rsync> {
+        &self.published
+    }
+
+    pub fn withdrawn(&self) -> &Vec<uri::Rsync> {
+        &self.withdrawn
+    }
+}
+
+impl fmt::Display for CommandDryRun {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Version: {}", self.version)?;
+
+        if self.events.is_empty() {
+            return writeln!(f, "No changes.");
+        }
+
+        writeln!(f, "Events:")?;
+        for event in &self.events {
+            writeln!(f, "  {}", event.msg)?;
+        }
+
+        if !self.issued.is_empty() {
+            writeln!(f, "Certificates issued:")?;
+            for cert in &self.issued {
+                writeln!(
+                    f,
+                    "  {} {} {}",
+                    cert.key, cert.uri, cert.resources
+                )?;
+            }
+        }
+        if !self.revoked.is_empty() {
+            writeln!(f, "Certificates revoked:")?;
+            for key in &self.revoked {
+                writeln!(f, "  {}", key)?;
+            }
+        }
+        if !self.revocation_requests.is_empty() {
+            writeln!(f, "Revocations requested from parents:")?;
+            for req in &self.revocation_requests {
+                writeln!(f, "  {} {}", req.class_name(), req.key())?;
+            }
+        }
+        if !self.published.is_empty() {
+            writeln!(f, "Objects published:")?;
+            for uri in &self.published {
+                writeln!(f, "  {}", uri)?;
+            }
+        }
+        if !self.withdrawn.is_empty() {
+            writeln!(f, "Objects withdrawn:")?;
+            for uri in &self.withdrawn {
+                writeln!(f, "  {}", uri)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+//------------ DryRunEvent ---------------------------------------------------
+
+/// An event that would be produced by a command in a dry run.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DryRunEvent {
+    msg: String,
+    details: serde_json::Value,
+}
+
+impl DryRunEvent {
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn details(&self) -> &serde_json::Value {
+        &self.details
+    }
+}
+
+//------------ DryRunCertificate ---------------------------------------------
+
+/// A certificate that would be issued to a child in a dry run.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct DryRunCertificate {
+    key: KeyIdentifier,
+    uri: uri::Rsync,
+    resources: ResourceSet,
+}
+
+impl DryRunCertificate {
+    pub fn key(&self) -> KeyIdentifier {
+        self.key
+    }
+
+    pub fn uri(&self) -> &uri::Rsync {
+        &self.uri
+    }
+
+    pub fn resources(&self) -> &ResourceSet {
+        &self.resources
+    }
+}
+
+//------------ PointInTime ---------------------------------------------------
+
+/// A point in the history of a CA, either a version of the CA or a time.
+///
+/// Versions are given as plain numbers, times in RFC 3339 format, e.g.
+/// 2020-04-09T19:37:02Z.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PointInTime {
+    Version(u64),
+    Time(Time),
+}
+
+impl FromStr for PointInTime {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(version) = u64::from_str(s) {
+            Ok(PointInTime::Version(version))
+        } else {
+            Time::from_str(s).map(PointInTime::Time).map_err(|_| {
+                format!(
+                    "expected a version number or an RFC 3339 date/time, got: {}",
+                    s
+                )
+            })
+        }
+    }
+}
+
+impl fmt::Display for PointInTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PointInTime::Version(version) => write!(f, "{}", version),
+            PointInTime::Time(time) => write!(
+                f,
+                "{}",
+                time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+            ),
+        }
+    }
+}
+
+//------------ HistoricCertAuthInfo ------------------------------------------
+
+/// The details of a CA as they were at a point in its history.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct HistoricCertAuthInfo {
+    /// The version of the CA at this point.
+    version: u64,
+
+    /// The time of the last command that was applied.
+    time: Timestamp,
+
+    info: CertAuthInfo,
+    roas: Vec<ConfiguredRoa>,
+    aspas: AspaDefinitionList,
+    children: HashMap<ChildHandle, ChildCaInfo>,
+}
+
+impl HistoricCertAuthInfo {
+    pub fn new(
+        version: u64,
+        time: Timestamp,
+        info: CertAuthInfo,
+        roas: Vec<ConfiguredRoa>,
+        aspas: AspaDefinitionList,
+        children: HashMap<ChildHandle, ChildCaInfo>,
+    ) -> Self {
+        HistoricCertAuthInfo {
+            version,
+            time,
+            info,
+            roas,
+            aspas,
+            children,
+        }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn time(&self) -> Timestamp {
+        self.time
+    }
+
+    pub fn info(&self) -> &CertAuthInfo {
+        &self.info
+    }
+
+    pub fn roas(&self) -> &Vec<ConfiguredRoa> {
+        &self.roas
+    }
+
+    pub fn aspas(&self) -> &AspaDefinitionList {
+        &self.aspas
+    }
+
+    pub fn children(&self) -> &HashMap<ChildHandle, ChildCaInfo> {
+        &self.children
+    }
+}
+
+impl fmt::Display for HistoricCertAuthInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Version:  {}", self.version)?;
+        writeln!(f, "Time:     {}", self.time.to_rfc3339())?;
+        write!(f, "{}", self.info)?;
+        writeln!(f)?;
+
+        writeln!(f, "ROAs:")?;
+        if self.roas.is_empty() {
+            writeln!(f, "<none>")?;
+        } else {
+            for roa in &self.roas {
+                writeln!(f, "{}", roa)?;
+            }
+        }
+        writeln!(f)?;
+
+        writeln!(f, "ASPAs:")?;
+        if self.aspas.is_empty() {
+            writeln!(f, "<none>")?;
+        } else {
+            write!(f, "{}", self.aspas)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "Child details:")?;
+        if self.children.is_empty() {
+            writeln!(f, "<none>")?;
+        } else {
+            let mut children: Vec<_> = self.children.iter().collect();
+            children.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+            for (child, info) in children {
+                writeln!(f, "{}:", child)?;
+                write!(f, "{}", info)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
 //------------ CaCommandDetails ----------------------------------------------
 pub type CaCommandDetails = StoredCommand<CertAuth>;
 
@@ -464,9 +1099,16 @@ pub enum CertAuthStorableCommand {
     KeyRollActivate {
         staged_for_seconds: i64,
     },
+    KeyRollEmergency {
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        resource_class_name: Option<ResourceClassName>,
+    },
     KeyRollFinish {
         resource_class_name: ResourceClassName,
     },
+    KeyRollPolicyUpdate {
+        policy: Option<KeyRollPolicy>,
+    },
     RoaDefinitionUpdates {
         updates: RoaConfigurationUpdates,
     },
@@ -483,6 +1125,9 @@ pub enum CertAuthStorableCommand {
         customer: CustomerAsn,
     },
     BgpSecDefinitionUpdates, // details in events
+    GhostbustersUpdate {
+        updates: GhostbusterDefinitionUpdates,
+    },
     RepoUpdate {
         service_uri: ServiceUri,
     },
@@ -496,6 +1141,9 @@ pub enum CertAuthStorableCommand {
         name: RtaName,
     },
     Deactivate,
+    CommandRevert {
+        version: u64,
+    },
 }
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -598,6 +1246,17 @@ impl WithStorableDetails for CertAuthStorableCommand {
             CertAuthStorableCommand::KeyRollFinish { resource_class_name } => {
                 CommandSummary::new("cmd-ca-keyroll-finish", self).with_rcn(resource_class_name)
             }
+            CertAuthStorableCommand::KeyRollEmergency { resource_class_name } => {
+                let summary = CommandSummary::new("cmd-ca-keyroll-emergency", self);
+                match resource_class_name {
+                    Some(rcn) => summary.with_rcn(rcn),
+                    None => summary,
+                }
+            }
+
+            CertAuthStorableCommand::KeyRollPolicyUpdate { .. } => {
+                CommandSummary::new("cmd-ca-keyroll-policy-update", self)
+            }
 
             // ROA
             CertAuthStorableCommand::RoaDefinitionUpdates { updates } => {
@@ -616,6 +1275,11 @@ impl WithStorableDetails for CertAuthStorableCommand {
             // BGPSec
             CertAuthStorableCommand::BgpSecDefinitionUpdates => CommandSummary::new("cmd-bgpsec-update", self),
 
+            // Ghostbusters
+            CertAuthStorableCommand::GhostbustersUpdate { .. } => {
+                CommandSummary::new("cmd-ca-ghostbusters-update", self)
+            }
+
             // REPO
             CertAuthStorableCommand::RepoUpdate { service_uri } => {
                 CommandSummary::new("cmd-ca-repo-update", self).with_service_uri(service_uri)
@@ -639,6 +1303,11 @@ impl WithStorableDetails for CertAuthStorableCommand {
 
             // Deactivation
             CertAuthStorableCommand::Deactivate => CommandSummary::new("cmd-ca-deactivate", self),
+
+            // History
+            CertAuthStorableCommand::CommandRevert { version } => {
+                CommandSummary::new("cmd-ca-revert", self).with_arg("version", version)
+            }
         }
     }
 
@@ -771,6 +1440,15 @@ impl fmt::Display for CertAuthStorableCommand {
             CertAuthStorableCommand::KeyRollFinish { resource_class_name } => {
                 write!(f, "Retire old revoked key in RC '{}'", resource_class_name)
             }
+            CertAuthStorableCommand::KeyRollEmergency { resource_class_name } => match resource_class_name {
+                Some(rcn) => write!(f, "Emergency key roll for compromised key in RC '{}'", rcn),
+                None => write!(f, "Emergency key roll for compromised keys in all RCs"),
+            },
+
+            CertAuthStorableCommand::KeyRollPolicyUpdate { policy } => match policy {
+                Some(policy) => write!(f, "Set key roll policy: {}", policy),
+                None => write!(f, "Remove key roll policy"),
+            },
 
             // ------------------------------------------------------------
             // ROA Support
@@ -816,6 +1494,13 @@ impl fmt::Display for CertAuthStorableCommand {
             // ------------------------------------------------------------
             CertAuthStorableCommand::BgpSecDefinitionUpdates => write!(f, "Update BGPSec definitions"),
 
+            // ------------------------------------------------------------
+            // Ghostbusters
+            // ------------------------------------------------------------
+            CertAuthStorableCommand::GhostbustersUpdate { updates } => {
+                write!(f, "{}", updates)
+            }
+
             // ------------------------------------------------------------
             // Publishing
             // ------------------------------------------------------------
@@ -834,6 +1519,13 @@ impl fmt::Display for CertAuthStorableCommand {
             // Deactivate
             // ------------------------------------------------------------
             CertAuthStorableCommand::Deactivate => write!(f, "Deactivate CA"),
+
+            // ------------------------------------------------------------
+            // History
+            // ------------------------------------------------------------
+            CertAuthStorableCommand::CommandRevert { version } => {
+                write!(f, "Revert command {}", version)
+            }
         }
     }
 }
@@ -12,6 +12,12 @@ pub use self::bgpsec::*;
 mod ca;
 pub use self::ca::*;
 
+mod expiry;
+pub use self::expiry::*;
+
+mod ghostbuster;
+pub use self::ghostbuster::*;
+
 mod history;
 pub use self::history::*;
 
@@ -22,6 +28,9 @@ pub use self::roas::*;
 
 pub mod rrdp;
 
+mod webhooks;
+pub use self::webhooks::*;
+
 use std::{collections::HashMap, fmt};
 
 use rpki::ca::csr::BgpsecCsr;
@@ -82,6 +91,10 @@ impl ErrorResponse {
         self.with_arg("cause", cause)
     }
 
+    pub fn with_param(self, param: &str) -> Self {
+        self.with_arg("param", param)
+    }
+
     pub fn with_publisher(self, publisher: &PublisherHandle) -> Self {
         self.with_arg("publisher", publisher)
     }
@@ -118,6 +131,10 @@ impl ErrorResponse {
         res
     }
 
+    pub fn with_prefix(self, prefix: &TypedPrefix) -> Self {
+        self.with_arg("prefix", prefix)
+    }
+
     pub fn with_asn(self, asn: Asn) -> Self {
         self.with_arg("asn", asn)
     }
@@ -127,6 +144,14 @@ impl ErrorResponse {
         self.with_arg("bgpsec_csr", base64)
     }
 
+    pub fn with_ghostbuster(self, name: &GhostbusterName) -> Self {
+        self.with_arg("ghostbuster", name)
+    }
+
+    pub fn with_webhook(self, name: &WebhookName) -> Self {
+        self.with_arg("webhook", name)
+    }
+
     pub fn with_roa_delta_error(
         mut self,
         roa_delta_error: &RoaDeltaError,
@@ -0,0 +1,346 @@
+//! Webhooks used to notify external systems of CA events and issues.
+
+use std::{fmt, str::FromStr};
+
+use rpki::ca::idexchange::CaHandle;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use url::Url;
+
+use super::Timestamp;
+
+//------------ WebhookName -------------------------------------------------
+
+/// The name used to refer to a webhook. This name is used in the API and
+/// CLI, and to store the webhook. So we only allow alphanumeric
+/// characters, '-' and '_'.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct WebhookName(String);
+
+impl WebhookName {
+    const MAX_LEN: usize = 64;
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for WebhookName {
+    type Err = WebhookNameFmtError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty()
+            || s.len() > Self::MAX_LEN
+            || !s
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            Err(WebhookNameFmtError(s.to_string()))
+        } else {
+            Ok(WebhookName(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for WebhookName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Serialize for WebhookName {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(s)
+    }
+}
+
+impl<'de> Deserialize<'de> for WebhookName {
+    fn deserialize<D>(d: D) -> Result<WebhookName, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let string = String::deserialize(d)?;
+        WebhookName::from_str(&string).map_err(de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct WebhookNameFmtError(String);
+
+impl std::error::Error for WebhookNameFmtError {}
+
+impl fmt::Display for WebhookNameFmtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid webhook name '{}'. Use 1 to {} alphanumeric characters, '-' or '_'",
+            self.0,
+            WebhookName::MAX_LEN
+        )
+    }
+}
+
+//------------ WebhookEventType --------------------------------------------
+
+/// The types of notifications that can be sent to webhooks.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventType {
+    /// A CA could not synchronise with its parent. This is reported once
+    /// when the issue appears in the CA issues report.
+    ParentSyncFailed,
+    /// A CA could not synchronise with its repository. This is reported
+    /// once when the issue appears in the CA issues report.
+    RepoSyncFailed,
+    /// A child of a CA was suspended because it was inactive.
+    ChildSuspended,
+    /// A key roll was started for a CA, either by an operator or because
+    /// of its key roll policy.
+    KeyRollStarted,
+    /// A key roll was finished for a CA, i.e. the old key was revoked and
+    /// removed.
+    KeyRollFinished,
+    /// The ROAs of a CA were updated, e.g. because ROA configurations were
+    /// added or removed, or because the resources of the CA changed.
+    RoasUpdated,
+    /// Sent on request, to test the webhook.
+    Test,
+}
+
+impl WebhookEventType {
+    pub fn all() -> &'static [WebhookEventType] {
+        &[
+            WebhookEventType::ParentSyncFailed,
+            WebhookEventType::RepoSyncFailed,
+            WebhookEventType::ChildSuspended,
+            WebhookEventType::KeyRollStarted,
+            WebhookEventType::KeyRollFinished,
+            WebhookEventType::RoasUpdated,
+            WebhookEventType::Test,
+        ]
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventType::ParentSyncFailed => "parent_sync_failed",
+            WebhookEventType::RepoSyncFailed => "repo_sync_failed",
+            WebhookEventType::ChildSuspended => "child_suspended",
+            WebhookEventType::KeyRollStarted => "key_roll_started",
+            WebhookEventType::KeyRollFinished => "key_roll_finished",
+            WebhookEventType::RoasUpdated => "roas_updated",
+            WebhookEventType::Test => "test",
+        }
+    }
+}
+
+impl FromStr for WebhookEventType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        WebhookEventType::all()
+            .iter()
+            .find(|t| t.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("Unknown webhook event type '{}'", s))
+    }
+}
+
+impl fmt::Display for WebhookEventType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+//------------ WebhookDefinition -------------------------------------------
+
+/// A webhook that notifications are POSTed to. The notifications are
+/// signed using HMAC-SHA256 with the shared secret, so that the receiver
+/// can verify that they came from this Krill instance.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WebhookDefinition {
+    name: WebhookName,
+    url: Url,
+    secret: String,
+
+    /// The event types to notify, all types if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    events: Vec<WebhookEventType>,
+}
+
+impl WebhookDefinition {
+    pub fn new(
+        name: WebhookName,
+        url: Url,
+        secret: String,
+        events: Vec<WebhookEventType>,
+    ) -> Self {
+        WebhookDefinition {
+            name,
+            url,
+            secret,
+            events,
+        }
+    }
+
+    pub fn name(&self) -> &WebhookName {
+        &self.name
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn secret(&self) -> &str {
+        &self.secret
+    }
+
+    pub fn events(&self) -> &Vec<WebhookEventType> {
+        &self.events
+    }
+
+    /// Returns whether notifications of this type should be sent to this
+    /// webhook. Test notifications are sent to a webhook explicitly.
+    pub fn wants(&self, event: WebhookEventType) -> bool {
+        event != WebhookEventType::Test
+            && (self.events.is_empty() || self.events.contains(&event))
+    }
+
+    /// Verifies that this definition can be used.
+    pub fn verify(&self) -> Result<(), String> {
+        if self.url.scheme() != "http" && self.url.scheme() != "https" {
+            Err(format!("URL '{}' must use http or https", self.url))
+        } else if self.secret.is_empty() {
+            Err("the secret must not be empty".to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns the details of this webhook that can be shown to users.
+    pub fn info(&self) -> WebhookInfo {
+        WebhookInfo {
+            name: self.name.clone(),
+            url: self.url.clone(),
+            events: self.events.clone(),
+        }
+    }
+}
+
+//------------ WebhookInfo -------------------------------------------------
+
+/// The details of a webhook, without its secret.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WebhookInfo {
+    pub name: WebhookName,
+    pub url: Url,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<WebhookEventType>,
+}
+
+impl fmt::Display for WebhookInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} events: ", self.name, self.url)?;
+        if self.events.is_empty() {
+            write!(f, "all")
+        } else {
+            let events: Vec<_> =
+                self.events.iter().map(|e| e.as_str()).collect();
+            write!(f, "{}", events.join(", "))
+        }
+    }
+}
+
+//------------ WebhookList -------------------------------------------------
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct WebhookList(Vec<WebhookInfo>);
+
+impl WebhookList {
+    pub fn new(webhooks: Vec<WebhookInfo>) -> Self {
+        WebhookList(webhooks)
+    }
+
+    pub fn unpack(self) -> Vec<WebhookInfo> {
+        self.0
+    }
+}
+
+impl fmt::Display for WebhookList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            writeln!(f, "no webhooks configured")
+        } else {
+            for webhook in &self.0 {
+                writeln!(f, "{}", webhook)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+//------------ WebhookNotification -----------------------------------------
+
+/// The JSON body POSTed to webhooks.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct WebhookNotification {
+    /// Unique id, so that receivers can recognise retried deliveries.
+    pub id: String,
+    pub timestamp: Timestamp,
+    pub event: WebhookEventType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca: Option<CaHandle>,
+    pub summary: String,
+}
+
+impl WebhookNotification {
+    pub fn new(
+        event: WebhookEventType,
+        ca: Option<CaHandle>,
+        summary: String,
+    ) -> Self {
+        WebhookNotification {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Timestamp::now(),
+            event,
+            ca,
+            summary,
+        }
+    }
+}
+
+//------------ Tests --------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn webhook_wants_event() {
+        let definition = |events: Vec<WebhookEventType>| {
+            WebhookDefinition::new(
+                WebhookName::from_str("ops").unwrap(),
+                Url::parse("https://example.com/hook").unwrap(),
+                "secret".to_string(),
+                events,
+            )
+        };
+
+        let all = definition(vec![]);
+        assert!(all.wants(WebhookEventType::ParentSyncFailed));
+        assert!(all.wants(WebhookEventType::RoasUpdated));
+        assert!(!all.wants(WebhookEventType::Test));
+
+        let roas = definition(vec![WebhookEventType::RoasUpdated]);
+        assert!(roas.wants(WebhookEventType::RoasUpdated));
+        assert!(!roas.wants(WebhookEventType::ChildSuspended));
+
+        for event in WebhookEventType::all() {
+            assert_eq!(
+                WebhookEventType::from_str(&event.to_string()).unwrap(),
+                *event
+            );
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::{sync::Arc, time::Duration};
 
+use bcder::{ConstOid, Oid};
 use bytes::Bytes;
 use rpki::{
     ca::{
@@ -20,7 +21,7 @@ use rpki::{
         manifest::ManifestContent,
         roa::RoaBuilder,
         rta,
-        sigobj::SignedObjectBuilder,
+        sigobj::{SignedObject, SignedObjectBuilder},
         x509::{Serial, Time, Validity},
         Cert, Crl, Manifest, Roa,
     },
@@ -55,6 +56,16 @@ use crate::commons::crypto::{
     SignerHandle,
 };
 
+/// The content type for Ghostbuster records, see section 9.1 of RFC 6493.
+/// This is not (yet) defined in the rpki crate.
+const CT_GHOSTBUSTERS: ConstOid =
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 35]);
+
+/// The content type for signed TALs (TAK objects), see RFC 9691.
+/// This is not (yet) defined in the rpki crate.
+const CT_SIGNED_TAL: ConstOid =
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 50]);
+
 /// High level signing interface between Krill and the [SignerRouter].
 ///
 /// KrillSigner:
@@ -365,6 +376,38 @@ impl KrillSigner {
             .map_err(crypto::Error::signing)
     }
 
+    pub fn sign_ghostbuster(
+        &self,
+        vcard: Bytes,
+        object_builder: SignedObjectBuilder,
+        key_id: &KeyIdentifier,
+    ) -> CryptoResult<SignedObject> {
+        object_builder
+            .finalize(
+                Oid(Bytes::from_static(CT_GHOSTBUSTERS.0)),
+                vcard,
+                &self.router,
+                key_id,
+            )
+            .map_err(crypto::Error::signing)
+    }
+
+    pub fn sign_tak(
+        &self,
+        tak: Bytes,
+        object_builder: SignedObjectBuilder,
+        key_id: &KeyIdentifier,
+    ) -> CryptoResult<SignedObject> {
+        object_builder
+            .finalize(
+                Oid(Bytes::from_static(CT_SIGNED_TAL.0)),
+                tak,
+                &self.router,
+                key_id,
+            )
+            .map_err(crypto::Error::signing)
+    }
+
     pub fn sign_rta(
         &self,
         rta_builder: &mut rta::RtaBuilder,
@@ -20,11 +20,11 @@ use crate::{
     commons::{
         api::{
             rrdp::PublicationDeltaError, CustomerAsn, ErrorResponse,
-            RoaPayload,
+            RoaPayload, TypedPrefix,
         },
         crypto::SignerError,
         eventsourcing::{AggregateStoreError, KeyValueError},
-        util::httpclient,
+        util::{httpclient, KrillVersion},
     },
     daemon::{ca::RoaPayloadJsonMapKey, http::tls_keys},
     ta,
@@ -32,7 +32,10 @@ use crate::{
 };
 
 use super::{
-    api::{BgpSecAsnKey, BgpSecDefinition, RoaConfiguration},
+    api::{
+        BgpSecAsnKey, BgpSecDefinition, GhostbusterName, PointInTime,
+        RoaConfiguration, WebhookName,
+    },
     eventsourcing::WalStoreError,
 };
 
@@ -210,6 +213,9 @@ pub enum Error {
     ApiUnknownResource,
     ApiInvalidHandle,
     ApiInvalidSeconds,
+    ApiInvalidQueryParam(String, String),
+    ApiInvalidIfMatch(String),
+    ApiWeakIfMatch(String),
     PostTooBig,
     PostCannotRead,
     ApiInvalidCredentials(String),
@@ -253,6 +259,8 @@ pub enum Error {
     //-----------------------------------------------------------------
     CaDuplicate(CaHandle),
     CaUnknown(CaHandle),
+    CaUnknownAt(CaHandle, PointInTime),
+    CaCommandNotRevertible(CaHandle, u64),
 
     // CA Repo Issues
     CaRepoInUse(CaHandle),
@@ -317,6 +325,42 @@ pub enum Error {
     BgpSecDefinitionInvalidlySigned(CaHandle, BgpSecDefinition, String),
     BgpSecDefinitionNotEntitled(CaHandle, BgpSecAsnKey),
 
+    //-----------------------------------------------------------------
+    // Ghostbusters
+    //-----------------------------------------------------------------
+    GhostbusterUnknown(CaHandle, GhostbusterName),
+    GhostbusterInvalid(CaHandle, GhostbusterName, String),
+
+    //-----------------------------------------------------------------
+    // Webhooks
+    //-----------------------------------------------------------------
+    WebhookUnknown(WebhookName),
+    WebhookDuplicate(WebhookName),
+    WebhookInvalid(WebhookName, String),
+
+    //-----------------------------------------------------------------
+    // Backup and Restore
+    //-----------------------------------------------------------------
+    BackupInvalid(String),
+    BackupTooNew(KrillVersion),
+    RestoreTargetNotEmpty(String),
+
+    //-----------------------------------------------------------------
+    // High Availability
+    //-----------------------------------------------------------------
+    NodeIsStandby(Option<String>),
+
+    //-----------------------------------------------------------------
+    // Storage Encryption
+    //-----------------------------------------------------------------
+    StorageEncryption(String),
+
+    //-----------------------------------------------------------------
+    // Task Queue
+    //-----------------------------------------------------------------
+    TaskNotRunnable(String),
+    TaskRunInvalid(String),
+
     //-----------------------------------------------------------------
     // Key Usage Issues
     //-----------------------------------------------------------------
@@ -328,6 +372,7 @@ pub enum Error {
     KeyUseNoMatch(KeyIdentifier),
     KeyRollInProgress,
     KeyRollActivatePendingRequests,
+    KeyRollPolicyInvalid(CaHandle, String),
 
     //-----------------------------------------------------------------
     // Resource Issues
@@ -350,12 +395,28 @@ pub enum Error {
     TaProxyHasNoRequest,
     TaProxyHasRequest,
     TaProxyRequestNonceMismatch(ta::Nonce, ta::Nonce),
+    TaKeyRollInProgress,
+    TaKeyRollNotInProgress,
+    TaKeyRollUrisReused,
+    TaProxyChildRemovalPending(ChildHandle),
 
     //-----------------------------------------------------------------
     // Resource Tagged Attestation issues
     //-----------------------------------------------------------------
     RtaResourcesNotHeld,
 
+    //-----------------------------------------------------------------
+    // RPKI Signed Checklist issues
+    //-----------------------------------------------------------------
+    RscResourcesNotHeld(CaHandle),
+    RscInvalid(CaHandle, String),
+
+    //-----------------------------------------------------------------
+    // Geofeed authentication issues
+    //-----------------------------------------------------------------
+    GeofeedPrefixNotHeld(CaHandle, TypedPrefix),
+    GeofeedInvalid(CaHandle, String),
+
     //-----------------------------------------------------------------
     // If we really don't know any more..
     //-----------------------------------------------------------------
@@ -371,6 +432,7 @@ impl fmt::Display for Error {
             //-----------------------------------------------------------------
             Error::IoError(e) => write!(f, "I/O error: {}", e),
             Error::KeyValueError(e) => write!(f, "Key/Value error: {}", e),
+            Error::AggregateStoreError(e @ AggregateStoreError::UnexpectedVersion(..)) => e.fmt(f),
             Error::AggregateStoreError(e) => write!(f, "Persistence (aggregate store) error: {}", e),
             Error::WalStoreError(e) => write!(f, "Persistence (wal store) error: {}", e),
             Error::SignerError(e) => write!(f, "Signing issue: {}", e),
@@ -388,6 +450,9 @@ impl fmt::Display for Error {
             Error::ApiUnknownResource => write!(f, "Unknown resource"),
             Error::ApiInvalidHandle => write!(f, "Invalid path argument for handle"),
             Error::ApiInvalidSeconds => write!(f, "Invalid path argument for seconds"),
+            Error::ApiInvalidQueryParam(param, e) => write!(f, "Invalid query parameter '{}': {}", param, e),
+            Error::ApiInvalidIfMatch(value) => write!(f, "Invalid If-Match header '{}', expected a version ETag", value),
+            Error::ApiWeakIfMatch(value) => write!(f, "Weak ETag in If-Match header '{}', only strong ETags can be matched", value),
             Error::PostTooBig => write!(f, "POST body exceeds configured limit"),
             Error::PostCannotRead => write!(f, "POST body cannot be read"),
             Error::ApiInvalidCredentials(e) => write!(f, "Invalid credentials: {}", e),
@@ -433,6 +498,11 @@ impl fmt::Display for Error {
             //-----------------------------------------------------------------
             Error::CaDuplicate(ca) => write!(f, "CA '{}' was already initialized", ca),
             Error::CaUnknown(ca) => write!(f, "CA '{}' is unknown", ca),
+            Error::CaUnknownAt(ca, point) => match point {
+                PointInTime::Version(version) => write!(f, "CA '{}' has no version {}", ca, version),
+                PointInTime::Time(_) => write!(f, "CA '{}' did not exist at {}", ca, point),
+            },
+            Error::CaCommandNotRevertible(ca, version) => write!(f, "Command {} of CA '{}' cannot be reverted, only successful ROA, ASPA and BGPSec definition updates can be reverted", version, ca),
 
             // CA Repo Issues
             Error::CaRepoInUse(ca) => write!(f, "CA '{}' already uses this repository", ca),
@@ -507,6 +577,43 @@ impl fmt::Display for Error {
             Error::BgpSecDefinitionInvalidlySigned(_ca, def, msg) => write!(f, "Invalidly signed BGPSec CSR remove BGPSec CSR for ASN '{}' and key '{}', error: {}", def.asn(), def.csr().public_key().key_identifier(), msg),
             Error::BgpSecDefinitionNotEntitled(_ca, key) => write!(f, "AS '{}' is not held by you", key.asn()),
 
+            //-----------------------------------------------------------------
+            // Ghostbusters
+            //-----------------------------------------------------------------
+            Error::GhostbusterUnknown(_ca, name) => write!(f, "No Ghostbuster record exists with name '{}'", name),
+            Error::GhostbusterInvalid(_ca, name, msg) => write!(f, "Invalid Ghostbuster record '{}': {}", name, msg),
+
+            //-----------------------------------------------------------------
+            // Webhooks
+            //-----------------------------------------------------------------
+            Error::WebhookUnknown(name) => write!(f, "Unknown webhook '{}'", name),
+            Error::WebhookDuplicate(name) => write!(f, "Duplicate webhook '{}'", name),
+            Error::WebhookInvalid(name, msg) => write!(f, "Invalid webhook '{}': {}", name, msg),
+
+            //-----------------------------------------------------------------
+            // Backup and Restore
+            //-----------------------------------------------------------------
+            Error::BackupInvalid(msg) => write!(f, "Invalid backup: {}", msg),
+            Error::BackupTooNew(version) => write!(f, "Backup was made with Krill version {}, which is newer than this version {}", version, KrillVersion::code_version()),
+            Error::RestoreTargetNotEmpty(msg) => write!(f, "Cannot restore into storage that is in use, {}", msg),
+
+            //-----------------------------------------------------------------
+            // High Availability
+            //-----------------------------------------------------------------
+            Error::NodeIsStandby(None) => write!(f, "This node is a standby and only serves read-only requests"),
+            Error::NodeIsStandby(Some(leader)) => write!(f, "This node is a standby and only serves read-only requests, the leader is node '{}'", leader),
+
+            //-----------------------------------------------------------------
+            // Storage Encryption
+            //-----------------------------------------------------------------
+            Error::StorageEncryption(msg) => write!(f, "Storage encryption error: {}", msg),
+
+            //-----------------------------------------------------------------
+            // Task Queue
+            //-----------------------------------------------------------------
+            Error::TaskNotRunnable(task) => write!(f, "Task '{}' cannot be triggered manually", task),
+            Error::TaskRunInvalid(msg) => write!(f, "Cannot run task: {}", msg),
+
 
             //-----------------------------------------------------------------
             // Key Usage Issues
@@ -519,6 +626,7 @@ impl fmt::Display for Error {
             Error::KeyUseNoMatch(ki) => write!(f, "No key found matching key identifier: '{}'", ki),
             Error::KeyRollInProgress => write!(f, "Key roll in progress"),
             Error::KeyRollActivatePendingRequests => write!(f, "Cannot activate key while there are still pending requests."),
+            Error::KeyRollPolicyInvalid(_ca, msg) => write!(f, "Invalid key roll policy: {}", msg),
 
             //-----------------------------------------------------------------
             // Resource Issues
@@ -542,12 +650,28 @@ impl fmt::Display for Error {
             Error::TaProxyHasNoRequest => write!(f, "Trust Anchor Proxy has no signer request"),
             Error::TaProxyHasRequest => write!(f, "Trust Anchor Proxy already has signer request"),
             Error::TaProxyRequestNonceMismatch(rcvd, expected) => write!(f, "Trust Anchor Response nonce '{}' does not match open Request nonce '{}'", rcvd, expected),
+            Error::TaKeyRollInProgress => write!(f, "Trust Anchor Signer already has a key roll in progress"),
+            Error::TaKeyRollNotInProgress => write!(f, "Trust Anchor Signer has no key roll in progress"),
+            Error::TaKeyRollUrisReused => write!(f, "The successor Trust Anchor certificate must use different URIs than the current certificate"),
+            Error::TaProxyChildRemovalPending(child) => write!(f, "Trust Anchor Proxy child '{}' is being removed", child),
 
             //-----------------------------------------------------------------
             // Resource Tagged Attestation issues
             //-----------------------------------------------------------------
             Error::RtaResourcesNotHeld => write!(f, "Your CA does not hold the requested resources"),
 
+            //-----------------------------------------------------------------
+            // RPKI Signed Checklist issues
+            //-----------------------------------------------------------------
+            Error::RscResourcesNotHeld(_ca) => write!(f, "Your CA does not hold the requested resources"),
+            Error::RscInvalid(_ca, msg) => write!(f, "Cannot sign RSC: {}", msg),
+
+            //-----------------------------------------------------------------
+            // Geofeed authentication issues
+            //-----------------------------------------------------------------
+            Error::GeofeedPrefixNotHeld(_ca, prefix) => write!(f, "Prefix '{}' in geofeed not held by you", prefix),
+            Error::GeofeedInvalid(_ca, msg) => write!(f, "Cannot sign geofeed: {}", msg),
+
             //-----------------------------------------------------------------
             // If we really don't know any more..
             //-----------------------------------------------------------------
@@ -697,15 +821,22 @@ impl Error {
         match self {
             // Most is bad requests by users, so just mapping the things that
             // are not
+            Error::AggregateStoreError(
+                AggregateStoreError::UnexpectedVersion(_, _, _),
+            )
+            | Error::ApiWeakIfMatch(_) => StatusCode::PRECONDITION_FAILED,
             Error::IoError(_)
             | Error::SignerError(_)
             | Error::AggregateStoreError(_)
             | Error::WalStoreError(_)
-            | Error::PublishingObjects(_) => {
+            | Error::PublishingObjects(_)
+            | Error::StorageEncryption(_) => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
             Error::PublisherUnknown(_)
+            | Error::WebhookUnknown(_)
             | Error::CaUnknown(_)
+            | Error::CaUnknownAt(_, _)
             | Error::CaChildUnknown(_, _)
             | Error::CaParentUnknown(_, _)
             | Error::ApiUnknownResource => StatusCode::NOT_FOUND,
@@ -717,6 +848,8 @@ impl Error {
             | Error::ApiLoginError(_) => StatusCode::UNAUTHORIZED,
             Error::ApiInsufficientRights(_) => StatusCode::FORBIDDEN,
 
+            Error::NodeIsStandby(_) => StatusCode::SERVICE_UNAVAILABLE,
+
             _ => StatusCode::BAD_REQUEST,
         }
     }
@@ -737,6 +870,11 @@ impl Error {
                 ErrorResponse::new("sys-kv", self).with_cause(e)
             }
 
+            // precondition failed, the If-Match version is not current
+            Error::AggregateStoreError(
+                AggregateStoreError::UnexpectedVersion(_, _, _),
+            ) => ErrorResponse::new("api-version-mismatch", self),
+
             // internal server error
             Error::AggregateStoreError(e) => {
                 ErrorResponse::new("sys-store", self).with_cause(e)
@@ -800,6 +938,19 @@ impl Error {
                 ErrorResponse::new("api-invalid-path-seconds", self)
             }
 
+            Error::ApiInvalidQueryParam(param, _) => {
+                ErrorResponse::new("api-invalid-query-param", self)
+                    .with_param(param)
+            }
+
+            Error::ApiInvalidIfMatch(_) => {
+                ErrorResponse::new("api-invalid-if-match", self)
+            }
+
+            Error::ApiWeakIfMatch(_) => {
+                ErrorResponse::new("api-weak-if-match", self)
+            }
+
             Error::PostTooBig => {
                 ErrorResponse::new("api-post-body-exceeds-limit", self)
             }
@@ -911,6 +1062,15 @@ impl Error {
                 ErrorResponse::new("ca-unknown", self).with_ca(ca)
             }
 
+            Error::CaUnknownAt(ca, _) => {
+                ErrorResponse::new("ca-unknown-at", self).with_ca(ca)
+            }
+
+            Error::CaCommandNotRevertible(ca, _) => {
+                ErrorResponse::new("ca-command-not-revertible", self)
+                    .with_ca(ca)
+            }
+
             Error::CaRepoInUse(ca) => {
                 ErrorResponse::new("ca-repo-same", self).with_ca(ca)
             }
@@ -1118,6 +1278,72 @@ impl Error {
                     .with_asn(key.asn())
             }
 
+            //-----------------------------------------------------------------
+            // Ghostbusters
+            //-----------------------------------------------------------------
+            Error::GhostbusterUnknown(ca, name) => {
+                ErrorResponse::new("ca-ghostbuster-unknown", self)
+                    .with_ca(ca)
+                    .with_ghostbuster(name)
+            }
+            Error::GhostbusterInvalid(ca, name, msg) => {
+                ErrorResponse::new("ca-ghostbuster-invalid", self)
+                    .with_ca(ca)
+                    .with_ghostbuster(name)
+                    .with_cause(msg)
+            }
+
+            //-----------------------------------------------------------------
+            // Webhooks (webhook-*)
+            //-----------------------------------------------------------------
+            Error::WebhookUnknown(name) => {
+                ErrorResponse::new("webhook-unknown", self).with_webhook(name)
+            }
+            Error::WebhookDuplicate(name) => {
+                ErrorResponse::new("webhook-duplicate", self)
+                    .with_webhook(name)
+            }
+            Error::WebhookInvalid(name, msg) => {
+                ErrorResponse::new("webhook-invalid", self)
+                    .with_webhook(name)
+                    .with_cause(msg)
+            }
+
+            //-----------------------------------------------------------------
+            // Backup and Restore (backup-*, restore-*)
+            //-----------------------------------------------------------------
+            Error::BackupInvalid(msg) => {
+                ErrorResponse::new("backup-invalid", self).with_cause(msg)
+            }
+            Error::BackupTooNew(_) => {
+                ErrorResponse::new("backup-too-new", self)
+            }
+            Error::RestoreTargetNotEmpty(msg) => {
+                ErrorResponse::new("restore-not-empty", self).with_cause(msg)
+            }
+
+            //-----------------------------------------------------------------
+            // High Availability (ha-*)
+            //-----------------------------------------------------------------
+            Error::NodeIsStandby(_) => ErrorResponse::new("ha-standby", self),
+
+            //-----------------------------------------------------------------
+            // Storage Encryption (storage-*)
+            //-----------------------------------------------------------------
+            Error::StorageEncryption(msg) => {
+                ErrorResponse::new("storage-encryption", self).with_cause(msg)
+            }
+
+            //-----------------------------------------------------------------
+            // Task Queue (task-*)
+            //-----------------------------------------------------------------
+            Error::TaskNotRunnable(_) => {
+                ErrorResponse::new("task-not-runnable", self)
+            }
+            Error::TaskRunInvalid(_) => {
+                ErrorResponse::new("task-run-invalid", self)
+            }
+
             //-----------------------------------------------------------------
             // Key Usage Issues (key-*)
             //-----------------------------------------------------------------
@@ -1142,6 +1368,11 @@ impl Error {
             Error::KeyRollActivatePendingRequests => {
                 ErrorResponse::new("key-roll-pending-requests", self)
             }
+            Error::KeyRollPolicyInvalid(ca, msg) => {
+                ErrorResponse::new("key-roll-policy-invalid", self)
+                    .with_ca(ca)
+                    .with_cause(msg)
+            }
 
             //-----------------------------------------------------------------
             // Resource Issues (label: rc-*)
@@ -1191,6 +1422,19 @@ impl Error {
             Error::TaProxyRequestNonceMismatch(_rcvd, _expected) => {
                 ErrorResponse::new("ta-proxy-response-nonce", self)
             }
+            Error::TaKeyRollInProgress => {
+                ErrorResponse::new("ta-key-roll-in-progress", self)
+            }
+            Error::TaKeyRollNotInProgress => {
+                ErrorResponse::new("ta-key-roll-not-in-progress", self)
+            }
+            Error::TaKeyRollUrisReused => {
+                ErrorResponse::new("ta-key-roll-uris-reused", self)
+            }
+            Error::TaProxyChildRemovalPending(child) => {
+                ErrorResponse::new("ta-proxy-child-removal-pending", self)
+                    .with_child(child)
+            }
 
             //-----------------------------------------------------------------
             // Resource Tagged Attestation issues
@@ -1199,6 +1443,32 @@ impl Error {
                 ErrorResponse::new("rta-resources-not-held", self)
             }
 
+            //-----------------------------------------------------------------
+            // RPKI Signed Checklist issues
+            //-----------------------------------------------------------------
+            Error::RscResourcesNotHeld(ca) => {
+                ErrorResponse::new("rsc-resources-not-held", self).with_ca(ca)
+            }
+            Error::RscInvalid(ca, msg) => {
+                ErrorResponse::new("rsc-invalid", self)
+                    .with_ca(ca)
+                    .with_cause(msg)
+            }
+
+            //-----------------------------------------------------------------
+            // Geofeed authentication issues
+            //-----------------------------------------------------------------
+            Error::GeofeedPrefixNotHeld(ca, prefix) => {
+                ErrorResponse::new("geofeed-prefix-not-held", self)
+                    .with_ca(ca)
+                    .with_prefix(prefix)
+            }
+            Error::GeofeedInvalid(ca, msg) => {
+                ErrorResponse::new("geofeed-invalid", self)
+                    .with_ca(ca)
+                    .with_cause(msg)
+            }
+
             //-----------------------------------------------------------------
             // If we really don't know any more..
             //-----------------------------------------------------------------
@@ -1348,6 +1618,14 @@ mod tests {
             ),
             Error::ApiUnknownResource,
         );
+        verify(
+            include_str!(
+                "../../test-resources/errors/api-version-mismatch.json"
+            ),
+            Error::AggregateStoreError(
+                AggregateStoreError::UnexpectedVersion(ca.clone(), 3, 5),
+            ),
+        );
 
         //-----------------------------------------------------------------
         // Repository Issues
@@ -197,6 +197,13 @@ impl<C: CommandDetails> SentCommand<C> {
         }
     }
 
+    /// Sets the version of the aggregate that this command updates. The
+    /// command is rejected if the aggregate is at another version.
+    pub fn with_version(mut self, version: Option<u64>) -> Self {
+        self.version = version;
+        self
+    }
+
     pub fn into_details(self) -> C {
         self.details
     }
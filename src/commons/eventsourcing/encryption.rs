@@ -0,0 +1,380 @@
+//! Envelope encryption of the values in a [`KeyValueStore`].
+//!
+//! Values are encrypted with AES-256-GCM using a random data key, and
+//! stored as a JSON object with a single `encrypted` member, so that they
+//! can be told apart from plain values. Each value is bound to its
+//! namespace and key, so that it cannot be moved to another key without
+//! this being detected. The data key itself is wrapped and kept by the
+//! daemon, see `daemon::encryption`.
+//!
+//! [`KeyValueStore`]: super::KeyValueStore
+use std::fmt;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use kvx::{
+    Key, KeyValueStoreBackend, Namespace, NamespaceBuf, ReadStore, Scope,
+    WriteStore,
+};
+use openssl::{
+    rand::rand_bytes,
+    symm::{decrypt_aead, encrypt_aead, Cipher},
+};
+use serde_json::Value;
+
+/// The member used for encrypted values.
+const ENCRYPTED_MEMBER: &str = "encrypted";
+
+const NONCE_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+
+//------------ DataKey -------------------------------------------------------
+
+/// A symmetric key used to encrypt the values in a store.
+#[derive(Clone)]
+pub struct DataKey([u8; DataKey::LEN]);
+
+impl DataKey {
+    pub const LEN: usize = 32;
+
+    /// Generates a new random data key.
+    pub fn generate() -> Result<Self, kvx::Error> {
+        let mut key = [0; Self::LEN];
+        rand_bytes(&mut key).map_err(crypto_err)?;
+        Ok(DataKey(key))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, kvx::Error> {
+        let key = bytes.try_into().map_err(|_| {
+            kvx::Error::Other(format!(
+                "data key must be {} bytes, found {}",
+                Self::LEN,
+                bytes.len()
+            ))
+        })?;
+        Ok(DataKey(key))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns whether the value was encrypted by a data key.
+    pub fn is_encrypted(value: &Value) -> bool {
+        match value.as_object() {
+            Some(object) => {
+                object.len() == 1 && object.contains_key(ENCRYPTED_MEMBER)
+            }
+            None => false,
+        }
+    }
+
+    /// Encrypts arbitrary bytes. The result contains the random nonce,
+    /// the cipher text and the authentication tag. The same additional
+    /// authenticated data must be given to [`Self::open`].
+    pub fn seal(
+        &self,
+        aad: &[u8],
+        data: &[u8],
+    ) -> Result<Vec<u8>, kvx::Error> {
+        let mut nonce = [0; NONCE_LEN];
+        rand_bytes(&mut nonce).map_err(crypto_err)?;
+
+        let mut tag = [0; TAG_LEN];
+        let cipher_text = encrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(&nonce),
+            aad,
+            data,
+            &mut tag,
+        )
+        .map_err(crypto_err)?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&cipher_text);
+        sealed.extend_from_slice(&tag);
+        Ok(sealed)
+    }
+
+    /// Decrypts bytes that were encrypted using [`Self::seal`].
+    pub fn open(
+        &self,
+        aad: &[u8],
+        sealed: &[u8],
+    ) -> Result<Vec<u8>, kvx::Error> {
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            return Err(kvx::Error::Other(
+                "encrypted data is too short".to_string(),
+            ));
+        }
+        let (nonce, rest) = sealed.split_at(NONCE_LEN);
+        let (cipher_text, tag) = rest.split_at(rest.len() - TAG_LEN);
+
+        decrypt_aead(
+            Cipher::aes_256_gcm(),
+            &self.0,
+            Some(nonce),
+            aad,
+            cipher_text,
+            tag,
+        )
+        .map_err(|_| {
+            kvx::Error::Other(
+                "cannot decrypt data, the data key does not match or the data was modified".to_string(),
+            )
+        })
+    }
+
+    /// Encrypts a value for storage under the given key in a namespace.
+    pub fn encrypt(
+        &self,
+        namespace: &Namespace,
+        key: &Key,
+        value: &Value,
+    ) -> Result<Value, kvx::Error> {
+        let sealed = self
+            .seal(&Self::aad(namespace, key), &serde_json::to_vec(value)?)?;
+        let mut object = serde_json::Map::new();
+        object.insert(
+            ENCRYPTED_MEMBER.to_string(),
+            Value::String(BASE64.encode(sealed)),
+        );
+        Ok(Value::Object(object))
+    }
+
+    /// Decrypts a value stored under the given key in a namespace. Fails
+    /// if the value is not encrypted, or if it was encrypted for another
+    /// key or namespace.
+    pub fn decrypt(
+        &self,
+        namespace: &Namespace,
+        key: &Key,
+        value: Value,
+    ) -> Result<Value, kvx::Error> {
+        let sealed = value
+            .get(ENCRYPTED_MEMBER)
+            .and_then(Value::as_str)
+            .filter(|_| Self::is_encrypted(&value))
+            .ok_or_else(|| {
+                kvx::Error::Other(format!(
+                    "value for key '{}' is not encrypted, use 'krillup encrypt' to encrypt existing data",
+                    key
+                ))
+            })?;
+        let sealed = BASE64.decode(sealed).map_err(|e| {
+            kvx::Error::Other(format!(
+                "value for key '{}' has invalid base64: {}",
+                key, e
+            ))
+        })?;
+        let data =
+            self.open(&Self::aad(namespace, key), &sealed).map_err(|_| {
+                kvx::Error::Other(format!(
+                    "cannot decrypt value for key '{}', the data key does not match or the value was modified or moved",
+                    key
+                ))
+            })?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Returns the additional authenticated data for a value, which binds
+    /// it to its namespace and key.
+    fn aad(namespace: &Namespace, key: &Key) -> Vec<u8> {
+        format!("{}:{}", namespace, key).into_bytes()
+    }
+
+    /// Encrypts a value that was decrypted for one key again for another
+    /// key, so that it can be moved.
+    fn reencrypt(
+        &self,
+        namespace: &Namespace,
+        from: &Key,
+        to: &Key,
+        value: Value,
+    ) -> Result<Value, kvx::Error> {
+        self.encrypt(namespace, to, &self.decrypt(namespace, from, value)?)
+    }
+}
+
+impl fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DataKey(..)")
+    }
+}
+
+fn crypto_err(e: openssl::error::ErrorStack) -> kvx::Error {
+    kvx::Error::Other(format!("encryption error: {}", e))
+}
+
+//------------ EncryptedBackend ----------------------------------------------
+
+/// Wraps a backend so that values are encrypted when they are stored, and
+/// decrypted when they are read. Values that are moved are encrypted again
+/// for their new key. All other operations are passed on as is.
+pub struct EncryptedBackend<'a> {
+    inner: &'a dyn KeyValueStoreBackend,
+    data_key: &'a DataKey,
+    namespace: &'a Namespace,
+}
+
+impl<'a> EncryptedBackend<'a> {
+    pub fn new(
+        inner: &'a dyn KeyValueStoreBackend,
+        data_key: &'a DataKey,
+        namespace: &'a Namespace,
+    ) -> Self {
+        EncryptedBackend {
+            inner,
+            data_key,
+            namespace,
+        }
+    }
+}
+
+impl ReadStore for EncryptedBackend<'_> {
+    fn is_empty(&self) -> Result<bool, kvx::Error> {
+        self.inner.is_empty()
+    }
+
+    fn has(&self, key: &Key) -> Result<bool, kvx::Error> {
+        self.inner.has(key)
+    }
+
+    fn has_scope(&self, scope: &Scope) -> Result<bool, kvx::Error> {
+        self.inner.has_scope(scope)
+    }
+
+    fn get(&self, key: &Key) -> Result<Option<Value>, kvx::Error> {
+        self.inner
+            .get(key)?
+            .map(|value| self.data_key.decrypt(self.namespace, key, value))
+            .transpose()
+    }
+
+    fn list_keys(&self, scope: &Scope) -> Result<Vec<Key>, kvx::Error> {
+        self.inner.list_keys(scope)
+    }
+
+    fn list_scopes(&self) -> Result<Vec<Scope>, kvx::Error> {
+        self.inner.list_scopes()
+    }
+}
+
+impl WriteStore for EncryptedBackend<'_> {
+    fn store(&self, key: &Key, value: Value) -> Result<(), kvx::Error> {
+        self.inner
+            .store(key, self.data_key.encrypt(self.namespace, key, &value)?)
+    }
+
+    fn move_value(&self, from: &Key, to: &Key) -> Result<(), kvx::Error> {
+        match self.inner.get(from)? {
+            Some(value) => {
+                let value = self.data_key.reencrypt(
+                    self.namespace,
+                    from,
+                    to,
+                    value,
+                )?;
+                self.inner.store(to, value)?;
+                self.inner.delete(from)
+            }
+            // Let the inner backend report the missing value.
+            None => self.inner.move_value(from, to),
+        }
+    }
+
+    fn move_scope(&self, from: &Scope, to: &Scope) -> Result<(), kvx::Error> {
+        for key in self.inner.list_keys(from)? {
+            if key.scope().starts_with(from) {
+                let mut scope = to.as_vec().clone();
+                scope.extend_from_slice(
+                    &key.scope().as_vec()[from.as_vec().len()..],
+                );
+                let moved = Key::new_scoped(Scope::new(scope), key.name());
+                self.move_value(&key, &moved)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn delete(&self, key: &Key) -> Result<(), kvx::Error> {
+        self.inner.delete(key)
+    }
+
+    fn delete_scope(&self, scope: &Scope) -> Result<(), kvx::Error> {
+        self.inner.delete_scope(scope)
+    }
+
+    fn clear(&self) -> Result<(), kvx::Error> {
+        self.inner.clear()
+    }
+
+    fn migrate_namespace(
+        &mut self,
+        _to: NamespaceBuf,
+    ) -> Result<(), kvx::Error> {
+        Err(kvx::Error::NamespaceMigration(
+            "cannot migrate a namespace inside a transaction".to_string(),
+        ))
+    }
+}
+
+impl KeyValueStoreBackend for EncryptedBackend<'_> {
+    fn transaction(
+        &self,
+        scope: &Scope,
+        callback: &mut dyn FnMut(
+            &dyn KeyValueStoreBackend,
+        ) -> Result<(), kvx::Error>,
+    ) -> Result<(), kvx::Error> {
+        self.inner.transaction(scope, &mut |kv| {
+            callback(&EncryptedBackend::new(
+                kv,
+                self.data_key,
+                self.namespace,
+            ))
+        })
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use kvx::Segment;
+
+    use super::*;
+
+    #[test]
+    fn encrypt_and_decrypt_value() {
+        let data_key = DataKey::generate().unwrap();
+        let namespace = kvx::namespace!("cas");
+        let key = Key::new_global(kvx::segment!("key"));
+        let value = serde_json::json!({ "name": "testbed", "version": 3 });
+
+        let encrypted = data_key.encrypt(namespace, &key, &value).unwrap();
+        assert!(DataKey::is_encrypted(&encrypted));
+        assert!(!DataKey::is_encrypted(&value));
+        assert_eq!(
+            data_key
+                .decrypt(namespace, &key, encrypted.clone())
+                .unwrap(),
+            value
+        );
+
+        // A plain value or a different key must not be accepted.
+        assert!(data_key.decrypt(namespace, &key, value).is_err());
+        let other_key = DataKey::generate().unwrap();
+        assert!(other_key
+            .decrypt(namespace, &key, encrypted.clone())
+            .is_err());
+
+        // Nor can the value be moved to another key or namespace.
+        let moved = Key::new_global(kvx::segment!("moved"));
+        assert!(data_key
+            .decrypt(namespace, &moved, encrypted.clone())
+            .is_err());
+        let other_namespace = kvx::namespace!("pubd");
+        assert!(data_key.decrypt(other_namespace, &key, encrypted).is_err());
+    }
+}
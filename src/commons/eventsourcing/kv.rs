@@ -1,13 +1,41 @@
-use std::{fmt, str::FromStr};
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{Arc, OnceLock, RwLock},
+};
 
 pub use kvx::{
     namespace, segment, Key, Namespace, Scope, Segment, SegmentBuf,
 };
-use kvx::{KeyValueStoreBackend, NamespaceBuf, ReadStore, WriteStore};
+use kvx::{KeyValueStoreBackend, NamespaceBuf, PubKeyValueStoreBackend};
 use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
 use url::Url;
 
-use crate::commons::error::KrillIoError;
+use crate::{
+    commons::error::KrillIoError,
+    constants::{ENCRYPTION_NS, SIGNERS_NS},
+};
+
+use super::encryption::{DataKey, EncryptedBackend};
+#[cfg(feature = "postgres")]
+use super::postgres::Postgres;
+
+/// The data keys for encrypted storage, by storage URI.
+static DATA_KEYS: OnceLock<RwLock<HashMap<String, Arc<DataKey>>>> =
+    OnceLock::new();
+
+/// Shared by all stores in this process while they are used, and held
+/// exclusively by [`KeyValueStore::with_exclusive_access`].
+static STORE_LOCK: RwLock<()> = RwLock::new(());
+
+thread_local! {
+    // Set while this thread holds the store lock, so that nested calls
+    // do not try to get it again.
+    static HOLDS_STORE_LOCK: Cell<bool> = const { Cell::new(false) };
+}
 
 pub trait SegmentExt {
     fn parse_lossy(value: &str) -> SegmentBuf;
@@ -46,7 +74,9 @@ impl SegmentExt for Segment {
 
 #[derive(Debug)]
 pub struct KeyValueStore {
-    inner: kvx::KeyValueStore,
+    inner: Box<dyn PubKeyValueStoreBackend>,
+    namespace: NamespaceBuf,
+    data_key: Option<Arc<DataKey>>,
 }
 
 // # Construct and high level functions.
@@ -56,9 +86,31 @@ impl KeyValueStore {
         storage_uri: &Url,
         namespace: &Namespace,
     ) -> Result<Self, KeyValueError> {
-        kvx::KeyValueStore::new(storage_uri, namespace)
-            .map(|inner| KeyValueStore { inner })
-            .map_err(KeyValueError::Inner)
+        Self::new(storage_uri, namespace.into())
+    }
+
+    fn new(
+        storage_uri: &Url,
+        namespace: NamespaceBuf,
+    ) -> Result<Self, KeyValueError> {
+        let data_key = Self::data_key(storage_uri, &namespace);
+        let inner: Box<dyn PubKeyValueStoreBackend> =
+            match storage_uri.scheme() {
+                #[cfg(feature = "postgres")]
+                "postgres" => Box::new(
+                    Postgres::new(storage_uri, namespace.clone())
+                        .map_err(KeyValueError::Inner)?,
+                ),
+                _ => Box::new(
+                    kvx::KeyValueStore::new(storage_uri, namespace.clone())
+                        .map_err(KeyValueError::Inner)?,
+                ),
+            };
+        Ok(KeyValueStore {
+            inner,
+            namespace,
+            data_key,
+        })
     }
 
     /// Returns true if this KeyValueStore (with this namespace) has any
@@ -89,15 +141,179 @@ impl KeyValueStore {
     /// T can be () if no return value is needed. If anything can
     /// fail in the closure, other than kvx calls, then T can be
     /// a Result<X,Y>.
+    ///
+    /// If encryption is enabled for this store, then values are encrypted
+    /// and decrypted transparently by the backend given to the closure.
     pub fn execute<F, T>(
         &self,
         scope: &Scope,
-        op: F,
+        mut op: F,
     ) -> Result<T, KeyValueError>
     where
         F: FnMut(&dyn KeyValueStoreBackend) -> Result<T, kvx::Error>,
     {
-        self.inner.execute(scope, op).map_err(KeyValueError::Inner)
+        self.execute_plain(scope, |kv| match &self.data_key {
+            Some(data_key) => {
+                op(&EncryptedBackend::new(kv, data_key, &self.namespace))
+            }
+            None => op(kv),
+        })
+    }
+
+    /// Executes the operations as is, regardless of whether encryption is
+    /// enabled for this store.
+    fn execute_plain<F, T>(
+        &self,
+        scope: &Scope,
+        mut op: F,
+    ) -> Result<T, KeyValueError>
+    where
+        F: FnMut(&dyn KeyValueStoreBackend) -> Result<T, kvx::Error>,
+    {
+        let _guard = if HOLDS_STORE_LOCK.with(Cell::get) {
+            None
+        } else {
+            Some(StoreLockGuard::new(STORE_LOCK.read().unwrap()))
+        };
+
+        let mut res = None;
+        self.inner
+            .transaction(scope, &mut |kv| {
+                res = Some(op(kv)?);
+                Ok(())
+            })
+            .map_err(KeyValueError::Inner)?;
+        res.ok_or(KeyValueError::Inner(kvx::Error::Unknown))
+    }
+
+    /// Runs the operation while no other thread in this process can use
+    /// any store. This can be used to read data from several namespaces
+    /// at a single moment. Other processes using the same storage, such
+    /// as other Krill nodes or `krillup`, are not affected.
+    pub fn with_exclusive_access<F, T>(op: F) -> T
+    where
+        F: FnOnce() -> T,
+    {
+        if HOLDS_STORE_LOCK.with(Cell::get) {
+            op()
+        } else {
+            let _guard = StoreLockGuard::new(STORE_LOCK.write().unwrap());
+            op()
+        }
+    }
+}
+
+/// Keeps the store lock, and marks the current thread as holding it.
+struct StoreLockGuard<G> {
+    _lock: G,
+}
+
+impl<G> StoreLockGuard<G> {
+    fn new(lock: G) -> Self {
+        HOLDS_STORE_LOCK.with(|holds| holds.set(true));
+        StoreLockGuard { _lock: lock }
+    }
+}
+
+impl<G> Drop for StoreLockGuard<G> {
+    fn drop(&mut self) {
+        HOLDS_STORE_LOCK.with(|holds| holds.set(false));
+    }
+}
+
+// # Encryption
+impl KeyValueStore {
+    /// Encrypts the values of all stores for the given storage URI that
+    /// are created after this call.
+    ///
+    /// The namespaces that are needed to unwrap the data key itself, i.e.
+    /// the encryption namespace and the signer mappings, are not encrypted.
+    pub fn enable_encryption(storage_uri: &Url, data_key: DataKey) {
+        DATA_KEYS
+            .get_or_init(Default::default)
+            .write()
+            .unwrap()
+            .insert(storage_uri.to_string(), Arc::new(data_key));
+    }
+
+    /// Returns whether values in this store are encrypted.
+    pub fn is_encrypted(&self) -> bool {
+        self.data_key.is_some()
+    }
+
+    /// Encrypts all values in this store that are not encrypted yet, and
+    /// returns the number of values that were encrypted.
+    pub fn encrypt_values(
+        &self,
+        data_key: &DataKey,
+    ) -> Result<usize, KeyValueError> {
+        self.update_values(|key, value| {
+            if DataKey::is_encrypted(&value) {
+                Ok(None)
+            } else {
+                data_key.encrypt(&self.namespace, key, &value).map(Some)
+            }
+        })
+    }
+
+    /// Decrypts all encrypted values in this store, and returns the number
+    /// of values that were decrypted.
+    pub fn decrypt_values(
+        &self,
+        data_key: &DataKey,
+    ) -> Result<usize, KeyValueError> {
+        self.update_values(|key, value| {
+            if DataKey::is_encrypted(&value) {
+                data_key.decrypt(&self.namespace, key, value).map(Some)
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    /// Replaces the stored values for which the operation returns a new
+    /// value. Stored values are passed on as is, regardless of whether
+    /// encryption is enabled for this store.
+    fn update_values<F>(&self, op: F) -> Result<usize, KeyValueError>
+    where
+        F: Fn(&Key, Value) -> Result<Option<Value>, kvx::Error>,
+    {
+        let mut scopes = self.scopes()?;
+        scopes.push(Scope::global());
+
+        let mut updated = 0;
+        for scope in scopes {
+            let keys = self.keys(&scope, "")?;
+            self.execute_plain(&scope, |kv| {
+                for key in &keys {
+                    if let Some(value) = kv.get(key)? {
+                        if let Some(value) = op(key, value)? {
+                            kv.store(key, value)?;
+                            updated += 1;
+                        }
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        Ok(updated)
+    }
+
+    /// Returns the data key for the given namespace, if encryption is
+    /// enabled for the storage URI and applies to the namespace.
+    pub fn data_key(
+        storage_uri: &Url,
+        namespace: &Namespace,
+    ) -> Option<Arc<DataKey>> {
+        if namespace == ENCRYPTION_NS || namespace == SIGNERS_NS {
+            return None;
+        }
+        DATA_KEYS
+            .get()?
+            .read()
+            .unwrap()
+            .get(storage_uri.as_str())
+            .cloned()
     }
 }
 
@@ -184,6 +400,42 @@ impl KeyValueStore {
 
 // # Scopes
 impl KeyValueStore {
+    /// Returns all key value pairs directly under a scope. They are read
+    /// while holding the lock for the scope, so that they are consistent
+    /// with each other.
+    ///
+    /// Values are returned as they are stored, i.e. still encrypted if
+    /// encryption is enabled for this store. They can be stored again
+    /// using [`Self::store_exported`].
+    pub fn export_scope(
+        &self,
+        scope: &Scope,
+    ) -> Result<Vec<(Key, Value)>, KeyValueError> {
+        self.execute_plain(scope, |kv| {
+            let mut values = vec![];
+            for key in kv.list_keys(scope)? {
+                if key.scope() == scope {
+                    if let Some(value) = kv.get(&key)? {
+                        values.push((key, value));
+                    }
+                }
+            }
+            Ok(values)
+        })
+    }
+
+    /// Stores a value as it was returned by [`Self::export_scope`], i.e.
+    /// without encrypting it again. Encrypted values can only be read if
+    /// they were exported from the same namespace and key, using the same
+    /// data key.
+    pub fn store_exported(
+        &self,
+        key: &Key,
+        value: &Value,
+    ) -> Result<(), KeyValueError> {
+        self.execute_plain(key.scope(), |kv| kv.store(key, value.clone()))
+    }
+
     /// Returns whether a scope exists
     pub fn has_scope(&self, scope: &Scope) -> Result<bool, KeyValueError> {
         self.execute(&Scope::global(), |kv| kv.has_scope(scope))
@@ -210,10 +462,21 @@ impl KeyValueStore {
         namespace: &Namespace,
     ) -> Result<Self, KeyValueError> {
         let namespace = Self::prefixed_namespace(namespace, "upgrade")?;
+        Self::new(storage_uri, namespace)
+    }
 
-        kvx::KeyValueStore::new(storage_uri, namespace)
-            .map(|inner| KeyValueStore { inner })
-            .map_err(KeyValueError::Inner)
+    /// Creates a new KeyValueStore for commands that were archived from
+    /// the store for the given namespace.
+    ///
+    /// Adds the implicit prefix "archived_" to the given namespace. This is
+    /// kept apart from the archive used by [`Self::migrate_to_archive`],
+    /// because that archive is wiped whenever a new upgrade is done.
+    pub fn create_command_archive(
+        storage_uri: &Url,
+        namespace: &Namespace,
+    ) -> Result<Self, KeyValueError> {
+        let namespace = Self::prefixed_namespace(namespace, "archived")?;
+        Self::new(storage_uri, namespace)
     }
 
     fn prefixed_namespace(
@@ -242,9 +505,7 @@ impl KeyValueStore {
         let archive_store = KeyValueStore::create(storage_uri, &archive_ns)?;
         archive_store.wipe()?;
 
-        self.inner
-            .migrate_namespace(archive_ns)
-            .map_err(KeyValueError::Inner)
+        self.migrate_namespace(storage_uri, archive_ns)
     }
 
     /// Make this (upgrade) store the current store.
@@ -262,13 +523,45 @@ impl KeyValueStore {
                 namespace
             )))
         } else {
-            self.inner
-                .migrate_namespace(namespace.into())
-                .map_err(KeyValueError::Inner)
+            self.migrate_namespace(storage_uri, namespace.into())
         }
     }
 
-    /// Import all data from the given KV store into this
+    /// Moves all data to the given namespace. Encrypted values are bound
+    /// to their namespace, so they are encrypted again for the new one, or
+    /// decrypted if values in the new namespace are not encrypted.
+    fn migrate_namespace(
+        &mut self,
+        storage_uri: &Url,
+        namespace: NamespaceBuf,
+    ) -> Result<(), KeyValueError> {
+        self.inner
+            .migrate_namespace(namespace.clone())
+            .map_err(KeyValueError::Inner)?;
+
+        let previous_key = std::mem::replace(
+            &mut self.data_key,
+            Self::data_key(storage_uri, &namespace),
+        );
+        let previous = std::mem::replace(&mut self.namespace, namespace);
+
+        if previous_key.is_some() || self.data_key.is_some() {
+            self.update_values(|key, mut value| {
+                if let Some(data_key) = &previous_key {
+                    value = data_key.decrypt(&previous, key, value)?;
+                }
+                if let Some(data_key) = &self.data_key {
+                    value = data_key.encrypt(&self.namespace, key, &value)?;
+                }
+                Ok(Some(value))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Import all data from the given KV store into this. Values are
+    /// decrypted and encrypted again if encryption is enabled for either
+    /// store.
     ///
     /// NOTE: This function is not transactional because both this, and the
     /// other       keystore could be in the same database and nested
@@ -282,9 +575,23 @@ impl KeyValueStore {
 
         for scope in scopes {
             for key in other.keys(&scope, "")? {
-                if let Some(value) =
+                if let Some(mut value) =
                     other.inner.get(&key).map_err(KeyValueError::Inner)?
                 {
+                    if let Some(data_key) = &other.data_key {
+                        value = data_key.decrypt(
+                            &other.namespace,
+                            &key,
+                            value,
+                        )?;
+                    }
+                    if let Some(data_key) = &self.data_key {
+                        value = data_key.encrypt(
+                            &self.namespace,
+                            &key,
+                            &value,
+                        )?;
+                    }
                     self.inner
                         .store(&key, value)
                         .map_err(KeyValueError::Inner)?;
@@ -404,6 +711,75 @@ mod tests {
         assert_eq!(store.get(&key).unwrap(), Some(content));
     }
 
+    #[test]
+    fn test_exclusive_access() {
+        let storage_uri = get_storage_uri();
+
+        let store = Arc::new(
+            KeyValueStore::create(&storage_uri, &random_namespace()).unwrap(),
+        );
+        let key = Key::new_global(random_segment());
+
+        let writer = KeyValueStore::with_exclusive_access(|| {
+            let writer = std::thread::spawn({
+                let store = store.clone();
+                let key = key.clone();
+                move || store.store(&key, &"content").unwrap()
+            });
+            std::thread::sleep(std::time::Duration::from_millis(100));
+
+            // Stores can still be used by this thread, but not by others.
+            assert!(!store.has(&key).unwrap());
+            writer
+        });
+
+        writer.join().unwrap();
+        assert!(store.has(&key).unwrap());
+    }
+
+    #[test]
+    fn test_encrypted_values_can_be_moved() {
+        let storage_uri = crate::test::mem_storage();
+        KeyValueStore::enable_encryption(
+            &storage_uri,
+            DataKey::generate().unwrap(),
+        );
+
+        let namespace = random_namespace();
+        let mut store =
+            KeyValueStore::create_upgrade_store(&storage_uri, &namespace)
+                .unwrap();
+        let content = "content".to_owned();
+        let name = random_segment();
+        let key = Key::new_scoped(
+            Scope::from_segment(segment!("scope")),
+            name.clone(),
+        );
+        store.store(&key, &content).unwrap();
+
+        let moved_scope = Scope::from_segment(segment!("moved"));
+        let moved = Key::new_scoped(moved_scope.clone(), name.clone());
+        store
+            .execute(&Scope::global(), |kv| kv.move_value(&key, &moved))
+            .unwrap();
+        assert_eq!(store.get(&moved).unwrap(), Some(content.clone()));
+
+        let other_scope = Scope::from_segment(segment!("other"));
+        let other = Key::new_scoped(other_scope.clone(), name.clone());
+        store
+            .execute(&Scope::global(), |kv| {
+                kv.move_scope(&moved_scope, &other_scope)
+            })
+            .unwrap();
+        assert_eq!(store.get(&other).unwrap(), Some(content.clone()));
+
+        store.migrate_to_current(&storage_uri, &namespace).unwrap();
+        assert_eq!(store.get(&other).unwrap(), Some(content.clone()));
+        let current =
+            KeyValueStore::create(&storage_uri, &namespace).unwrap();
+        assert_eq!(current.get(&other).unwrap(), Some(content));
+    }
+
     #[test]
     fn test_store_new() {
         let storage_uri = get_storage_uri();
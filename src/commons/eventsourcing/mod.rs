@@ -18,6 +18,17 @@ pub use self::store::*;
 mod listener;
 pub use self::listener::*;
 
+mod verify;
+pub use self::verify::*;
+
+mod encryption;
+pub use self::encryption::DataKey;
+
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "postgres")]
+pub use self::postgres::blocking;
+
 mod kv;
 pub use self::kv::{
     namespace, segment, Key, KeyValueError, KeyValueStore, Namespace, Scope,
@@ -37,12 +48,15 @@ mod tests {
 
     use serde::Serialize;
 
-    use rpki::ca::idexchange::MyHandle;
+    use rpki::{ca::idexchange::MyHandle, repository::x509::Time};
 
     use crate::{
         commons::{
             actor::Actor,
-            api::{CommandHistoryCriteria, CommandSummary},
+            api::{
+                AuditCriteria, CommandHistoryCriteria, CommandSummary,
+                PointInTime,
+            },
         },
         constants::ACTOR_DEF_TEST,
         test::mem_storage,
@@ -429,6 +443,15 @@ mod tests {
         assert_eq!("alice smith-doe", alice.name());
         assert_eq!(21, alice.age());
 
+        // A command for a version that is no longer current is rejected
+        // and not stored.
+        let stale =
+            PersonCommand::change_name(&alice_handle, Some(22), "bob");
+        assert!(manager.command(stale).is_err());
+        let alice = manager.get_latest(&alice_handle).unwrap();
+        assert_eq!("alice smith-doe", alice.name());
+        assert_eq!(23, alice.version());
+
         // Should read state again when restarted with same data store
         // mapping.
         let manager = AggregateStore::<Person>::create(
@@ -460,6 +483,158 @@ mod tests {
         crit.set_excludes(&["person-around-sun"]);
         let history = manager.command_history(&alice_handle, crit).unwrap();
         assert_eq!(history.total(), 1);
+
+        // Rebuild earlier versions from the stored commands
+        let (alice, _) = manager
+            .get_at(&alice_handle, PointInTime::Version(5))
+            .unwrap()
+            .unwrap();
+        assert_eq!(5, alice.version());
+        assert_eq!("alice smith", alice.name());
+        assert_eq!(4, alice.age());
+
+        let (alice, _) = manager
+            .get_at(&alice_handle, PointInTime::Time(Time::now()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(23, alice.version());
+        assert_eq!("alice smith-doe", alice.name());
+
+        let before_init = Time::now() - chrono::Duration::days(1);
+        for point in [
+            PointInTime::Version(0),
+            PointInTime::Version(24),
+            PointInTime::Time(before_init),
+        ] {
+            assert!(manager.get_at(&alice_handle, point).unwrap().is_none());
+        }
+
+        // Verify the stored data, then break it and verify again
+        manager.save_snapshot(&alice_handle).unwrap();
+        assert!(manager.verify(&alice_handle).unwrap().is_empty());
+
+        let kv = KeyValueStore::create(&storage_uri, namespace!("person"))
+            .unwrap();
+        let scope = Scope::from_segment(segment!("alice"));
+        let snapshot_key =
+            Key::new_scoped(scope.clone(), segment!("snapshot.json"));
+        let mut snapshot: serde_json::Value =
+            kv.get(&snapshot_key).unwrap().unwrap();
+        snapshot["name"] = "bob".into();
+        kv.store(&snapshot_key, &snapshot).unwrap();
+        assert_eq!(
+            vec![StorageIssue::SnapshotDivergence {
+                version: 23,
+                path: "name".to_string()
+            }],
+            manager.verify(&alice_handle).unwrap()
+        );
+
+        kv.drop_key(&Key::new_scoped(
+            scope.clone(),
+            segment!("command-10.json"),
+        ))
+        .unwrap();
+        kv.store(&Key::new_scoped(scope, segment!("command-12.json")), &1)
+            .unwrap();
+
+        let issues = manager.verify(&alice_handle).unwrap();
+        assert_eq!(3, issues.len());
+        assert_eq!(StorageIssue::MissingCommand(10), issues[0]);
+        assert!(matches!(issues[1], StorageIssue::CorruptJson { .. }));
+        assert_eq!(
+            StorageIssue::SnapshotAhead {
+                snapshot: 23,
+                replayed: 10
+            },
+            issues[2]
+        );
         // })
     }
+
+    #[test]
+    fn archive_commands() {
+        let storage_uri = mem_storage();
+        let create_store = || {
+            AggregateStore::<Person>::create(
+                &storage_uri,
+                namespace!("people"),
+                true,
+            )
+            .unwrap()
+        };
+        let store = create_store();
+
+        let bob_handle = MyHandle::from_str("bob").unwrap();
+        store
+            .add(PersonInitCommand::make(&bob_handle, "bob".to_string()))
+            .unwrap();
+        for _ in 0..10 {
+            store
+                .command(PersonCommand::go_around_sun(&bob_handle, None))
+                .unwrap();
+        }
+
+        // Nothing is old enough yet
+        let before = Time::now() - chrono::Duration::days(1);
+        assert_eq!(0, store.archive_commands(&bob_handle, before).unwrap());
+
+        let before = Time::now() + chrono::Duration::days(1);
+        assert_eq!(10, store.archive_commands(&bob_handle, before).unwrap());
+        assert_eq!(0, store.archive_commands(&bob_handle, before).unwrap());
+
+        store
+            .command(PersonCommand::go_around_sun(&bob_handle, None))
+            .unwrap();
+
+        // The history only includes the current commands
+        let history = store
+            .command_history(&bob_handle, CommandHistoryCriteria::default())
+            .unwrap();
+        assert_eq!(1, history.total());
+        assert_eq!(11, history.commands().first().unwrap().version);
+        let archived = history.archived().unwrap();
+        assert_eq!(10, archived.count());
+        assert_eq!(10, archived.last_version());
+
+        // The audit log includes archived commands
+        let audit = store
+            .audit_records(&bob_handle, &AuditCriteria::default())
+            .unwrap();
+        assert_eq!(
+            (1..=11).collect::<Vec<u64>>(),
+            audit
+                .iter()
+                .map(|record| record.version)
+                .collect::<Vec<_>>()
+        );
+
+        // Archived commands can still be found and replayed
+        assert_eq!(5, store.get_command(&bob_handle, 5).unwrap().version());
+        let (bob, _) = store
+            .get_at(&bob_handle, PointInTime::Version(5))
+            .unwrap()
+            .unwrap();
+        assert_eq!(4, bob.age());
+
+        let store = create_store();
+        assert_eq!(11, store.get_latest(&bob_handle).unwrap().age());
+        assert!(store.verify(&bob_handle).unwrap().is_empty());
+
+        // The snapshot is needed, because the commands were archived
+        let kv = KeyValueStore::create(&storage_uri, namespace!("people"))
+            .unwrap();
+        kv.drop_key(&Key::new_scoped(
+            Scope::from_segment(segment!("bob")),
+            segment!("snapshot.json"),
+        ))
+        .unwrap();
+
+        let store = create_store();
+        assert!(store.get_latest(&bob_handle).is_err());
+        assert!(store
+            .verify(&bob_handle)
+            .unwrap()
+            .contains(&StorageIssue::MissingSnapshot));
+    }
 }
@@ -0,0 +1,658 @@
+//! A PostgreSQL backend for the [`KeyValueStore`].
+//!
+//! kvx has a PostgreSQL backend of its own, but Krill does not use it, and
+//! it is disabled in the kvx dependency. That backend creates a connection
+//! pool for every store, expects the `store` table to exist already, runs
+//! its transactions as SERIALIZABLE and repeats the whole callback up to
+//! ten times on any error, not just on serialization failures, and blocks
+//! the thread that drives the Tokio runtime. Krill creates many short lived
+//! stores, and its callbacks are not always safe to repeat. So, all stores,
+//! including the task queue, use this backend instead.
+//!
+//! All namespaces are kept in a single `store` table, which has the same
+//! layout as the table used by the kvx PostgreSQL backend. The schema is
+//! created, or migrated, when the first store for a storage URI is created.
+//! This also happens when `krillup` prepares an upgrade, or migrates data
+//! to a `postgres://` storage URI.
+//!
+//! The disk backend uses a lock file for each scope, so that changes to a
+//! scope are done one at a time, also by different processes. Here each
+//! transaction holds an advisory lock for its namespace and scope instead,
+//! which PostgreSQL releases when the transaction ends. All stores for the
+//! same storage URI share a connection pool.
+//!
+//! The `postgres` client blocks on a runtime of its own, which cannot be
+//! done from a thread that drives the Tokio runtime of the daemon. All
+//! database access is therefore done through [`blocking`].
+//!
+//! [`KeyValueStore`]: super::KeyValueStore
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{Mutex, OnceLock},
+};
+
+use kvx::{
+    Key, KeyValueStoreBackend, Namespace, NamespaceBuf, ReadStore, Scope,
+    SegmentBuf, WriteStore,
+};
+use postgres::{types::ToSql, NoTls, Row, Transaction};
+use r2d2_postgres::{r2d2::Pool, PostgresConnectionManager};
+use serde_json::Value;
+use tokio::runtime::{Handle, RuntimeFlavor};
+use url::Url;
+
+type PgPool = Pool<PostgresConnectionManager<NoTls>>;
+
+/// The connection pools, by storage URI.
+static POOLS: OnceLock<Mutex<HashMap<String, PgPool>>> = OnceLock::new();
+
+const POOL_MAX_SIZE: u32 = 10;
+
+/// The advisory lock held while the schema is migrated.
+const SCHEMA_LOCK: i64 = 0x6b72_696c_6c00;
+
+/// The migrations of the schema, in order. The version of the schema is
+/// the number of migrations that were applied.
+const MIGRATIONS: &[&str] = &[
+    // 1: The store, compatible with the kvx PostgreSQL backend.
+    "CREATE TABLE IF NOT EXISTS store (
+        namespace text NOT NULL,
+        scope text[] NOT NULL,
+        key text NOT NULL,
+        value jsonb NOT NULL,
+        PRIMARY KEY (namespace, scope, key)
+    )",
+];
+
+//------------ Postgres ------------------------------------------------------
+
+/// A store for a single namespace, using either the connection pool, or a
+/// transaction.
+pub struct Postgres<C> {
+    namespace: NamespaceBuf,
+    client: C,
+}
+
+impl Postgres<PgPool> {
+    pub fn new(
+        storage_uri: &Url,
+        namespace: NamespaceBuf,
+    ) -> Result<Self, kvx::Error> {
+        Ok(Postgres {
+            namespace,
+            client: blocking(|| pool(storage_uri))?,
+        })
+    }
+}
+
+impl<C> fmt::Debug for Postgres<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Postgres({})", self.namespace)
+    }
+}
+
+impl<C> fmt::Display for Postgres<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "KeyValueStore::Postgres({})", self.namespace)
+    }
+}
+
+impl KeyValueStoreBackend for Postgres<PgPool> {
+    fn transaction(
+        &self,
+        scope: &Scope,
+        callback: &mut dyn FnMut(
+            &dyn KeyValueStoreBackend,
+        ) -> Result<(), kvx::Error>,
+    ) -> Result<(), kvx::Error> {
+        blocking(|| {
+            let mut connection = self.client.get().map_err(db_err)?;
+            let mut transaction = connection.transaction().map_err(db_err)?;
+            transaction
+                .execute(
+                    "SELECT pg_advisory_xact_lock($1)",
+                    &[&lock_id(&self.namespace, scope)],
+                )
+                .map_err(db_err)?;
+
+            let store = Postgres {
+                namespace: self.namespace.clone(),
+                client: RefCell::new(transaction),
+            };
+
+            // The transaction is rolled back when it is dropped.
+            callback(&store)?;
+            store.client.into_inner().commit().map_err(db_err)?;
+            Ok(())
+        })
+    }
+}
+
+impl KeyValueStoreBackend for Postgres<RefCell<Transaction<'_>>> {
+    fn transaction(
+        &self,
+        _scope: &Scope,
+        callback: &mut dyn FnMut(
+            &dyn KeyValueStoreBackend,
+        ) -> Result<(), kvx::Error>,
+    ) -> Result<(), kvx::Error> {
+        callback(self)
+    }
+}
+
+impl<C: Client> ReadStore for Postgres<C> {
+    fn is_empty(&self) -> Result<bool, kvx::Error> {
+        Ok(self
+            .client
+            .query(
+                "SELECT 1 FROM store WHERE namespace = $1 LIMIT 1",
+                &[&self.namespace.as_str()],
+            )?
+            .is_empty())
+    }
+
+    fn has(&self, key: &Key) -> Result<bool, kvx::Error> {
+        Ok(!self
+            .client
+            .query(
+                "SELECT 1 FROM store
+                 WHERE namespace = $1 AND scope = $2 AND key = $3",
+                &[
+                    &self.namespace.as_str(),
+                    &segments(key.scope()),
+                    &key.name().as_str(),
+                ],
+            )?
+            .is_empty())
+    }
+
+    fn has_scope(&self, scope: &Scope) -> Result<bool, kvx::Error> {
+        Ok(!self
+            .client
+            .query(
+                "SELECT 1 FROM store
+                 WHERE namespace = $1 AND scope[:$3] = $2 LIMIT 1",
+                &[&self.namespace.as_str(), &segments(scope), &scope.len()],
+            )?
+            .is_empty())
+    }
+
+    fn get(&self, key: &Key) -> Result<Option<Value>, kvx::Error> {
+        Ok(self
+            .client
+            .query(
+                "SELECT value FROM store
+                 WHERE namespace = $1 AND scope = $2 AND key = $3",
+                &[
+                    &self.namespace.as_str(),
+                    &segments(key.scope()),
+                    &key.name().as_str(),
+                ],
+            )?
+            .first()
+            .map(|row| row.get(0)))
+    }
+
+    fn list_keys(&self, scope: &Scope) -> Result<Vec<Key>, kvx::Error> {
+        self.client
+            .query(
+                "SELECT scope, key FROM store
+                 WHERE namespace = $1 AND scope[:$3] = $2",
+                &[&self.namespace.as_str(), &segments(scope), &scope.len()],
+            )?
+            .iter()
+            .map(|row| {
+                let scope = parse_scope(row.get(0))?;
+                let name = SegmentBuf::from_str(row.get(1))?;
+                Ok(Key::new_scoped(scope, name))
+            })
+            .collect()
+    }
+
+    fn list_scopes(&self) -> Result<Vec<Scope>, kvx::Error> {
+        let mut scopes = vec![];
+        for row in self.client.query(
+            "SELECT DISTINCT scope FROM store WHERE namespace = $1",
+            &[&self.namespace.as_str()],
+        )? {
+            scopes.append(&mut parse_scope(row.get(0))?.sub_scopes());
+        }
+        scopes.sort();
+        scopes.dedup();
+        Ok(scopes)
+    }
+}
+
+impl<C: Client> WriteStore for Postgres<C> {
+    fn store(&self, key: &Key, value: Value) -> Result<(), kvx::Error> {
+        self.client.execute(
+            "INSERT INTO store (namespace, scope, key, value)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (namespace, scope, key)
+             DO UPDATE SET value = EXCLUDED.value",
+            &[
+                &self.namespace.as_str(),
+                &segments(key.scope()),
+                &key.name().as_str(),
+                &value,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn move_value(&self, from: &Key, to: &Key) -> Result<(), kvx::Error> {
+        if !self.has(from)? {
+            return Err(kvx::Error::UnknownKey);
+        }
+        self.delete(to)?;
+        self.client.execute(
+            "UPDATE store SET scope = $4, key = $5
+             WHERE namespace = $1 AND scope = $2 AND key = $3",
+            &[
+                &self.namespace.as_str(),
+                &segments(from.scope()),
+                &from.name().as_str(),
+                &segments(to.scope()),
+                &to.name().as_str(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn move_scope(&self, from: &Scope, to: &Scope) -> Result<(), kvx::Error> {
+        self.client.execute(
+            "UPDATE store SET scope = $3 || scope[$4 + 1:]
+             WHERE namespace = $1 AND scope[:$4] = $2",
+            &[
+                &self.namespace.as_str(),
+                &segments(from),
+                &segments(to),
+                &from.len(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delete(&self, key: &Key) -> Result<(), kvx::Error> {
+        self.client.execute(
+            "DELETE FROM store
+             WHERE namespace = $1 AND scope = $2 AND key = $3",
+            &[
+                &self.namespace.as_str(),
+                &segments(key.scope()),
+                &key.name().as_str(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn delete_scope(&self, scope: &Scope) -> Result<(), kvx::Error> {
+        self.client.execute(
+            "DELETE FROM store WHERE namespace = $1 AND scope[:$3] = $2",
+            &[&self.namespace.as_str(), &segments(scope), &scope.len()],
+        )?;
+        Ok(())
+    }
+
+    fn clear(&self) -> Result<(), kvx::Error> {
+        self.client.execute(
+            "DELETE FROM store WHERE namespace = $1",
+            &[&self.namespace.as_str()],
+        )?;
+        Ok(())
+    }
+
+    fn migrate_namespace(
+        &mut self,
+        to: NamespaceBuf,
+    ) -> Result<(), kvx::Error> {
+        let target = Postgres {
+            namespace: to,
+            client: &self.client,
+        };
+        if !target.is_empty()? {
+            return Err(kvx::Error::NamespaceMigration(format!(
+                "target namespace {} is not empty",
+                target.namespace
+            )));
+        }
+
+        self.client.execute(
+            "UPDATE store SET namespace = $2 WHERE namespace = $1",
+            &[&self.namespace.as_str(), &target.namespace.as_str()],
+        )?;
+        self.namespace = target.namespace;
+        Ok(())
+    }
+}
+
+//------------ Client --------------------------------------------------------
+
+/// Runs statements using a pooled connection, or in a transaction.
+pub trait Client {
+    fn query(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, kvx::Error>;
+
+    fn execute(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, kvx::Error>;
+}
+
+impl Client for PgPool {
+    fn query(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, kvx::Error> {
+        blocking(|| {
+            self.get()
+                .map_err(db_err)?
+                .query(statement, params)
+                .map_err(db_err)
+        })
+    }
+
+    fn execute(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, kvx::Error> {
+        blocking(|| {
+            self.get()
+                .map_err(db_err)?
+                .execute(statement, params)
+                .map_err(db_err)
+        })
+    }
+}
+
+impl Client for RefCell<Transaction<'_>> {
+    fn query(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, kvx::Error> {
+        self.borrow_mut().query(statement, params).map_err(db_err)
+    }
+
+    fn execute(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, kvx::Error> {
+        self.borrow_mut().execute(statement, params).map_err(db_err)
+    }
+}
+
+impl<C: Client> Client for &C {
+    fn query(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, kvx::Error> {
+        (*self).query(statement, params)
+    }
+
+    fn execute(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, kvx::Error> {
+        (*self).execute(statement, params)
+    }
+}
+
+//------------ Helpers -------------------------------------------------------
+
+/// Runs an operation that blocks on database access. When called from a
+/// worker thread of a multi-threaded Tokio runtime, the worker hands off
+/// its other tasks first. A single-threaded runtime cannot do that, so the
+/// database cannot be used from there.
+pub fn blocking<T>(op: impl FnOnce() -> T) -> T {
+    match Handle::try_current() {
+        Ok(handle)
+            if handle.runtime_flavor() == RuntimeFlavor::MultiThread =>
+        {
+            tokio::task::block_in_place(op)
+        }
+        _ => op(),
+    }
+}
+
+/// Returns the connection pool for the storage URI. The pool is created,
+/// and the schema is migrated, the first time.
+fn pool(storage_uri: &Url) -> Result<PgPool, kvx::Error> {
+    let mut pools = POOLS
+        .get_or_init(Default::default)
+        .lock()
+        .map_err(|e| kvx::Error::MutexLock(e.to_string()))?;
+
+    if let Some(pool) = pools.get(storage_uri.as_str()) {
+        return Ok(pool.clone());
+    }
+
+    let config =
+        postgres::Config::from_str(storage_uri.as_str()).map_err(db_err)?;
+    let pool = Pool::builder()
+        .max_size(POOL_MAX_SIZE)
+        .min_idle(Some(1))
+        .build(PostgresConnectionManager::new(config, NoTls))
+        .map_err(db_err)?;
+    migrate_schema(&pool)?;
+
+    pools.insert(storage_uri.to_string(), pool.clone());
+    Ok(pool)
+}
+
+/// Applies any migrations of the schema that were not applied yet. Fails
+/// if the schema is newer than this version of Krill supports.
+fn migrate_schema(pool: &PgPool) -> Result<(), kvx::Error> {
+    let mut connection = pool.get().map_err(db_err)?;
+    let mut transaction = connection.transaction().map_err(db_err)?;
+    transaction
+        .execute("SELECT pg_advisory_xact_lock($1)", &[&SCHEMA_LOCK])
+        .map_err(db_err)?;
+    transaction.batch_execute(
+        "CREATE TABLE IF NOT EXISTS krill_schema (version integer NOT NULL)",
+    ).map_err(db_err)?;
+
+    let version: i32 = transaction
+        .query_one("SELECT COALESCE(MAX(version), 0) FROM krill_schema", &[])
+        .map_err(db_err)?
+        .get(0);
+    let version = usize::try_from(version).unwrap_or_default();
+    if version > MIGRATIONS.len() {
+        return Err(kvx::Error::Other(format!(
+            "the database schema version {} is newer than version {} supported by this version of Krill",
+            version,
+            MIGRATIONS.len()
+        )));
+    }
+
+    for (applied, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        info!("Migrating the database schema to version {}", applied + 1);
+        transaction.batch_execute(migration).map_err(db_err)?;
+        transaction
+            .execute(
+                "INSERT INTO krill_schema (version) VALUES ($1)",
+                &[&(applied as i32 + 1)],
+            )
+            .map_err(db_err)?;
+    }
+
+    transaction.commit().map_err(db_err)?;
+    Ok(())
+}
+
+/// Returns the advisory lock for a scope in a namespace.
+fn lock_id(namespace: &Namespace, scope: &Scope) -> i64 {
+    let digest =
+        openssl::sha::sha256(format!("{}/{}", namespace, scope).as_bytes());
+    let mut id = [0; 8];
+    id.copy_from_slice(&digest[..8]);
+    i64::from_be_bytes(id)
+}
+
+/// Converts errors of the database client and the connection pool.
+fn db_err(e: impl fmt::Display) -> kvx::Error {
+    kvx::Error::Other(format!("database error: {}", e))
+}
+
+fn segments(scope: &Scope) -> Vec<&str> {
+    scope.into_iter().map(|segment| segment.as_str()).collect()
+}
+
+fn parse_scope(segments: Vec<String>) -> Result<Scope, kvx::Error> {
+    segments
+        .iter()
+        .map(|segment| SegmentBuf::from_str(segment))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Scope::new)
+        .map_err(kvx::Error::from)
+}
+
+//------------ Tests ---------------------------------------------------------
+
+/// These tests need a PostgreSQL database, which is used by the
+/// `postgres-tests` feature. Set `KRILL_TEST_POSTGRES_URI` to use another
+/// database than the default. Note that the store in this database is
+/// cleared.
+#[cfg(all(test, feature = "postgres-tests"))]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use kvx::{namespace, segment, Segment};
+
+    use super::*;
+
+    fn test_store(namespace: &Namespace) -> Postgres<PgPool> {
+        let storage_uri = crate::test::postgres_storage();
+        let store = Postgres::new(&storage_uri, namespace.into()).unwrap();
+        store.clear().unwrap();
+        store
+    }
+
+    #[test]
+    fn store_and_list_values() {
+        let store = test_store(namespace!("pg_values"));
+        let scope = Scope::from_segment(segment!("ca"));
+        let sub_scope = scope.with_sub_scope(segment!("archived"));
+        let global = Key::new_global(segment!("global.json"));
+        let key = Key::new_scoped(scope.clone(), segment!("key.json"));
+        let sub_key =
+            Key::new_scoped(sub_scope.clone(), segment!("old.json"));
+
+        assert!(store.is_empty().unwrap());
+        store.store(&global, Value::from(1)).unwrap();
+        store.store(&key, Value::from(2)).unwrap();
+        store.store(&key, Value::from(3)).unwrap();
+        store.store(&sub_key, Value::from(4)).unwrap();
+
+        assert!(!store.is_empty().unwrap());
+        assert!(store.has(&key).unwrap());
+        assert!(store.has_scope(&scope).unwrap());
+        assert_eq!(Some(Value::from(3)), store.get(&key).unwrap());
+        assert_eq!(
+            vec![scope.clone(), sub_scope.clone()],
+            store.list_scopes().unwrap()
+        );
+        assert_eq!(3, store.list_keys(&Scope::global()).unwrap().len());
+        assert_eq!(2, store.list_keys(&scope).unwrap().len());
+
+        let moved = Key::new_scoped(scope.clone(), segment!("moved.json"));
+        store.move_value(&key, &moved).unwrap();
+        assert!(!store.has(&key).unwrap());
+        assert_eq!(Some(Value::from(3)), store.get(&moved).unwrap());
+        assert!(store.move_value(&key, &moved).is_err());
+
+        let other = Scope::from_segment(segment!("other"));
+        store.move_scope(&scope, &other).unwrap();
+        assert!(!store.has_scope(&scope).unwrap());
+        assert_eq!(
+            Some(Value::from(4)),
+            store
+                .get(&Key::new_scoped(
+                    other.with_sub_scope(segment!("archived")),
+                    segment!("old.json")
+                ))
+                .unwrap()
+        );
+
+        store.delete_scope(&other).unwrap();
+        assert_eq!(vec![global], store.list_keys(&Scope::global()).unwrap());
+    }
+
+    #[test]
+    fn transactions_lock_the_scope() {
+        let store = Arc::new(test_store(namespace!("pg_locks")));
+        let scope = Scope::from_segment(segment!("counter"));
+        let key = Key::new_scoped(scope.clone(), segment!("count.json"));
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let store = store.clone();
+                let scope = scope.clone();
+                let key = key.clone();
+                thread::spawn(move || {
+                    for _ in 0..10 {
+                        store
+                            .transaction(&scope, &mut |kv| {
+                                let count = kv
+                                    .get(&key)?
+                                    .and_then(|v| v.as_u64())
+                                    .unwrap_or_default();
+                                kv.store(&key, Value::from(count + 1))
+                            })
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(Some(Value::from(40)), store.get(&key).unwrap());
+    }
+
+    #[test]
+    fn failed_transaction_is_rolled_back() {
+        let store = test_store(namespace!("pg_rollback"));
+        let key = Key::new_global(segment!("key.json"));
+
+        let res = store.transaction(&Scope::global(), &mut |kv| {
+            kv.store(&key, Value::from(1))?;
+            Err(kvx::Error::Unknown)
+        });
+        assert!(res.is_err());
+        assert!(!store.has(&key).unwrap());
+    }
+
+    #[test]
+    fn migrate_namespace() {
+        let mut store = test_store(namespace!("pg_upgrade"));
+        let current = test_store(namespace!("pg_current"));
+        let key = Key::new_global(segment!("key.json"));
+        store.store(&key, Value::from(1)).unwrap();
+
+        current.store(&key, Value::from(0)).unwrap();
+        assert!(store
+            .migrate_namespace(namespace!("pg_current").into())
+            .is_err());
+
+        current.clear().unwrap();
+        store
+            .migrate_namespace(namespace!("pg_current").into())
+            .unwrap();
+        assert_eq!(Some(Value::from(1)), current.get(&key).unwrap());
+        assert_eq!(Some(Value::from(1)), store.get(&key).unwrap());
+    }
+}
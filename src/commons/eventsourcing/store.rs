@@ -11,16 +11,22 @@ use serde::{de::DeserializeOwned, Serialize};
 use url::Url;
 
 use crate::commons::{
-    api::{CommandHistory, CommandHistoryCriteria, CommandHistoryRecord},
+    api::{
+        ArchivedCommands, AuditCriteria, CommandHistory,
+        CommandHistoryCriteria, CommandHistoryRecord, PointInTime, Timestamp,
+    },
     error::KrillIoError,
     eventsourcing::{
         cmd::Command, segment, Aggregate, Key, KeyValueError, KeyValueStore,
         PostSaveEventListener, PreSaveEventListener, Scope, Segment,
-        SegmentExt, StoredCommand, StoredCommandBuilder,
+        SegmentExt, StorageIssue, StoredCommand, StoredCommandBuilder,
     },
 };
 
-use super::InitCommand;
+use super::{
+    verify::{first_difference, numbered_keys, read_verified},
+    InitCommand,
+};
 
 pub type StoreResult<T> = Result<T, AggregateStoreError>;
 
@@ -40,6 +46,7 @@ impl<T: Clone + Serialize + DeserializeOwned + Sized + 'static> Storable
 /// This type is responsible for managing aggregates.
 pub struct AggregateStore<A: Aggregate> {
     kv: KeyValueStore,
+    archive: KeyValueStore,
     cache: RwLock<HashMap<MyHandle, Arc<A>>>,
     history_cache:
         Option<Mutex<HashMap<MyHandle, Vec<CommandHistoryRecord>>>>,
@@ -56,7 +63,9 @@ impl<A: Aggregate> AggregateStore<A> {
         use_history_cache: bool,
     ) -> StoreResult<Self> {
         let kv = KeyValueStore::create(storage_uri, namespace)?;
-        Self::create_from_kv(kv, use_history_cache)
+        let archive =
+            KeyValueStore::create_command_archive(storage_uri, namespace)?;
+        Self::create_from_kv(kv, archive, use_history_cache)
     }
 
     /// Creates an AggregateStore for upgrades using the given storage url
@@ -67,11 +76,14 @@ impl<A: Aggregate> AggregateStore<A> {
     ) -> StoreResult<Self> {
         let kv =
             KeyValueStore::create_upgrade_store(storage_uri, name_space)?;
-        Self::create_from_kv(kv, use_history_cache)
+        let archive =
+            KeyValueStore::create_command_archive(storage_uri, name_space)?;
+        Self::create_from_kv(kv, archive, use_history_cache)
     }
 
     fn create_from_kv(
         kv: KeyValueStore,
+        archive: KeyValueStore,
         use_history_cache: bool,
     ) -> StoreResult<Self> {
         let cache = RwLock::new(HashMap::new());
@@ -85,6 +97,7 @@ impl<A: Aggregate> AggregateStore<A> {
 
         let store = AggregateStore {
             kv,
+            archive,
             cache,
             history_cache,
             pre_save_listeners,
@@ -236,6 +249,45 @@ where
         self.execute_opt_command(cmd.handle(), Some(&cmd), false)
     }
 
+    /// Processes the commands, in order, on a copy of the latest version
+    /// of the aggregate, without storing anything. Returns the events that
+    /// the commands would result in, and the aggregate as it would be.
+    ///
+    /// The pre-save and post-save listeners are not informed.
+    pub fn dry_run(
+        &self,
+        handle: &MyHandle,
+        cmds: Vec<A::Command>,
+    ) -> Result<(A, Vec<A::Event>), A::Error> {
+        let mut agg = self.get_latest(handle)?.as_ref().clone();
+        let mut all_events = vec![];
+
+        for cmd in cmds {
+            if let Some(expected) = cmd.version() {
+                if expected != agg.version() {
+                    return Err(A::Error::from(
+                        AggregateStoreError::UnexpectedVersion(
+                            handle.clone(),
+                            expected,
+                            agg.version(),
+                        ),
+                    ));
+                }
+            }
+
+            let events = agg.process_command(cmd)?;
+            if !events.is_empty() {
+                agg.increment_version();
+                for event in &events {
+                    agg.apply(event.clone());
+                }
+                all_events.extend(events);
+            }
+        }
+
+        Ok((agg, all_events))
+    }
+
     /// Returns true if an instance exists for the id
     pub fn has(&self, id: &MyHandle) -> Result<bool, AggregateStoreError> {
         let init_command_key = Self::key_for_command(id, 0);
@@ -284,6 +336,11 @@ where
                                 let agg: A = serde_json::from_value(value)?;
                                 Ok(Arc::new(agg))
                             }
+                            None if kv.has(&Self::key_for_archived(handle))? => {
+                                // Replaying from the init command would miss the
+                                // archived commands.
+                                Err(A::Error::from(AggregateStoreError::ArchivedWithoutSnapshot(handle.clone())))
+                            }
                             None => {
                                 let init_key = Self::key_for_command(handle, 0);
                                 match kv.get(&init_key)? {
@@ -349,6 +406,21 @@ where
                 let res = if let Some(cmd) = cmd_opt {
                     trace!("apply command {} to {}", cmd, handle);
 
+                    // Reject a command for a specific version of the aggregate if
+                    // it has moved on since, without storing the command.
+                    if let Some(expected) = cmd.version() {
+                        if expected != agg.version() {
+                            if changed_from_cached {
+                                self.cache_update(handle, agg.clone());
+                            }
+                            return Ok(Err(A::Error::from(AggregateStoreError::UnexpectedVersion(
+                                handle.clone(),
+                                expected,
+                                agg.version(),
+                            ))));
+                        }
+                    }
+
                     let aggregate = Arc::make_mut(&mut agg);
 
                     let version = aggregate.version();
@@ -507,33 +579,93 @@ where
             CommandHistory::new(offset, total, matching)
         }
 
+        let archived = self.archived_commands(id)?;
+
+        let history =
+            self.with_history_records(id, archived.as_ref(), |records| {
+                command_history_for_records(crit, records)
+            })?;
+
+        Ok(history.with_archived(archived))
+    }
+
+    /// Returns the history records for the given aggregate that match the
+    /// audit criteria, including those for archived commands.
+    pub fn audit_records(
+        &self,
+        id: &MyHandle,
+        crit: &AuditCriteria,
+    ) -> Result<Vec<CommandHistoryRecord>, AggregateStoreError> {
+        let archived = self.archived_commands(id)?;
+
+        let mut matching = vec![];
+        if let Some(archived) = &archived {
+            for version in 1..=archived.last_version() {
+                let key = Self::key_for_command(id, version);
+                if let Some(command) =
+                    self.archive.get::<StoredCommand<A>>(&key)?
+                {
+                    let record = CommandHistoryRecord::from(command);
+                    if crit.matches(&record) {
+                        matching.push(record);
+                    }
+                }
+            }
+        }
+
+        self.with_history_records(id, archived.as_ref(), |records| {
+            matching.extend(
+                records
+                    .iter()
+                    .filter(|record| crit.matches(record))
+                    .cloned(),
+            );
+        })?;
+
+        Ok(matching)
+    }
+
+    /// Calls the given function with the up-to-date history records for
+    /// the given aggregate, using the history cache if there is one.
+    fn with_history_records<T>(
+        &self,
+        id: &MyHandle,
+        archived: Option<&ArchivedCommands>,
+        f: impl FnOnce(&[CommandHistoryRecord]) -> T,
+    ) -> Result<T, AggregateStoreError> {
         match &self.history_cache {
             Some(mutex) => {
                 let mut cache_lock = mutex.lock().unwrap();
                 let records = cache_lock.entry(id.clone()).or_default();
-                self.update_history_records(records, id)?;
-                Ok(command_history_for_records(crit, records))
+                self.update_history_records(records, id, archived)?;
+                Ok(f(records))
             }
             None => {
                 let mut records = vec![];
-                self.update_history_records(&mut records, id)?;
-                Ok(command_history_for_records(crit, &records))
+                self.update_history_records(&mut records, id, archived)?;
+                Ok(f(&records))
             }
         }
     }
 
-    /// Updates history records for a given aggregate
+    /// Updates history records for a given aggregate. Archived commands
+    /// are not included.
     fn update_history_records(
         &self,
         records: &mut Vec<CommandHistoryRecord>,
         id: &MyHandle,
+        archived: Option<&ArchivedCommands>,
     ) -> Result<(), AggregateStoreError> {
-        let mut version = match records.last() {
-            Some(record) => record.version + 1,
-            None => 1,
+        let mut version = match (records.last(), archived) {
+            (Some(record), _) => record.version + 1,
+            (None, Some(archived)) => archived.last_version() + 1,
+            (None, None) => 1,
         };
 
-        while let Ok(command) = self.get_command(id, version) {
+        while let Ok(Some(command)) = self
+            .kv
+            .get::<StoredCommand<A>>(&Self::key_for_command(id, version))
+        {
             records.push(CommandHistoryRecord::from(command));
             version += 1;
         }
@@ -541,21 +673,176 @@ where
         Ok(())
     }
 
+    /// Returns the summary of archived commands for the given aggregate,
+    /// if any commands were archived.
+    pub fn archived_commands(
+        &self,
+        id: &MyHandle,
+    ) -> Result<Option<ArchivedCommands>, AggregateStoreError> {
+        self.kv
+            .get(&Self::key_for_archived(id))
+            .map_err(AggregateStoreError::from)
+    }
+
+    /// Moves the commands for the given aggregate that were issued
+    /// before the given time to the archive. The snapshot is saved first
+    /// and becomes the base for rebuilding the aggregate, so only commands
+    /// covered by it are archived. The init command is always kept.
+    ///
+    /// Returns the number of commands that were archived.
+    pub fn archive_commands(
+        &self,
+        id: &MyHandle,
+        before: Time,
+    ) -> Result<u64, A::Error> {
+        let snapshot_version = self.save_snapshot(id)?.version();
+
+        // Hold on to the history cache lock, so that no incomplete
+        // history is cached while commands are moved.
+        let mut cache_lock = self
+            .history_cache
+            .as_ref()
+            .map(|mutex| mutex.lock().unwrap());
+
+        let previous = self.archived_commands(id)?;
+        let first =
+            previous.as_ref().map(|a| a.last_version() + 1).unwrap_or(1);
+
+        let mut moved = vec![];
+        let mut last_time = None;
+        for version in first..snapshot_version {
+            let key = Self::key_for_command(id, version);
+            let value: serde_json::Value =
+                match self.kv.get(&key).map_err(AggregateStoreError::from)? {
+                    Some(value) => value,
+                    None => break,
+                };
+
+            let command: StoredCommand<A> =
+                serde_json::from_value(value.clone())
+                    .map_err(KeyValueError::from)
+                    .map_err(AggregateStoreError::from)?;
+            if command.time() >= before {
+                break;
+            }
+
+            self.archive
+                .store(&key, &value)
+                .map_err(AggregateStoreError::from)?;
+            last_time = Some(command.time());
+            moved.push(key);
+        }
+
+        let last_time = match last_time {
+            Some(time) => time,
+            None => return Ok(0),
+        };
+
+        // Update the summary before dropping the commands, so that the
+        // history always starts at a command that can still be found.
+        let count = moved.len() as u64;
+        let archived = ArchivedCommands::new(
+            previous.map(|a| a.count()).unwrap_or(0) + count,
+            first + count - 1,
+            Timestamp::from(last_time),
+        );
+        self.kv
+            .store(&Self::key_for_archived(id), &archived)
+            .map_err(AggregateStoreError::from)?;
+
+        for key in moved {
+            self.kv.drop_key(&key).map_err(AggregateStoreError::from)?;
+        }
+
+        if let Some(cache) = cache_lock.as_mut() {
+            cache.remove(id);
+        }
+
+        Ok(count)
+    }
+
+    /// Rebuilds the aggregate as it was at the given point in its history,
+    /// by replaying its stored commands. Returns the aggregate and the time
+    /// of the last applied command, or `None` if the aggregate did not
+    /// exist at that point or never had the given version.
+    pub fn get_at(
+        &self,
+        id: &MyHandle,
+        point: PointInTime,
+    ) -> Result<Option<(A, Time)>, AggregateStoreError> {
+        let init_command = match self.stored_command(id, 0)? {
+            Some(command) => command,
+            None => return Ok(None),
+        };
+
+        let mut time = init_command.time();
+        if let PointInTime::Time(at) = point {
+            if time > at {
+                return Ok(None);
+            }
+        }
+
+        let mut agg = match init_command.into_init() {
+            Some(init_event) => A::init(id.clone(), init_event),
+            None => return Err(AggregateStoreError::InitError(id.clone())),
+        };
+
+        loop {
+            if point == PointInTime::Version(agg.version()) {
+                break;
+            }
+
+            let command = match self.stored_command(id, agg.version())? {
+                Some(command) => command,
+                None => break,
+            };
+
+            if let PointInTime::Time(at) = point {
+                if command.time() > at {
+                    break;
+                }
+            }
+
+            time = command.time();
+            agg.apply_command(command);
+        }
+
+        match point {
+            PointInTime::Version(version) if version != agg.version() => {
+                Ok(None)
+            }
+            _ => Ok(Some((agg, time))),
+        }
+    }
+
     /// Get the command for this key, if it exists
     pub fn get_command(
         &self,
         id: &MyHandle,
         version: u64,
     ) -> Result<StoredCommand<A>, AggregateStoreError> {
-        let key = Self::key_for_command(id, version);
-
-        match self.kv.get(&key)? {
+        match self.stored_command(id, version)? {
             Some(cmd) => Ok(cmd),
             None => {
                 Err(AggregateStoreError::CommandNotFound(id.clone(), version))
             }
         }
     }
+
+    /// Get the command for this key from the current store, or from the
+    /// archive if it was archived.
+    fn stored_command(
+        &self,
+        id: &MyHandle,
+        version: u64,
+    ) -> Result<Option<StoredCommand<A>>, AggregateStoreError> {
+        let key = Self::key_for_command(id, version);
+
+        match self.kv.get(&key)? {
+            Some(cmd) => Ok(Some(cmd)),
+            None => self.archive.get(&key).map_err(AggregateStoreError::from),
+        }
+    }
 }
 
 impl<A: Aggregate> AggregateStore<A>
@@ -575,6 +862,127 @@ where
     }
 }
 
+/// # Verify stored data
+impl<A: Aggregate> AggregateStore<A>
+where
+    A::Error: From<AggregateStoreError>,
+{
+    /// Verifies the stored data for an aggregate. All commands must be
+    /// readable and have contiguous versions starting with the init
+    /// command, and replaying them up to the version of the snapshot, if
+    /// there is one, must result in the same state as the snapshot.
+    ///
+    /// Returns all issues found. Errors are only returned if the store
+    /// itself cannot be accessed.
+    pub fn verify(
+        &self,
+        id: &MyHandle,
+    ) -> Result<Vec<StorageIssue>, AggregateStoreError> {
+        let mut issues = vec![];
+
+        let scope = Self::scope_for_agg(id);
+        let current = numbered_keys(&self.kv, &scope, "command-")?;
+        let archived = numbered_keys(&self.archive, &scope, "command-")?;
+
+        let mut versions = current.clone();
+        versions.extend(archived);
+        versions.sort_unstable();
+        versions.dedup();
+
+        let mut expected = 0;
+        for version in &versions {
+            for missing in expected..*version {
+                if missing == 0 {
+                    issues.push(StorageIssue::MissingInitCommand);
+                } else {
+                    issues.push(StorageIssue::MissingCommand(missing));
+                }
+            }
+            expected = version + 1;
+        }
+
+        let mut init_command: Option<StoredCommand<A>> = None;
+        for version in versions {
+            let key = Self::key_for_command(id, version);
+            let kv = if current.contains(&version) {
+                &self.kv
+            } else {
+                &self.archive
+            };
+            let command: Option<StoredCommand<A>> =
+                read_verified(kv, &key, &mut issues);
+
+            if let Some(command) = command {
+                if command.version() != version {
+                    issues.push(StorageIssue::CommandVersionMismatch {
+                        key,
+                        version: command.version(),
+                    });
+                } else if version == 0 {
+                    init_command = Some(command);
+                }
+            }
+        }
+
+        let snapshot: Option<A> =
+            read_verified(&self.kv, &Self::key_for_snapshot(id), &mut issues);
+
+        if snapshot.is_none() && self.archived_commands(id)?.is_some() {
+            issues.push(StorageIssue::MissingSnapshot);
+        }
+
+        // Replay the commands the same way as is done when there is no
+        // snapshot, up to the version of the snapshot.
+        if let (Some(init_command), Some(snapshot)) = (init_command, snapshot)
+        {
+            let mut agg = match init_command.into_init() {
+                Some(init) => A::init(id.clone(), init),
+                None => {
+                    issues.push(StorageIssue::MissingInitCommand);
+                    return Ok(issues);
+                }
+            };
+
+            while agg.version() < snapshot.version() {
+                match self.stored_command(id, agg.version()).ok().flatten() {
+                    Some(command) => agg.apply_command(command),
+                    None => break,
+                }
+            }
+
+            if agg.version() == snapshot.version() {
+                Self::verify_snapshot(&agg, &snapshot, &mut issues)?;
+            } else {
+                issues.push(StorageIssue::SnapshotAhead {
+                    snapshot: snapshot.version(),
+                    replayed: agg.version(),
+                });
+            }
+        }
+
+        Ok(issues)
+    }
+
+    fn verify_snapshot(
+        replayed: &A,
+        snapshot: &A,
+        issues: &mut Vec<StorageIssue>,
+    ) -> Result<(), AggregateStoreError> {
+        let replayed_json =
+            serde_json::to_value(replayed).map_err(KeyValueError::from)?;
+        let snapshot_json =
+            serde_json::to_value(snapshot).map_err(KeyValueError::from)?;
+
+        if let Some(path) = first_difference(&replayed_json, &snapshot_json) {
+            issues.push(StorageIssue::SnapshotDivergence {
+                version: snapshot.version(),
+                path,
+            });
+        }
+        Ok(())
+    }
+}
+
 /// # Manage values in the KeyValue store
 impl<A: Aggregate> AggregateStore<A>
 where
@@ -590,6 +998,10 @@ where
         Key::new_scoped(Self::scope_for_agg(agg), segment!("snapshot.json"))
     }
 
+    fn key_for_archived(agg: &MyHandle) -> Key {
+        Key::new_scoped(Self::scope_for_agg(agg), segment!("archived.json"))
+    }
+
     fn key_for_command(agg: &MyHandle, version: u64) -> Key {
         Key::new_scoped(
             Self::scope_for_agg(agg),
@@ -618,6 +1030,9 @@ where
         let scope = Self::scope_for_agg(id);
 
         self.kv.execute(&scope, |kv| kv.delete_scope(&scope))?;
+        if self.archive.has_scope(&scope)? {
+            self.archive.drop_scope(&scope)?;
+        }
 
         self.cache_remove(id);
         Ok(())
@@ -642,6 +1057,8 @@ pub enum AggregateStoreError {
     CouldNotArchive(MyHandle, String),
     CommandCorrupt(MyHandle, u64),
     CommandNotFound(MyHandle, u64),
+    ArchivedWithoutSnapshot(MyHandle),
+    UnexpectedVersion(MyHandle, u64, u64),
 }
 
 impl fmt::Display for AggregateStoreError {
@@ -706,6 +1123,16 @@ impl fmt::Display for AggregateStoreError {
                     handle, key
                 )
             }
+            AggregateStoreError::ArchivedWithoutSnapshot(handle) => write!(
+                f,
+                "Cannot rebuild '{}' without its snapshot, because older commands were archived",
+                handle
+            ),
+            AggregateStoreError::UnexpectedVersion(handle, expected, actual) => write!(
+                f,
+                "'{}' was expected at version '{}', but it is at version '{}'",
+                handle, expected, actual
+            ),
         }
     }
 }
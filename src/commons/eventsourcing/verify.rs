@@ -0,0 +1,187 @@
+use std::fmt;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use super::{Key, KeyValueError, KeyValueStore, Scope};
+
+//------------ StorageIssue --------------------------------------------------
+
+/// An inconsistency found when verifying the stored data for a single
+/// aggregate or WAL supporting instance.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StorageIssue {
+    /// The value for the key cannot be parsed.
+    CorruptJson { key: Key, error: String },
+
+    /// There is no init command (command-0), but there are other commands.
+    MissingInitCommand,
+
+    /// The command for this version is missing while later commands exist.
+    MissingCommand(u64),
+
+    /// The command stored under this key claims a different version.
+    CommandVersionMismatch { key: Key, version: u64 },
+
+    /// The snapshot is for a version that cannot be reached by replaying
+    /// the stored commands.
+    SnapshotAhead { snapshot: u64, replayed: u64 },
+
+    /// Replaying the commands up to the version of the snapshot does not
+    /// result in the same state as the snapshot. The path points to the
+    /// first value found to be different.
+    SnapshotDivergence { version: u64, path: String },
+
+    /// There is no snapshot for a WAL supporting instance.
+    MissingSnapshot,
+
+    /// The WAL set for this revision is missing while later sets exist.
+    MissingWalSet(u64),
+
+    /// The WAL set stored under this key claims a different revision.
+    WalSetRevisionMismatch { key: Key, revision: u64 },
+}
+
+impl fmt::Display for StorageIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageIssue::CorruptJson { key, error } => {
+                write!(f, "cannot parse '{}': {}", key, error)
+            }
+            StorageIssue::MissingInitCommand => {
+                write!(f, "init command is missing")
+            }
+            StorageIssue::MissingCommand(version) => {
+                write!(f, "command for version {} is missing", version)
+            }
+            StorageIssue::CommandVersionMismatch { key, version } => {
+                write!(f, "'{}' contains command for version {}", key, version)
+            }
+            StorageIssue::SnapshotAhead { snapshot, replayed } => write!(
+                f,
+                "snapshot is at version {} but commands only reach version {}",
+                snapshot, replayed
+            ),
+            StorageIssue::SnapshotDivergence { version, path } => write!(
+                f,
+                "snapshot differs from replayed commands at version {} in '{}'",
+                version, path
+            ),
+            StorageIssue::MissingSnapshot => write!(f, "snapshot is missing"),
+            StorageIssue::MissingWalSet(revision) => {
+                write!(f, "WAL set for revision {} is missing", revision)
+            }
+            StorageIssue::WalSetRevisionMismatch { key, revision } => write!(
+                f,
+                "'{}' contains WAL set for revision {}",
+                key, revision
+            ),
+        }
+    }
+}
+
+/// Reads and parses the value for a key that is expected to be present.
+/// If the value cannot be read or parsed this is added to the issues and
+/// `None` is returned.
+pub(super) fn read_verified<V: DeserializeOwned>(
+    kv: &KeyValueStore,
+    key: &Key,
+    issues: &mut Vec<StorageIssue>,
+) -> Option<V> {
+    let result = kv
+        .get::<serde_json::Value>(key)
+        .map_err(|e| e.to_string())
+        .and_then(|value| {
+            value
+                .map(serde_json::from_value)
+                .transpose()
+                .map_err(|e| e.to_string())
+        });
+
+    match result {
+        Ok(value) => value,
+        Err(error) => {
+            issues.push(StorageIssue::CorruptJson {
+                key: key.clone(),
+                error,
+            });
+            None
+        }
+    }
+}
+
+/// Returns the sorted numbers of all keys named "{prefix}{number}.json"
+/// in the scope.
+pub(super) fn numbered_keys(
+    kv: &KeyValueStore,
+    scope: &Scope,
+    prefix: &str,
+) -> Result<Vec<u64>, KeyValueError> {
+    let mut numbers: Vec<u64> = kv
+        .keys(scope, prefix)?
+        .iter()
+        .filter_map(|key| {
+            key.name()
+                .as_str()
+                .strip_prefix(prefix)
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|nr| nr.parse().ok())
+        })
+        .collect();
+    numbers.sort_unstable();
+    Ok(numbers)
+}
+
+/// Fields that are set to the current time when an event is applied,
+/// rather than to a time recorded in the event. They will differ between
+/// a snapshot and replayed commands, so they are not compared.
+const APPLY_TIME_FIELDS: &[&str] = &["last_key_change", "since"];
+
+/// Returns the path to the first value that differs between the two
+/// JSON values, or `None` if they are equal. Fields listed in
+/// `APPLY_TIME_FIELDS` are ignored.
+pub(super) fn first_difference(
+    left: &Value,
+    right: &Value,
+) -> Option<String> {
+    match (left, right) {
+        (Value::Object(left), Value::Object(right)) => {
+            for (name, left_value) in left {
+                if APPLY_TIME_FIELDS.contains(&name.as_str()) {
+                    continue;
+                }
+                let diff = match right.get(name) {
+                    Some(right_value) => {
+                        first_difference(left_value, right_value)
+                    }
+                    None => Some(String::new()),
+                };
+                if let Some(path) = diff {
+                    return Some(join_path(name, &path));
+                }
+            }
+            right
+                .keys()
+                .find(|name| !left.contains_key(*name))
+                .map(|name| name.to_string())
+        }
+        (Value::Array(left), Value::Array(right))
+            if left.len() == right.len() =>
+        {
+            left.iter().zip(right).enumerate().find_map(|(nr, (l, r))| {
+                first_difference(l, r)
+                    .map(|path| join_path(&nr.to_string(), &path))
+            })
+        }
+        _ if left == right => None,
+        _ => Some(String::new()),
+    }
+}
+
+fn join_path(name: &str, path: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", name, path)
+    }
+}
@@ -12,9 +12,11 @@ use url::Url;
 
 use crate::commons::eventsourcing::{
     segment, Key, KeyValueError, KeyValueStore, Scope, Segment, SegmentExt,
-    Storable,
+    Storable, StorageIssue,
 };
 
+use super::verify::{numbered_keys, read_verified};
+
 //------------ WalSupport ----------------------------------------------------
 
 /// Implement this trait to get write-ahead logging support for a type.
@@ -404,6 +406,59 @@ impl<T: WalSupport> WalStore<T> {
         self.execute_opt_command(handle, None, true)
     }
 
+    /// Verifies the stored data for an instance. The snapshot and all WAL
+    /// sets must be readable, and the WAL sets following the snapshot must
+    /// have contiguous revisions. Older WAL sets are ignored, as they are
+    /// never applied.
+    ///
+    /// Returns all issues found. Errors are only returned if the store
+    /// itself cannot be accessed.
+    pub fn verify(
+        &self,
+        handle: &MyHandle,
+    ) -> WalStoreResult<Vec<StorageIssue>> {
+        let mut issues = vec![];
+
+        let snapshot_key = Self::key_for_snapshot(handle);
+        let snapshot: Option<T> = if self.kv.has(&snapshot_key)? {
+            read_verified(&self.kv, &snapshot_key, &mut issues)
+        } else {
+            issues.push(StorageIssue::MissingSnapshot);
+            None
+        };
+
+        let revisions =
+            numbered_keys(&self.kv, &Self::scope_for_handle(handle), "wal-")?;
+
+        let mut expected =
+            snapshot.as_ref().map(|snapshot| snapshot.revision());
+        for revision in revisions {
+            let key = Self::key_for_wal_set(handle, revision);
+            let set: Option<WalSet<T>> =
+                read_verified(&self.kv, &key, &mut issues);
+
+            if let Some(set) = set {
+                if set.revision != revision {
+                    issues.push(StorageIssue::WalSetRevisionMismatch {
+                        key,
+                        revision: set.revision,
+                    });
+                }
+            }
+
+            if let Some(next) = expected {
+                if revision >= next {
+                    for missing in next..revision {
+                        issues.push(StorageIssue::MissingWalSet(missing));
+                    }
+                    expected = Some(revision + 1);
+                }
+            }
+        }
+
+        Ok(issues)
+    }
+
     fn cache_get(&self, id: &MyHandle) -> Option<Arc<T>> {
         self.cache.read().unwrap().get(id).cloned()
     }
@@ -3,7 +3,7 @@ use std::{env, fmt, path::PathBuf, str::FromStr, time::Duration};
 
 use bytes::Bytes;
 use reqwest::{
-    header::{HeaderMap, HeaderValue, CONTENT_TYPE, USER_AGENT},
+    header::{HeaderMap, HeaderValue, CONTENT_TYPE, IF_MATCH, USER_AGENT},
     Response, StatusCode,
 };
 use serde::{de::DeserializeOwned, Serialize};
@@ -223,7 +223,19 @@ pub async fn post_empty(
     uri: &str,
     token: Option<&Token>,
 ) -> Result<(), Error> {
-    let res = do_empty_post(uri, token).await?;
+    let res = do_empty_post(uri, None, token).await?;
+    empty_response(uri, res).await
+}
+
+/// Performs a POST with no data to the given URI, with an If-Match header
+/// so that the server only applies it if the target is still at the
+/// given version.
+pub async fn post_empty_if_match(
+    uri: &str,
+    version: u64,
+    token: Option<&Token>,
+) -> Result<(), Error> {
+    let res = do_empty_post(uri, Some(version), token).await?;
     empty_response(uri, res).await
 }
 
@@ -232,19 +244,27 @@ pub async fn post_empty_with_response<T: DeserializeOwned>(
     uri: &str,
     token: Option<&Token>,
 ) -> Result<T, Error> {
-    let res = do_empty_post(uri, token).await?;
+    let res = do_empty_post(uri, None, token).await?;
     process_json_response(uri, res).await
 }
 
 async fn do_empty_post(
     uri: &str,
+    if_match: Option<u64>,
     token: Option<&Token>,
 ) -> Result<Response, Error> {
     if env::var(KRILL_CLI_API_ENV).is_ok() {
         report_post_and_exit(uri, None, token, "<empty>");
     }
 
-    let headers = headers(uri, Some(JSON_CONTENT), token)?;
+    let mut headers = headers(uri, Some(JSON_CONTENT), token)?;
+    if let Some(version) = if_match {
+        headers.insert(
+            IF_MATCH,
+            HeaderValue::from_str(&format!("\"{}\"", version))
+                .map_err(|e| Error::request_build(uri, e))?,
+        );
+    }
     client(uri)?
         .post(uri)
         .headers(headers)
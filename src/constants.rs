@@ -49,6 +49,7 @@ pub fn test_announcements_enabled() -> bool {
 pub const TASK_QUEUE_NS: &Namespace = namespace!("tasks");
 pub const CASERVER_NS: &Namespace = namespace!("cas");
 pub const CA_OBJECTS_NS: &Namespace = namespace!("ca_objects");
+pub const ENCRYPTION_NS: &Namespace = namespace!("encryption");
 pub const KEYS_NS: &Namespace = namespace!("keys");
 pub const PUBSERVER_CONTENT_NS: &Namespace = namespace!("pubd_objects");
 pub const PUBSERVER_NS: &Namespace = namespace!("pubd");
@@ -57,6 +58,31 @@ pub const SIGNERS_NS: &Namespace = namespace!("signers");
 pub const STATUS_NS: &Namespace = namespace!("status");
 pub const TA_PROXY_SERVER_NS: &Namespace = namespace!("ta_proxy");
 pub const TA_SIGNER_SERVER_NS: &Namespace = namespace!("ta_signer");
+pub const WEBHOOKS_NS: &Namespace = namespace!("webhooks");
+pub const CRYPT_STATE_NS: &Namespace = namespace!("login_sessions");
+
+// Commands archived because of `history_archive_days`, see
+// KeyValueStore::create_command_archive
+pub const ARCHIVED_CASERVER_NS: &Namespace = namespace!("archived_cas");
+
+/// The namespaces with the data kept by Krill for its CAs, Publication
+/// Server, Trust Anchor, webhooks and login sessions. The task queue, the
+/// signer mappings and the storage encryption data key are not included,
+/// because they need to be handled separately.
+pub const KRILL_DATA_NAMESPACES: &[&Namespace] = &[
+    CASERVER_NS,
+    ARCHIVED_CASERVER_NS,
+    CA_OBJECTS_NS,
+    KEYS_NS,
+    CRYPT_STATE_NS,
+    PROPERTIES_NS,
+    PUBSERVER_CONTENT_NS,
+    PUBSERVER_NS,
+    STATUS_NS,
+    TA_PROXY_SERVER_NS,
+    TA_SIGNER_SERVER_NS,
+    WEBHOOKS_NS,
+];
 
 pub const PROPERTIES_DFLT_NAME: &str = "main";
 
@@ -84,6 +110,9 @@ pub const KRILL_CLI_MY_CA_ENV: &str = "KRILL_CLI_MY_CA";
 
 pub const CA_REFRESH_SECONDS_MIN: u32 = 3600;
 pub const CA_REFRESH_SECONDS_MAX: u32 = 3 * 24 * 3600; // 3 days
+
+// The staging period for new keys in automatic key rolls, see RFC 6489
+pub const KEYROLL_ACTIVATE_AFTER_HOURS_DEFAULT: u32 = 24;
 pub const CA_SUSPEND_MIN_HOURS: u32 = 48; // at least 2 days
 pub const SCHEDULER_REQUEUE_DELAY_SECONDS: i64 = 300;
 pub const SCHEDULER_RESYNC_REPO_CAS_THRESHOLD: usize = 5;
@@ -101,6 +130,12 @@ pub const BGP_RIS_REFRESH_MINUTES: i64 = 60;
 pub const HTTP_CLIENT_TIMEOUT_SECS: u64 = 120;
 pub const HTTP_USER_AGENT_TRUNCATE: usize = 256; // Will truncate received user-agent values at this size.
 pub const OPENID_CONNECT_HTTP_CLIENT_TIMEOUT_SECS: u64 = 30;
+pub const EVENT_STREAM_KEEP_ALIVE_SECS: u64 = 30;
+pub const WEBHOOK_TIMEOUT_SECS: u64 = 30;
+pub const WEBHOOK_MAX_ATTEMPTS: u32 = 10;
+pub const WEBHOOK_RETRY_DELAY_SECS: i64 = 30; // Doubled after each attempt
+pub const WEBHOOK_RETRY_DELAY_MAX_SECS: i64 = 3600;
+pub const WEBHOOK_MAX_CONCURRENT_DELIVERIES: usize = 4;
 
 pub const NO_RESOURCE: NoResourceType = NoResourceType;
 
@@ -23,10 +23,11 @@
 
 use std::sync::atomic::{AtomicU64, Ordering};
 
-use kvx::{namespace, segment, Key, Namespace, Segment};
+use kvx::{segment, Key, Segment};
 
 use crate::{
     commons::{error::Error, util::ext_serde, KrillResult},
+    constants::CRYPT_STATE_NS,
     daemon::config::Config,
 };
 
@@ -40,7 +41,6 @@ const CLEARTEXT_PREFIX_LEN: usize =
     CHACHA20_NONCE_BYTE_LEN + POLY1305_TAG_BYTE_LEN;
 const UNUSED_AAD: [u8; 0] = [0; 0];
 
-const CRYPT_STATE_NS: &Namespace = namespace!("login_sessions");
 const CRYPT_STATE_KEY: &Segment = segment!("main_key");
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -74,6 +74,10 @@ iterable_enum! {
         ASPAS_ANALYSIS,
         BGPSEC_READ,
         BGPSEC_UPDATE,
+        GHOSTBUSTERS_READ,
+        GHOSTBUSTERS_UPDATE,
+        GEOFEED_SIGN,
+        RSC_SIGN,
         RTA_LIST,
         RTA_READ,
         RTA_UPDATE
@@ -0,0 +1,410 @@
+//! Backup and restore of all data kept by a Krill instance.
+//!
+//! A backup contains all key value pairs of the namespaces used by Krill
+//! in its `storage_uri`, together with a manifest of the stored versions
+//! of all aggregates. The data is read while no changes can be made by
+//! this Krill process, see [`KeyValueStore::with_exclusive_access`], so
+//! that all namespaces are consistent with each other. The complete
+//! backup is kept in memory.
+//!
+//! Values are included as they are stored. So, if storage encryption is
+//! enabled, then the backup contains the encrypted values together with
+//! the wrapped data key, and it can only be restored using the same
+//! passphrase or HSM key. Otherwise it contains all data, including the
+//! keys of an OpenSSL signer, in plain text.
+//!
+//! A backup can only be restored into empty storage, and only by a Krill
+//! version that is the same or newer than the version that made it. Any
+//! data migrations will then be done when Krill is started. A restore is
+//! not atomic, but if it fails then the data that was restored so far is
+//! removed again.
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use kvx::Namespace;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    commons::{
+        api::Timestamp,
+        error::Error,
+        eventsourcing::{Key, KeyValueStore, Scope},
+        util::KrillVersion,
+        KrillResult,
+    },
+    constants::{ENCRYPTION_NS, KRILL_DATA_NAMESPACES, SIGNERS_NS},
+    daemon::{
+        config::Config, encryption::enable_storage_encryption,
+        properties::PropertiesManager,
+    },
+};
+
+/// Returns the namespaces included in a backup. The task queue is left
+/// out, because Krill plans its tasks again when it is started.
+fn backup_namespaces() -> impl Iterator<Item = &'static Namespace> {
+    KRILL_DATA_NAMESPACES
+        .iter()
+        .copied()
+        .chain([SIGNERS_NS, ENCRYPTION_NS])
+}
+
+//------------ KrillBackup ---------------------------------------------------
+
+/// A backup of all data of a Krill instance.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct KrillBackup {
+    manifest: BackupManifest,
+    data: Vec<NamespaceData>,
+}
+
+impl KrillBackup {
+    /// Creates a backup of all data in the given storage.
+    pub fn create(storage_uri: &Url) -> KrillResult<Self> {
+        let properties = PropertiesManager::create(storage_uri, false)?;
+        // Properties are only initialized after the first upgrade, so if
+        // they are missing then the data was made by this version.
+        let krill_version = if properties.is_initialized() {
+            properties.current_krill_version()?
+        } else {
+            KrillVersion::code_version()
+        };
+
+        // No changes can be made by this Krill process while the data is
+        // exported, so that all namespaces are consistent with each other.
+        let data = KeyValueStore::with_exclusive_access(|| {
+            backup_namespaces()
+                .map(|namespace| {
+                    NamespaceData::export(storage_uri, namespace)
+                })
+                .collect::<KrillResult<Vec<_>>>()
+        })?;
+
+        let manifest = BackupManifest {
+            krill_version,
+            created: Timestamp::now(),
+            encrypted: Self::has_data_key(&data),
+            namespaces: data
+                .iter()
+                .map(NamespaceManifest::for_data)
+                .collect(),
+        };
+
+        Ok(KrillBackup { manifest, data })
+    }
+
+    pub fn manifest(&self) -> &BackupManifest {
+        &self.manifest
+    }
+
+    /// Restores this backup into the storage of the given configuration.
+    ///
+    /// The backup is verified against its manifest, and its Krill version
+    /// must not be newer than this Krill version. The storage must not
+    /// contain any data yet. Nothing is written unless all checks pass,
+    /// and if storing the data fails then everything that was restored is
+    /// removed again.
+    ///
+    /// If storage encryption is configured, then the restored data is
+    /// encrypted. An encrypted backup can only be restored if the data key
+    /// in it can be unwrapped using the configuration.
+    pub fn restore(&self, config: &Config) -> KrillResult<()> {
+        let storage_uri = &config.storage_uri;
+        self.verify()?;
+
+        let code_version = KrillVersion::code_version();
+        if self.manifest.krill_version > code_version {
+            return Err(Error::BackupTooNew(
+                self.manifest.krill_version.clone(),
+            ));
+        }
+
+        let properties = PropertiesManager::create(storage_uri, false)?;
+        if properties.is_initialized() {
+            let current = properties.current_krill_version()?;
+            return Err(Error::RestoreTargetNotEmpty(format!(
+                "it contains data for Krill version {}",
+                current
+            )));
+        }
+
+        for namespace in backup_namespaces() {
+            if !KeyValueStore::create(storage_uri, namespace)?.is_empty()? {
+                return Err(Error::RestoreTargetNotEmpty(format!(
+                    "namespace '{}' contains data",
+                    namespace
+                )));
+            }
+        }
+
+        if self.manifest.encrypted && config.storage_encryption.is_none() {
+            return Err(Error::StorageEncryption(
+                "the backup is encrypted, configure [storage_encryption] with the passphrase or signer that was used when it was made".to_string(),
+            ));
+        }
+
+        // The storage was empty, so if anything goes wrong then everything
+        // that was restored can be removed again.
+        let res = self.restore_data(config);
+        if res.is_err() {
+            for namespace in backup_namespaces() {
+                if let Err(e) = KeyValueStore::create(storage_uri, namespace)
+                    .and_then(|kv| kv.wipe())
+                {
+                    error!("Could not remove partially restored data: {}", e);
+                }
+            }
+        }
+        res
+    }
+
+    /// Stores the data of this backup into empty storage.
+    ///
+    /// The values of an encrypted backup are stored as they are, after its
+    /// data key was restored and unwrapped. Otherwise they are encrypted
+    /// using a new data key if storage encryption is configured.
+    fn restore_data(&self, config: &Config) -> KrillResult<()> {
+        let (keys, data): (Vec<_>, Vec<_>) = self
+            .data
+            .iter()
+            .partition(|data| data.namespace == ENCRYPTION_NS.as_str());
+
+        if self.manifest.encrypted {
+            for data in keys {
+                data.restore(&config.storage_uri, true)?;
+            }
+        }
+        enable_storage_encryption(config)?;
+
+        for data in data {
+            data.restore(&config.storage_uri, self.manifest.encrypted)?;
+        }
+        Ok(())
+    }
+
+    /// Verifies that the data in this backup matches its manifest.
+    fn verify(&self) -> KrillResult<()> {
+        if self.data.len() != self.manifest.namespaces.len() {
+            return Err(Error::BackupInvalid(
+                "the namespaces do not match the manifest".to_string(),
+            ));
+        }
+
+        for (data, expected) in
+            self.data.iter().zip(&self.manifest.namespaces)
+        {
+            if !backup_namespaces().any(|ns| ns.as_str() == data.namespace) {
+                return Err(Error::BackupInvalid(format!(
+                    "unexpected namespace '{}'",
+                    data.namespace
+                )));
+            }
+
+            for key in data.values.keys() {
+                Self::parse_key(key)?;
+            }
+
+            if &NamespaceManifest::for_data(data) != expected {
+                return Err(Error::BackupInvalid(format!(
+                    "the data for namespace '{}' does not match the manifest",
+                    data.namespace
+                )));
+            }
+        }
+
+        if self.manifest.encrypted != Self::has_data_key(&self.data) {
+            return Err(Error::BackupInvalid(
+                "the data key does not match the manifest".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Returns whether the data includes a data key, i.e. whether its
+    /// values are encrypted.
+    fn has_data_key(data: &[NamespaceData]) -> bool {
+        data.iter().any(|data| {
+            data.namespace == ENCRYPTION_NS.as_str()
+                && !data.values.is_empty()
+        })
+    }
+
+    fn parse_namespace(namespace: &str) -> KrillResult<&Namespace> {
+        Namespace::parse(namespace).map_err(|_| {
+            Error::BackupInvalid(format!("invalid namespace '{}'", namespace))
+        })
+    }
+
+    fn parse_key(key: &str) -> KrillResult<Key> {
+        Key::from_str(key).map_err(|_| {
+            Error::BackupInvalid(format!("invalid key '{}'", key))
+        })
+    }
+}
+
+//------------ NamespaceData -------------------------------------------------
+
+/// All key value pairs for a namespace, using the string representation
+/// of the keys.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct NamespaceData {
+    namespace: String,
+    values: BTreeMap<String, serde_json::Value>,
+}
+
+impl NamespaceData {
+    /// Exports all key value pairs of a namespace in the given storage.
+    fn export(storage_uri: &Url, namespace: &Namespace) -> KrillResult<Self> {
+        let kv = KeyValueStore::create(storage_uri, namespace)?;
+
+        let mut scopes = kv.scopes()?;
+        scopes.push(Scope::global());
+
+        let mut values = BTreeMap::new();
+        for scope in scopes {
+            for (key, value) in kv.export_scope(&scope)? {
+                values.insert(key.to_string(), value);
+            }
+        }
+
+        Ok(NamespaceData {
+            namespace: namespace.to_string(),
+            values,
+        })
+    }
+
+    /// Stores all key value pairs into the given storage. Exported values
+    /// are stored as is, otherwise they are encrypted if encryption is
+    /// enabled for the namespace.
+    fn restore(&self, storage_uri: &Url, exported: bool) -> KrillResult<()> {
+        let namespace = KrillBackup::parse_namespace(&self.namespace)?;
+        let kv = KeyValueStore::create(storage_uri, namespace)?;
+        for (key, value) in &self.values {
+            let key = KrillBackup::parse_key(key)?;
+            if exported {
+                kv.store_exported(&key, value)?;
+            } else {
+                kv.store(&key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//------------ BackupManifest ------------------------------------------------
+
+/// Describes the contents of a backup.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BackupManifest {
+    krill_version: KrillVersion,
+    created: Timestamp,
+    /// Whether the values are encrypted, and the backup includes the
+    /// wrapped data key.
+    #[serde(default)]
+    encrypted: bool,
+    namespaces: Vec<NamespaceManifest>,
+}
+
+impl BackupManifest {
+    pub fn krill_version(&self) -> &KrillVersion {
+        &self.krill_version
+    }
+
+    pub fn created(&self) -> Timestamp {
+        self.created
+    }
+
+    pub fn encrypted(&self) -> bool {
+        self.encrypted
+    }
+
+    pub fn namespaces(&self) -> &Vec<NamespaceManifest> {
+        &self.namespaces
+    }
+}
+
+impl fmt::Display for BackupManifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Krill version: {}", self.krill_version)?;
+        writeln!(f, "Created: {}", self.created.to_rfc3339())?;
+        writeln!(
+            f,
+            "Encrypted: {}",
+            if self.encrypted { "yes" } else { "no" }
+        )?;
+        writeln!(f)?;
+        writeln!(f, "namespace::keys::aggregates")?;
+        for namespace in &self.namespaces {
+            writeln!(
+                f,
+                "{}::{}::{}",
+                namespace.namespace,
+                namespace.keys,
+                namespace.aggregates.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+//------------ NamespaceManifest ---------------------------------------------
+
+/// Describes the contents of a namespace in a backup. Aggregates are
+/// listed with the version of their last stored command.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NamespaceManifest {
+    namespace: String,
+    keys: usize,
+    aggregates: BTreeMap<String, u64>,
+}
+
+impl NamespaceManifest {
+    /// Describes the data of a namespace in the given storage, without
+    /// keeping the data itself.
+    pub fn for_storage(
+        storage_uri: &Url,
+        namespace: &Namespace,
+    ) -> KrillResult<Self> {
+        NamespaceData::export(storage_uri, namespace)
+            .map(|data| Self::for_data(&data))
+    }
+
+    fn for_data(data: &NamespaceData) -> Self {
+        let mut aggregates = BTreeMap::new();
+
+        for key in data.values.keys() {
+            if let Some((scope, name)) = key.rsplit_once(Scope::SEPARATOR) {
+                let version = name
+                    .strip_prefix("command-")
+                    .and_then(|name| name.strip_suffix(".json"))
+                    .and_then(|version| u64::from_str(version).ok());
+
+                if let Some(version) = version {
+                    let last =
+                        aggregates.entry(scope.to_string()).or_default();
+                    if version > *last {
+                        *last = version;
+                    }
+                }
+            }
+        }
+
+        NamespaceManifest {
+            namespace: data.namespace.clone(),
+            keys: data.values.len(),
+            aggregates,
+        }
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    pub fn keys(&self) -> usize {
+        self.keys
+    }
+
+    pub fn aggregates(&self) -> &BTreeMap<String, u64> {
+        &self.aggregates
+    }
+}
@@ -5,6 +5,7 @@ use chrono::Duration;
 
 use rpki::{
     ca::{
+        csr::BgpsecCsr,
         idexchange,
         idexchange::{CaHandle, ChildHandle, MyHandle, ParentHandle},
         provisioning,
@@ -30,11 +31,13 @@ use crate::{
             import::{ExportChild, ImportChild, ImportChildCertificate},
             AspaDefinition, AspaDefinitionList, AspaDefinitionUpdates,
             AspaProvidersUpdate, BgpSecAsnKey, BgpSecCsrInfoList,
-            BgpSecDefinitionUpdates, CertAuthInfo, CertAuthStorableCommand,
-            ConfiguredRoa, CustomerAsn, IdCertInfo, ObjectName,
-            ParentCaContact, ReceivedCert, RepositoryContact,
-            ResourceClassNameMapping, Revocation, RoaConfiguration,
-            RoaConfigurationUpdates, RtaList, RtaName, RtaPrepResponse,
+            BgpSecDefinition, BgpSecDefinitionUpdates, CertAuthInfo,
+            CertAuthStorableCommand, ConfiguredRoa, CustomerAsn,
+            GhostbusterDefinitionList, GhostbusterDefinitionUpdates,
+            IdCertInfo, KeyRollPolicy, ObjectName, ParentCaContact,
+            ReceivedCert, RepositoryContact, ResourceClassNameMapping,
+            Revocation, RoaConfiguration, RoaConfigurationUpdates,
+            RoaPayload, RtaList, RtaName, RtaPrepResponse, Timestamp,
         },
         crypto::{CsrInfo, KrillSigner},
         error::{Error, RoaDeltaError},
@@ -46,10 +49,13 @@ use crate::{
         ca::{
             events::ChildCertificateUpdates, AspaDefinitions,
             BgpSecDefinitions, CertAuthCommand, CertAuthCommandDetails,
-            CertAuthEvent, CertAuthInitEvent, ChildDetails, DropReason,
-            PreparedRta, ResourceClass, ResourceTaggedAttestation, Rfc8183Id,
-            RoaInfo, RoaPayloadJsonMapKey, Routes, RtaContentRequest,
-            RtaPrepareRequest, Rtas, SignedRta, StoredBgpSecCsr,
+            CertAuthEvent, CertAuthInitEvent, CertifiedKey, ChildDetails,
+            CommandRevertUpdates, DropReason, GeofeedSignRequest,
+            GhostbusterDefinitions, PreparedRta, ResourceClass,
+            ResourceTaggedAttestation, Rfc8183Id, RoaInfo,
+            RoaPayloadJsonMapKey, Routes, RscSignRequest, RtaContentRequest,
+            RtaPrepareRequest, Rtas, SignedChecklist, SignedGeofeed,
+            SignedRta, StoredBgpSecCsr,
         },
         config::{Config, IssuanceTimingConfig},
     },
@@ -93,6 +99,15 @@ pub struct CertAuth {
 
     #[serde(skip_serializing_if = "BgpSecDefinitions::is_empty", default)]
     bgpsec_defs: BgpSecDefinitions,
+
+    #[serde(
+        skip_serializing_if = "GhostbusterDefinitions::is_empty",
+        default
+    )]
+    ghostbusters: GhostbusterDefinitions,
+
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    keyroll_policy: Option<KeyRollPolicy>,
 }
 
 impl Aggregate for CertAuth {
@@ -120,6 +135,8 @@ impl Aggregate for CertAuth {
         let rtas = Rtas::default();
         let aspas = AspaDefinitions::default();
         let bgpsec_defs = BgpSecDefinitions::default();
+        let ghostbusters = GhostbusterDefinitions::default();
+        let keyroll_policy = None;
 
         CertAuth {
             handle,
@@ -139,6 +156,8 @@ impl Aggregate for CertAuth {
             rtas,
             aspas,
             bgpsec_defs,
+            ghostbusters,
+            keyroll_policy,
         }
     }
 
@@ -331,6 +350,16 @@ impl Aggregate for CertAuth {
                     .unwrap()
                     .pending_key_id_added(pending_key);
             }
+            CertAuthEvent::KeyRollEmergencyInitiated {
+                resource_class_name,
+                pending_key_id,
+                compromised_key,
+            } => {
+                self.resources
+                    .get_mut(&resource_class_name)
+                    .unwrap()
+                    .emergency_key_id_added(pending_key_id, compromised_key);
+            }
             CertAuthEvent::KeyPendingToNew {
                 resource_class_name,
                 new_key,
@@ -372,6 +401,9 @@ impl Aggregate for CertAuth {
                 // requests are picked up by the `MessageQueue`
                 // listener.
             }
+            CertAuthEvent::KeyRollPolicyUpdated { policy } => {
+                self.keyroll_policy = policy;
+            }
 
             //-----------------------------------------------------------------------
             // Route Authorizations
@@ -437,6 +469,27 @@ impl Aggregate for CertAuth {
                 rc.bgpsec_certificates_updated(updates);
             }
 
+            //-----------------------------------------------------------------------
+            // Ghostbusters
+            //-----------------------------------------------------------------------
+            CertAuthEvent::GhostbusterConfigAdded { definition } => {
+                self.ghostbusters.add_or_replace(definition)
+            }
+            CertAuthEvent::GhostbusterConfigUpdated { definition } => {
+                self.ghostbusters.add_or_replace(definition)
+            }
+            CertAuthEvent::GhostbusterConfigRemoved { name } => {
+                self.ghostbusters.remove(&name);
+            }
+            CertAuthEvent::GhostbusterObjectsUpdated {
+                resource_class_name,
+                updates,
+            } => self
+                .resources
+                .get_mut(&resource_class_name)
+                .unwrap()
+                .ghostbuster_objects_updated(updates),
+
             //-----------------------------------------------------------------------
             // Publication
             //-----------------------------------------------------------------------
@@ -555,9 +608,15 @@ impl Aggregate for CertAuth {
                 config,
                 signer,
             ) => self.keyroll_activate(duration, config, signer),
+            CertAuthCommandDetails::KeyRollEmergency(rcn, signer) => {
+                self.keyroll_emergency(rcn, signer)
+            }
             CertAuthCommandDetails::KeyRollFinish(rcn, response) => {
                 self.keyroll_finish(rcn, response)
             }
+            CertAuthCommandDetails::KeyRollPolicyUpdate(policy) => {
+                self.keyroll_policy_update(policy)
+            }
 
             // Route Authorizations
             CertAuthCommandDetails::RouteAuthorizationsUpdate(
@@ -598,6 +657,16 @@ impl Aggregate for CertAuth {
                 self.bgpsec_renew(&config, &signer)
             }
 
+            // Ghostbusters
+            CertAuthCommandDetails::GhostbustersUpdate(
+                updates,
+                config,
+                signer,
+            ) => self.ghostbusters_update(updates, &config, &signer),
+            CertAuthCommandDetails::GhostbustersRenew(config, signer) => {
+                self.ghostbusters_renew(&config, &signer)
+            }
+
             // Republish
             CertAuthCommandDetails::RepoUpdate(contact, signer) => {
                 self.update_repo(contact, &signer)
@@ -615,6 +684,14 @@ impl Aggregate for CertAuth {
             CertAuthCommandDetails::RtaSign(name, request, signer) => {
                 self.rta_sign(name, request, signer.deref())
             }
+
+            // History
+            CertAuthCommandDetails::CommandRevert(
+                _version,
+                updates,
+                config,
+                signer,
+            ) => self.command_revert(updates, &config, signer),
         }
     }
 
@@ -665,6 +742,8 @@ impl CertAuth {
             resources,
             children,
             suspended_children,
+            self.keyroll_policy,
+            self.next_keyroll().map(Timestamp::from),
         )
     }
 
@@ -1855,6 +1934,7 @@ impl CertAuth {
             &self.routes,
             &self.aspas,
             &self.bgpsec_defs,
+            &self.ghostbusters,
             config,
             signer.deref(),
         )
@@ -1923,6 +2003,42 @@ impl CertAuth {
         Ok(res)
     }
 
+    /// Starts an emergency key roll for the given resource class, or all
+    /// resource classes if none is specified. The new key(s) will be
+    /// activated as soon as they are certified by the parent.
+    fn keyroll_emergency(
+        &self,
+        rcn: Option<ResourceClassName>,
+        signer: Arc<KrillSigner>,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        let repo = self.repository_contact()?;
+
+        let classes: Vec<(&ResourceClassName, &ResourceClass)> = match &rcn {
+            Some(rcn) => {
+                let rc = self.resources.get(rcn).ok_or_else(|| {
+                    Error::ResourceClassUnknown(rcn.clone())
+                })?;
+                vec![(rcn, rc)]
+            }
+            None => self.resources.iter().collect(),
+        };
+
+        let mut res = vec![];
+
+        for (rcn, rc) in classes {
+            res.append(&mut rc.keyroll_emergency(repo.repo_info(), &signer)?);
+
+            warn!(
+                "Started emergency key roll for ca: {}, rc: {}, under parent: {}",
+                &self.handle,
+                rcn,
+                rc.parent_handle()
+            );
+        }
+
+        Ok(res)
+    }
+
     fn keyroll_activate(
         &self,
         staging_time: Duration,
@@ -1959,6 +2075,67 @@ impl CertAuth {
         Ok(res)
     }
 
+    /// Returns the policy for automatic key rolls, if any.
+    pub fn keyroll_policy(&self) -> Option<&KeyRollPolicy> {
+        self.keyroll_policy.as_ref()
+    }
+
+    /// Returns the time of the next planned automatic key roll for any of
+    /// the resource classes in this CA, if there is a key roll policy.
+    pub fn next_keyroll(&self) -> Option<Time> {
+        let policy = self.keyroll_policy.as_ref()?;
+        self.resources
+            .values()
+            .filter_map(|rc| rc.next_keyroll(policy))
+            .min()
+    }
+
+    /// Returns the time at which the next new key should be activated
+    /// according to the key roll policy, if there is any new key.
+    pub fn next_keyroll_activation(&self) -> Option<Time> {
+        let policy = self.keyroll_policy.as_ref()?;
+        self.resources
+            .values()
+            .filter_map(|rc| rc.next_keyroll_activation(policy))
+            .min()
+    }
+
+    /// Returns the age of the current key in each resource class.
+    pub fn current_key_ages(&self) -> HashMap<ResourceClassName, Duration> {
+        self.resources
+            .iter()
+            .filter_map(|(rcn, rc)| {
+                rc.current_key_age().map(|age| (rcn.clone(), age))
+            })
+            .collect()
+    }
+
+    fn keyroll_policy_update(
+        &self,
+        policy: Option<KeyRollPolicy>,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        if let Some(policy) = policy.as_ref() {
+            if policy.roll_after_days() == 0 {
+                return Err(Error::KeyRollPolicyInvalid(
+                    self.handle.clone(),
+                    "keys must be rolled after at least 1 day".to_string(),
+                ));
+            }
+            if policy.activate_after() >= policy.roll_after() {
+                return Err(Error::KeyRollPolicyInvalid(
+                    self.handle.clone(),
+                    "the staging period must be shorter than the key roll interval".to_string(),
+                ));
+            }
+        }
+
+        if policy == self.keyroll_policy {
+            Ok(vec![])
+        } else {
+            Ok(vec![CertAuthEvent::KeyRollPolicyUpdated { policy }])
+        }
+    }
+
     fn keyroll_finish(
         &self,
         rcn: ResourceClassName,
@@ -2544,6 +2721,219 @@ impl CertAuth {
     }
 }
 
+/// # Ghostbusters
+impl CertAuth {
+    pub fn ghostbusters_show(&self) -> GhostbusterDefinitionList {
+        self.ghostbusters.info_list()
+    }
+
+    /// Process Ghostbuster definition updates, and issue or remove
+    /// Ghostbuster records in all resource classes accordingly.
+    pub fn ghostbusters_update(
+        &self,
+        updates: GhostbusterDefinitionUpdates,
+        config: &Config,
+        signer: &KrillSigner,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        let mut events = vec![];
+
+        let (add_or_replace, removals) = updates.unpack();
+
+        // Keep track of a copy of the definitions so we can use them to
+        // update the objects in each resource class.
+        let mut definitions = self.ghostbusters.clone();
+
+        for name in removals {
+            if !definitions.remove(&name) {
+                return Err(Error::GhostbusterUnknown(
+                    self.handle.clone(),
+                    name,
+                ));
+            }
+            events.push(CertAuthEvent::GhostbusterConfigRemoved { name });
+        }
+
+        for definition in add_or_replace {
+            definition.verify().map_err(|msg| {
+                Error::GhostbusterInvalid(
+                    self.handle.clone(),
+                    definition.name().clone(),
+                    msg,
+                )
+            })?;
+
+            match definitions.get(definition.name()) {
+                None => events.push(CertAuthEvent::GhostbusterConfigAdded {
+                    definition: definition.clone(),
+                }),
+                Some(existing) => {
+                    if existing != &definition {
+                        events.push(
+                            CertAuthEvent::GhostbusterConfigUpdated {
+                                definition: definition.clone(),
+                            },
+                        );
+                    }
+                }
+            }
+
+            definitions.add_or_replace(definition);
+        }
+
+        for (rcn, rc) in self.resources.iter() {
+            let updates =
+                rc.update_ghostbusters(&definitions, config, signer)?;
+            if updates.contains_changes() {
+                events.push(CertAuthEvent::GhostbusterObjectsUpdated {
+                    resource_class_name: rcn.clone(),
+                    updates,
+                });
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Renew any Ghostbuster records if needed.
+    pub fn ghostbusters_renew(
+        &self,
+        config: &Config,
+        signer: &KrillSigner,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        let mut events = vec![];
+
+        for (rcn, rc) in self.resources.iter() {
+            let updates =
+                rc.renew_ghostbusters(&config.issuance_timing, signer)?;
+
+            if updates.contains_changes() {
+                info!(
+                    "CA '{}' reissued Ghostbuster records under RC '{}' before they would expire",
+                    self.handle, rcn
+                );
+
+                events.push(CertAuthEvent::GhostbusterObjectsUpdated {
+                    resource_class_name: rcn.clone(),
+                    updates,
+                });
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+/// # RPKI Signed Checklists
+impl CertAuth {
+    /// Signs an RPKI Signed Checklist (RFC 9323). The RSC is returned and
+    /// not kept, and neither is its one-off EE certificate, so this does not
+    /// change the state of the CA.
+    pub fn rsc_sign(
+        &self,
+        request: RscSignRequest,
+        signer: &KrillSigner,
+    ) -> KrillResult<SignedChecklist> {
+        request
+            .verify()
+            .map_err(|msg| Error::RscInvalid(self.handle.clone(), msg))?;
+
+        if !self.all_resources().contains(request.resources()) {
+            return Err(Error::RscResourcesNotHeld(self.handle.clone()));
+        }
+
+        let signing_key = self
+            .one_off_signing_key(request.resources())
+            .ok_or_else(|| {
+                Error::RscInvalid(
+                    self.handle.clone(),
+                    "resources are held under more than one parent or resource class".to_string(),
+                )
+            })?;
+
+        let (resources, validity, checklist) = request.unpack();
+        let rsc = SignedChecklist::sign(
+            &resources,
+            validity,
+            &checklist,
+            signing_key,
+            signer,
+        )?;
+
+        info!(
+            "CA '{}' signed an RSC for resources: {}",
+            self.handle, resources
+        );
+
+        Ok(rsc)
+    }
+
+    /// Returns the current key of the resource class which holds all the
+    /// given resources. Objects signed with a one-off EE certificate have a
+    /// single signer, so they cannot use resources held under different
+    /// resource classes.
+    fn one_off_signing_key(
+        &self,
+        resources: &ResourceSet,
+    ) -> Option<&CertifiedKey> {
+        self.resources
+            .values()
+            .find(|rc| {
+                rc.current_resources()
+                    .map(|held| held.contains(resources))
+                    .unwrap_or(false)
+            })
+            .and_then(|rc| rc.current_key())
+    }
+}
+
+/// # Geofeeds
+impl CertAuth {
+    /// Signs a geofeed file as described in RFC 9632. All prefixes in the
+    /// geofeed must be held by this CA. The signature is made using a
+    /// one-off EE certificate for the prefixes in the geofeed, and neither
+    /// is kept, so this does not change the state of the CA.
+    pub fn geofeed_sign(
+        &self,
+        request: GeofeedSignRequest,
+        signer: &KrillSigner,
+    ) -> KrillResult<SignedGeofeed> {
+        let prefixes = request
+            .prefixes()
+            .map_err(|msg| Error::GeofeedInvalid(self.handle.clone(), msg))?;
+
+        let all_resources = self.all_resources();
+        let mut resources = ResourceSet::default();
+        for prefix in prefixes {
+            let prefix_resources = ResourceSet::from(prefix);
+            if !all_resources.contains(&prefix_resources) {
+                return Err(Error::GeofeedPrefixNotHeld(
+                    self.handle.clone(),
+                    prefix,
+                ));
+            }
+            resources = resources.union(&prefix_resources);
+        }
+
+        let signing_key =
+            self.one_off_signing_key(&resources).ok_or_else(|| {
+                Error::GeofeedInvalid(
+                    self.handle.clone(),
+                    "prefixes are held under more than one parent or resource class".to_string(),
+                )
+            })?;
+
+        let signed =
+            SignedGeofeed::sign(&request, &resources, signing_key, signer)?;
+
+        info!(
+            "CA '{}' signed a geofeed for prefixes: {}",
+            self.handle, resources
+        );
+
+        Ok(signed)
+    }
+}
+
 /// # Resource Tagged Attestations
 impl CertAuth {
     pub fn rta_list(&self) -> RtaList {
@@ -2803,6 +3193,160 @@ impl CertAuth {
     }
 }
 
+/// # History
+impl CertAuth {
+    /// Returns the updates needed to undo the ROA, ASPA and BGPSec
+    /// definition changes made by the given events.
+    ///
+    /// This CertAuth must be the state just *before* the events were
+    /// applied, so that we can find the definitions as they were. Returns
+    /// None if the events contain no definition changes at all.
+    pub fn revert_updates(
+        &self,
+        events: &[CertAuthEvent],
+    ) -> KrillResult<Option<CommandRevertUpdates>> {
+        let mut roas_added: Vec<RoaConfiguration> = vec![];
+        let mut roas_removed: Vec<RoaPayload> = vec![];
+        let mut new_auths: Vec<RoaPayloadJsonMapKey> = vec![];
+        let mut customers: Vec<CustomerAsn> = vec![];
+        let mut bgpsec_keys: Vec<BgpSecAsnKey> = vec![];
+
+        for event in events {
+            match event {
+                CertAuthEvent::RouteAuthorizationAdded { auth } => {
+                    new_auths.push(*auth);
+                    roas_removed.push((*auth).into());
+                }
+                CertAuthEvent::RouteAuthorizationRemoved { auth } => {
+                    roas_added.push(self.route_configuration(auth));
+                }
+                CertAuthEvent::RouteAuthorizationComment { auth, .. } => {
+                    if !new_auths.contains(auth) {
+                        roas_added.push(self.route_configuration(auth));
+                    }
+                }
+                CertAuthEvent::AspaConfigAdded { aspa_config } => {
+                    if !customers.contains(&aspa_config.customer()) {
+                        customers.push(aspa_config.customer());
+                    }
+                }
+                CertAuthEvent::AspaConfigUpdated { customer, .. }
+                | CertAuthEvent::AspaConfigRemoved { customer } => {
+                    if !customers.contains(customer) {
+                        customers.push(*customer);
+                    }
+                }
+                CertAuthEvent::BgpSecDefinitionAdded { key, .. }
+                | CertAuthEvent::BgpSecDefinitionUpdated { key, .. }
+                | CertAuthEvent::BgpSecDefinitionRemoved { key } => {
+                    if !bgpsec_keys.contains(key) {
+                        bgpsec_keys.push(*key);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let roas = if roas_added.is_empty() && roas_removed.is_empty() {
+            None
+        } else {
+            Some(RoaConfigurationUpdates::new(roas_added, roas_removed))
+        };
+
+        let aspas = if customers.is_empty() {
+            None
+        } else {
+            let mut add_or_replace = vec![];
+            let mut remove = vec![];
+            for customer in customers {
+                match self.aspas.get(customer) {
+                    Some(definition) => {
+                        add_or_replace.push(definition.clone())
+                    }
+                    None => remove.push(customer),
+                }
+            }
+            Some(AspaDefinitionUpdates::new(add_or_replace, remove))
+        };
+
+        let bgpsec = if bgpsec_keys.is_empty() {
+            None
+        } else {
+            let mut add = vec![];
+            let mut remove = vec![];
+            for key in bgpsec_keys {
+                match self.bgpsec_defs.get_stored_csr(&key) {
+                    Some(stored) => {
+                        let csr = BgpsecCsr::decode(
+                            stored.csr().to_bytes().as_ref(),
+                        )
+                        .map_err(|e| {
+                            Error::Custom(format!(
+                                "Cannot decode stored CSR for '{}': {}",
+                                key, e
+                            ))
+                        })?;
+                        add.push(BgpSecDefinition::new(key.asn(), csr));
+                    }
+                    None => remove.push(key),
+                }
+            }
+            Some(BgpSecDefinitionUpdates::new(add, remove))
+        };
+
+        let updates = CommandRevertUpdates::new(roas, aspas, bgpsec);
+        if updates.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(updates))
+        }
+    }
+
+    /// Returns the configuration, including comment, of a ROA payload as
+    /// it is known in this CertAuth.
+    fn route_configuration(
+        &self,
+        auth: &RoaPayloadJsonMapKey,
+    ) -> RoaConfiguration {
+        let comment = self
+            .routes
+            .info(auth)
+            .and_then(|info| info.comment().cloned());
+        RoaConfiguration::new((*auth).into(), comment)
+    }
+
+    /// Applies the updates that revert an earlier command.
+    fn command_revert(
+        &self,
+        updates: CommandRevertUpdates,
+        config: &Config,
+        signer: Arc<KrillSigner>,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        let (roas, aspas, bgpsec) = updates.unpack();
+
+        let mut events = vec![];
+        if let Some(roas) = roas {
+            events.append(&mut self.route_authorizations_update(
+                roas,
+                config,
+                signer.clone(),
+            )?);
+        }
+        if let Some(aspas) = aspas {
+            events.append(
+                &mut self.aspas_definitions_update(aspas, config, &signer)?,
+            );
+        }
+        if let Some(bgpsec) = bgpsec {
+            events.append(
+                &mut self
+                    .bgpsec_definitions_update(bgpsec, config, &signer)?,
+            );
+        }
+        Ok(events)
+    }
+}
+
 //------------ Tests ---------------------------------------------------------
 
 #[cfg(test)]
@@ -19,7 +19,8 @@ use crate::{
         api::{
             import::ImportChild, AspaDefinitionUpdates, AspaProvidersUpdate,
             BgpSecDefinitionUpdates, CertAuthStorableCommand, CustomerAsn,
-            IdCertInfo, ParentCaContact, ReceivedCert, RepositoryContact,
+            GhostbusterDefinitionUpdates, IdCertInfo, KeyRollPolicy,
+            ParentCaContact, ReceivedCert, RepositoryContact,
             ResourceClassNameMapping, RoaConfigurationUpdates, RtaName,
             StorableRcEntitlement,
         },
@@ -189,11 +190,23 @@ pub enum CertAuthCommandDetails {
     // rolls.
     KeyRollActivate(Duration, Arc<Config>, Arc<KrillSigner>),
 
+    // Start an emergency key roll for a resource class, or all resource
+    // classes if None, because the current key is believed to be
+    // compromised. A new key is created and certified immediately, and
+    // then activated without observing a staging period. The compromised
+    // key is revoked when the new key is activated.
+    KeyRollEmergency(Option<ResourceClassName>, Arc<KrillSigner>),
+
     // Finish the keyroll after the parent confirmed that a key for a parent
     // and resource class has been revoked. I.e. remove the old key, and
     // withdraw the crl and mft for it.
     KeyRollFinish(ResourceClassName, RevocationResponse),
 
+    // Set or remove the policy for automatic key rolls. The key rolls
+    // themselves are triggered by the scheduler, using the commands
+    // above.
+    KeyRollPolicyUpdate(Option<KeyRollPolicy>),
+
     // ------------------------------------------------------------
     // ROA Support
     // ------------------------------------------------------------
@@ -254,6 +267,22 @@ pub enum CertAuthCommandDetails {
     // expire in some time.
     BgpSecRenew(Arc<Config>, Arc<KrillSigner>),
 
+    // ------------------------------------------------------------
+    // Ghostbusters
+    // ------------------------------------------------------------
+
+    // Update GhostbusterDefinitions, adding new, replacing existing, or
+    // removing surplus.
+    GhostbustersUpdate(
+        GhostbusterDefinitionUpdates,
+        Arc<Config>,
+        Arc<KrillSigner>,
+    ),
+
+    // Re-issue any and all Ghostbuster records which would otherwise
+    // expire in some time.
+    GhostbustersRenew(Arc<Config>, Arc<KrillSigner>),
+
     // ------------------------------------------------------------
     // Publishing
     // ------------------------------------------------------------
@@ -273,6 +302,14 @@ pub enum CertAuthCommandDetails {
 
     // Co-sign an existing multi-signed RTA
     RtaCoSign(RtaName, ResourceTaggedAttestation, Arc<KrillSigner>),
+
+    // ------------------------------------------------------------
+    // History
+    // ------------------------------------------------------------
+
+    // Undo the changes to ROA, ASPA and BGPSec definitions made by the
+    // earlier command with the given version.
+    CommandRevert(u64, CommandRevertUpdates, Arc<Config>, Arc<KrillSigner>),
 }
 
 impl eventsourcing::CommandDetails for CertAuthCommandDetails {
@@ -429,11 +466,20 @@ impl From<CertAuthCommandDetails> for CertAuthStorableCommand {
                     staged_for_seconds: staged_for.num_seconds(),
                 }
             }
+            CertAuthCommandDetails::KeyRollEmergency(
+                resource_class_name,
+                _,
+            ) => CertAuthStorableCommand::KeyRollEmergency {
+                resource_class_name,
+            },
             CertAuthCommandDetails::KeyRollFinish(resource_class_name, _) => {
                 CertAuthStorableCommand::KeyRollFinish {
                     resource_class_name,
                 }
             }
+            CertAuthCommandDetails::KeyRollPolicyUpdate(policy) => {
+                CertAuthStorableCommand::KeyRollPolicyUpdate { policy }
+            }
 
             // ------------------------------------------------------------
             // ROA Support
@@ -479,6 +525,16 @@ impl From<CertAuthCommandDetails> for CertAuthStorableCommand {
                 CertAuthStorableCommand::ReissueBeforeExpiring
             }
 
+            // ------------------------------------------------------------
+            // Ghostbusters
+            // ------------------------------------------------------------
+            CertAuthCommandDetails::GhostbustersUpdate(updates, _, _) => {
+                CertAuthStorableCommand::GhostbustersUpdate { updates }
+            }
+            CertAuthCommandDetails::GhostbustersRenew(_, _) => {
+                CertAuthStorableCommand::ReissueBeforeExpiring
+            }
+
             // ------------------------------------------------------------
             // Publishing
             // ------------------------------------------------------------
@@ -500,6 +556,13 @@ impl From<CertAuthCommandDetails> for CertAuthStorableCommand {
             CertAuthCommandDetails::RtaCoSign(name, _, _) => {
                 CertAuthStorableCommand::RtaCoSign { name }
             }
+
+            // ------------------------------------------------------------
+            // History
+            // ------------------------------------------------------------
+            CertAuthCommandDetails::CommandRevert(version, _, _, _) => {
+                CertAuthStorableCommand::CommandRevert { version }
+            }
         }
     }
 }
@@ -807,6 +870,20 @@ impl CertAuthCommandDetails {
         )
     }
 
+    pub fn key_roll_emergency(
+        handle: &CaHandle,
+        rcn: Option<ResourceClassName>,
+        signer: Arc<KrillSigner>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::KeyRollEmergency(rcn, signer),
+            actor,
+        )
+    }
+
     pub fn key_roll_finish(
         handle: &CaHandle,
         rcn: ResourceClassName,
@@ -821,6 +898,19 @@ impl CertAuthCommandDetails {
         )
     }
 
+    pub fn key_roll_policy_update(
+        handle: &CaHandle,
+        policy: Option<KeyRollPolicy>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::KeyRollPolicyUpdate(policy),
+            actor,
+        )
+    }
+
     pub fn update_repo(
         handle: &CaHandle,
         contact: RepositoryContact,
@@ -911,6 +1001,26 @@ impl CertAuthCommandDetails {
         )
     }
 
+    //-------------------------------------------------------------------------------
+    // Ghostbusters
+    //-------------------------------------------------------------------------------
+    pub fn ghostbusters_update(
+        ca: &CaHandle,
+        updates: GhostbusterDefinitionUpdates,
+        config: Arc<Config>,
+        signer: Arc<KrillSigner>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            ca,
+            None,
+            CertAuthCommandDetails::GhostbustersUpdate(
+                updates, config, signer,
+            ),
+            actor,
+        )
+    }
+
     //-------------------------------------------------------------------------------
     // Resource Tagged Attestations
     //-------------------------------------------------------------------------------
@@ -958,4 +1068,64 @@ impl CertAuthCommandDetails {
             actor,
         )
     }
+
+    //-------------------------------------------------------------------------------
+    // History
+    //-------------------------------------------------------------------------------
+    pub fn command_revert(
+        handle: &CaHandle,
+        version: u64,
+        updates: CommandRevertUpdates,
+        config: Arc<Config>,
+        signer: Arc<KrillSigner>,
+        actor: &Actor,
+    ) -> CertAuthCommand {
+        eventsourcing::SentCommand::new(
+            handle,
+            None,
+            CertAuthCommandDetails::CommandRevert(
+                version, updates, config, signer,
+            ),
+            actor,
+        )
+    }
+}
+
+//------------ CommandRevertUpdates ----------------------------------------
+
+/// The updates to ROA, ASPA and BGPSec definitions that undo the changes
+/// made by an earlier command.
+#[derive(Clone, Debug, Default)]
+pub struct CommandRevertUpdates {
+    roas: Option<RoaConfigurationUpdates>,
+    aspas: Option<AspaDefinitionUpdates>,
+    bgpsec: Option<BgpSecDefinitionUpdates>,
+}
+
+impl CommandRevertUpdates {
+    pub fn new(
+        roas: Option<RoaConfigurationUpdates>,
+        aspas: Option<AspaDefinitionUpdates>,
+        bgpsec: Option<BgpSecDefinitionUpdates>,
+    ) -> Self {
+        CommandRevertUpdates {
+            roas,
+            aspas,
+            bgpsec,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roas.is_none() && self.aspas.is_none() && self.bgpsec.is_none()
+    }
+
+    pub fn unpack(
+        self,
+    ) -> (
+        Option<RoaConfigurationUpdates>,
+        Option<AspaDefinitionUpdates>,
+        Option<BgpSecDefinitionUpdates>,
+    ) {
+        (self.roas, self.aspas, self.bgpsec)
+    }
 }
@@ -16,7 +16,8 @@ use crate::{
     commons::{
         api::{
             AspaDefinition, AspaProvidersUpdate, BgpSecAsnKey, CustomerAsn,
-            IdCertInfo, IssuedCertificate, ObjectName, ParentCaContact,
+            GhostbusterDefinition, GhostbusterName, IdCertInfo,
+            IssuedCertificate, KeyRollPolicy, ObjectName, ParentCaContact,
             ReceivedCert, RepositoryContact, ResourceClassNameMapping,
             RoaAggregateKey, RtaName, SuspendedCert, UnsuspendedCert,
         },
@@ -25,8 +26,8 @@ use crate::{
         KrillResult,
     },
     daemon::ca::{
-        AspaInfo, CertifiedKey, PreparedRta, RoaInfo, RoaPayloadJsonMapKey,
-        SignedRta,
+        AspaInfo, CertifiedKey, GhostbusterInfo, PreparedRta, RoaInfo,
+        RoaPayloadJsonMapKey, SignedRta,
     },
 };
 
@@ -337,6 +338,47 @@ impl BgpSecCertificateUpdates {
     }
 }
 
+//------------ GhostbusterObjectsUpdates -----------------------------------
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GhostbusterObjectsUpdates {
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    updated: Vec<GhostbusterInfo>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    removed: Vec<GhostbusterName>,
+}
+
+impl GhostbusterObjectsUpdates {
+    pub fn add_updated(&mut self, update: GhostbusterInfo) {
+        self.updated.push(update)
+    }
+
+    pub fn add_removed(&mut self, name: GhostbusterName) {
+        self.removed.push(name)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.updated.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn contains_changes(&self) -> bool {
+        !self.is_empty()
+    }
+
+    pub fn unpack(self) -> (Vec<GhostbusterInfo>, Vec<GhostbusterName>) {
+        (self.updated, self.removed)
+    }
+
+    pub fn updated(&self) -> &Vec<GhostbusterInfo> {
+        &self.updated
+    }
+
+    pub fn removed(&self) -> &Vec<GhostbusterName> {
+        &self.removed
+    }
+}
+
 //------------ ChildCertificateUpdates -------------------------------------
 
 /// Describes an update to the set of ROAs under a ResourceClass.
@@ -539,6 +581,16 @@ pub enum CertAuthEvent {
         resource_class_name: ResourceClassName,
         pending_key_id: KeyIdentifier,
     },
+    KeyRollEmergencyInitiated {
+        // A pending key is added to an existing resource class because the
+        // current key is believed to be compromised. Unlike a normal roll
+        // the new key is activated, and the compromised key is revoked, as
+        // soon as the new key receives its certificate. Note that there
+        // will be a separate 'CertificateRequested' event for this key.
+        resource_class_name: ResourceClassName,
+        pending_key_id: KeyIdentifier,
+        compromised_key: KeyIdentifier,
+    },
     KeyPendingToNew {
         // A pending key is marked as 'new' when it has received its (first)
         // certificate. This means that the key is staged and a mft
@@ -586,6 +638,11 @@ pub enum CertAuthEvent {
         resource_class_name: ResourceClassName,
         revoke_req: RevocationRequest,
     },
+    KeyRollPolicyUpdated {
+        // The policy for automatic key rolls was set, changed, or removed
+        // in case it is None.
+        policy: Option<KeyRollPolicy>,
+    },
 
     // Route Authorizations
     RouteAuthorizationAdded {
@@ -648,6 +705,23 @@ pub enum CertAuthEvent {
         updates: BgpSecCertificateUpdates,
     },
 
+    // Ghostbusters
+    GhostbusterConfigAdded {
+        definition: GhostbusterDefinition,
+    },
+    GhostbusterConfigUpdated {
+        definition: GhostbusterDefinition,
+    },
+    GhostbusterConfigRemoved {
+        name: GhostbusterName,
+    },
+    GhostbusterObjectsUpdated {
+        // Tracks Ghostbuster *objects* which are (re-)issued in a resource
+        // class.
+        resource_class_name: ResourceClassName,
+        updates: GhostbusterObjectsUpdates,
+    },
+
     // Publishing
     RepoUpdated {
         // Adds the repository contact for this CA so that publication can
@@ -939,6 +1013,15 @@ impl fmt::Display for CertAuthEvent {
                     pending_key_id, resource_class_name
                 )
             }
+            CertAuthEvent::KeyRollEmergencyInitiated {
+                resource_class_name,
+                pending_key_id,
+                compromised_key,
+            } => write!(
+                f,
+                "emergency key roll: added pending key '{}' to replace compromised key '{}' under resource class '{}'",
+                pending_key_id, compromised_key, resource_class_name
+            ),
             CertAuthEvent::KeyPendingToNew {
                 resource_class_name,
                 new_key,
@@ -979,6 +1062,11 @@ impl fmt::Display for CertAuthEvent {
                 revoke_req.key()
             ),
 
+            CertAuthEvent::KeyRollPolicyUpdated { policy } => match policy {
+                Some(policy) => write!(f, "key roll: set policy to {}", policy),
+                None => write!(f, "key roll: removed policy"),
+            },
+
             // Route Authorizations
             CertAuthEvent::RouteAuthorizationAdded { auth } => write!(f, "added ROA: '{}'", auth),
             CertAuthEvent::RouteAuthorizationComment { auth, comment } => {
@@ -1094,6 +1182,40 @@ impl fmt::Display for CertAuthEvent {
                 Ok(())
             }
 
+            // Ghostbusters
+            CertAuthEvent::GhostbusterConfigAdded { definition } => {
+                write!(f, "added Ghostbuster config {}", definition)
+            }
+            CertAuthEvent::GhostbusterConfigUpdated { definition } => {
+                write!(f, "updated Ghostbuster config {}", definition)
+            }
+            CertAuthEvent::GhostbusterConfigRemoved { name } => {
+                write!(f, "removed Ghostbuster config '{}'", name)
+            }
+            CertAuthEvent::GhostbusterObjectsUpdated {
+                resource_class_name,
+                updates,
+            } => {
+                write!(
+                    f,
+                    "updated Ghostbuster records under resource class '{}'",
+                    resource_class_name
+                )?;
+                if !updates.updated().is_empty() {
+                    write!(f, " updated:")?;
+                    for upd in updates.updated() {
+                        write!(f, " {}", upd.object_name())?;
+                    }
+                }
+                if !updates.removed().is_empty() {
+                    write!(f, " removed:")?;
+                    for rem in updates.removed() {
+                        write!(f, " {}", ObjectName::ghostbuster(rem))?;
+                    }
+                }
+                Ok(())
+            }
+
             // Publishing
             CertAuthEvent::RepoUpdated { contact } => {
                 write!(
@@ -0,0 +1,219 @@
+//! Geofeed authenticators, see RFC 9632.
+//!
+//! A geofeed file (RFC 8805) can be authenticated by appending a detached
+//! CMS signature over its content. The signature is made using a one-off
+//! EE certificate for the prefixes in the geofeed, and is appended as a
+//! block of base64 encoded comment lines.
+
+use std::{fmt, str::FromStr};
+
+use bcder::Oid;
+use rpki::{
+    ca::publication::Base64,
+    repository::{resources::ResourceSet, x509::Validity},
+};
+
+use crate::{
+    commons::{api::TypedPrefix, crypto::KrillSigner, KrillResult},
+    daemon::ca::{make_one_off_signed_data, CertifiedKey},
+};
+
+/// The content type for geofeeds, id-ct-geofeedCSVwithCRLF:
+/// 1.2.840.113549.1.9.16.1.47
+const CT_GEOFEED_CSV_WITH_CRLF: Oid<&[u8]> =
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 47]);
+
+const SIGNATURE_START: &str = "# RPKI Signature:";
+const SIGNATURE_END: &str = "# End Signature:";
+
+/// The maximum length of the base64 encoded signature on a single line.
+const SIGNATURE_LINE_LEN: usize = 64;
+
+//------------ GeofeedSignRequest -------------------------------------------
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GeofeedSignRequest {
+    geofeed: String,
+    validity: Validity,
+}
+
+impl GeofeedSignRequest {
+    pub fn new(geofeed: String, validity: Validity) -> Self {
+        GeofeedSignRequest { geofeed, validity }
+    }
+
+    pub fn geofeed(&self) -> &str {
+        &self.geofeed
+    }
+
+    pub fn validity(&self) -> Validity {
+        self.validity
+    }
+
+    /// Returns the prefixes from the geofeed entries. Comment and empty
+    /// lines are skipped. Returns a message describing the problem if an
+    /// entry cannot be parsed, if the geofeed has no entries, or if it is
+    /// already signed.
+    pub fn prefixes(&self) -> Result<Vec<TypedPrefix>, String> {
+        let mut prefixes = vec![];
+
+        for (nr, line) in self.geofeed.lines().enumerate() {
+            let line = line.trim();
+
+            if line.starts_with(SIGNATURE_START) {
+                return Err("the geofeed is already signed".to_string());
+            } else if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // Safe: split always returns at least one element
+            let prefix = line.split(',').next().unwrap().trim();
+            let prefix = TypedPrefix::from_str(prefix).map_err(|_| {
+                format!("invalid prefix '{}' on line {}", prefix, nr + 1)
+            })?;
+            prefixes.push(prefix);
+        }
+
+        if prefixes.is_empty() {
+            Err("no entries found in the geofeed".to_string())
+        } else {
+            Ok(prefixes)
+        }
+    }
+
+    /// Returns the geofeed with all line endings converted to CRLF, as
+    /// required for the signed content.
+    fn canonical_geofeed(&self) -> String {
+        let mut res = String::with_capacity(self.geofeed.len());
+        for line in self.geofeed.lines() {
+            res.push_str(line);
+            res.push_str("\r\n");
+        }
+        res
+    }
+}
+
+impl fmt::Display for GeofeedSignRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "validity, {}-{}",
+            self.validity.not_before().to_rfc3339(),
+            self.validity.not_after().to_rfc3339()
+        )?;
+        write!(f, "{}", self.geofeed)
+    }
+}
+
+//------------ SignedGeofeed ------------------------------------------------
+
+/// A geofeed followed by its RFC 9632 signature block.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SignedGeofeed {
+    geofeed: String,
+}
+
+impl SignedGeofeed {
+    /// Signs the geofeed in the request, using a one-off EE certificate for
+    /// the given resources issued under the given key. The resources must
+    /// include all prefixes in the geofeed.
+    pub fn sign(
+        request: &GeofeedSignRequest,
+        resources: &ResourceSet,
+        signing_key: &CertifiedKey,
+        signer: &KrillSigner,
+    ) -> KrillResult<Self> {
+        let mut geofeed = request.canonical_geofeed();
+
+        let signature = make_one_off_signed_data(
+            &CT_GEOFEED_CSV_WITH_CRLF,
+            geofeed.as_bytes(),
+            true,
+            resources,
+            request.validity(),
+            signing_key,
+            signer,
+        )?;
+        let signature = Base64::from_content(signature.as_ref()).to_string();
+
+        let ranges = Self::address_ranges(resources);
+
+        geofeed.push_str(&format!("{} {}\r\n", SIGNATURE_START, ranges));
+        // Safe: base64 is ASCII, so we can split at any byte
+        for chunk in signature.as_bytes().chunks(SIGNATURE_LINE_LEN) {
+            geofeed.push_str("# ");
+            geofeed.push_str(std::str::from_utf8(chunk).unwrap());
+            geofeed.push_str("\r\n");
+        }
+        geofeed.push_str(&format!("{} {}\r\n", SIGNATURE_END, ranges));
+
+        Ok(SignedGeofeed { geofeed })
+    }
+
+    pub fn geofeed(&self) -> &str {
+        &self.geofeed
+    }
+
+    fn address_ranges(resources: &ResourceSet) -> String {
+        let ipv4 = resources.ipv4();
+        let ipv6 = resources.ipv6();
+
+        match (ipv4.is_empty(), ipv6.is_empty()) {
+            (false, false) => format!("{}, {}", ipv4, ipv6),
+            (false, true) => ipv4.to_string(),
+            _ => ipv6.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SignedGeofeed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.geofeed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::commons::crypto::SignSupport;
+
+    fn request(geofeed: &str) -> GeofeedSignRequest {
+        GeofeedSignRequest::new(
+            geofeed.to_string(),
+            SignSupport::sign_validity_days(7),
+        )
+    }
+
+    #[test]
+    fn geofeed_prefixes() {
+        let geofeed = "# prefix,country,region,city,postal\n\
+                       192.0.2.0/24,NL,NL-NH,Amsterdam,\n\
+                       \n\
+                       2001:db8::/32,NL,,,\n";
+
+        let prefixes = request(geofeed).prefixes().unwrap();
+        assert_eq!(
+            vec![
+                TypedPrefix::from_str("192.0.2.0/24").unwrap(),
+                TypedPrefix::from_str("2001:db8::/32").unwrap()
+            ],
+            prefixes
+        );
+
+        assert!(request("# nothing here\n").prefixes().is_err());
+        assert!(request("192.0.2.0/33,NL,,,\n").prefixes().is_err());
+        assert!(request("192.0.2.0/24,NL,,,\n# RPKI Signature: x\n")
+            .prefixes()
+            .is_err());
+    }
+
+    #[test]
+    fn geofeed_canonical_line_endings() {
+        let geofeed = "192.0.2.0/24,NL,,,\r\n2001:db8::/32,NL,,,";
+        assert_eq!(
+            "192.0.2.0/24,NL,,,\r\n2001:db8::/32,NL,,,\r\n",
+            request(geofeed).canonical_geofeed()
+        );
+    }
+}
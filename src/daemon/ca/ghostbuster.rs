@@ -0,0 +1,314 @@
+//! Ghostbuster records, see RFC 6493.
+//!
+//! The contact details (vCard) are configured at the level of the CA. A
+//! Ghostbuster record is then issued for each definition under every
+//! resource class that has a current key, so that every CA certificate
+//! held by the CA has the contact details published alongside it.
+
+use std::collections::HashMap;
+
+use bcder::encode::Values;
+use bcder::Mode;
+use rpki::{
+    ca::publication::Base64,
+    repository::{
+        sigobj::{SignedObject, SignedObjectBuilder},
+        x509::{Serial, Time, Validity},
+    },
+    rrdp::Hash,
+    uri,
+};
+
+use crate::{
+    commons::{
+        api::{
+            GhostbusterDefinition, GhostbusterDefinitionList,
+            GhostbusterName, ObjectName,
+        },
+        crypto::KrillSigner,
+        KrillResult,
+    },
+    daemon::{
+        ca::{CertifiedKey, GhostbusterObjectsUpdates},
+        config::{Config, IssuanceTimingConfig},
+    },
+};
+
+pub fn make_ghostbuster_object(
+    definition: &GhostbusterDefinition,
+    certified_key: &CertifiedKey,
+    validity: Validity,
+    signer: &KrillSigner,
+) -> KrillResult<SignedObject> {
+    let name = definition.object_name();
+
+    let object_builder = {
+        let incoming_cert = certified_key.incoming_cert();
+
+        let crl_uri = incoming_cert.crl_uri();
+        let gbr_uri = incoming_cert.uri_for_name(&name);
+        let ca_issuer = incoming_cert.uri().clone();
+
+        let mut object_builder = SignedObjectBuilder::new(
+            signer.random_serial()?,
+            validity,
+            crl_uri,
+            ca_issuer,
+            gbr_uri,
+        );
+        object_builder.set_issuer(Some(incoming_cert.subject().clone()));
+        object_builder.set_signing_time(Some(Time::now()));
+
+        // RFC 6493 section 6: the EE certificate MUST use "inherit" for
+        // its resources.
+        object_builder.set_v4_resources_inherit();
+        object_builder.set_v6_resources_inherit();
+        object_builder.set_as_resources_inherit();
+
+        object_builder
+    };
+
+    Ok(signer.sign_ghostbuster(
+        definition.to_vcard(),
+        object_builder,
+        certified_key.key_id(),
+    )?)
+}
+
+//------------ GhostbusterDefinitions --------------------------------------
+
+/// This type contains the Ghostbuster definitions for a CA.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GhostbusterDefinitions(
+    HashMap<GhostbusterName, GhostbusterDefinition>,
+);
+
+impl GhostbusterDefinitions {
+    // Add or replace a definition
+    pub fn add_or_replace(&mut self, definition: GhostbusterDefinition) {
+        self.0.insert(definition.name().clone(), definition);
+    }
+
+    // Remove an existing definition (if it is present)
+    pub fn remove(&mut self, name: &GhostbusterName) -> bool {
+        self.0.remove(name).is_some()
+    }
+
+    pub fn get(
+        &self,
+        name: &GhostbusterName,
+    ) -> Option<&GhostbusterDefinition> {
+        self.0.get(name)
+    }
+
+    pub fn has(&self, name: &GhostbusterName) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = &GhostbusterDefinition> {
+        self.0.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn info_list(&self) -> GhostbusterDefinitionList {
+        let mut definitions: Vec<_> = self.0.values().cloned().collect();
+        definitions.sort_by(|a, b| a.name().cmp(b.name()));
+        GhostbusterDefinitionList::new(definitions)
+    }
+}
+
+//------------ GhostbusterObjects ------------------------------------------
+
+/// Ghostbuster records issued under a resource class in a CA.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GhostbusterObjects(HashMap<GhostbusterName, GhostbusterInfo>);
+
+impl GhostbusterObjects {
+    fn make_ghostbuster(
+        &self,
+        definition: GhostbusterDefinition,
+        certified_key: &CertifiedKey,
+        issuance_timing: &IssuanceTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<GhostbusterInfo> {
+        let object = make_ghostbuster_object(
+            &definition,
+            certified_key,
+            issuance_timing.new_ghostbuster_validity(),
+            signer,
+        )?;
+        Ok(GhostbusterInfo::new(definition, object))
+    }
+
+    /// Issue new Ghostbuster records for new or changed definitions, and
+    /// remove records for which the definition was removed.
+    ///
+    /// Unlike ROAs and ASPAs, Ghostbuster records do not depend on the
+    /// resources held, so all definitions are relevant to every key.
+    pub fn update(
+        &self,
+        all_definitions: &GhostbusterDefinitions,
+        certified_key: &CertifiedKey,
+        config: &Config,
+        signer: &KrillSigner,
+    ) -> KrillResult<GhostbusterObjectsUpdates> {
+        let mut updates = GhostbusterObjectsUpdates::default();
+
+        for definition in all_definitions.all() {
+            let need_to_issue = self
+                .0
+                .get(definition.name())
+                .map(|existing| existing.definition() != definition)
+                .unwrap_or(true);
+
+            if need_to_issue {
+                let info = self.make_ghostbuster(
+                    definition.clone(),
+                    certified_key,
+                    &config.issuance_timing,
+                    signer,
+                )?;
+                updates.add_updated(info);
+            }
+        }
+
+        for name in self.0.keys() {
+            if !all_definitions.has(name) {
+                updates.add_removed(name.clone());
+            }
+        }
+
+        Ok(updates)
+    }
+
+    /// Re-new Ghostbuster records. If the renew_threshold is specified,
+    /// then only objects which will expire before that time will be
+    /// renewed. Otherwise all objects are renewed, e.g. when a new key is
+    /// activated.
+    pub fn renew(
+        &self,
+        certified_key: &CertifiedKey,
+        renew_threshold: Option<Time>,
+        issuance_timing: &IssuanceTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<GhostbusterObjectsUpdates> {
+        let mut updates = GhostbusterObjectsUpdates::default();
+
+        for info in self.0.values().filter(|info| {
+            renew_threshold
+                .map(|threshold| info.expires() < threshold)
+                .unwrap_or(true)
+        }) {
+            let renewed = self.make_ghostbuster(
+                info.definition().clone(),
+                certified_key,
+                issuance_timing,
+                signer,
+            )?;
+            updates.add_updated(renewed);
+        }
+
+        Ok(updates)
+    }
+
+    /// Applies updates from an event.
+    pub fn updated(&mut self, updates: GhostbusterObjectsUpdates) {
+        let (updated, removed) = updates.unpack();
+        for info in updated {
+            self.0.insert(info.name().clone(), info);
+        }
+        for name in removed {
+            self.0.remove(&name);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+//------------ GhostbusterInfo ---------------------------------------------
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GhostbusterInfo {
+    // The contact details included in this object
+    definition: GhostbusterDefinition,
+
+    // The validity time for this object
+    validity: Validity,
+
+    // The serial number (needed for revocation)
+    serial: Serial,
+
+    // The URI where this object is expected to be published
+    uri: uri::Rsync,
+
+    // The actual Ghostbuster record in base64 format
+    base64: Base64,
+
+    // The object's hash
+    hash: Hash,
+}
+
+impl GhostbusterInfo {
+    pub fn new(
+        definition: GhostbusterDefinition,
+        object: SignedObject,
+    ) -> Self {
+        let validity = object.cert().validity();
+        let serial = object.cert().serial_number();
+        let uri = object.cert().signed_object().unwrap().clone(); // safe for our own objects
+        let base64 = Base64::from_content(
+            object.encode_ref().to_captured(Mode::Der).as_slice(),
+        );
+        let hash = base64.to_hash();
+
+        GhostbusterInfo {
+            definition,
+            validity,
+            serial,
+            uri,
+            base64,
+            hash,
+        }
+    }
+
+    pub fn definition(&self) -> &GhostbusterDefinition {
+        &self.definition
+    }
+
+    pub fn name(&self) -> &GhostbusterName {
+        self.definition.name()
+    }
+
+    pub fn object_name(&self) -> ObjectName {
+        self.definition.object_name()
+    }
+
+    pub fn expires(&self) -> Time {
+        self.validity.not_after()
+    }
+
+    pub fn serial(&self) -> Serial {
+        self.serial
+    }
+
+    pub fn uri(&self) -> &uri::Rsync {
+        &self.uri
+    }
+
+    pub fn base64(&self) -> &Base64 {
+        &self.base64
+    }
+
+    pub fn hash(&self) -> Hash {
+        self.hash
+    }
+}
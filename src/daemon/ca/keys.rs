@@ -39,6 +39,11 @@ pub struct CertifiedKey {
     request: Option<IssuanceRequest>,
     #[serde(skip_serializing_if = "Option::is_none")]
     old_repo: Option<RepoInfo>,
+    // The time when this key was first certified. This is used to
+    // determine the age of the key for automatic key rolls. It is
+    // unknown for keys that were certified before this was tracked.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    certified: Option<Time>,
 }
 
 impl CertifiedKey {
@@ -53,6 +58,7 @@ impl CertifiedKey {
             incoming_cert,
             request,
             old_repo,
+            certified: None,
         }
     }
 
@@ -63,6 +69,7 @@ impl CertifiedKey {
             incoming_cert,
             request: None,
             old_repo: None,
+            certified: Some(Time::now()),
         }
     }
 
@@ -92,6 +99,10 @@ impl CertifiedKey {
         self.old_repo = Some(repo)
     }
 
+    pub fn certified(&self) -> Option<Time> {
+        self.certified
+    }
+
     pub fn wants_update(
         &self,
         handle: &CaHandle,
@@ -360,7 +371,7 @@ impl KeyState {
         }
     }
 
-    fn revoke_key(
+    pub fn revoke_key(
         class_name: ResourceClassName,
         key_id: &KeyIdentifier,
         signer: &KrillSigner,
@@ -674,6 +685,47 @@ impl KeyState {
         }
     }
 
+    /// Starts an emergency key roll. This is similar to a normal key roll,
+    /// except that the current key is marked as compromised, so that the
+    /// new key can be activated as soon as it is certified.
+    pub fn keyroll_emergency(
+        &self,
+        resource_class_name: ResourceClassName,
+        parent_class_name: ResourceClassName,
+        base_repo: &RepoInfo,
+        name_space: &str,
+        signer: &KrillSigner,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        match self {
+            KeyState::Active(current) => {
+                let pending_key_id = signer.create_key()?;
+
+                let req = self.create_issuance_req(
+                    base_repo,
+                    name_space,
+                    parent_class_name,
+                    &pending_key_id,
+                    signer,
+                )?;
+
+                Ok(vec![
+                    CertAuthEvent::KeyRollEmergencyInitiated {
+                        resource_class_name: resource_class_name.clone(),
+                        pending_key_id,
+                        compromised_key: *current.key_id(),
+                    },
+                    CertAuthEvent::CertificateRequested {
+                        resource_class_name,
+                        req,
+                        ki: pending_key_id,
+                    },
+                ])
+            }
+            KeyState::Pending(_) => Err(Error::KeyUseNoCurrentKey),
+            _ => Err(Error::KeyRollInProgress),
+        }
+    }
+
     /// Marks the new key as current, and the current key as old, and requests
     /// revocation of the old key.
     pub fn keyroll_activate(
@@ -16,7 +16,7 @@ use rpki::{
         publication::{ListReply, Publish, PublishDelta, Update, Withdraw},
     },
     crypto::KeyIdentifier,
-    repository::resources::ResourceSet,
+    repository::{resources::ResourceSet, x509::Time},
     uri,
 };
 
@@ -26,21 +26,23 @@ use crate::{
         api::{
             import::{ExportChild, ImportChild},
             rrdp::PublishElement,
-            BgpSecCsrInfoList, BgpSecDefinitionUpdates, IdCertInfo,
-            ParentServerInfo, PublicationServerInfo, RoaConfigurationUpdates,
-            Timestamp,
+            BgpSecCsrInfoList, BgpSecDefinitionUpdates, ExpiringObject,
+            ExpiringObjectType, GhostbusterDefinitionList,
+            GhostbusterDefinitionUpdates, IdCertInfo, KeyRollPolicy,
+            ObjectExpiryReport, ParentServerInfo, PointInTime,
+            PublicationServerInfo, RoaConfigurationUpdates, Timestamp,
         },
         api::{
             AddChildRequest, AspaDefinitionList, AspaDefinitionUpdates,
-            AspaProvidersUpdate, CaCommandDetails, CertAuthList,
-            CertAuthSummary, ChildCaInfo, CommandHistory,
-            CommandHistoryCriteria, CustomerAsn, ParentCaContact,
-            ParentCaReq, ReceivedCert, RepositoryContact, RtaName,
-            UpdateChildRequest,
+            AspaProvidersUpdate, AuditCriteria, AuditLog, AuditSource,
+            CaCommandDetails, CertAuthList, CertAuthSummary, ChildCaInfo,
+            CommandDryRun, CommandHistory, CommandHistoryCriteria,
+            CustomerAsn, ParentCaContact, ParentCaReq, ReceivedCert,
+            RepositoryContact, RtaName, UpdateChildRequest,
         },
         crypto::KrillSigner,
         error::Error,
-        eventsourcing::{Aggregate, AggregateStore},
+        eventsourcing::{Aggregate, AggregateStore, AggregateStoreError},
         util::{cmslogger::CmsLogger, httpclient},
         KrillResult,
     },
@@ -52,19 +54,23 @@ use crate::{
         auth::Handle,
         ca::{
             CaObjectsStore, CaStatus, CertAuth, CertAuthCommand,
-            CertAuthCommandDetails, DeprecatedRepository,
-            ResourceTaggedAttestation, RtaContentRequest, RtaPrepareRequest,
-            StatusStore,
+            CertAuthCommandDetails, CertAuthEvent, DeprecatedRepository,
+            GeofeedSignRequest, ResourceTaggedAttestation, RscSignRequest,
+            RtaContentRequest, RtaPrepareRequest, SignedChecklist,
+            SignedGeofeed, StatusStore,
         },
         config::Config,
+        eventstream::EventStream,
+        ha::RoleListener,
         mq::{now, Task, TaskQueue},
+        webhooks::WebhookManager,
     },
     pubd::RepositoryManager,
     ta::{
-        self, ta_handle, TrustAnchorProxy, TrustAnchorProxyCommand,
-        TrustAnchorProxyInitCommand, TrustAnchorSignedRequest,
-        TrustAnchorSignedResponse, TrustAnchorSigner,
-        TrustAnchorSignerCommand, TrustAnchorSignerInfo,
+        self, ta_handle, TrustAnchorChild, TrustAnchorProxy,
+        TrustAnchorProxyCommand, TrustAnchorProxyInitCommand,
+        TrustAnchorSignedRequest, TrustAnchorSignedResponse,
+        TrustAnchorSigner, TrustAnchorSignerCommand, TrustAnchorSignerInfo,
         TrustAnchorSignerInitCommand, TrustAnchorSignerInitCommandDetails,
         TA_NAME,
     },
@@ -119,6 +125,8 @@ impl CaManager {
         tasks: Arc<TaskQueue>,
         signer: Arc<KrillSigner>,
         system_actor: Actor,
+        event_stream: Arc<EventStream>,
+        webhooks: Arc<WebhookManager>,
     ) -> KrillResult<Self> {
         // Create the AggregateStore for the event-sourced `CertAuth`
         // structures that handle most CA functions.
@@ -189,6 +197,12 @@ impl CaManager {
         // RFC 6492).
         ca_store.add_post_save_listener(tasks.clone());
 
+        // Pass on committed events to subscribers of the event stream.
+        ca_store.add_post_save_listener(event_stream.clone());
+
+        // Notify configured webhooks of relevant changes.
+        ca_store.add_post_save_listener(webhooks.clone());
+
         // Create TA proxy store if we need it.
         let ta_proxy_store = if config.ta_proxy_enabled() {
             let mut store = AggregateStore::<TrustAnchorProxy>::create(
@@ -207,6 +221,7 @@ impl CaManager {
             // - re-sync for local children when the proxy has new responses
             //   AND is saved
             store.add_post_save_listener(tasks.clone());
+            store.add_post_save_listener(event_stream);
 
             Some(store)
         } else {
@@ -226,9 +241,12 @@ impl CaManager {
         // Create the status store which will maintain the last known
         // connection status between each CA and their parent(s) and
         // repository.
-        let status_store =
+        let mut status_store =
             StatusStore::create(&config.storage_uri, STATUS_NS)?;
 
+        // Notify configured webhooks of new issues.
+        status_store.add_listener(webhooks);
+
         Ok(CaManager {
             ca_store,
             ca_objects_store,
@@ -254,6 +272,51 @@ impl CaManager {
         self.ca_store.command(cmd)
     }
 
+    /// Processes commands for a CA without storing them. Reports the events
+    /// that would be produced, and the resulting changes to certificates
+    /// and published objects.
+    fn ca_dry_run(
+        &self,
+        ca: &CaHandle,
+        cmds: Vec<CertAuthCommand>,
+    ) -> KrillResult<CommandDryRun> {
+        let version = self.ca_store.get_latest(ca)?.version();
+        let (_, events) = self.ca_store.dry_run(ca, cmds)?;
+
+        let mut dry_run = CommandDryRun::new(version);
+        for event in &events {
+            dry_run.add_event(event);
+            match event {
+                CertAuthEvent::ChildCertificatesUpdated {
+                    updates, ..
+                } => {
+                    for issued in updates.issued() {
+                        dry_run.add_issued(issued);
+                    }
+                    for key in updates.removed() {
+                        dry_run.add_revoked(*key);
+                    }
+                }
+                CertAuthEvent::ResourceClassRemoved {
+                    revoke_requests,
+                    ..
+                } => {
+                    for req in revoke_requests {
+                        dry_run.add_revocation_request(req.clone());
+                    }
+                }
+                CertAuthEvent::KeyRollActivated { revoke_req, .. }
+                | CertAuthEvent::UnexpectedKeyFound { revoke_req, .. } => {
+                    dry_run.add_revocation_request(revoke_req.clone());
+                }
+                _ => {}
+            }
+        }
+        self.ca_objects_store.dry_run(ca, &events, &mut dry_run)?;
+
+        Ok(dry_run)
+    }
+
     /// Republish the embedded TA and CAs if needed, i.e. if they are close
     /// to their next update time.
     pub async fn republish_all(
@@ -510,6 +573,38 @@ impl CaManager {
         Ok(())
     }
 
+    /// Shows a child of the TA proxy.
+    pub async fn ta_proxy_children_show(
+        &self,
+        child: &ChildHandle,
+    ) -> KrillResult<TrustAnchorChild> {
+        let proxy = self.get_trust_anchor_proxy().await?;
+        proxy.get_child(child).cloned()
+    }
+
+    /// Removes a child from the TA proxy. If the child has any keys in use,
+    /// then their revocation is included in the next signer request and the
+    /// child is removed when the signer response is processed.
+    ///
+    /// Errors if:
+    /// - there is no proxy, or no such child
+    /// - the child is already being removed
+    /// - there is an open signer request
+    pub async fn ta_proxy_children_remove(
+        &self,
+        child: ChildHandle,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let cmd = TrustAnchorProxyCommand::remove_child(
+            &ta_handle(),
+            child.clone(),
+            actor,
+        );
+        self.send_ta_proxy_command(cmd).await?;
+        self.status_store.remove_child(&ta_handle(), &child)?;
+        Ok(())
+    }
+
     /// Initializes an embedded trust anchor with all resources.
     pub async fn ta_init_fully_embedded(
         &self,
@@ -642,6 +737,22 @@ impl CaManager {
             .map_err(|_| Error::CaUnknown(handle.clone()))
     }
 
+    /// Gets a CA as it was at the given point in its history, together
+    /// with the time of the last command applied to it.
+    pub async fn get_ca_at(
+        &self,
+        handle: &CaHandle,
+        point: PointInTime,
+    ) -> KrillResult<(CertAuth, Time)> {
+        if !self.has_ca(handle)? {
+            return Err(Error::CaUnknown(handle.clone()));
+        }
+        self.ca_store
+            .get_at(handle, point)
+            .map_err(Error::AggregateStoreError)?
+            .ok_or_else(|| Error::CaUnknownAt(handle.clone(), point))
+    }
+
     /// Checks whether a CA by the given handle exists.
     pub fn has_ca(&self, handle: &CaHandle) -> KrillResult<bool> {
         self.ca_store
@@ -728,6 +839,40 @@ impl CaManager {
 
         Ok(())
     }
+
+    /// Reports what deleting a CA would do, without deleting it: all its
+    /// objects would be withdrawn, and revocation of its keys would be
+    /// requested from its parents.
+    pub async fn delete_ca_dry_run(
+        &self,
+        ca_handle: &CaHandle,
+    ) -> KrillResult<CommandDryRun> {
+        let ca = self.get_ca(ca_handle).await?;
+
+        let mut dry_run = CommandDryRun::new(ca.version());
+        for parent in ca.parents() {
+            for req in ca
+                .revoke_under_parent(parent, &self.signer)?
+                .into_values()
+                .flatten()
+            {
+                dry_run.add_revocation_request(req);
+            }
+        }
+        let mut withdrawn: Vec<_> = self
+            .ca_objects_store
+            .ca_objects(ca_handle)?
+            .all_publish_elements()
+            .into_iter()
+            .map(|el| el.unpack().0)
+            .collect();
+        withdrawn.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        for uri in withdrawn {
+            dry_run.add_withdrawn(uri);
+        }
+
+        Ok(dry_run)
+    }
 }
 
 /// # CA History
@@ -751,6 +896,104 @@ impl CaManager {
             .get_command(handle, version)
             .map_err(Error::AggregateStoreError)
     }
+
+    /// Reverts the ROA, ASPA or BGPSec definition changes made by an
+    /// earlier command of a CA. The revert itself is stored as a new
+    /// command.
+    pub async fn ca_command_revert(
+        &self,
+        ca: &CaHandle,
+        reverted: u64,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let cmd = self
+            .ca_command_revert_command(ca, reverted, version, actor)
+            .await?;
+        self.send_ca_command(cmd).await?;
+        Ok(())
+    }
+
+    /// Reports what reverting an earlier command would do, without
+    /// reverting it.
+    pub async fn ca_command_revert_dry_run(
+        &self,
+        ca: &CaHandle,
+        reverted: u64,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        let cmd = self
+            .ca_command_revert_command(ca, reverted, version, actor)
+            .await?;
+        self.ca_dry_run(ca, vec![cmd])
+    }
+
+    /// Builds the command that reverts the command stored at the given
+    /// version, using the state of the CA just before that command to
+    /// find the definitions that need to be restored.
+    async fn ca_command_revert_command(
+        &self,
+        ca: &CaHandle,
+        reverted: u64,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CertAuthCommand> {
+        let not_revertible =
+            || Error::CaCommandNotRevertible(ca.clone(), reverted);
+
+        let command =
+            self.ca_store
+                .get_command(ca, reverted)
+                .map_err(|e| match e {
+                    AggregateStoreError::CommandNotFound(_, _) => {
+                        Error::CaUnknownAt(
+                            ca.clone(),
+                            PointInTime::Version(reverted),
+                        )
+                    }
+                    e => Error::AggregateStoreError(e),
+                })?;
+        let events = command.events().ok_or_else(not_revertible)?;
+
+        let (before, _) =
+            self.get_ca_at(ca, PointInTime::Version(reverted)).await?;
+        let updates =
+            before.revert_updates(events)?.ok_or_else(not_revertible)?;
+
+        Ok(CertAuthCommandDetails::command_revert(
+            ca,
+            reverted,
+            updates,
+            self.config.clone(),
+            self.signer.clone(),
+            actor,
+        )
+        .with_version(version))
+    }
+
+    /// Moves the commands of all CAs that are older than the given number
+    /// of days to the archive.
+    pub fn ca_archive_commands(&self, days: u32) -> KrillResult<()> {
+        let before = Time::now() - Duration::days(days.into());
+
+        for ca in self.ca_store.list()? {
+            match self.ca_store.archive_commands(&ca, before) {
+                Ok(0) => {}
+                Ok(count) => {
+                    info!("Archived {} old commands for CA '{}'", count, ca)
+                }
+                Err(e) => {
+                    error!(
+                        "Could not archive commands for CA '{}': {}",
+                        ca, e
+                    )
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 /// # CAs as parents
@@ -758,11 +1001,15 @@ impl CaManager {
     /// Adds a child under a CA. If the `AddChildRequest` contains resources
     /// not held by this CA, then an `Error::CaChildExtraResources` is
     /// returned.
+    ///
+    /// If a version is given, the child is only added if the CA is still
+    /// at that version. This does not apply to the Trust Anchor.
     pub async fn ca_add_child(
         &self,
         ca: &CaHandle,
         req: AddChildRequest,
         service_uri: &uri::Https,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillResult<idexchange::ParentResponse> {
         info!("CA '{}' process add child request: {}", &ca, &req);
@@ -775,7 +1022,8 @@ impl CaManager {
                 id_cert.into(),
                 child_res,
                 actor,
-            );
+            )
+            .with_version(version);
             self.send_ca_command(add_child).await?;
             self.ca_parent_response(ca, child_handle, service_uri).await
         } else {
@@ -787,6 +1035,30 @@ impl CaManager {
         }
     }
 
+    /// Reports what adding a child under a CA would do, without adding it.
+    /// This is not supported for the Trust Anchor.
+    pub async fn ca_add_child_dry_run(
+        &self,
+        ca: &CaHandle,
+        req: AddChildRequest,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        if ca.as_str() == TA_NAME {
+            return Err(Error::TaNotAllowed);
+        }
+        let (child_handle, child_res, id_cert) = req.unpack();
+        let add_child = CertAuthCommandDetails::child_add(
+            ca,
+            child_handle,
+            id_cert.into(),
+            child_res,
+            actor,
+        )
+        .with_version(version);
+        self.ca_dry_run(ca, vec![add_child])
+    }
+
     /// Show details for a child under the CA.
     pub async fn ca_show_child(
         &self,
@@ -898,13 +1170,52 @@ impl CaManager {
     /// Internet Number Resource (INR) types (IPv4, IPV6, ASN). Setting
     /// resource entitlements beyond the resources held by the parent CA will
     /// return an `Error::CaChildExtraResources`.
+    ///
+    /// If a version is given, then the update is only done if the CA is
+    /// still at that version. It applies to the first of the resulting
+    /// commands, as the CA moves on with each of them.
     pub async fn ca_child_update(
         &self,
         ca: &CaHandle,
         child: ChildHandle,
         req: UpdateChildRequest,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillResult<()> {
+        for cmd in
+            Self::ca_child_update_commands(ca, child, req, version, actor)
+        {
+            self.send_ca_command(cmd).await?;
+        }
+        Ok(())
+    }
+
+    /// Reports what updating a child would do, without updating it.
+    pub async fn ca_child_update_dry_run(
+        &self,
+        ca: &CaHandle,
+        child: ChildHandle,
+        req: UpdateChildRequest,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_dry_run(
+            ca,
+            Self::ca_child_update_commands(ca, child, req, version, actor),
+        )
+    }
+
+    /// Returns the commands for a child update, in the order in which they
+    /// need to be applied. The version applies to the first command only.
+    fn ca_child_update_commands(
+        ca: &CaHandle,
+        child: ChildHandle,
+        req: UpdateChildRequest,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> Vec<CertAuthCommand> {
+        let mut version = version;
+        let mut cmds = vec![];
         let (
             id_opt,
             resources_opt,
@@ -913,53 +1224,57 @@ impl CaManager {
         ) = req.unpack();
 
         if let Some(id) = id_opt {
-            self.send_ca_command(CertAuthCommandDetails::child_update_id(
-                ca,
-                child.clone(),
-                id.into(),
-                actor,
-            ))
-            .await?;
+            cmds.push(
+                CertAuthCommandDetails::child_update_id(
+                    ca,
+                    child.clone(),
+                    id.into(),
+                    actor,
+                )
+                .with_version(version.take()),
+            );
         }
         if let Some(resources) = resources_opt {
-            self.send_ca_command(
+            cmds.push(
                 CertAuthCommandDetails::child_update_resources(
                     ca,
                     child.clone(),
                     resources,
                     actor,
-                ),
-            )
-            .await?;
+                )
+                .with_version(version.take()),
+            );
         }
         if let Some(suspend) = suspend_opt {
             if suspend {
-                self.send_ca_command(
+                cmds.push(
                     CertAuthCommandDetails::child_suspend_inactive(
                         ca,
                         child.clone(),
                         actor,
-                    ),
-                )
-                .await?;
+                    )
+                    .with_version(version.take()),
+                );
             } else {
-                self.send_ca_command(
+                cmds.push(
                     CertAuthCommandDetails::child_unsuspend(
                         ca,
                         child.clone(),
                         actor,
-                    ),
-                )
-                .await?;
+                    )
+                    .with_version(version.take()),
+                );
             }
         }
         if let Some(mapping) = resource_class_name_mapping_opt {
-            self.send_ca_command(CertAuthCommandDetails::child_update_resource_class_name_mapping(
-                ca, child, mapping, actor,
-            ))
-            .await?;
+            cmds.push(
+                CertAuthCommandDetails::child_update_resource_class_name_mapping(
+                    ca, child, mapping, actor,
+                )
+                .with_version(version.take()),
+            );
         }
-        Ok(())
+        cmds
     }
 
     /// Removes a child from this CA. This will also ensure that certificates
@@ -968,17 +1283,34 @@ impl CaManager {
         &self,
         ca: &CaHandle,
         child: ChildHandle,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillResult<()> {
-        self.status_store.remove_child(ca, &child)?;
-        self.send_ca_command(CertAuthCommandDetails::child_remove(
-            ca, child, actor,
-        ))
+        self.send_ca_command(
+            CertAuthCommandDetails::child_remove(ca, child.clone(), actor)
+                .with_version(version),
+        )
         .await?;
+        self.status_store.remove_child(ca, &child)?;
 
         Ok(())
     }
 
+    /// Reports what removing a child would do, without removing it.
+    pub async fn ca_child_remove_dry_run(
+        &self,
+        ca: &CaHandle,
+        child: ChildHandle,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_dry_run(
+            ca,
+            vec![CertAuthCommandDetails::child_remove(ca, child, actor)
+                .with_version(version)],
+        )
+    }
+
     /// Processes an RFC 6492 request sent to this CA:
     /// - parses the message bytes
     /// - validates the request
@@ -1066,6 +1398,7 @@ impl CaManager {
                     ca.handle(),
                     child_handle.clone(),
                     req,
+                    None,
                     actor,
                 )
                 .await?;
@@ -1365,6 +1698,30 @@ impl CaManager {
         Ok(())
     }
 
+    /// Reports what removing a parent from a CA would do, without removing
+    /// it.
+    pub async fn ca_parent_remove_dry_run(
+        &self,
+        handle: CaHandle,
+        parent: ParentHandle,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        // The revocations are requested outside of the command, as they are
+        // best effort.
+        let revoke_requests = self
+            .get_ca(&handle)
+            .await?
+            .revoke_under_parent(&parent, &self.signer)?;
+
+        let upd =
+            CertAuthCommandDetails::remove_parent(&handle, parent, actor);
+        let mut dry_run = self.ca_dry_run(&handle, vec![upd])?;
+        for req in revoke_requests.into_values().flatten() {
+            dry_run.add_revocation_request(req);
+        }
+        Ok(dry_run)
+    }
+
     /// Send revocation requests for a parent of a CA when the parent is
     /// removed.
     pub async fn ca_parent_revoke(
@@ -1466,7 +1823,7 @@ impl CaManager {
 
                     let req = UpdateChildRequest::suspend();
                     if let Err(e) = self
-                        .ca_child_update(ca_handle, child, req, actor)
+                        .ca_child_update(ca_handle, child, req, None, actor)
                         .await
                     {
                         error!(
@@ -2668,6 +3025,88 @@ impl CaManager {
         Ok(self.ca_objects_store.ca_objects(ca)?.repo_elements_map())
     }
 
+    /// Get the current objects, and the received certificates used to sign
+    /// them, for all CAs that the given actor is permitted to see. Includes
+    /// the TA certificate and objects if there is a TA proxy and the actor
+    /// may see it.
+    pub async fn object_expiry_report(
+        &self,
+        actor: &Actor,
+    ) -> KrillResult<ObjectExpiryReport> {
+        let mut report = ObjectExpiryReport::default();
+
+        for ca in self.ca_list(actor)?.cas() {
+            self.ca_objects_store
+                .ca_objects(ca.handle())?
+                .add_expiring_objects(&mut report);
+        }
+
+        let ta_handle = ta_handle();
+        let ta_allowed = matches!(
+            actor.is_allowed(Permission::CA_READ, Handle::from(&ta_handle)),
+            Ok(true)
+        );
+
+        if ta_allowed {
+            if let Ok(proxy) = self.get_trust_anchor_proxy().await {
+                if let Ok(ta_cert_details) = proxy.get_ta_details() {
+                    let cert = ta_cert_details.cert();
+                    report.add(ExpiringObject::new(
+                        ta_handle.clone(),
+                        ExpiringObjectType::TaCert,
+                        cert.uri().clone(),
+                        cert.expires(),
+                        None,
+                    ));
+                    proxy
+                        .get_trust_anchor_objects()?
+                        .add_expiring_objects(&ta_handle, &mut report)?;
+                }
+            }
+        }
+
+        report.sort();
+        Ok(report)
+    }
+
+    /// Adds the commands for all CAs that the actor may see, and for the
+    /// TA proxy if there is one, to the audit log.
+    pub fn add_audit_records(
+        &self,
+        log: &mut AuditLog,
+        crit: &AuditCriteria,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        for ca in self.ca_list(actor)?.cas() {
+            if crit.matches_handle(ca.handle()) {
+                log.add(
+                    AuditSource::Ca,
+                    self.ca_store.audit_records(ca.handle(), crit)?,
+                );
+            }
+        }
+
+        let ta_handle = ta_handle();
+        let ta_allowed = matches!(
+            actor.is_allowed(Permission::CA_READ, Handle::from(&ta_handle)),
+            Ok(true)
+        );
+
+        if let Some(ta_proxy_store) = self.ta_proxy_store.as_ref() {
+            if ta_allowed
+                && crit.matches_handle(&ta_handle)
+                && ta_proxy_store.has(&ta_handle)?
+            {
+                log.add(
+                    AuditSource::TaProxy,
+                    ta_proxy_store.audit_records(&ta_handle, crit)?,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
     /// Get deprecated repositories so that they can be cleaned.
     pub fn ca_deprecated_repos(
         &self,
@@ -2950,6 +3389,7 @@ impl CaManager {
         &self,
         ca: CaHandle,
         updates: AspaDefinitionUpdates,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillResult<()> {
         self.send_ca_command(
@@ -2959,12 +3399,35 @@ impl CaManager {
                 self.config.clone(),
                 self.signer.clone(),
                 actor,
-            ),
+            )
+            .with_version(version),
         )
         .await?;
         Ok(())
     }
 
+    /// Reports what updating the ASPA definitions would do, without
+    /// updating them.
+    pub async fn ca_aspas_definitions_update_dry_run(
+        &self,
+        ca: CaHandle,
+        updates: AspaDefinitionUpdates,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_dry_run(
+            &ca,
+            vec![CertAuthCommandDetails::aspas_definitions_update(
+                &ca,
+                updates,
+                self.config.clone(),
+                self.signer.clone(),
+                actor,
+            )
+            .with_version(version)],
+        )
+    }
+
     /// Update the ASPA definition for this CA and the customer ASN in the
     /// update.
     pub async fn ca_aspas_update_aspa(
@@ -2972,19 +3435,46 @@ impl CaManager {
         ca: CaHandle,
         customer: CustomerAsn,
         update: AspaProvidersUpdate,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillResult<()> {
-        self.send_ca_command(CertAuthCommandDetails::aspas_update_aspa(
-            &ca,
-            customer,
-            update,
-            self.config.clone(),
-            self.signer.clone(),
-            actor,
-        ))
+        self.send_ca_command(
+            CertAuthCommandDetails::aspas_update_aspa(
+                &ca,
+                customer,
+                update,
+                self.config.clone(),
+                self.signer.clone(),
+                actor,
+            )
+            .with_version(version),
+        )
         .await?;
         Ok(())
     }
+
+    /// Reports what updating an ASPA definition would do, without updating it.
+    pub async fn ca_aspas_update_aspa_dry_run(
+        &self,
+        ca: CaHandle,
+        customer: CustomerAsn,
+        update: AspaProvidersUpdate,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_dry_run(
+            &ca,
+            vec![CertAuthCommandDetails::aspas_update_aspa(
+                &ca,
+                customer,
+                update,
+                self.config.clone(),
+                self.signer.clone(),
+                actor,
+            )
+            .with_version(version)],
+        )
+    }
 }
 
 /// # BGPSec functions
@@ -3001,6 +3491,7 @@ impl CaManager {
         &self,
         ca: CaHandle,
         updates: BgpSecDefinitionUpdates,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillResult<()> {
         self.send_ca_command(
@@ -3010,8 +3501,63 @@ impl CaManager {
                 self.config.clone(),
                 self.signer.clone(),
                 actor,
-            ),
+            )
+            .with_version(version),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Reports what updating the BGPSec definitions would do, without
+    /// updating them.
+    pub async fn ca_bgpsec_definitions_update_dry_run(
+        &self,
+        ca: CaHandle,
+        updates: BgpSecDefinitionUpdates,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_dry_run(
+            &ca,
+            vec![CertAuthCommandDetails::bgpsec_update_definitions(
+                &ca,
+                updates,
+                self.config.clone(),
+                self.signer.clone(),
+                actor,
+            )
+            .with_version(version)],
         )
+    }
+}
+
+/// # Ghostbuster functions
+impl CaManager {
+    /// Show the current Ghostbuster definitions for this CA.
+    pub async fn ca_ghostbusters_show(
+        &self,
+        ca: CaHandle,
+    ) -> KrillResult<GhostbusterDefinitionList> {
+        let ca = self.get_ca(&ca).await?;
+        Ok(ca.ghostbusters_show())
+    }
+
+    /// Add, replace or remove Ghostbuster definitions for this CA. This
+    /// will trigger that Ghostbuster records are (re-)issued or removed
+    /// under each resource class of the CA.
+    pub async fn ca_ghostbusters_update(
+        &self,
+        ca: CaHandle,
+        updates: GhostbusterDefinitionUpdates,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        self.send_ca_command(CertAuthCommandDetails::ghostbusters_update(
+            &ca,
+            updates,
+            self.config.clone(),
+            self.signer.clone(),
+            actor,
+        ))
         .await?;
         Ok(())
     }
@@ -3032,6 +3578,7 @@ impl CaManager {
         &self,
         ca: CaHandle,
         updates: RoaConfigurationUpdates,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillResult<()> {
         self.send_ca_command(
@@ -3041,12 +3588,34 @@ impl CaManager {
                 self.config.clone(),
                 self.signer.clone(),
                 actor,
-            ),
+            )
+            .with_version(version),
         )
         .await?;
         Ok(())
     }
 
+    /// Reports what updating the routes would do, without updating them.
+    pub async fn ca_routes_update_dry_run(
+        &self,
+        ca: CaHandle,
+        updates: RoaConfigurationUpdates,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_dry_run(
+            &ca,
+            vec![CertAuthCommandDetails::route_authorizations_update(
+                &ca,
+                updates,
+                self.config.clone(),
+                self.signer.clone(),
+                actor,
+            )
+            .with_version(version)],
+        )
+    }
+
     /// Re-issue about to expire objects in all CAs. This is a no-op in case
     /// ROAs do not need re-issuance. If new objects are created they will
     /// also be published (event will trigger that MFT and CRL are also
@@ -3105,6 +3674,20 @@ impl CaManager {
             if let Err(e) = self.send_ca_command(cmd).await {
                 error!("Renewing BGPSec certificates for CA '{}' failed with error: {}", ca, e);
             }
+
+            let cmd = CertAuthCommand::new(
+                &ca,
+                None,
+                CertAuthCommandDetails::GhostbustersRenew(
+                    self.config.clone(),
+                    self.signer.clone(),
+                ),
+                actor,
+            );
+
+            if let Err(e) = self.send_ca_command(cmd).await {
+                error!("Renewing Ghostbuster records for CA '{}' failed with error: {}", ca, e);
+            }
         }
         Ok(())
     }
@@ -3160,6 +3743,26 @@ impl CaManager {
         Ok(())
     }
 
+    /// Sign an RPKI Signed Checklist. This does not change the CA.
+    pub async fn rsc_sign(
+        &self,
+        ca: &CaHandle,
+        request: RscSignRequest,
+    ) -> KrillResult<SignedChecklist> {
+        let ca = self.get_ca(ca).await?;
+        ca.rsc_sign(request, &self.signer)
+    }
+
+    /// Sign a geofeed. This does not change the CA.
+    pub async fn geofeed_sign(
+        &self,
+        ca: &CaHandle,
+        request: GeofeedSignRequest,
+    ) -> KrillResult<SignedGeofeed> {
+        let ca = self.get_ca(ca).await?;
+        ca.geofeed_sign(request, &self.signer)
+    }
+
     /// Prepare a multi-singed RTA
     pub async fn rta_multi_prep(
         &self,
@@ -3219,6 +3822,26 @@ impl CaManager {
         Ok(())
     }
 
+    /// Start an emergency key roll for the given resource class, or all
+    /// resource classes if none is given. The new key is certified by the
+    /// parent right away, and is then activated without staging, while the
+    /// compromised key is revoked.
+    pub async fn ca_keyroll_emergency(
+        &self,
+        handle: CaHandle,
+        rcn: Option<ResourceClassName>,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let emergency_key_roll = CertAuthCommandDetails::key_roll_emergency(
+            &handle,
+            rcn,
+            self.signer.clone(),
+            actor,
+        );
+        self.send_ca_command(emergency_key_roll).await?;
+        Ok(())
+    }
+
     /// Activate a new key, as part of the key roll process (RFC 6489). Only
     /// new keys that have an age equal to or greater than the staging
     /// period are promoted. The RFC mandates a staging period of 24
@@ -3240,4 +3863,74 @@ impl CaManager {
         self.send_ca_command(activate_cmd).await?;
         Ok(())
     }
+
+    /// Set or remove the policy for automatic key rolls for a CA. The
+    /// scheduler will use this policy to initiate and activate key rolls.
+    pub async fn ca_keyroll_policy_update(
+        &self,
+        handle: CaHandle,
+        policy: Option<KeyRollPolicy>,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let cmd = CertAuthCommandDetails::key_roll_policy_update(
+            &handle, policy, actor,
+        );
+        self.send_ca_command(cmd).await?;
+        Ok(())
+    }
+
+    /// Initiate a key roll for a CA, if its key roll policy says that
+    /// it is due for any of its resource classes.
+    pub async fn ca_keyroll_init_if_needed(
+        &self,
+        handle: &CaHandle,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let ca = self.get_ca(handle).await?;
+        if let Some(policy) = ca.keyroll_policy() {
+            if ca.next_keyroll().map(|t| t <= Time::now()).unwrap_or(false) {
+                info!("Initiate automatic key roll for CA '{}'", handle);
+                self.ca_keyroll_init(
+                    handle.clone(),
+                    policy.roll_after(),
+                    actor,
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Activate new keys for a CA, if they have been staged for at least
+    /// the period required by its key roll policy.
+    pub async fn ca_keyroll_activate_if_needed(
+        &self,
+        handle: &CaHandle,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        let ca = self.get_ca(handle).await?;
+        if let Some(policy) = ca.keyroll_policy() {
+            if ca
+                .next_keyroll_activation()
+                .map(|t| t <= Time::now())
+                .unwrap_or(false)
+            {
+                info!("Activate new key(s) for CA '{}'", handle);
+                self.ca_keyroll_activate(
+                    handle.clone(),
+                    policy.activate_after(),
+                    actor,
+                )
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The cached CA status is only used while this node is active.
+impl RoleListener for CaManager {
+    fn active_changed(&self, active: bool) -> KrillResult<()> {
+        self.status_store.set_active(active)
+    }
 }
@@ -15,6 +15,12 @@ pub use self::certauth::CertAuth;
 mod child;
 pub use self::child::*;
 
+mod geofeed;
+pub use self::geofeed::*;
+
+mod ghostbuster;
+pub use self::ghostbuster::*;
+
 mod rc;
 pub use self::rc::ResourceClass;
 
@@ -37,6 +43,12 @@ pub use self::events::*;
 mod manager;
 pub use self::manager::CaManager;
 
+mod oneoff;
+pub use self::oneoff::*;
+
+mod rsc;
+pub use self::rsc::*;
+
 mod rta;
 pub use self::rta::*;
 
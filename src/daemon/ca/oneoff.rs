@@ -0,0 +1,137 @@
+//! CMS signed data using one-off EE certificates.
+//!
+//! Some RPKI signed objects, such as RPKI Signed Checklists (RFC 9323) and
+//! geofeed authenticators (RFC 9632), are not published in the repository.
+//! Their EE certificate MUST NOT include the Subject Information Access
+//! extension. The rpki crate always includes it for signed objects, so we
+//! assemble the CMS here.
+
+use bcder::{
+    encode,
+    encode::{PrimitiveContent, Values},
+    Captured, Mode, OctetString, Oid, Tag,
+};
+use bytes::Bytes;
+use rpki::{
+    crypto::DigestAlgorithm,
+    oid,
+    repository::{
+        resources::ResourceSet,
+        sigobj::MessageDigest,
+        x509::{Time, Validity},
+    },
+};
+
+use crate::{
+    commons::{
+        crypto::{KrillSigner, SignSupport},
+        error::Error,
+        KrillResult,
+    },
+    daemon::ca::CertifiedKey,
+};
+
+/// Returns the DER encoded CMS SignedData for the given content, signed
+/// using a one-off EE certificate for the given resources, issued under
+/// the given key. If `detached` is true, then the content is left out of
+/// the CMS, as is done for geofeed authenticators.
+pub fn make_one_off_signed_data(
+    content_type: &Oid<&'static [u8]>,
+    content: &[u8],
+    detached: bool,
+    resources: &ResourceSet,
+    validity: Validity,
+    signing_key: &CertifiedKey,
+    signer: &KrillSigner,
+) -> KrillResult<Bytes> {
+    let digest_algorithm = DigestAlgorithm::default();
+
+    let message_digest =
+        MessageDigest::from(digest_algorithm.digest(content));
+    let signed_attrs =
+        encode_signed_attrs(content_type, &message_digest, Time::now());
+
+    // The signed attributes are signed using their SET OF encoding,
+    // rather than the implicitly tagged encoding used in the SignerInfo.
+    let (signature, ee_key) = signer
+        .sign_one_off(
+            encode::set(&signed_attrs).to_captured(Mode::Der).as_slice(),
+        )
+        .map_err(Error::signer)?;
+    let sid = ee_key.key_identifier();
+
+    let ee = SignSupport::make_rta_ee_cert(
+        resources,
+        signing_key,
+        validity,
+        ee_key,
+        signer,
+    )?;
+
+    let e_content = if detached {
+        None
+    } else {
+        Some(encode::sequence_as(
+            Tag::CTX_0,
+            OctetString::encode_slice(content),
+        ))
+    };
+
+    let bytes = encode::sequence((
+        oid::SIGNED_DATA.encode(),
+        encode::sequence_as(
+            Tag::CTX_0,
+            encode::sequence((
+                3u8.encode(),
+                digest_algorithm.encode_set(),
+                encode::sequence((content_type.encode_ref(), e_content)),
+                encode::sequence_as(Tag::CTX_0, ee.encode_ref()),
+                encode::set(encode::sequence((
+                    3u8.encode(),
+                    sid.encode_ref_as(Tag::CTX_0),
+                    digest_algorithm.encode(),
+                    encode::set_as(Tag::CTX_0, &signed_attrs),
+                    signature.algorithm().cms_encode(),
+                    OctetString::encode_slice(signature.value().as_ref()),
+                ))),
+            )),
+        ),
+    ))
+    .to_captured(Mode::Der)
+    .into_bytes();
+
+    Ok(bytes)
+}
+
+/// Returns the DER encoded content of the signed attributes. In DER the
+/// values of a SET OF are ordered by their encoding.
+fn encode_signed_attrs(
+    content_type: &Oid<&'static [u8]>,
+    message_digest: &MessageDigest,
+    signing_time: Time,
+) -> Captured {
+    let mut attrs = vec![
+        encode::sequence((
+            oid::CONTENT_TYPE.encode(),
+            encode::set(content_type.encode_ref()),
+        ))
+        .to_captured(Mode::Der),
+        encode::sequence((
+            oid::MESSAGE_DIGEST.encode(),
+            encode::set(message_digest.encode_ref()),
+        ))
+        .to_captured(Mode::Der),
+        encode::sequence((
+            oid::SIGNING_TIME.encode(),
+            encode::set(signing_time.encode_varied()),
+        ))
+        .to_captured(Mode::Der),
+    ];
+    attrs.sort_by(|a, b| a.as_slice().cmp(b.as_slice()));
+
+    let mut res = Captured::builder(Mode::Der);
+    for attr in attrs {
+        res.extend(attr);
+    }
+    res.freeze()
+}
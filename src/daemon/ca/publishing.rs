@@ -23,8 +23,10 @@ use url::Url;
 use crate::{
     commons::{
         api::{
-            rrdp::PublishElement, CertInfo, IssuedCertificate, ObjectName,
-            ReceivedCert, RepositoryContact, Revocation, Revocations,
+            rrdp::PublishElement, CertInfo, CommandDryRun, ExpiringObject,
+            ExpiringObjectType, IssuedCertificate, ObjectExpiryReport,
+            ObjectName, ReceivedCert, RepositoryContact, Revocation,
+            Revocations,
         },
         crypto::KrillSigner,
         error::Error,
@@ -46,7 +48,7 @@ use crate::{
 
 use super::{
     AspaInfo, AspaObjectsUpdates, BgpSecCertInfo, BgpSecCertificateUpdates,
-    RoaInfo,
+    GhostbusterInfo, GhostbusterObjectsUpdates, RoaInfo,
 };
 
 //------------ CaObjectsStore ----------------------------------------------
@@ -101,113 +103,130 @@ impl PreSaveEventListener<CertAuth> for CaObjectsStore {
         let signer = &self.signer;
 
         self.with_ca_objects(ca.handle(), |objects| {
-            let mut force_reissue = false;
+            Self::apply_events(objects, events, timing, signer)
+        })
+    }
+}
 
-            for event in events {
-                match event {
-                    super::CertAuthEvent::RoasUpdated {
-                        resource_class_name,
-                        updates,
-                    } => {
-                        objects.update_roas(resource_class_name, updates)?;
-                        force_reissue = true;
-                    }
-                    super::CertAuthEvent::AspaObjectsUpdated {
-                        resource_class_name,
-                        updates,
-                    } => {
-                        objects.update_aspas(resource_class_name, updates)?;
-                        force_reissue = true;
-                    }
-                    super::CertAuthEvent::BgpSecCertificatesUpdated {
-                        resource_class_name,
-                        updates,
-                    } => {
-                        objects.update_bgpsec_certs(
-                            resource_class_name,
-                            updates,
-                        )?;
-                        force_reissue = true;
-                    }
-                    super::CertAuthEvent::ChildCertificatesUpdated {
-                        resource_class_name,
-                        updates,
-                    } => {
-                        objects.update_certs(resource_class_name, updates)?;
-                        force_reissue = true;
-                    }
-                    super::CertAuthEvent::KeyPendingToActive {
+impl CaObjectsStore {
+    /// Applies the events to the objects and re-issues the manifests and
+    /// CRLs where needed.
+    fn apply_events(
+        objects: &mut CaObjects,
+        events: &[CertAuthEvent],
+        timing: &IssuanceTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<()> {
+        let mut force_reissue = false;
+
+        for event in events {
+            match event {
+                super::CertAuthEvent::RoasUpdated {
+                    resource_class_name,
+                    updates,
+                } => {
+                    objects.update_roas(resource_class_name, updates)?;
+                    force_reissue = true;
+                }
+                super::CertAuthEvent::AspaObjectsUpdated {
+                    resource_class_name,
+                    updates,
+                } => {
+                    objects.update_aspas(resource_class_name, updates)?;
+                    force_reissue = true;
+                }
+                super::CertAuthEvent::BgpSecCertificatesUpdated {
+                    resource_class_name,
+                    updates,
+                } => {
+                    objects
+                        .update_bgpsec_certs(resource_class_name, updates)?;
+                    force_reissue = true;
+                }
+                super::CertAuthEvent::GhostbusterObjectsUpdated {
+                    resource_class_name,
+                    updates,
+                } => {
+                    objects
+                        .update_ghostbusters(resource_class_name, updates)?;
+                    force_reissue = true;
+                }
+                super::CertAuthEvent::ChildCertificatesUpdated {
+                    resource_class_name,
+                    updates,
+                } => {
+                    objects.update_certs(resource_class_name, updates)?;
+                    force_reissue = true;
+                }
+                super::CertAuthEvent::KeyPendingToActive {
+                    resource_class_name,
+                    current_key,
+                } => {
+                    objects.add_class(
                         resource_class_name,
                         current_key,
-                    } => {
-                        objects.add_class(
-                            resource_class_name,
-                            current_key,
-                            timing,
-                            signer,
-                        )?;
-                    }
-                    super::CertAuthEvent::KeyPendingToNew {
+                        timing,
+                        signer,
+                    )?;
+                }
+                super::CertAuthEvent::KeyPendingToNew {
+                    resource_class_name,
+                    new_key,
+                } => {
+                    objects.keyroll_stage(
                         resource_class_name,
                         new_key,
-                    } => {
-                        objects.keyroll_stage(
-                            resource_class_name,
-                            new_key,
-                            timing,
-                            signer,
-                        )?;
-                    }
-                    super::CertAuthEvent::KeyRollActivated {
-                        resource_class_name,
-                        ..
-                    } => {
-                        objects.keyroll_activate(resource_class_name)?;
-                        force_reissue = true;
-                    }
-                    super::CertAuthEvent::KeyRollFinished {
-                        resource_class_name,
-                    } => {
-                        objects.keyroll_finish(resource_class_name)?;
-                    }
-                    super::CertAuthEvent::CertificateReceived {
+                        timing,
+                        signer,
+                    )?;
+                }
+                super::CertAuthEvent::KeyRollActivated {
+                    resource_class_name,
+                    ..
+                } => {
+                    objects.keyroll_activate(resource_class_name)?;
+                    force_reissue = true;
+                }
+                super::CertAuthEvent::KeyRollFinished {
+                    resource_class_name,
+                } => {
+                    objects.keyroll_finish(resource_class_name)?;
+                }
+                super::CertAuthEvent::CertificateReceived {
+                    resource_class_name,
+                    rcvd_cert,
+                    ..
+                } => {
+                    objects.update_received_cert(
                         resource_class_name,
                         rcvd_cert,
-                        ..
-                    } => {
-                        objects.update_received_cert(
-                            resource_class_name,
-                            rcvd_cert,
-                        )?;
-                        // this in itself constitutes no need to force
-                        // re-issuance if the new
-                        // certificate triggered that the set of objects
-                        // changed, e.g. because a ROA
-                        // became overclaiming, then we would see another
-                        // event for that which *will* result in forcing
-                        // re-issuance.
-                    }
-                    super::CertAuthEvent::ResourceClassRemoved {
-                        resource_class_name,
-                        ..
-                    } => {
-                        objects.remove_class(resource_class_name);
-                        force_reissue = true;
-                    }
-                    super::CertAuthEvent::RepoUpdated { contact } => {
-                        objects.update_repo(contact);
-                        force_reissue = true;
-                    }
-                    _ => {}
+                    )?;
+                    // this in itself constitutes no need to force
+                    // re-issuance if the new
+                    // certificate triggered that the set of objects
+                    // changed, e.g. because a ROA
+                    // became overclaiming, then we would see another
+                    // event for that which *will* result in forcing
+                    // re-issuance.
                 }
+                super::CertAuthEvent::ResourceClassRemoved {
+                    resource_class_name,
+                    ..
+                } => {
+                    objects.remove_class(resource_class_name);
+                    force_reissue = true;
+                }
+                super::CertAuthEvent::RepoUpdated { contact } => {
+                    objects.update_repo(contact);
+                    force_reissue = true;
+                }
+                _ => {}
             }
-            objects.re_issue(force_reissue, timing, signer)?;
-            Ok(())
-        })
+        }
+        objects.re_issue(force_reissue, timing, signer)?;
+        Ok(())
     }
-}
 
-impl CaObjectsStore {
     fn key(ca: &CaHandle) -> Key {
         Key::new_global(Segment::parse_lossy(&format!("{}.json", ca))) // ca should always be a valid Segment
     }
@@ -260,6 +279,49 @@ impl CaObjectsStore {
         }
     }
 
+    /// Adds the objects that would be published and withdrawn for a CA, if
+    /// the given events were applied, to the dry run. Nothing is stored.
+    pub fn dry_run(
+        &self,
+        ca: &CaHandle,
+        events: &[CertAuthEvent],
+        dry_run: &mut CommandDryRun,
+    ) -> KrillResult<()> {
+        let mut objects = self.ca_objects(ca)?;
+        let before: HashMap<uri::Rsync, Base64> = objects
+            .all_publish_elements()
+            .into_iter()
+            .map(|el| el.unpack())
+            .collect();
+
+        Self::apply_events(
+            &mut objects,
+            events,
+            &self.issuance_timing,
+            &self.signer,
+        )?;
+
+        let mut after: Vec<_> = objects.all_publish_elements();
+        after.sort_by(|a, b| a.uri().as_str().cmp(b.uri().as_str()));
+        let mut withdrawn: Vec<_> = before
+            .keys()
+            .filter(|uri| !after.iter().any(|el| el.uri() == *uri))
+            .cloned()
+            .collect();
+        withdrawn.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+        for el in after {
+            if before.get(el.uri()) != Some(el.base64()) {
+                dry_run.add_published(el.uri().clone());
+            }
+        }
+        for uri in withdrawn {
+            dry_run.add_withdrawn(uri);
+        }
+
+        Ok(())
+    }
+
     /// Perform an action (closure) on a mutable instance of the CaObjects for
     /// a CA. If the CA did not have any CaObjects yet, one will be
     /// created. The closure is executed within a write lock.
@@ -412,6 +474,14 @@ impl CaObjects {
         all_elements
     }
 
+    /// Adds all current objects, and the certificates received for the
+    /// keys that sign them, to the expiry report.
+    pub fn add_expiring_objects(&self, report: &mut ObjectExpiryReport) {
+        for resource_class_objects in self.classes.values() {
+            resource_class_objects.add_expiring_objects(&self.ca, report);
+        }
+    }
+
     pub fn deprecated_repos(&self) -> &Vec<DeprecatedRepository> {
         &self.deprecated_repos
     }
@@ -539,6 +609,16 @@ impl CaObjects {
             .map(|rco| rco.update_bgpsec_certs(updates))
     }
 
+    // Update the Ghostbuster records in the current set
+    fn update_ghostbusters(
+        &mut self,
+        rcn: &ResourceClassName,
+        updates: &GhostbusterObjectsUpdates,
+    ) -> KrillResult<()> {
+        self.get_class_mut(rcn)
+            .map(|rco| rco.update_ghostbusters(updates))
+    }
+
     // Update the issued certificates in the current set
     fn update_certs(
         &mut self,
@@ -649,6 +729,26 @@ impl ResourceClassObjects {
         }
     }
 
+    fn add_expiring_objects(
+        &self,
+        ca: &CaHandle,
+        report: &mut ObjectExpiryReport,
+    ) {
+        match &self.keys {
+            ResourceClassKeyState::Current(state) => {
+                state.current_set.add_expiring_objects(ca, report)
+            }
+            ResourceClassKeyState::Staging(state) => {
+                state.current_set.add_expiring_objects(ca, report);
+                state.staging_set.add_expiring_objects(ca, report);
+            }
+            ResourceClassKeyState::Old(state) => {
+                state.current_set.add_expiring_objects(ca, report);
+                state.old_set.add_expiring_objects(ca, report);
+            }
+        }
+    }
+
     fn create(
         key: &CertifiedKey,
         timing: &IssuanceTimingConfig,
@@ -773,6 +873,20 @@ impl ResourceClassObjects {
         }
     }
 
+    fn update_ghostbusters(&mut self, updates: &GhostbusterObjectsUpdates) {
+        match self.keys.borrow_mut() {
+            ResourceClassKeyState::Current(state) => {
+                state.current_set.update_ghostbusters(updates)
+            }
+            ResourceClassKeyState::Staging(state) => {
+                state.current_set.update_ghostbusters(updates)
+            }
+            ResourceClassKeyState::Old(state) => {
+                state.current_set.update_ghostbusters(updates)
+            }
+        }
+    }
+
     fn update_certs(&mut self, cert_updates: &ChildCertificateUpdates) {
         match self.keys.borrow_mut() {
             ResourceClassKeyState::Current(state) => {
@@ -1131,6 +1245,46 @@ impl KeyObjectSet {
         }
     }
 
+    fn add_expiring_objects(
+        &self,
+        ca: &CaHandle,
+        report: &mut ObjectExpiryReport,
+    ) {
+        report.add(ExpiringObject::new(
+            ca.clone(),
+            ExpiringObjectType::ReceivedCert,
+            self.signing_cert.uri().clone(),
+            self.signing_cert.expires(),
+            None,
+        ));
+
+        report.add(ExpiringObject::new(
+            ca.clone(),
+            ExpiringObjectType::Manifest,
+            self.signing_cert.mft_uri(),
+            self.manifest.expires,
+            Some(self.revision.next_update),
+        ));
+
+        report.add(ExpiringObject::new(
+            ca.clone(),
+            ExpiringObjectType::Crl,
+            self.signing_cert.crl_uri(),
+            self.crl.expires,
+            Some(self.revision.next_update),
+        ));
+
+        for (name, object) in &self.published_objects {
+            report.add(ExpiringObject::new(
+                ca.clone(),
+                ExpiringObjectType::from(name),
+                self.signing_cert.uri_for_name(name),
+                object.expires,
+                None,
+            ));
+        }
+    }
+
     pub fn requires_reissuance(&self, hours: i64) -> bool {
         Time::now() > self.next_update() - Duration::hours(hours)
     }
@@ -1211,6 +1365,24 @@ impl KeyObjectSet {
         }
     }
 
+    fn update_ghostbusters(&mut self, updates: &GhostbusterObjectsUpdates) {
+        for info in updates.updated() {
+            let published_object = PublishedObject::for_ghostbuster(info);
+            if let Some(old) = self
+                .published_objects
+                .insert(info.object_name(), published_object)
+            {
+                self.revocations.add(old.revoke());
+            }
+        }
+        for removed in updates.removed() {
+            let name = ObjectName::ghostbuster(removed);
+            if let Some(old) = self.published_objects.remove(&name) {
+                self.revocations.add(old.revoke());
+            }
+        }
+    }
+
     fn update_certs(&mut self, cert_updates: &ChildCertificateUpdates) {
         for removed in cert_updates.removed() {
             let name = ObjectName::new(removed, "cer");
@@ -1406,6 +1578,14 @@ impl<T> PublishedItem<T> {
         }
     }
 
+    pub fn name(&self) -> &ObjectName {
+        &self.name
+    }
+
+    pub fn expires(&self) -> Time {
+        self.expires
+    }
+
     pub fn publish_element(&self, uri: uri::Rsync) -> PublishElement {
         PublishElement::new(self.base64.clone(), uri)
     }
@@ -1491,6 +1671,15 @@ impl PublishedObject {
             cert.expires(),
         )
     }
+
+    pub fn for_ghostbuster(info: &GhostbusterInfo) -> Self {
+        PublishedObject::new(
+            info.object_name(),
+            info.base64().clone(),
+            info.serial(),
+            info.expires(),
+        )
+    }
 }
 
 //------------ CrlBuilder --------------------------------------------------
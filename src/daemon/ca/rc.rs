@@ -20,8 +20,9 @@ use rpki::{
 use crate::{
     commons::{
         api::{
-            IssuedCertificate, ReceivedCert, ResourceClassInfo,
-            RoaConfiguration, SuspendedCert, UnsuspendedCert,
+            IssuedCertificate, KeyRollPolicy, ReceivedCert,
+            ResourceClassInfo, RoaConfiguration, SuspendedCert,
+            UnsuspendedCert,
         },
         crypto::{CsrInfo, KrillSigner, SignSupport},
         error::Error,
@@ -41,7 +42,8 @@ use crate::{
 
 use super::{
     AspaDefinitions, BgpSecCertificateUpdates, BgpSecCertificates,
-    BgpSecDefinitions, RoaInfo,
+    BgpSecDefinitions, GhostbusterDefinitions, GhostbusterObjects,
+    GhostbusterObjectsUpdates, RoaInfo,
 };
 
 //------------ ResourceClass -----------------------------------------------
@@ -74,11 +76,18 @@ pub struct ResourceClass {
     #[serde(skip_serializing_if = "BgpSecCertificates::is_empty", default)]
     bgpsec_certificates: BgpSecCertificates,
 
+    #[serde(skip_serializing_if = "GhostbusterObjects::is_empty", default)]
+    ghostbusters: GhostbusterObjects,
+
     #[serde(skip_serializing_if = "ChildCertificates::is_empty", default)]
     certificates: ChildCertificates,
 
     last_key_change: Time,
     key_state: KeyState,
+
+    // Set during an emergency key roll, until the new key is activated.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    compromised_key: Option<KeyIdentifier>,
 }
 
 /// # Creating new instances
@@ -100,8 +109,10 @@ impl ResourceClass {
             aspas: AspaObjects::default(),
             certificates: ChildCertificates::default(),
             bgpsec_certificates: BgpSecCertificates::default(),
+            ghostbusters: GhostbusterObjects::default(),
             last_key_change: Time::now(),
             key_state: KeyState::create(pending_key),
+            compromised_key: None,
         }
     }
 
@@ -118,8 +129,10 @@ impl ResourceClass {
             aspas: AspaObjects::default(),
             certificates: ChildCertificates::default(),
             bgpsec_certificates: BgpSecCertificates::default(),
+            ghostbusters: GhostbusterObjects::default(),
             last_key_change: Time::now(),
             key_state: KeyState::create(pending_key),
+            compromised_key: None,
         }
     }
 }
@@ -187,6 +200,43 @@ impl ResourceClass {
         }
     }
 
+    /// Returns the time since when the given key is certified. Falls back
+    /// to the time of the last key change in this resource class for keys
+    /// for which this was not recorded.
+    fn certified_since(&self, key: &CertifiedKey) -> Time {
+        key.certified().unwrap_or(self.last_key_change)
+    }
+
+    /// Returns the age of the current key, if there is any.
+    pub fn current_key_age(&self) -> Option<Duration> {
+        self.current_key().map(|key| {
+            Time::now().signed_duration_since(*self.certified_since(key))
+        })
+    }
+
+    /// Returns the time at which the current key should be rolled
+    /// according to the given policy. Returns None if there is no current
+    /// key, or if a key roll is already in progress.
+    pub fn next_keyroll(&self, policy: &KeyRollPolicy) -> Option<Time> {
+        match &self.key_state {
+            KeyState::Active(current) => {
+                Some(self.certified_since(current) + policy.roll_after())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the time at which the new key should be activated according
+    /// to the given policy. Returns None if there is no new key.
+    pub fn next_keyroll_activation(
+        &self,
+        policy: &KeyRollPolicy,
+    ) -> Option<Time> {
+        self.key_state
+            .new_key()
+            .map(|new| self.certified_since(new) + policy.activate_after())
+    }
+
     /// Returns a ResourceClassInfo for this, which contains all the
     /// same data, but which does not have any behavior.
     pub fn as_info(&self) -> ResourceClassInfo {
@@ -216,6 +266,7 @@ impl ResourceClass {
         all_routes: &Routes,
         all_aspas: &AspaDefinitions,
         all_bgpsecs: &BgpSecDefinitions,
+        all_ghostbusters: &GhostbusterDefinitions,
         config: &Config,
         signer: &KrillSigner,
     ) -> KrillResult<Vec<CertAuthEvent>> {
@@ -256,6 +307,12 @@ impl ResourceClass {
                         config,
                         signer,
                     )?;
+                    let ghostbuster_updates = self.ghostbusters.update(
+                        all_ghostbusters,
+                        &current_key,
+                        config,
+                        signer,
+                    )?;
 
                     let mut events =
                         vec![CertAuthEvent::KeyPendingToActive {
@@ -286,6 +343,15 @@ impl ResourceClass {
                         )
                     }
 
+                    if ghostbuster_updates.contains_changes() {
+                        events.push(
+                            CertAuthEvent::GhostbusterObjectsUpdated {
+                                resource_class_name: self.name.clone(),
+                                updates: ghostbuster_updates,
+                            },
+                        )
+                    }
+
                     Ok(events)
                 }
             }
@@ -302,10 +368,44 @@ impl ResourceClass {
             KeyState::RollPending(pending, current) => {
                 if rcvd_cert_ki == pending.key_id() {
                     let new_key = CertifiedKey::create(rcvd_cert);
-                    Ok(vec![CertAuthEvent::KeyPendingToNew {
-                        resource_class_name: self.name.clone(),
-                        new_key,
-                    }])
+
+                    if let Some(compromised_key) = self.compromised_key {
+                        // Emergency key roll: do not stage the new key, but
+                        // activate it straight away and revoke the
+                        // compromised key.
+                        info!(
+                            "Activating new key for CA '{}' under RC '{}' to replace compromised key '{}'",
+                            handle, self.name, compromised_key
+                        );
+
+                        let revoke_req = KeyState::revoke_key(
+                            self.parent_rc_name.clone(),
+                            current.key_id(),
+                            signer,
+                        )?;
+
+                        let mut events = vec![
+                            CertAuthEvent::KeyPendingToNew {
+                                resource_class_name: self.name.clone(),
+                                new_key: new_key.clone(),
+                            },
+                            CertAuthEvent::KeyRollActivated {
+                                resource_class_name: self.name.clone(),
+                                revoke_req,
+                            },
+                        ];
+                        events.append(&mut self.reissue_under_new_key(
+                            &new_key,
+                            &config.issuance_timing,
+                            signer,
+                        )?);
+                        Ok(events)
+                    } else {
+                        Ok(vec![CertAuthEvent::KeyPendingToNew {
+                            resource_class_name: self.name.clone(),
+                            new_key,
+                        }])
+                    }
                 } else {
                     self.update_rcvd_cert_current(
                         handle,
@@ -588,6 +688,16 @@ impl ResourceClass {
         }
     }
 
+    /// Adds a pending key to replace a compromised current key.
+    pub fn emergency_key_id_added(
+        &mut self,
+        key_id: KeyIdentifier,
+        compromised_key: KeyIdentifier,
+    ) {
+        self.pending_key_id_added(key_id);
+        self.compromised_key = Some(compromised_key);
+    }
+
     /// Moves a pending key to new
     pub fn pending_key_to_new(&mut self, new: CertifiedKey) {
         match &self.key_state {
@@ -618,6 +728,7 @@ impl ResourceClass {
             KeyState::RollNew(new, current) => {
                 let old_key = OldKey::new(current.clone(), revoke_req);
                 self.key_state = KeyState::RollOld(new.clone(), old_key);
+                self.compromised_key = None;
             }
             _ => panic!("Should never create event to activate key when no roll in progress"),
         }
@@ -641,10 +752,12 @@ impl ResourceClass {
         duration: Duration,
         signer: &KrillSigner,
     ) -> KrillResult<Vec<CertAuthEvent>> {
-        if duration > Duration::seconds(0)
-            && self.last_key_change + duration > Time::now()
-        {
-            return Ok(vec![]);
+        if duration > Duration::seconds(0) {
+            if let Some(current) = self.current_key() {
+                if self.certified_since(current) + duration > Time::now() {
+                    return Ok(vec![]);
+                }
+            }
         }
 
         self.key_state.keyroll_initiate(
@@ -656,6 +769,21 @@ impl ResourceClass {
         )
     }
 
+    /// Initiate an emergency key roll, replacing the current key.
+    pub fn keyroll_emergency(
+        &self,
+        base_repo: &RepoInfo,
+        signer: &KrillSigner,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        self.key_state.keyroll_emergency(
+            self.name.clone(),
+            self.parent_rc_name.clone(),
+            base_repo,
+            &self.name_space,
+            signer,
+        )
+    }
+
     /// Activate a new key, if it's been longer than the staging period.
     pub fn keyroll_activate(
         &self,
@@ -665,7 +793,7 @@ impl ResourceClass {
     ) -> KrillResult<Vec<CertAuthEvent>> {
         if let Some(new_key) = self.key_state.new_key() {
             if staging_time > Duration::seconds(0)
-                && self.last_key_change + staging_time > Time::now()
+                && self.certified_since(new_key) + staging_time > Time::now()
             {
                 Ok(vec![])
             } else {
@@ -676,61 +804,11 @@ impl ResourceClass {
                 )?;
 
                 let mut events = vec![key_activated];
-
-                let roa_updates = self.roas.renew(
-                    true,
-                    new_key,
-                    issuance_timing,
-                    signer,
-                )?;
-                if !roa_updates.is_empty() {
-                    let roas_updated = CertAuthEvent::RoasUpdated {
-                        resource_class_name: self.name.clone(),
-                        updates: roa_updates,
-                    };
-                    events.push(roas_updated);
-                }
-
-                let aspa_updates = self.aspas.renew(
+                events.append(&mut self.reissue_under_new_key(
                     new_key,
-                    None,
                     issuance_timing,
                     signer,
-                )?;
-                if !aspa_updates.is_empty() {
-                    let aspas_updated = CertAuthEvent::AspaObjectsUpdated {
-                        resource_class_name: self.name.clone(),
-                        updates: aspa_updates,
-                    };
-                    events.push(aspas_updated);
-                }
-
-                let cert_updates = self.certificates.activate_key(
-                    new_key.incoming_cert(),
-                    issuance_timing,
-                    signer,
-                )?;
-                if !cert_updates.is_empty() {
-                    let certs_updated =
-                        CertAuthEvent::ChildCertificatesUpdated {
-                            resource_class_name: self.name.clone(),
-                            updates: cert_updates,
-                        };
-                    events.push(certs_updated);
-                }
-
-                let bgpsec_updates = self.bgpsec_certificates.renew(
-                    new_key,
-                    None,
-                    issuance_timing,
-                    signer,
-                )?;
-                if !bgpsec_updates.is_empty() {
-                    events.push(CertAuthEvent::BgpSecCertificatesUpdated {
-                        resource_class_name: self.name.clone(),
-                        updates: bgpsec_updates,
-                    });
-                }
+                )?);
 
                 Ok(events)
             }
@@ -739,6 +817,78 @@ impl ResourceClass {
         }
     }
 
+    /// Re-issues all objects and child certificates under the new key,
+    /// when it is activated.
+    fn reissue_under_new_key(
+        &self,
+        new_key: &CertifiedKey,
+        issuance_timing: &IssuanceTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<Vec<CertAuthEvent>> {
+        let mut events = vec![];
+
+        let roa_updates =
+            self.roas.renew(true, new_key, issuance_timing, signer)?;
+        if !roa_updates.is_empty() {
+            let roas_updated = CertAuthEvent::RoasUpdated {
+                resource_class_name: self.name.clone(),
+                updates: roa_updates,
+            };
+            events.push(roas_updated);
+        }
+
+        let aspa_updates =
+            self.aspas.renew(new_key, None, issuance_timing, signer)?;
+        if !aspa_updates.is_empty() {
+            let aspas_updated = CertAuthEvent::AspaObjectsUpdated {
+                resource_class_name: self.name.clone(),
+                updates: aspa_updates,
+            };
+            events.push(aspas_updated);
+        }
+
+        let cert_updates = self.certificates.activate_key(
+            new_key.incoming_cert(),
+            issuance_timing,
+            signer,
+        )?;
+        if !cert_updates.is_empty() {
+            let certs_updated = CertAuthEvent::ChildCertificatesUpdated {
+                resource_class_name: self.name.clone(),
+                updates: cert_updates,
+            };
+            events.push(certs_updated);
+        }
+
+        let bgpsec_updates = self.bgpsec_certificates.renew(
+            new_key,
+            None,
+            issuance_timing,
+            signer,
+        )?;
+        if !bgpsec_updates.is_empty() {
+            events.push(CertAuthEvent::BgpSecCertificatesUpdated {
+                resource_class_name: self.name.clone(),
+                updates: bgpsec_updates,
+            });
+        }
+
+        let ghostbuster_updates = self.ghostbusters.renew(
+            new_key,
+            None,
+            issuance_timing,
+            signer,
+        )?;
+        if !ghostbuster_updates.is_empty() {
+            events.push(CertAuthEvent::GhostbusterObjectsUpdated {
+                resource_class_name: self.name.clone(),
+                updates: ghostbuster_updates,
+            });
+        }
+
+        Ok(events)
+    }
+
     /// Finish a key roll, withdraw the old key
     pub fn keyroll_finish(&self) -> KrillResult<CertAuthEvent> {
         match &self.key_state {
@@ -966,6 +1116,55 @@ impl ResourceClass {
     }
 }
 
+/// # Ghostbusters
+impl ResourceClass {
+    /// Updates the Ghostbuster records in accordance with the supplied
+    /// definitions
+    pub fn update_ghostbusters(
+        &self,
+        all_ghostbusters: &GhostbusterDefinitions,
+        config: &Config,
+        signer: &KrillSigner,
+    ) -> KrillResult<GhostbusterObjectsUpdates> {
+        if let Ok(key) = self.get_current_key() {
+            self.ghostbusters
+                .update(all_ghostbusters, key, config, signer)
+        } else {
+            debug!("no Ghostbuster records to update - resource class has no current key");
+            Ok(GhostbusterObjectsUpdates::default())
+        }
+    }
+
+    /// Renew Ghostbuster records that would expire otherwise.
+    pub fn renew_ghostbusters(
+        &self,
+        issuance_timing: &IssuanceTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<GhostbusterObjectsUpdates> {
+        if let Ok(key) = self.get_current_key() {
+            let renew_threshold =
+                Some(issuance_timing.new_ghostbuster_issuance_threshold());
+            self.ghostbusters.renew(
+                key,
+                renew_threshold,
+                issuance_timing,
+                signer,
+            )
+        } else {
+            debug!("no Ghostbuster records to renew - resource class has no current key");
+            Ok(GhostbusterObjectsUpdates::default())
+        }
+    }
+
+    /// Apply Ghostbuster record changes from events
+    pub fn ghostbuster_objects_updated(
+        &mut self,
+        updates: GhostbusterObjectsUpdates,
+    ) {
+        self.ghostbusters.updated(updates)
+    }
+}
+
 /// # Resource Tagged Attestations (RTA)
 impl ResourceClass {
     /// Create an EE certificate to be used on an RTA,
@@ -0,0 +1,258 @@
+//! RPKI Signed Checklists (RSC), see RFC 9323.
+//!
+//! An RSC is a signed list of file hashes, made using a one-off EE
+//! certificate for resources held by the CA. RSCs are not published, so
+//! the CMS is made using [`make_one_off_signed_data`].
+
+use std::fmt;
+
+use bcder::{encode, encode::Values, Ia5String, Mode, OctetString, Oid, Tag};
+use bytes::Bytes;
+use rpki::{
+    ca::publication::Base64,
+    crypto::DigestAlgorithm,
+    repository::{
+        resources::{AddressFamily, ResourceSet},
+        x509::Validity,
+    },
+    rrdp::Hash,
+};
+
+use crate::{
+    commons::{crypto::KrillSigner, util::ext_serde, KrillResult},
+    daemon::ca::{make_one_off_signed_data, CertifiedKey},
+};
+
+/// The content type for RSC, id-ct-signedChecklist: 1.2.840.113549.1.9.16.1.48
+const CT_SIGNED_CHECKLIST: Oid<&[u8]> =
+    Oid(&[42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 48]);
+
+//------------ RscChecklistItem ---------------------------------------------
+
+/// An entry in the checklist of an RSC. This is the SHA-256 hash of the
+/// content of a file, and optionally its name.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RscChecklistItem {
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    file_name: Option<String>,
+    hash: Hash,
+}
+
+impl RscChecklistItem {
+    pub fn new(file_name: Option<String>, hash: Hash) -> Self {
+        RscChecklistItem { file_name, hash }
+    }
+
+    /// Creates an item for the given file name and content. The file name
+    /// is only included if it is a portable file name as required by RFC
+    /// 9323, i.e. if it consists of letters, digits, '.', '_' and '-' only.
+    pub fn for_file(file_name: &str, content: &[u8]) -> Self {
+        let file_name = Some(file_name.to_string())
+            .filter(|name| Self::is_portable_file_name(name));
+        RscChecklistItem {
+            file_name,
+            hash: Hash::from_data(content),
+        }
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.file_name.as_deref()
+    }
+
+    pub fn hash(&self) -> Hash {
+        self.hash
+    }
+
+    pub fn is_portable_file_name(name: &str) -> bool {
+        !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "._-".contains(c))
+    }
+
+    fn encode_ref(&self) -> impl encode::Values + '_ {
+        encode::sequence((
+            self.file_name.as_ref().map(|name| {
+                // Safe: portable file names are checked before signing
+                Ia5String::from_string(name.clone()).unwrap().encode()
+            }),
+            OctetString::encode_slice(self.hash.as_ref()),
+        ))
+    }
+}
+
+impl fmt::Display for RscChecklistItem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.file_name {
+            Some(name) => write!(f, "{} {}", self.hash, name),
+            None => write!(f, "{}", self.hash),
+        }
+    }
+}
+
+//------------ RscSignRequest -----------------------------------------------
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RscSignRequest {
+    resources: ResourceSet,
+    validity: Validity,
+    checklist: Vec<RscChecklistItem>,
+}
+
+impl RscSignRequest {
+    pub fn new(
+        resources: ResourceSet,
+        validity: Validity,
+        checklist: Vec<RscChecklistItem>,
+    ) -> Self {
+        RscSignRequest {
+            resources,
+            validity,
+            checklist,
+        }
+    }
+
+    pub fn resources(&self) -> &ResourceSet {
+        &self.resources
+    }
+
+    /// Verifies that an RSC can be made for this request. Returns a
+    /// message describing the problem if it cannot.
+    pub fn verify(&self) -> Result<(), String> {
+        if self.resources.is_empty() {
+            return Err("no resources specified".to_string());
+        }
+        if self.checklist.is_empty() {
+            return Err("no files to include on the checklist".to_string());
+        }
+        for name in self.checklist.iter().filter_map(|item| item.file_name())
+        {
+            if !RscChecklistItem::is_portable_file_name(name) {
+                return Err(format!("file name '{}' is not portable", name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn unpack(self) -> (ResourceSet, Validity, Vec<RscChecklistItem>) {
+        (self.resources, self.validity, self.checklist)
+    }
+}
+
+impl fmt::Display for RscSignRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "resources: {}", &self.resources)?;
+        writeln!(
+            f,
+            "validity, {}-{}",
+            self.validity.not_before().to_rfc3339(),
+            self.validity.not_after().to_rfc3339()
+        )?;
+        writeln!(f, "checklist:")?;
+        for item in &self.checklist {
+            writeln!(f, "  {}", item)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ SignedChecklist ----------------------------------------------
+
+/// A DER encoded RPKI Signed Checklist.
+#[derive(Clone, Debug, Deserialize, Eq, Serialize, PartialEq)]
+pub struct SignedChecklist {
+    #[serde(
+        deserialize_with = "ext_serde::de_bytes",
+        serialize_with = "ext_serde::ser_bytes"
+    )]
+    bytes: Bytes,
+}
+
+impl AsRef<Bytes> for SignedChecklist {
+    fn as_ref(&self) -> &Bytes {
+        &self.bytes
+    }
+}
+
+impl SignedChecklist {
+    /// Signs an RSC for the given resources and checklist, using a one-off
+    /// EE certificate issued under the given key.
+    pub fn sign(
+        resources: &ResourceSet,
+        validity: Validity,
+        checklist: &[RscChecklistItem],
+        signing_key: &CertifiedKey,
+        signer: &KrillSigner,
+    ) -> KrillResult<Self> {
+        let content = Self::encode_content(
+            resources,
+            DigestAlgorithm::default(),
+            checklist,
+        )
+        .to_captured(Mode::Der);
+
+        let bytes = make_one_off_signed_data(
+            &CT_SIGNED_CHECKLIST,
+            content.as_slice(),
+            false,
+            resources,
+            validity,
+            signing_key,
+            signer,
+        )?;
+
+        Ok(SignedChecklist { bytes })
+    }
+
+    /// Encodes the RpkiSignedChecklist content. The version is left out as
+    /// it is the default (0).
+    fn encode_content<'a>(
+        resources: &'a ResourceSet,
+        digest_algorithm: DigestAlgorithm,
+        checklist: &'a [RscChecklistItem],
+    ) -> impl encode::Values + 'a {
+        let asns = resources.asn();
+        let ipv4 = resources.ipv4();
+        let ipv6 = resources.ipv6();
+
+        let as_id = if asns.is_empty() {
+            None
+        } else {
+            Some(encode::sequence_as(
+                Tag::CTX_0,
+                encode::sequence(encode::sequence_as(
+                    Tag::CTX_0,
+                    encode::sequence(asns.encode_ref()),
+                )),
+            ))
+        };
+
+        let ip_addr_blocks = if ipv4.is_empty() && ipv6.is_empty() {
+            None
+        } else {
+            Some(encode::sequence_as(
+                Tag::CTX_1,
+                encode::sequence((
+                    (!ipv4.is_empty())
+                        .then(|| ipv4.encode_family(AddressFamily::Ipv4)),
+                    (!ipv6.is_empty())
+                        .then(|| ipv6.encode_family(AddressFamily::Ipv6)),
+                )),
+            ))
+        };
+
+        encode::sequence((
+            encode::sequence((as_id, ip_addr_blocks)),
+            digest_algorithm.encode(),
+            encode::sequence(encode::iter(
+                checklist.iter().map(|item| item.encode_ref()),
+            )),
+        ))
+    }
+}
+
+impl fmt::Display for SignedChecklist {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Base64::from_content(self.as_ref()))
+    }
+}
@@ -1,4 +1,11 @@
-use std::{collections::HashMap, str::FromStr, sync::RwLock};
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+};
 
 use kvx::Namespace;
 use rpki::ca::{
@@ -65,11 +72,40 @@ impl CaStatus {
     }
 }
 
+//------------ StatusIssue ---------------------------------------------------
+
+/// An issue with the connection of a CA to its parent or repository, as
+/// reported in the CA issues.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StatusIssue {
+    ParentSyncFailed {
+        parent: ParentHandle,
+        error: ErrorResponse,
+    },
+    RepoSyncFailed {
+        error: ErrorResponse,
+    },
+}
+
+//------------ StatusListener ------------------------------------------------
+
+/// Listens for new issues in the status of CAs. Issues are only passed on
+/// when they appear, and not for subsequent failures.
+pub trait StatusListener: Send + Sync + 'static {
+    fn issue_found(&self, ca: &CaHandle, issue: StatusIssue);
+}
+
 //------------ StatusStore ---------------------------------------------------
 
 pub struct StatusStore {
     store: KeyValueStore,
     cache: RwLock<HashMap<CaHandle, CaStatus>>,
+    listeners: Vec<Arc<dyn StatusListener>>,
+
+    // Whether this node is active, and may use and update the cache. A
+    // standby node reads the status from storage instead, because the
+    // leader can change it at any time.
+    active: AtomicBool,
 }
 
 impl StatusStore {
@@ -80,32 +116,70 @@ impl StatusStore {
         let store = KeyValueStore::create(storage_uri, namespace)?;
         let cache = RwLock::new(HashMap::new());
 
-        let store = StatusStore { store, cache };
+        let store = StatusStore {
+            store,
+            cache,
+            listeners: vec![],
+            active: AtomicBool::new(true),
+        };
         store.warm()?;
 
         Ok(store)
     }
 
+    pub fn add_listener<L: StatusListener>(&mut self, listener: Arc<L>) {
+        self.listeners.push(listener);
+    }
+
+    fn notify_listeners(&self, ca: &CaHandle, issue: StatusIssue) {
+        for listener in &self.listeners {
+            listener.issue_found(ca, issue.clone());
+        }
+    }
+
     /// Load existing status from disk, support the pre 0.9.5 format and
     /// silently convert it if needed.
     fn warm(&self) -> KrillResult<()> {
+        let mut cache = HashMap::new();
         for scope in self.store.scopes()? {
             if let Ok(ca) = CaHandle::from_str(&scope.to_string()) {
                 self.convert_pre_0_9_5_full_status_if_present(&ca)?;
-                self.load_full_status(&ca)?;
+                let status = self.read_full_status(&ca)?;
+                cache.insert(ca, status);
             }
         }
 
+        // Update the cache. Note that this is what we will use at runtime.
+        // Changes go directly in to the cached object. We will save smaller
+        // JSON files as well but we only do this full parsing on startup,
+        // or when this node becomes active.
+        *self.cache.write().unwrap() = cache;
+
+        Ok(())
+    }
+
+    /// Sets whether this node is active. The cache is loaded again when
+    /// the node becomes active, because another node may have changed the
+    /// status in the meantime.
+    pub fn set_active(&self, active: bool) -> KrillResult<()> {
+        if active {
+            if !self.active.load(Ordering::Acquire) {
+                self.warm()?;
+            }
+        } else {
+            self.cache.write().unwrap().clear();
+        }
+        self.active.store(active, Ordering::Release);
         Ok(())
     }
 
-    /// Load current status from disk, to be used when starting up. If there
-    /// are any issues parsing data then default values are used - this
-    /// data is not critical so any missing, corrupted, or no longer
-    /// supported data format - can be ignored. It will get updated with
-    /// new status values as Krill is running.
+    /// Read current status from disk. If there are any issues parsing
+    /// data then default values are used - this data is not critical so
+    /// any missing, corrupted, or no longer supported data format - can be
+    /// ignored. It will get updated with new status values as Krill is
+    /// running.
     #[allow(clippy::manual_unwrap_or_default)] // False positive in nightly
-    fn load_full_status(&self, ca: &CaHandle) -> KrillResult<()> {
+    fn read_full_status(&self, ca: &CaHandle) -> KrillResult<CaStatus> {
         let repo: RepoStatus =
             match self.store.get(&Self::repo_status_key(ca)) {
                 Ok(Some(status)) => status,
@@ -183,18 +257,11 @@ impl StatusStore {
             }
         }
 
-        let status = CaStatus {
+        Ok(CaStatus {
             repo,
             parents,
             children,
-        };
-
-        // Update the cache. Note that this is what we will use at runtime.
-        // Changes go directly in to the cached object. We will save smaller
-        // JSON files as well but we only do this full parsing on startup.
-        self.cache.write().unwrap().insert(ca.clone(), status);
-
-        Ok(())
+        })
     }
 
     fn convert_pre_0_9_5_full_status_if_present(
@@ -265,12 +332,16 @@ impl StatusStore {
     /// Returns the stored CaStatus for a CA, or a default (empty) status if
     /// it can't be found
     pub fn get_ca_status(&self, ca: &CaHandle) -> CaStatus {
-        self.cache
-            .read()
-            .unwrap()
-            .get(ca)
-            .cloned()
-            .unwrap_or_default()
+        if self.active.load(Ordering::Acquire) {
+            self.cache
+                .read()
+                .unwrap()
+                .get(ca)
+                .cloned()
+                .unwrap_or_default()
+        } else {
+            self.read_full_status(ca).unwrap_or_default()
+        }
     }
 
     pub fn set_parent_failure(
@@ -281,9 +352,27 @@ impl StatusStore {
         error: &Error,
     ) -> KrillResult<()> {
         let error_response = Self::error_to_error_res(error);
+        let was_failing = self
+            .get_ca_status(ca)
+            .parents()
+            .get(parent)
+            .and_then(|status| status.to_failure_opt())
+            .is_some();
+
         self.update_ca_parent_status(ca, parent, |status| {
-            status.set_failure(uri.clone(), error_response)
-        })
+            status.set_failure(uri.clone(), error_response.clone())
+        })?;
+
+        if !was_failing {
+            self.notify_listeners(
+                ca,
+                StatusIssue::ParentSyncFailed {
+                    parent: parent.clone(),
+                    error: error_response,
+                },
+            );
+        }
+        Ok(())
     }
 
     pub fn set_parent_last_updated(
@@ -399,9 +488,22 @@ impl StatusStore {
         error: &Error,
     ) -> KrillResult<()> {
         let error_response = Self::error_to_error_res(error);
+        let was_failing =
+            self.get_ca_status(ca).repo().to_failure_opt().is_some();
+
         self.update_repo_status(ca, |status| {
-            status.set_failure(uri, error_response)
-        })
+            status.set_failure(uri, error_response.clone())
+        })?;
+
+        if !was_failing {
+            self.notify_listeners(
+                ca,
+                StatusIssue::RepoSyncFailed {
+                    error: error_response,
+                },
+            );
+        }
+        Ok(())
     }
 
     pub fn set_status_repo_success(
@@ -562,4 +664,27 @@ mod tests {
 
         assert_eq!(status_testbed_before_migration, status_testbed_migrated);
     }
+
+    #[test]
+    fn standby_reads_status_from_storage() {
+        let storage_uri = test::mem_storage();
+        let leader = StatusStore::create(&storage_uri, STATUS_NS).unwrap();
+        let standby = StatusStore::create(&storage_uri, STATUS_NS).unwrap();
+        standby.set_active(false).unwrap();
+
+        let ca = CaHandle::from_str("ca").unwrap();
+        let uri = test::service_uri("https://localhost/rfc8181/ca/");
+        leader.set_status_repo_success(&ca, uri.clone()).unwrap();
+        assert_eq!(leader.get_ca_status(&ca), standby.get_ca_status(&ca));
+
+        // A node that becomes active uses what the leader stored last.
+        standby.set_active(true).unwrap();
+        leader
+            .set_status_repo_failure(&ca, uri, &Error::custom("unreachable"))
+            .unwrap();
+        assert_ne!(leader.get_ca_status(&ca), standby.get_ca_status(&ca));
+        standby.set_active(false).unwrap();
+        standby.set_active(true).unwrap();
+        assert_eq!(leader.get_ca_status(&ca), standby.get_ca_status(&ca));
+    }
 }
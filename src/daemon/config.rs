@@ -253,6 +253,14 @@ impl ConfigDefaults {
         4
     }
 
+    fn timing_ghostbuster_valid_weeks() -> u32 {
+        52
+    }
+
+    fn timing_ghostbuster_reissue_weeks_before() -> u32 {
+        4
+    }
+
     pub fn openssl_signer_only() -> Vec<SignerConfig> {
         let signer_config = OpenSslSignerConfig {
             keys_storage_uri: None,
@@ -372,6 +380,10 @@ impl ConfigDefaults {
     pub fn signer_probe_retry_seconds() -> u64 {
         30
     }
+
+    fn ha_lease_seconds() -> u32 {
+        30
+    }
 }
 
 //------------ Config --------------------------------------------------------
@@ -506,9 +518,21 @@ pub struct Config {
     )]
     pub storage_uri: Url,
 
+    #[serde(default)]
+    pub storage_encryption: Option<StorageEncryptionConfig>,
+
     #[serde(default = "ConfigDefaults::dflt_true")]
     pub use_history_cache: bool,
 
+    #[serde(default)]
+    pub history_archive_days: Option<u32>,
+
+    #[serde(default)]
+    pub ha_node: Option<String>,
+
+    #[serde(default = "ConfigDefaults::ha_lease_seconds")]
+    pub ha_lease_seconds: u32,
+
     tls_keys_dir: Option<PathBuf>,
 
     repo_dir: Option<PathBuf>,
@@ -674,6 +698,12 @@ pub struct IssuanceTimingConfig {
     timing_bgpsec_valid_weeks: u32,
     #[serde(default = "ConfigDefaults::timing_bgpsec_reissue_weeks_before")]
     timing_bgpsec_reissue_weeks_before: u32,
+    #[serde(default = "ConfigDefaults::timing_ghostbuster_valid_weeks")]
+    timing_ghostbuster_valid_weeks: u32,
+    #[serde(
+        default = "ConfigDefaults::timing_ghostbuster_reissue_weeks_before"
+    )]
+    timing_ghostbuster_reissue_weeks_before: u32,
 }
 
 impl IssuanceTimingConfig {
@@ -779,6 +809,26 @@ impl IssuanceTimingConfig {
         Time::now()
             + Duration::weeks(self.timing_bgpsec_reissue_weeks_before.into())
     }
+
+    //-- Ghostbusters
+
+    /// Validity period for new Ghostbuster records
+    pub fn new_ghostbuster_validity(&self) -> Validity {
+        SignSupport::sign_validity_weeks(
+            self.timing_ghostbuster_valid_weeks.into(),
+        )
+    }
+
+    /// Threshold time for issuing new Ghostbuster records
+    ///
+    /// i.e. records with a not after time *before* this moment should be
+    /// re-issued.
+    pub fn new_ghostbuster_issuance_threshold(&self) -> Time {
+        Time::now()
+            + Duration::weeks(
+                self.timing_ghostbuster_reissue_weeks_before.into(),
+            )
+    }
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, Serialize)]
@@ -904,6 +954,19 @@ pub struct Benchmark {
     pub ca_roas: usize,
 }
 
+/// Settings for the encryption of all data in the `storage_uri`. The key
+/// that the data is encrypted with is wrapped using a passphrase, or using
+/// a key held by one of the configured HSM signers.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct StorageEncryptionConfig {
+    /// A file with the passphrase used to wrap the data key.
+    pub passphrase_file: Option<PathBuf>,
+
+    /// The name of the signer that holds the key used to wrap the data
+    /// key.
+    pub signer: Option<String>,
+}
+
 /// # Accessors
 impl Config {
     /// General purpose KV store, can be used to track server settings
@@ -1011,6 +1074,12 @@ impl Config {
                                         // set
     }
 
+    /// Returns the PID file that Krill uses by default for the storage, if
+    /// it is a local directory.
+    pub fn storage_pid_file(&self) -> Option<PathBuf> {
+        self.data_dir().map(|data_dir| data_dir.join("krill.pid"))
+    }
+
     /// Returns whether TA support is explicitly enabled in the config, or
     /// implicitly enabled in case testbed (or benchmark) mode is used.
     pub fn ta_proxy_enabled(&self) -> bool {
@@ -1179,6 +1248,10 @@ impl Config {
             ConfigDefaults::timing_bgpsec_valid_weeks();
         let timing_bgpsec_reissue_weeks_before =
             ConfigDefaults::timing_bgpsec_reissue_weeks_before();
+        let timing_ghostbuster_valid_weeks =
+            ConfigDefaults::timing_ghostbuster_valid_weeks();
+        let timing_ghostbuster_reissue_weeks_before =
+            ConfigDefaults::timing_ghostbuster_reissue_weeks_before();
 
         let issuance_timing = IssuanceTimingConfig {
             timing_publish_next_hours,
@@ -1192,6 +1265,8 @@ impl Config {
             timing_aspa_reissue_weeks_before,
             timing_bgpsec_valid_weeks,
             timing_bgpsec_reissue_weeks_before,
+            timing_ghostbuster_valid_weeks,
+            timing_ghostbuster_reissue_weeks_before,
         };
 
         let rrdp_updates_config = RrdpUpdatesConfig {
@@ -1229,7 +1304,11 @@ impl Config {
             port,
             https_mode,
             storage_uri: storage_uri.clone(),
+            storage_encryption: None,
             use_history_cache: false,
+            history_archive_days: None,
+            ha_node: None,
+            ha_lease_seconds: ConfigDefaults::ha_lease_seconds(),
             tls_keys_dir: data_dir.map(|d| d.join(HTTPS_SUB_DIR)),
             repo_dir: data_dir.map(|d| d.join(REPOSITORY_DIR)),
             ta_support_enabled: false, /* but, enabled by testbed where
@@ -1393,9 +1472,8 @@ impl Config {
         }
 
         if self.pid_file.is_none() {
-            if let Some(mut data_dir) = self.data_dir() {
-                data_dir.push("krill.pid");
-                self.pid_file = Some(data_dir);
+            if let Some(pid_file) = self.storage_pid_file() {
+                self.pid_file = Some(pid_file);
             } else {
                 return Err(ConfigError::other("'pid_file' is not configured, but 'storage_uri' is not a local directory, please configure an 'pid_file'"));
             }
@@ -1569,6 +1647,57 @@ impl Config {
             }
         }
 
+        if self.history_archive_days == Some(0) {
+            return Err(ConfigError::other(
+                "history_archive_days must be 1 or higher (or not set at all)",
+            ));
+        }
+
+        if let Some(node) = &self.ha_node {
+            if node.trim().is_empty() {
+                return Err(ConfigError::other(
+                    "ha_node must not be empty (or not set at all)",
+                ));
+            }
+            if self.ha_lease_seconds < 3 {
+                return Err(ConfigError::other(
+                    "ha_lease_seconds must be 3 or higher",
+                ));
+            }
+        }
+
+        if let Some(encryption) = &self.storage_encryption {
+            match (&encryption.passphrase_file, &encryption.signer) {
+                (Some(_), None) => {}
+                (None, Some(name)) => {
+                    match self.signers.iter().find(|s| &s.name == name) {
+                        None => {
+                            return Err(ConfigError::Other(format!(
+                                "'{}' cannot be used for [storage_encryption] as no signer with that name is defined",
+                                name
+                            )))
+                        }
+                        Some(signer) => {
+                            if matches!(
+                                signer.signer_type,
+                                SignerType::OpenSsl(_)
+                            ) {
+                                return Err(ConfigError::Other(format!(
+                                    "'{}' cannot be used for [storage_encryption] as it is not an HSM signer",
+                                    name
+                                )));
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    return Err(ConfigError::other(
+                        "[storage_encryption] requires either 'passphrase_file' or 'signer'",
+                    ))
+                }
+            }
+        }
+
         if let Some(benchmark) = &self.benchmark {
             if self.testbed.is_none() {
                 return Err(ConfigError::other(
@@ -0,0 +1,433 @@
+//! Encryption of the data in the `storage_uri` at rest.
+//!
+//! All values are encrypted using a random data key, see
+//! [`KeyValueStore::enable_encryption`]. The data key is wrapped using a
+//! key encryption key, which is derived from a passphrase or from a
+//! signature made by a key held in an HSM, and the wrapped data key is
+//! kept in its own namespace.
+//!
+//! The signer mappings are not encrypted, because they are needed to find
+//! the HSM key. The keys of an OpenSSL signer are encrypted like all other
+//! data, so they can only be read once the data key is unwrapped. This is
+//! why the key encryption key cannot be kept by an OpenSSL signer.
+//!
+//! The task queue is not part of the Krill data that is backed up, but its
+//! values are encrypted as well.
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use openssl::{hash::MessageDigest, pkcs5::pbkdf2_hmac, rand::rand_bytes};
+use rpki::crypto::KeyIdentifier;
+use url::Url;
+
+use crate::{
+    commons::{
+        crypto::KrillSignerBuilder,
+        error::Error,
+        eventsourcing::{
+            segment, DataKey, Key, KeyValueStore, Namespace, Segment,
+        },
+        KrillResult,
+    },
+    constants::{ENCRYPTION_NS, KRILL_DATA_NAMESPACES, TASK_QUEUE_NS},
+    daemon::config::{Config, StorageEncryptionConfig},
+};
+
+const DATA_KEY: &Segment = segment!("data_key.json");
+
+/// The additional authenticated data for the wrapped data key.
+const DATA_KEY_AAD: &[u8] = b"krill data key";
+
+const SALT_LEN: usize = 16;
+const PASSPHRASE_ITERATIONS: usize = 600_000;
+
+/// The label signed by an HSM key, together with the salt, to derive the
+/// key encryption key.
+const SIGNER_LABEL: &[u8] = b"krill storage encryption";
+
+//------------ WrappedDataKey ------------------------------------------------
+
+/// The data key, encrypted using the key encryption key.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct WrappedDataKey {
+    kek: KeyEncryptionKey,
+    wrapped: String,
+}
+
+/// How the key encryption key is derived.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum KeyEncryptionKey {
+    Passphrase {
+        salt: String,
+        iterations: usize,
+    },
+    Signer {
+        signer: String,
+        key: KeyIdentifier,
+        salt: String,
+    },
+}
+
+impl KeyEncryptionKey {
+    /// Creates a new key encryption key for the configuration.
+    fn create(
+        config: &Config,
+        encryption: &StorageEncryptionConfig,
+    ) -> KrillResult<Self> {
+        let mut salt = [0; SALT_LEN];
+        rand_bytes(&mut salt).map_err(crypto_err)?;
+        let salt = BASE64.encode(salt);
+
+        match &encryption.signer {
+            Some(name) => {
+                let key = build_signer(config, name)?.create_key()?;
+                Ok(KeyEncryptionKey::Signer {
+                    signer: name.clone(),
+                    key,
+                    salt,
+                })
+            }
+            None => Ok(KeyEncryptionKey::Passphrase {
+                salt,
+                iterations: PASSPHRASE_ITERATIONS,
+            }),
+        }
+    }
+
+    /// Derives the key encryption key. Fails if the configuration does not
+    /// match the way the data key was wrapped.
+    fn derive(
+        &self,
+        config: &Config,
+        encryption: &StorageEncryptionConfig,
+    ) -> KrillResult<DataKey> {
+        match (self, &encryption.passphrase_file, &encryption.signer) {
+            (
+                KeyEncryptionKey::Passphrase { salt, iterations },
+                Some(passphrase_file),
+                _,
+            ) => {
+                let passphrase = std::fs::read_to_string(passphrase_file)
+                    .map_err(|e| {
+                        Error::StorageEncryption(format!(
+                            "cannot read passphrase file '{}': {}",
+                            passphrase_file.to_string_lossy(),
+                            e
+                        ))
+                    })?;
+                let passphrase = passphrase.trim_end_matches(['\r', '\n']);
+                if passphrase.is_empty() {
+                    return Err(Error::StorageEncryption(format!(
+                        "passphrase file '{}' is empty",
+                        passphrase_file.to_string_lossy()
+                    )));
+                }
+
+                let mut kek = [0; DataKey::LEN];
+                pbkdf2_hmac(
+                    passphrase.as_bytes(),
+                    &decode_salt(salt)?,
+                    *iterations,
+                    MessageDigest::sha256(),
+                    &mut kek,
+                )
+                .map_err(crypto_err)?;
+                Ok(DataKey::from_bytes(&kek).map_err(kv_err)?)
+            }
+            (
+                KeyEncryptionKey::Signer { signer, key, salt },
+                _,
+                Some(name),
+            ) if signer == name => {
+                let mut data = decode_salt(salt)?;
+                data.extend_from_slice(SIGNER_LABEL);
+                let signature = build_signer(config, name)?.sign(key, &data)?;
+                let kek = openssl::sha::sha256(signature.value());
+                Ok(DataKey::from_bytes(&kek).map_err(kv_err)?)
+            }
+            (KeyEncryptionKey::Passphrase { .. }, _, _) => {
+                Err(Error::StorageEncryption(
+                    "the data key is wrapped using a passphrase, but no 'passphrase_file' is configured".to_string(),
+                ))
+            }
+            (KeyEncryptionKey::Signer { signer, .. }, _, _) => {
+                Err(Error::StorageEncryption(format!(
+                    "the data key is wrapped using signer '{}', but that signer is not configured for [storage_encryption]",
+                    signer
+                )))
+            }
+        }
+    }
+}
+
+//------------ Enable, Encrypt and Decrypt -----------------------------------
+
+/// Enables the encryption of the data in the `storage_uri` if this is
+/// configured.
+///
+/// A new data key is created if there is no data yet. If there is data
+/// that was stored without encryption, then that needs to be encrypted
+/// first using `krillup encrypt`.
+pub fn enable_storage_encryption(config: &Config) -> KrillResult<()> {
+    let encryption = match &config.storage_encryption {
+        Some(encryption) => encryption,
+        None => return Ok(()),
+    };
+
+    let data_key = match load_data_key(config, encryption)? {
+        Some(data_key) => data_key,
+        None => {
+            if !storage_is_empty(&config.storage_uri)? {
+                return Err(Error::StorageEncryption(
+                    "storage encryption is configured, but the existing data is not encrypted. Stop Krill and use 'krillup encrypt' to encrypt it.".to_string(),
+                ));
+            }
+            create_data_key(config, encryption)?
+        }
+    };
+
+    KeyValueStore::enable_encryption(&config.storage_uri, data_key);
+    Ok(())
+}
+
+/// Encrypts all data in the `storage_uri` that is not encrypted yet, and
+/// returns the number of values that were encrypted. A new data key is
+/// created if needed.
+///
+/// Krill must not be running while this is done.
+pub fn encrypt_storage(config: &Config) -> KrillResult<usize> {
+    let encryption = config.storage_encryption.as_ref().ok_or_else(|| {
+        Error::StorageEncryption(
+            "[storage_encryption] is not configured".to_string(),
+        )
+    })?;
+
+    let data_key = match load_data_key(config, encryption)? {
+        Some(data_key) => data_key,
+        None => create_data_key(config, encryption)?,
+    };
+
+    let mut encrypted = 0;
+    for namespace in KRILL_DATA_NAMESPACES {
+        encrypted += data_store(&config.storage_uri, namespace)?
+            .encrypt_values(&data_key)?;
+    }
+    encrypted += data_store(&config.storage_uri, TASK_QUEUE_NS)?
+        .encrypt_values(&data_key)?;
+    Ok(encrypted)
+}
+
+/// Decrypts all data in the `storage_uri`, and removes the data key. Returns
+/// the number of values that were decrypted.
+///
+/// Krill must not be running while this is done, and must be started
+/// without [storage_encryption] afterwards.
+pub fn decrypt_storage(config: &Config) -> KrillResult<usize> {
+    let encryption = config.storage_encryption.as_ref().ok_or_else(|| {
+        Error::StorageEncryption(
+            "[storage_encryption] must still be configured to decrypt the data".to_string(),
+        )
+    })?;
+
+    let data_key = load_data_key(config, encryption)?.ok_or_else(|| {
+        Error::StorageEncryption("no data key was found".to_string())
+    })?;
+
+    let mut decrypted = 0;
+    for namespace in KRILL_DATA_NAMESPACES {
+        decrypted += data_store(&config.storage_uri, namespace)?
+            .decrypt_values(&data_key)?;
+    }
+    decrypted += data_store(&config.storage_uri, TASK_QUEUE_NS)?
+        .decrypt_values(&data_key)?;
+
+    KeyValueStore::create(&config.storage_uri, ENCRYPTION_NS)?
+        .drop_key(&Key::new_global(DATA_KEY))?;
+
+    Ok(decrypted)
+}
+
+//------------ Helpers -------------------------------------------------------
+
+fn load_data_key(
+    config: &Config,
+    encryption: &StorageEncryptionConfig,
+) -> KrillResult<Option<DataKey>> {
+    let kv = KeyValueStore::create(&config.storage_uri, ENCRYPTION_NS)?;
+    let wrapped: WrappedDataKey = match kv.get(&Key::new_global(DATA_KEY))? {
+        Some(wrapped) => wrapped,
+        None => return Ok(None),
+    };
+
+    let kek = wrapped.kek.derive(config, encryption)?;
+    let sealed = BASE64.decode(&wrapped.wrapped).map_err(|e| {
+        Error::StorageEncryption(format!("invalid wrapped data key: {}", e))
+    })?;
+    let data_key = kek.open(DATA_KEY_AAD, &sealed).map_err(|_| {
+        Error::StorageEncryption(
+            "cannot unwrap the data key, is the passphrase or signer correct?"
+                .to_string(),
+        )
+    })?;
+
+    DataKey::from_bytes(&data_key).map(Some).map_err(kv_err)
+}
+
+fn create_data_key(
+    config: &Config,
+    encryption: &StorageEncryptionConfig,
+) -> KrillResult<DataKey> {
+    let data_key = DataKey::generate().map_err(kv_err)?;
+    let kek = KeyEncryptionKey::create(config, encryption)?;
+    let wrapped = WrappedDataKey {
+        wrapped: BASE64.encode(
+            kek.derive(config, encryption)?
+                .seal(DATA_KEY_AAD, data_key.as_bytes())
+                .map_err(kv_err)?,
+        ),
+        kek,
+    };
+
+    KeyValueStore::create(&config.storage_uri, ENCRYPTION_NS)?
+        .store_new(&Key::new_global(DATA_KEY), &wrapped)
+        .map_err(|e| {
+            Error::StorageEncryption(format!(
+                "cannot store the data key: {}",
+                e
+            ))
+        })?;
+
+    Ok(data_key)
+}
+
+fn storage_is_empty(storage_uri: &Url) -> KrillResult<bool> {
+    for namespace in KRILL_DATA_NAMESPACES {
+        if !data_store(storage_uri, namespace)?.is_empty()? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn data_store(
+    storage_uri: &Url,
+    namespace: &Namespace,
+) -> KrillResult<KeyValueStore> {
+    KeyValueStore::create(storage_uri, namespace).map_err(Error::from)
+}
+
+fn build_signer(
+    config: &Config,
+    name: &str,
+) -> KrillResult<crate::commons::crypto::KrillSigner> {
+    let signer_config = config
+        .signers
+        .iter()
+        .find(|signer| signer.name == name)
+        .ok_or_else(|| {
+            Error::StorageEncryption(format!("unknown signer '{}'", name))
+        })?;
+
+    let probe_interval =
+        std::time::Duration::from_secs(config.signer_probe_retry_seconds);
+    KrillSignerBuilder::new(
+        &config.storage_uri,
+        probe_interval,
+        std::slice::from_ref(signer_config),
+    )
+    .build()
+}
+
+fn decode_salt(salt: &str) -> KrillResult<Vec<u8>> {
+    BASE64
+        .decode(salt)
+        .map_err(|e| Error::StorageEncryption(format!("invalid salt: {}", e)))
+}
+
+fn crypto_err(e: openssl::error::ErrorStack) -> Error {
+    Error::StorageEncryption(e.to_string())
+}
+
+fn kv_err(e: kvx::Error) -> Error {
+    Error::StorageEncryption(e.to_string())
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        constants::STATUS_NS,
+        daemon::mq::{now, Task, TaskQueue},
+        test,
+    };
+
+    fn passphrase_config(storage_uri: &Url) -> (Config, impl FnOnce()) {
+        let (dir, cleanup) = test::tmp_dir();
+        let passphrase_file = dir.join("passphrase");
+        std::fs::write(&passphrase_file, "correct horse battery staple\n")
+            .unwrap();
+
+        let mut config =
+            Config::test(storage_uri, Some(&dir), false, false, false, false);
+        config.storage_encryption = Some(StorageEncryptionConfig {
+            passphrase_file: Some(passphrase_file),
+            signer: None,
+        });
+        (config, cleanup)
+    }
+
+    #[test]
+    fn encrypt_and_decrypt_existing_data() {
+        let storage_uri = test::mem_storage();
+        let (config, cleanup) = passphrase_config(&storage_uri);
+
+        let key = Key::new_global(segment!("value.json"));
+        let plain = KeyValueStore::create(&storage_uri, STATUS_NS).unwrap();
+        plain.store(&key, &"secret").unwrap();
+        TaskQueue::new(&storage_uri)
+            .unwrap()
+            .schedule(Task::QueueStartTasks, now())
+            .unwrap();
+
+        // Encryption cannot be enabled while there is plain data.
+        assert!(enable_storage_encryption(&config).is_err());
+
+        assert_eq!(2, encrypt_storage(&config).unwrap());
+        assert_eq!(0, encrypt_storage(&config).unwrap());
+        let raw: serde_json::Value = plain.get(&key).unwrap().unwrap();
+        assert!(DataKey::is_encrypted(&raw));
+
+        enable_storage_encryption(&config).unwrap();
+        let kv = KeyValueStore::create(&storage_uri, STATUS_NS).unwrap();
+        assert_eq!(Some("secret".to_string()), kv.get(&key).unwrap());
+        let running = TaskQueue::new(&storage_uri).unwrap().pop().unwrap();
+        assert_eq!(
+            Task::QueueStartTasks,
+            serde_json::from_value(running.value).unwrap()
+        );
+
+        assert_eq!(2, decrypt_storage(&config).unwrap());
+        assert_eq!(Some("secret".to_string()), plain.get(&key).unwrap());
+
+        cleanup();
+    }
+
+    #[test]
+    fn wrong_passphrase_is_rejected() {
+        let storage_uri = test::mem_storage();
+        let (config, cleanup) = passphrase_config(&storage_uri);
+        enable_storage_encryption(&config).unwrap();
+
+        let passphrase_file = config
+            .storage_encryption
+            .as_ref()
+            .and_then(|encryption| encryption.passphrase_file.as_ref())
+            .unwrap();
+        std::fs::write(passphrase_file, "wrong").unwrap();
+        assert!(enable_storage_encryption(&config).is_err());
+
+        cleanup();
+    }
+}
@@ -0,0 +1,319 @@
+//! Support for streaming committed aggregate events to API clients.
+//!
+//! The [`EventStream`] is registered as a post-save listener with the
+//! stores for CAs, the Publication Server and the Trust Anchor Proxy. It
+//! passes on every committed event to all current subscribers. Subscribers
+//! that cannot keep up will miss events, they are never blocking the
+//! stores.
+use std::{fmt, str::FromStr, sync::Arc};
+
+use rpki::ca::idexchange::{CaHandle, MyHandle};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::{
+    commons::eventsourcing::{self, Aggregate},
+    constants::PUBSERVER_DFLT,
+    daemon::ca::CertAuth,
+    pubd::RepositoryAccess,
+    ta::{ta_handle, TrustAnchorProxy},
+};
+
+//------------ StreamedAggregateType ---------------------------------------
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamedAggregateType {
+    Ca,
+    PubServer,
+    TaProxy,
+}
+
+//------------ StreamedEvent -----------------------------------------------
+
+/// An event as it was committed for an aggregate.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StreamedEvent {
+    pub aggregate_type: StreamedAggregateType,
+    pub handle: MyHandle,
+
+    /// The version of the aggregate after the change was committed.
+    pub version: u64,
+
+    /// The snake case name of the event type, e.g. "roas_updated".
+    pub event_type: String,
+
+    /// A human readable summary of the event.
+    pub summary: String,
+
+    /// The full event, as it is stored.
+    pub event: serde_json::Value,
+}
+
+impl StreamedEvent {
+    fn new<E: Serialize + fmt::Display>(
+        aggregate_type: StreamedAggregateType,
+        handle: MyHandle,
+        version: u64,
+        event: &E,
+    ) -> Self {
+        let event_json =
+            serde_json::to_value(event).unwrap_or(serde_json::Value::Null);
+
+        StreamedEvent {
+            aggregate_type,
+            handle,
+            version,
+            event_type: Self::event_type(&event_json),
+            summary: event.to_string(),
+            event: event_json,
+        }
+    }
+
+    /// Derives the event type from the JSON representation of the event.
+    /// Most events are internally tagged using a "type" field, but some
+    /// use the default externally tagged enum representation.
+    fn event_type(json: &serde_json::Value) -> String {
+        let name = match json {
+            serde_json::Value::String(name) => name.as_str(),
+            serde_json::Value::Object(map) => {
+                match map.get("type").and_then(|t| t.as_str()) {
+                    Some(name) => name,
+                    None if map.len() == 1 => {
+                        map.keys().next().map(|k| k.as_str()).unwrap_or("")
+                    }
+                    None => "",
+                }
+            }
+            _ => "",
+        };
+
+        let mut snake_case = String::with_capacity(name.len() + 4);
+        for (i, c) in name.chars().enumerate() {
+            if c.is_ascii_uppercase() {
+                if i > 0 {
+                    snake_case.push('_');
+                }
+                snake_case.push(c.to_ascii_lowercase());
+            } else {
+                snake_case.push(c);
+            }
+        }
+        snake_case
+    }
+
+    /// Returns the handle of the CA this event is for, if any. Trust
+    /// Anchor Proxy events are considered to be for the TA.
+    pub fn ca(&self) -> Option<CaHandle> {
+        match self.aggregate_type {
+            StreamedAggregateType::Ca | StreamedAggregateType::TaProxy => {
+                Some(self.handle.convert())
+            }
+            StreamedAggregateType::PubServer => None,
+        }
+    }
+
+    /// Formats this event as a Server-Sent Events message.
+    pub fn to_sse_message(&self) -> String {
+        format!(
+            "event: {}\ndata: {}\n\n",
+            self.event_type,
+            serde_json::to_string(self).unwrap_or_default()
+        )
+    }
+}
+
+//------------ EventStreamFilter -------------------------------------------
+
+/// Selects the events that a subscriber is interested in.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EventStreamFilter {
+    ca: Option<CaHandle>,
+    event_types: Vec<String>,
+}
+
+impl EventStreamFilter {
+    /// Creates a filter for events for the given CA, if any, and of any of
+    /// the given comma separated event types, if any.
+    pub fn new(ca: Option<CaHandle>, event_types: Option<&str>) -> Self {
+        let event_types = event_types
+            .map(|types| {
+                types
+                    .split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        EventStreamFilter { ca, event_types }
+    }
+
+    pub fn matches(&self, event: &StreamedEvent) -> bool {
+        if let Some(ca) = &self.ca {
+            if event.ca().as_ref() != Some(ca) {
+                return false;
+            }
+        }
+
+        self.event_types.is_empty()
+            || self.event_types.contains(&event.event_type)
+    }
+}
+
+//------------ EventStream -------------------------------------------------
+
+/// Passes on committed events to all current subscribers.
+pub struct EventStream {
+    sender: broadcast::Sender<Arc<StreamedEvent>>,
+}
+
+impl Default for EventStream {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(Self::CAPACITY);
+        EventStream { sender }
+    }
+}
+
+impl EventStream {
+    /// The number of events kept for subscribers that are lagging behind.
+    const CAPACITY: usize = 1024;
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<StreamedEvent>> {
+        self.sender.subscribe()
+    }
+
+    fn send<E: Serialize + fmt::Display>(
+        &self,
+        aggregate_type: StreamedAggregateType,
+        handle: &MyHandle,
+        version: u64,
+        events: &[E],
+    ) {
+        // Skip serialization if there is no one listening
+        if self.sender.receiver_count() == 0 {
+            return;
+        }
+
+        for event in events {
+            let event = StreamedEvent::new(
+                aggregate_type,
+                handle.clone(),
+                version,
+                event,
+            );
+            // This can only fail if all subscribers went away.
+            let _ = self.sender.send(Arc::new(event));
+        }
+    }
+}
+
+impl eventsourcing::PostSaveEventListener<CertAuth> for EventStream {
+    fn listen(
+        &self,
+        ca: &CertAuth,
+        events: &[<CertAuth as Aggregate>::Event],
+    ) {
+        self.send(
+            StreamedAggregateType::Ca,
+            &ca.handle().convert(),
+            ca.version(),
+            events,
+        );
+    }
+}
+
+impl eventsourcing::PostSaveEventListener<RepositoryAccess> for EventStream {
+    fn listen(
+        &self,
+        access: &RepositoryAccess,
+        events: &[<RepositoryAccess as Aggregate>::Event],
+    ) {
+        self.send(
+            StreamedAggregateType::PubServer,
+            &MyHandle::from_str(PUBSERVER_DFLT).unwrap(),
+            access.version(),
+            events,
+        );
+    }
+}
+
+impl eventsourcing::PostSaveEventListener<TrustAnchorProxy> for EventStream {
+    fn listen(
+        &self,
+        proxy: &TrustAnchorProxy,
+        events: &[<TrustAnchorProxy as Aggregate>::Event],
+    ) {
+        self.send(
+            StreamedAggregateType::TaProxy,
+            &ta_handle().into_converted(),
+            proxy.version(),
+            events,
+        );
+    }
+}
+
+//------------ Tests --------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_type_from_json() {
+        let check = |json: serde_json::Value, expected: &str| {
+            assert_eq!(StreamedEvent::event_type(&json), expected)
+        };
+
+        check(
+            serde_json::json!({ "type": "roas_updated", "updates": {} }),
+            "roas_updated",
+        );
+        check(serde_json::json!({ "ChildAdded": {} }), "child_added");
+        check(
+            serde_json::json!("SignerRequestMade"),
+            "signer_request_made",
+        );
+    }
+
+    #[test]
+    fn filter_events() {
+        let event =
+            |aggregate_type, handle: &str, event_type: &str| StreamedEvent {
+                aggregate_type,
+                handle: MyHandle::from_str(handle).unwrap(),
+                version: 1,
+                event_type: event_type.to_string(),
+                summary: String::new(),
+                event: serde_json::Value::Null,
+            };
+
+        let ca_roas = event(StreamedAggregateType::Ca, "CA", "roas_updated");
+        let ta_child =
+            event(StreamedAggregateType::TaProxy, "ta", "child_added");
+        let publisher = event(
+            StreamedAggregateType::PubServer,
+            PUBSERVER_DFLT,
+            "publisher_added",
+        );
+
+        let all = EventStreamFilter::default();
+        assert!(all.matches(&ca_roas));
+        assert!(all.matches(&ta_child));
+        assert!(all.matches(&publisher));
+
+        let ca = EventStreamFilter::new(
+            Some(CaHandle::from_str("CA").unwrap()),
+            None,
+        );
+        assert!(ca.matches(&ca_roas));
+        assert!(!ca.matches(&ta_child));
+        assert!(!ca.matches(&publisher));
+
+        let types =
+            EventStreamFilter::new(None, Some("child_added,publisher_added"));
+        assert!(!types.matches(&ca_roas));
+        assert!(types.matches(&ta_child));
+        assert!(types.matches(&publisher));
+    }
+}
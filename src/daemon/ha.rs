@@ -0,0 +1,426 @@
+//! Active/passive high availability.
+//!
+//! Several Krill nodes can share the same storage, but only one of them
+//! may run background tasks and make changes at any time. That node is
+//! the leader: it holds a lease that is kept in the shared properties
+//! namespace, and renews it every third of the lease duration. Other nodes
+//! are standby. They only serve read-only requests, and try to take over
+//! the lease when it expires.
+//!
+//! The lease is read and updated while holding the lock for the global
+//! scope of the properties namespace, so that only one node can get it.
+//!
+//! Components that cache data from storage are told when this node starts
+//! or stops being active, see [`RoleListener`], so that a new leader does
+//! not act on data cached before another node changed it.
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use tokio::time::sleep;
+use url::Url;
+
+use crate::{
+    commons::{
+        api::{NodeRole, Timestamp},
+        error::Error,
+        eventsourcing::{segment, Key, KeyValueStore, Scope, Segment},
+        KrillResult,
+    },
+    constants::PROPERTIES_NS,
+    daemon::{config::Config, properties::PropertiesManager},
+    upgrades::upgrade_needed,
+};
+
+const LEASE_KEY: &Segment = segment!("leader_lease.json");
+
+//------------ NodeLease -----------------------------------------------------
+
+/// The lease held by the leader node.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct NodeLease {
+    node: String,
+    since: Timestamp,
+    expires: Timestamp,
+}
+
+//------------ RoleListener --------------------------------------------------
+
+/// Is told when this node starts or stops being active.
+pub trait RoleListener: Send + Sync + 'static {
+    /// Called before this node becomes active, and whenever it is found to
+    /// be standby. If this fails when the node is about to become active,
+    /// then it stays standby and tries again at the next heartbeat.
+    fn active_changed(&self, active: bool) -> KrillResult<()>;
+}
+
+//------------ HaManager -----------------------------------------------------
+
+/// Keeps track of the role of this node, and the lease if it is the
+/// leader.
+pub struct HaManager {
+    config: Arc<Config>,
+    kv: KeyValueStore,
+    state: RwLock<HaState>,
+    listeners: RwLock<Vec<Arc<dyn RoleListener>>>,
+}
+
+#[derive(Clone, Debug)]
+struct HaState {
+    role: NodeRole,
+
+    // The node holding the lease, if known.
+    leader: Option<String>,
+
+    // When the lease held by this node expires, using the local clock
+    // so that it is not affected by changes to the wall clock.
+    valid_until: Option<Instant>,
+}
+
+impl HaManager {
+    pub fn create(config: Arc<Config>) -> KrillResult<Self> {
+        let kv = KeyValueStore::create(&config.storage_uri, PROPERTIES_NS)?;
+        let role = if config.ha_node.is_some() {
+            NodeRole::Standby
+        } else {
+            NodeRole::Single
+        };
+
+        Ok(HaManager {
+            config,
+            kv,
+            state: RwLock::new(HaState {
+                role,
+                leader: None,
+                valid_until: None,
+            }),
+            listeners: RwLock::new(vec![]),
+        })
+    }
+
+    pub fn add_listener<L: RoleListener>(&self, listener: Arc<L>) {
+        self.listeners.write().unwrap().push(listener);
+    }
+
+    fn notify_listeners(&self, active: bool) -> KrillResult<()> {
+        for listener in self.listeners.read().unwrap().iter() {
+            listener.active_changed(active)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the node holding a lease for the given storage that has not
+    /// expired yet, if any. That node is most likely running.
+    pub fn active_leader(storage_uri: &Url) -> KrillResult<Option<String>> {
+        let kv = KeyValueStore::create(storage_uri, PROPERTIES_NS)?;
+        let lease: Option<NodeLease> = kv.get(&Key::new_global(LEASE_KEY))?;
+        let now = Timestamp::now();
+        Ok(lease
+            .filter(|lease| lease.expires > now)
+            .map(|lease| lease.node))
+    }
+
+    /// Returns the name of this node, if high availability is enabled.
+    pub fn node(&self) -> Option<&String> {
+        self.config.ha_node.as_ref()
+    }
+
+    pub fn role(&self) -> NodeRole {
+        let state = self.state.read().unwrap();
+        match state.role {
+            NodeRole::Leader if !Self::lease_valid(&state) => {
+                NodeRole::Standby
+            }
+            role => role,
+        }
+    }
+
+    /// Returns whether this node may run background tasks and make
+    /// changes. A leader stops being one as soon as its lease expires,
+    /// even if it could not find out that another node took over.
+    pub fn is_active(&self) -> bool {
+        self.role().is_active()
+    }
+
+    /// Returns the node holding the lease, if known.
+    pub fn leader(&self) -> Option<String> {
+        match self.role() {
+            NodeRole::Single => None,
+            NodeRole::Leader => self.node().cloned(),
+            NodeRole::Standby => self.state.read().unwrap().leader.clone(),
+        }
+    }
+
+    fn lease_duration(&self) -> Duration {
+        Duration::from_secs(self.config.ha_lease_seconds.into())
+    }
+
+    /// Returns the interval at which the lease is renewed, or taking it
+    /// over is attempted.
+    pub fn heartbeat_interval(&self) -> Duration {
+        self.lease_duration() / 3
+    }
+
+    fn lease_valid(state: &HaState) -> bool {
+        state
+            .valid_until
+            .map(|until| Instant::now() < until)
+            .unwrap_or_default()
+    }
+
+    /// Renews the lease if this node holds it, or takes it over if it
+    /// has expired. Returns the resulting role of this node.
+    ///
+    /// After startup a standby node only takes over if the data in storage
+    /// is current for this Krill version, because upgrades are only done
+    /// when Krill starts.
+    pub fn heartbeat(&self, check_data: bool) -> KrillResult<NodeRole> {
+        let node = match self.node() {
+            Some(node) => node.clone(),
+            None => return Ok(NodeRole::Single),
+        };
+
+        let was_leader = self.role() == NodeRole::Leader;
+        if !was_leader && check_data && !self.data_is_current() {
+            let leader = self.current_lease()?.map(|lease| lease.node);
+            self.set_standby(leader);
+            return Ok(NodeRole::Standby);
+        }
+
+        let key = Key::new_global(LEASE_KEY);
+        let duration = self.lease_duration();
+        let started = Instant::now();
+
+        let lease: Result<NodeLease, NodeLease> =
+            self.kv.execute(&Scope::global(), |kv| {
+                let now = Timestamp::now();
+                let current = match kv.get(&key)? {
+                    Some(value) => {
+                        Some(serde_json::from_value::<NodeLease>(value)?)
+                    }
+                    None => None,
+                };
+
+                match current {
+                    Some(lease)
+                        if lease.node != node && lease.expires > now =>
+                    {
+                        Ok(Err(lease))
+                    }
+                    current => {
+                        let since = current
+                            .filter(|lease| lease.node == node)
+                            .map(|lease| lease.since)
+                            .unwrap_or(now);
+                        let lease = NodeLease {
+                            node: node.clone(),
+                            since,
+                            expires: now
+                                .plus_seconds(duration.as_secs() as i64),
+                        };
+                        kv.store(&key, serde_json::to_value(&lease)?)?;
+                        Ok(Ok(lease))
+                    }
+                }
+            })?;
+
+        match lease {
+            Ok(_) => {
+                if !was_leader {
+                    if let Err(e) = self.notify_listeners(true) {
+                        error!(
+                            "Node '{}' holds the lease, but cannot become the leader yet: {}",
+                            node, e
+                        );
+                        self.set_standby(Some(node));
+                        return Ok(NodeRole::Standby);
+                    }
+                    info!("Node '{}' is now the leader", node);
+                }
+                self.set_state(
+                    NodeRole::Leader,
+                    Some(node),
+                    Some(started + duration),
+                );
+                Ok(NodeRole::Leader)
+            }
+            Err(lease) => {
+                if was_leader {
+                    warn!(
+                        "Node '{}' lost its lease to node '{}'",
+                        node, lease.node
+                    );
+                }
+                self.set_standby(Some(lease.node));
+                Ok(NodeRole::Standby)
+            }
+        }
+    }
+
+    /// Keeps renewing or trying to get the lease.
+    pub async fn run(&self) {
+        if self.node().is_none() {
+            // Nothing to coordinate, this is the only node.
+            return std::future::pending().await;
+        }
+
+        loop {
+            sleep(self.heartbeat_interval()).await;
+            if let Err(e) = self.heartbeat(true) {
+                // The lease will not be renewed, so a leader will stop
+                // being active when it expires.
+                error!("Could not renew or get the leader lease: {}", e);
+            }
+        }
+    }
+
+    /// Waits until this node may start. Standby nodes wait until the
+    /// leader has upgraded the data in storage, if needed, or until they
+    /// become the leader themselves.
+    pub async fn wait_for_start(
+        &self,
+        properties_manager: &PropertiesManager,
+    ) -> KrillResult<()> {
+        loop {
+            if self.heartbeat(false)?.is_active()
+                || !upgrade_needed(&self.config, properties_manager)
+                    .map_err(|e| Error::Custom(e.to_string()))?
+            {
+                return Ok(());
+            }
+
+            info!(
+                "Waiting for the leader node to upgrade the data, or for its lease to expire"
+            );
+            sleep(self.heartbeat_interval()).await;
+        }
+    }
+
+    fn current_lease(&self) -> KrillResult<Option<NodeLease>> {
+        self.kv
+            .get(&Key::new_global(LEASE_KEY))
+            .map_err(Error::KeyValueError)
+    }
+
+    fn data_is_current(&self) -> bool {
+        PropertiesManager::create(&self.config.storage_uri, false)
+            .and_then(|properties| {
+                upgrade_needed(&self.config, &properties)
+                    .map_err(|e| Error::Custom(e.to_string()))
+            })
+            .map(|needed| !needed)
+            .unwrap_or_default()
+    }
+
+    /// Makes this node standby, and tells the listeners. They are told
+    /// even if the node was not active, because its lease may have expired
+    /// without this node noticing.
+    fn set_standby(&self, leader: Option<String>) {
+        self.set_state(NodeRole::Standby, leader, None);
+        if let Err(e) = self.notify_listeners(false) {
+            error!("Could not stop acting as the leader: {}", e);
+        }
+    }
+
+    fn set_state(
+        &self,
+        role: NodeRole,
+        leader: Option<String>,
+        valid_until: Option<Instant>,
+    ) {
+        let mut state = self.state.write().unwrap();
+        state.role = role;
+        state.leader = leader;
+        state.valid_until = valid_until;
+    }
+}
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test;
+
+    fn manager(
+        storage_uri: &url::Url,
+        data_dir: &std::path::Path,
+        node: &str,
+    ) -> HaManager {
+        let mut config = test::test_config(
+            storage_uri,
+            Some(data_dir),
+            false,
+            false,
+            false,
+            false,
+        );
+        config.ha_node = Some(node.to_string());
+        config.ha_lease_seconds = 3;
+        HaManager::create(Arc::new(config)).unwrap()
+    }
+
+    /// Remembers whether it was last told that the node is active.
+    #[derive(Default)]
+    struct Listener(RwLock<Option<bool>>);
+
+    impl RoleListener for Listener {
+        fn active_changed(&self, active: bool) -> KrillResult<()> {
+            *self.0.write().unwrap() = Some(active);
+            Ok(())
+        }
+    }
+
+    impl Listener {
+        fn active(&self) -> Option<bool> {
+            *self.0.read().unwrap()
+        }
+    }
+
+    #[test]
+    fn take_over_expired_lease() {
+        let storage_uri = test::mem_storage();
+        let (data_dir, cleanup) = test::tmp_dir();
+        let a = manager(&storage_uri, &data_dir, "a");
+        let b = manager(&storage_uri, &data_dir, "b");
+        let a_listener = Arc::new(Listener::default());
+        let b_listener = Arc::new(Listener::default());
+        a.add_listener(a_listener.clone());
+        b.add_listener(b_listener.clone());
+
+        assert_eq!(NodeRole::Leader, a.heartbeat(true).unwrap());
+        assert_eq!(NodeRole::Standby, b.heartbeat(true).unwrap());
+        assert_eq!(Some("a".to_string()), b.leader());
+        assert!(a.is_active());
+        assert!(!b.is_active());
+        assert_eq!(Some(true), a_listener.active());
+        assert_eq!(Some(false), b_listener.active());
+
+        // Renewing the lease keeps the leader, and the original start.
+        let since = a.current_lease().unwrap().unwrap().since;
+        assert_eq!(NodeRole::Leader, a.heartbeat(true).unwrap());
+        assert_eq!(since, a.current_lease().unwrap().unwrap().since);
+
+        // Let the lease expire, as if node "a" stopped.
+        let now = Timestamp::now();
+        let expired = NodeLease {
+            node: "a".to_string(),
+            since: now.plus_seconds(-10),
+            expires: now.plus_seconds(-1),
+        };
+        a.kv.store(&Key::new_global(LEASE_KEY), &expired).unwrap();
+
+        assert_eq!(NodeRole::Leader, b.heartbeat(true).unwrap());
+        assert!(b.is_active());
+        assert_eq!(Some(true), b_listener.active());
+
+        assert_eq!(NodeRole::Standby, a.heartbeat(true).unwrap());
+        assert_eq!(Some("b".to_string()), a.leader());
+        assert!(!a.is_active());
+        assert_eq!(Some(false), a_listener.active());
+
+        cleanup();
+    }
+}
@@ -1,11 +1,15 @@
-use std::{io, str::from_utf8, str::FromStr};
+use std::{convert::Infallible, io, str::from_utf8, str::FromStr};
 
 use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
 use serde::{de::DeserializeOwned, Serialize};
 
-use http_body_util::{BodyExt, Either, Empty, Full, Limited};
-use hyper::body::Body;
-use hyper::header::USER_AGENT;
+use http_body_util::{
+    combinators::UnsyncBoxBody, BodyExt, Either, Empty, Full, Limited,
+    StreamBody,
+};
+use hyper::body::{Body, Frame};
+use hyper::header::{HeaderValue, ETAG, IF_MATCH, USER_AGENT};
 use hyper::http::uri::PathAndQuery;
 use hyper::{HeaderMap, Method, StatusCode};
 
@@ -41,6 +45,8 @@ enum ContentType {
     Rfc8181,
     Rfc6492,
     Text,
+    Csv,
+    JsonLines,
     Xml,
     Html,
     Fav,
@@ -49,6 +55,7 @@ enum ContentType {
     Svg,
     Woff,
     Woff2,
+    EventStream,
 }
 
 impl AsRef<str> for ContentType {
@@ -59,6 +66,8 @@ impl AsRef<str> for ContentType {
             ContentType::Rfc8181 => publication::CONTENT_TYPE,
             ContentType::Rfc6492 => provisioning::CONTENT_TYPE,
             ContentType::Text => "text/plain",
+            ContentType::Csv => "text/csv",
+            ContentType::JsonLines => "application/x-ndjson",
             ContentType::Xml => "application/xml",
 
             ContentType::Html => "text/html",
@@ -68,6 +77,7 @@ impl AsRef<str> for ContentType {
             ContentType::Svg => "image/svg+xml",
             ContentType::Woff => "font/woff",
             ContentType::Woff2 => "font/woff2",
+            ContentType::EventStream => "text/event-stream",
         }
     }
 }
@@ -75,7 +85,9 @@ impl AsRef<str> for ContentType {
 //------------ HyperRequest and HyperResponse --------------------------------
 
 pub type HyperRequest = hyper::Request<hyper::body::Incoming>;
-pub type HyperResponseBody = Either<Empty<Bytes>, Full<Bytes>>;
+pub type HyperResponseBody =
+    Either<FixedResponseBody, UnsyncBoxBody<Bytes, Infallible>>;
+pub type FixedResponseBody = Either<Empty<Bytes>, Full<Bytes>>;
 pub type HyperResponse = hyper::Response<HyperResponseBody>;
 
 //----------- Response -------------------------------------------------------
@@ -114,9 +126,9 @@ impl Response {
         }
 
         let body = if self.body.is_empty() {
-            Either::Left(Empty::new())
+            Either::Left(Either::Left(Empty::new()))
         } else {
-            Either::Right(Full::new(self.body.into()))
+            Either::Left(Either::Right(Full::new(self.body.into())))
         };
         let response = builder.body(body).unwrap();
 
@@ -214,6 +226,16 @@ impl HttpResponse {
         self.response.headers()
     }
 
+    /// Adds the version of the aggregate that this response was rendered
+    /// from as a strong `ETag`, so that clients can use it in `If-Match`.
+    pub fn with_etag(mut self, version: u64) -> Self {
+        if let Ok(value) = HeaderValue::from_str(&format!("\"{}\"", version))
+        {
+            self.response.headers_mut().insert(ETAG, value);
+        }
+        self
+    }
+
     fn ok_response(content_type: ContentType, body: Vec<u8>) -> Self {
         Response {
             status: StatusCode::OK,
@@ -238,17 +260,47 @@ impl HttpResponse {
         Self::ok_response(ContentType::Text, body)
     }
 
+    pub fn csv(body: Vec<u8>) -> Self {
+        Self::ok_response(ContentType::Csv, body)
+    }
+
+    /// Returns JSON Lines, i.e. one JSON object per line.
+    pub fn json_lines(body: Vec<u8>) -> Self {
+        Self::ok_response(ContentType::JsonLines, body)
+    }
+
     pub fn text_no_cache(body: Vec<u8>) -> Self {
         HttpResponse::new(
             hyper::Response::builder()
                 .status(StatusCode::OK)
                 .header("Content-Type", ContentType::Text.as_ref())
                 .header("Cache-Control", "no-cache")
-                .body(Either::Right(Full::new(body.into())))
+                .body(Either::Left(Either::Right(Full::new(body.into()))))
                 .unwrap(),
         )
     }
 
+    /// Streams the messages to the client as they become available, using
+    /// the Server-Sent Events format. The messages themselves must already
+    /// be formatted as events.
+    pub fn event_stream<S>(messages: S) -> Self
+    where
+        S: Stream<Item = String> + Send + 'static,
+    {
+        let frames = messages.map(|msg| Ok(Frame::data(Bytes::from(msg))));
+
+        let mut res = HttpResponse::new(
+            hyper::Response::builder()
+                .status(StatusCode::OK)
+                .header("Content-Type", ContentType::EventStream.as_ref())
+                .header("Cache-Control", "no-cache")
+                .body(Either::Right(StreamBody::new(frames).boxed_unsync()))
+                .unwrap(),
+        );
+        res.do_not_log();
+        res
+    }
+
     pub fn xml(body: Vec<u8>) -> Self {
         Self::ok_response(ContentType::Xml, body)
     }
@@ -327,7 +379,7 @@ impl HttpResponse {
             hyper::Response::builder()
                 .status(StatusCode::FOUND)
                 .header("Location", location)
-                .body(Either::Left(Empty::new()))
+                .body(Either::Left(Either::Left(Empty::new())))
                 .unwrap(),
         )
     }
@@ -371,6 +423,50 @@ impl Request {
         self.request.headers()
     }
 
+    /// Returns the aggregate version from the `If-Match` header, if present.
+    /// The version must be given as an ETag previously returned by Krill,
+    /// e.g. `"12"`. A `*` matches any version, and is the same as leaving
+    /// out the header. Weak ETags are refused.
+    pub fn if_match(&self) -> Result<Option<u64>, Error> {
+        let value = match self.headers().get(&IF_MATCH) {
+            None => return Ok(None),
+            Some(value) => value,
+        };
+
+        let invalid = || {
+            Error::ApiInvalidIfMatch(
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )
+        };
+
+        let value = value.to_str().map_err(|_| invalid())?.trim();
+        if value == "*" {
+            return Ok(None);
+        }
+
+        // If-Match uses the strong comparison, so a weak tag never
+        // matches (RFC 9110, section 13.1.1).
+        if value.starts_with("W/") {
+            return Err(Error::ApiWeakIfMatch(value.to_string()));
+        }
+
+        value
+            .strip_prefix('"')
+            .and_then(|tag| tag.strip_suffix('"'))
+            .and_then(|version| u64::from_str(version).ok())
+            .map(Some)
+            .ok_or_else(invalid)
+    }
+
+    /// Returns whether the `dry_run` query parameter is set to `true`. A
+    /// dry run reports what a change would do, without applying it.
+    pub fn dry_run(&self) -> Result<bool, Error> {
+        Ok(self
+            .path
+            .query_param_parsed::<bool>("dry_run")?
+            .unwrap_or(false))
+    }
+
     pub fn user_agent(&self) -> Option<String> {
         match self.headers().get(&USER_AGENT) {
             None => None,
@@ -426,6 +522,12 @@ impl Request {
         self.request.method() == Method::POST
     }
 
+    /// Returns whether this request does not change anything, judging by
+    /// its method.
+    pub fn is_read_only(&self) -> bool {
+        matches!(*self.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+    }
+
     /// Returns whether the request is a DELETE request.
     pub fn is_delete(&self) -> bool {
         self.request.method() == Method::DELETE
@@ -563,4 +665,70 @@ impl RequestPath {
     {
         self.next().and_then(|s| T::from_str(s).ok())
     }
+
+    /// Returns the (raw, not percent-decoded) value of the first query
+    /// parameter with the given name, if present. Returns an empty value
+    /// for a parameter without '='.
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.path.query()?.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            if key == name {
+                Some(value)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the percent-decoded value of the first query parameter with
+    /// the given name, if present.
+    pub fn query_param_decoded(&self, name: &str) -> Option<String> {
+        url::form_urlencoded::parse(self.path.query()?.as_bytes())
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    }
+
+    /// Parses the percent-decoded value of the query parameter with the
+    /// given name, if present.
+    pub fn query_param_decoded_parsed<T>(
+        &self,
+        name: &str,
+    ) -> Result<Option<T>, Error>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        self.query_param_decoded(name)
+            .map(|value| {
+                T::from_str(&value).map_err(|e| {
+                    Error::ApiInvalidQueryParam(
+                        name.to_string(),
+                        e.to_string(),
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    /// Parses the value of the query parameter with the given name, if
+    /// present.
+    pub fn query_param_parsed<T>(
+        &self,
+        name: &str,
+    ) -> Result<Option<T>, Error>
+    where
+        T: FromStr,
+        T::Err: std::fmt::Display,
+    {
+        self.query_param(name)
+            .map(|value| {
+                T::from_str(value).map_err(|e| {
+                    Error::ApiInvalidQueryParam(
+                        name.to_string(),
+                        e.to_string(),
+                    )
+                })
+            })
+            .transpose()
+    }
 }
@@ -1,16 +1,18 @@
 //! Hyper based HTTP server for Krill.
 use std::collections::HashMap;
-use std::fs::File;
+use std::env;
+use std::fs::{self, File};
 use std::io::Read;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::Arc;
-use std::{env, process};
+use std::time::Duration;
 
 use base64::engine::general_purpose::STANDARD as BASE64_ENGINE;
 use base64::engine::Engine as _;
 use bytes::Bytes;
+use fslock::LockFile;
 use hyper::header::HeaderName;
 use hyper::http::HeaderValue;
 use hyper::service::service_fn;
@@ -20,33 +22,44 @@ use rpki::ca::idexchange;
 use rpki::ca::idexchange::{
     CaHandle, ChildHandle, ParentHandle, PublisherHandle,
 };
+use rpki::ca::provisioning::ResourceClassName;
 use rpki::repository::resources::Asn;
 use serde::Serialize;
 use tokio::net::TcpListener;
 use tokio::select;
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio_rustls::TlsAcceptor;
 
 use crate::{
     commons::{
+        actor::Actor,
         api::{
-            ApiRepositoryContact, AspaDefinitionUpdates, BgpStats,
-            CommandHistoryCriteria, ParentCaReq, PublisherList,
-            RepositoryContact, RoaConfigurationUpdates, RtaName, Token,
+            ApiRepositoryContact, AspaDefinitionUpdates, AuditCriteria,
+            AuditFormat, BgpStats, CommandHistoryCriteria, ExpiryWindow,
+            ParentCaReq, PointInTime, PublisherList, RepositoryContact,
+            RoaConfigurationUpdates, RtaName, Token, WebhookName,
         },
         bgp::BgpAnalysisAdvice,
         error::Error,
         eventsourcing::AggregateStoreError,
         util::file,
+        KrillResult,
     },
     constants::{
-        KRILL_ENV_HTTP_LOG_INFO, KRILL_ENV_UPGRADE_ONLY, KRILL_VERSION_MAJOR,
-        KRILL_VERSION_MINOR, KRILL_VERSION_PATCH, NO_RESOURCE,
+        EVENT_STREAM_KEEP_ALIVE_SECS, KRILL_ENV_HTTP_LOG_INFO,
+        KRILL_ENV_UPGRADE_ONLY, KRILL_VERSION_MAJOR, KRILL_VERSION_MINOR,
+        KRILL_VERSION_PATCH, NO_RESOURCE,
     },
     daemon::{
         auth::common::permissions::Permission,
         auth::{Auth, Handle},
         ca::CaStatus,
         config::Config,
+        encryption::enable_storage_encryption,
+        eventstream::{
+            EventStreamFilter, StreamedAggregateType, StreamedEvent,
+        },
+        ha::HaManager,
         http::{
             auth::auth, statics::statics, testbed::testbed, tls, tls_keys,
             HttpResponse, HyperRequest, HyperResponse, Request, RequestPath,
@@ -76,14 +89,37 @@ fn print_write_error_hint_and_die(error_msg: String) {
     eprintln!("ReadWritePaths=/local/path1 /local/path2 ...");
 }
 
-fn write_pid_file_or_die(config: &Config) {
-    if let Err(e) =
-        file::save(process::id().to_string().as_bytes(), config.pid_file())
-    {
-        print_write_error_hint_and_die(format!(
-            "Could not write PID file: {}",
-            e
-        ));
+/// Writes the PID file, and keeps it locked for as long as the returned
+/// lock is held, so that krillup can tell that Krill is running. Fails
+/// without touching the file if another process holds the lock, so every
+/// Krill instance needs its own PID file.
+fn lock_pid_file(config: &Config) -> Result<LockFile, Error> {
+    let pid_file = config.pid_file();
+    let locked = pid_file
+        .parent()
+        .map(fs::create_dir_all)
+        .unwrap_or(Ok(()))
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            let mut lock =
+                LockFile::open(pid_file).map_err(|e| e.to_string())?;
+            Ok(lock
+                .try_lock_with_pid()
+                .map_err(|e| e.to_string())?
+                .then_some(lock))
+        });
+
+    match locked {
+        Ok(Some(lock)) => Ok(lock),
+        Ok(None) => Err(Error::Custom(format!(
+            "PID file {} is locked by another process. Is Krill already running? Use a different 'pid_file' for each Krill instance.",
+            pid_file.display()
+        ))),
+        Err(e) => {
+            let msg = format!("Could not write PID file: {}", e);
+            print_write_error_hint_and_die(msg.clone());
+            Err(Error::Custom(msg))
+        }
     }
 }
 
@@ -119,9 +155,12 @@ fn test_data_dirs_or_die(config: &Config) {
 }
 
 pub async fn start_krill_daemon(config: Arc<Config>) -> Result<(), Error> {
-    write_pid_file_or_die(&config);
+    let _pid_lock = lock_pid_file(&config)?;
     test_data_dirs_or_die(&config);
 
+    // Encryption must be enabled before any data is read or written.
+    enable_storage_encryption(&config)?;
+
     // Set up the runtime properties manager, so that we can check
     // the version used for the current data in storage
     let properties_manager = PropertiesManager::create(
@@ -129,14 +168,23 @@ pub async fn start_krill_daemon(config: Arc<Config>) -> Result<(), Error> {
         config.use_history_cache,
     )?;
 
+    // In an active/passive set up only the leader may upgrade the data, so
+    // a standby node waits until that is done, or until it can take over.
+    let ha = Arc::new(HaManager::create(config.clone())?);
+    ha.wait_for_start(&properties_manager).await?;
+
     // Call upgrade, this will only do actual work if needed.
-    let upgrade_report = prepare_upgrade_data_migrations(UpgradeMode::PrepareToFinalise, &config, &properties_manager)
-        .map_err(|e| match e {
-            UpgradeError::CodeOlderThanData(_,_) => {
-                Error::Custom(e.to_string())
-            },
-            _ => Error::Custom(format!("Upgrade data migration failed with error: {}\n\nNOTE: your data was not changed. Please downgrade your krill instance to your previous version.", e))
-        })?;
+    let upgrade_report = if ha.is_active() {
+        prepare_upgrade_data_migrations(UpgradeMode::PrepareToFinalise, &config, &properties_manager)
+            .map_err(|e| match e {
+                UpgradeError::CodeOlderThanData(_,_) => {
+                    Error::Custom(e.to_string())
+                },
+                _ => Error::Custom(format!("Upgrade data migration failed with error: {}\n\nNOTE: your data was not changed. Please downgrade your krill instance to your previous version.", e))
+            })?
+    } else {
+        None
+    };
 
     if let Some(report) = &upgrade_report {
         finalise_data_migration(report.versions(), &config, &properties_manager).map_err(|e| {
@@ -153,7 +201,7 @@ pub async fn start_krill_daemon(config: Arc<Config>) -> Result<(), Error> {
 
     // Create the server, this will create the necessary data sub-directories
     // if needed
-    let krill_server = KrillServer::build(config.clone()).await?;
+    let krill_server = KrillServer::build(config.clone(), ha.clone()).await?;
 
     // Call post-start upgrades to trigger any upgrade related runtime
     // actions, such as re-issuing ROAs because subject name strategy has
@@ -197,6 +245,7 @@ pub async fn start_krill_daemon(config: Arc<Config>) -> Result<(), Error> {
     select!(
         _ = server_futures => error!("http server stopped unexpectedly"),
         _ = scheduler_future => error!("scheduler stopped unexpectedly"),
+        _ = ha.run() => error!("leader election stopped unexpectedly"),
     );
 
     Err(Error::custom("stopping krill process"))
@@ -342,13 +391,26 @@ async fn map_requests(
     // refreshing.
     let new_auth = req.actor().new_auth();
 
+    // A standby node only serves read-only requests. Logging in and out
+    // does not change any stored data, so that is allowed.
+    let mut res = if req.is_read_only() || req.path().segment() == "auth" {
+        Err(req)
+    } else {
+        match req.state().verify_active() {
+            Ok(()) => Err(req),
+            Err(e) => render_error(e),
+        }
+    };
+
     // We used to use .or_else() here but that causes a large recursive call
     // tree due to these calls being to async functions, large enough with the
     // given Request object passed each time that it eventually resulted in
     // stack overflow. By doing it by hand like this we avoid the use of the
     // macros that cause the recursion. We could also look at putting less
     // data on the stack.
-    let mut res = api(req).await;
+    if let Err(req) = res {
+        res = api(req).await;
+    }
     if let Err(req) = res {
         res = auth(req).await;
     }
@@ -443,6 +505,19 @@ fn render_json_res<O: Serialize>(res: Result<O, Error>) -> RoutingResult {
     }
 }
 
+/// Renders the JSON with the version of the aggregate it was taken from as
+/// its ETag. The version should be read before the object, so that the
+/// ETag is never newer than the content.
+fn render_json_res_with_etag<O: Serialize>(
+    res: Result<O, Error>,
+    version: u64,
+) -> RoutingResult {
+    match res {
+        Ok(o) => Ok(HttpResponse::json(&o).with_etag(version)),
+        Err(e) => render_error(e),
+    }
+}
+
 /// A clean 404 result for the API (no content, not for humans)
 #[allow(clippy::unnecessary_wraps)]
 fn render_unknown_resource() -> RoutingResult {
@@ -513,6 +588,14 @@ pub async fn metrics(req: Request) -> RoutingResult {
         let mut res = String::new();
 
         let info = server.server_info();
+        res.push_str("# HELP krill_node_leader whether this node is active (1), i.e. it is the leader or the only node, or a standby (0)\n");
+        res.push_str("# TYPE krill_node_leader gauge\n");
+        res.push_str(&format!(
+            "krill_node_leader{{role=\"{}\"}} {}\n",
+            info.role(),
+            i32::from(info.role().is_active())
+        ));
+
         res.push_str("# HELP krill_server_start unix timestamp in seconds of last krill server start\n");
         res.push_str("# TYPE krill_server_start gauge\n");
         res.push_str(&format!("krill_server_start {}\n", info.started()));
@@ -684,6 +767,71 @@ pub async fn metrics(req: Request) -> RoutingResult {
                     }
                 }
 
+                {
+                    // CA key ages
+
+                    // krill_ca_key_age_seconds{{ca="ca", rc="0"}} 86400
+
+                    res.push('\n');
+                    res.push_str("# HELP krill_ca_key_age_seconds age in seconds of the current key in a CA resource class\n");
+                    res.push_str("# TYPE krill_ca_key_age_seconds gauge\n");
+                    for (ca, status) in cas_stats.iter() {
+                        for (rcn, age) in status.key_ages().iter() {
+                            res.push_str(&format!(
+                                "krill_ca_key_age_seconds{{ca=\"{}\", rc=\"{}\"}} {}\n",
+                                ca, rcn, age
+                            ));
+                        }
+                    }
+                }
+
+                if let Ok(report) = server
+                    .object_expiry_report(None, server.system_actor())
+                    .await
+                {
+                    // Signed object expiry, including TA objects. Only the
+                    // earliest time per CA and object type is shown, so
+                    // that the number of series does not grow with the
+                    // number of objects.
+
+                    // krill_ca_object_not_after{{ca="ca", type="roa"}}
+                    // 1630921599
+                    // krill_ca_object_next_update{{ca="ca", type="manifest"}}
+                    // 1630921599
+                    let earliest = report.earliest();
+
+                    res.push('\n');
+                    res.push_str("# HELP krill_ca_object_not_after unix timestamp in seconds of the earliest not after time of the current signed objects of this type\n");
+                    res.push_str("# TYPE krill_ca_object_not_after gauge\n");
+                    for ((ca, object_type), (not_after, _)) in earliest.iter()
+                    {
+                        res.push_str(&format!(
+                            "krill_ca_object_not_after{{ca=\"{}\", type=\"{}\"}} {}\n",
+                            ca,
+                            object_type,
+                            not_after.timestamp()
+                        ));
+                    }
+
+                    res.push('\n');
+                    res.push_str("# HELP krill_ca_object_next_update unix timestamp in seconds of the earliest next update time of the current manifests or CRLs\n");
+                    res.push_str(
+                        "# TYPE krill_ca_object_next_update gauge\n",
+                    );
+                    for ((ca, object_type), (_, next_update)) in
+                        earliest.iter()
+                    {
+                        if let Some(next_update) = next_update {
+                            res.push_str(&format!(
+                                "krill_ca_object_next_update{{ca=\"{}\", type=\"{}\"}} {}\n",
+                                ca,
+                                object_type,
+                                next_update.timestamp()
+                            ));
+                        }
+                    }
+                }
+
                 // Do not show child metrics if none of the CAs has any
                 // children.. Many users do not delegate so,
                 // showing these metrics would just be confusing.
@@ -1243,8 +1391,20 @@ async fn api(req: Request) -> RoutingResult {
                 // Make sure access is allowed
                 aa!(req, Permission::LOGIN, {
                     match restricted_endpoint {
+                        Some("audit") => api_audit(req).await,
+                        Some("admin") => aa!(
+                            req,
+                            Permission::CA_ADMIN,
+                            aa!(
+                                req,
+                                Permission::PUB_ADMIN,
+                                api_admin(req, &mut path).await
+                            )
+                        ),
                         Some("bulk") => api_bulk(req, &mut path).await,
                         Some("cas") => api_cas(req, &mut path).await,
+                        Some("expiry") => api_expiry(req).await,
+                        Some("events") => api_events(req).await,
                         Some("pubd") => aa!(
                             req,
                             Permission::PUB_ADMIN,
@@ -1255,6 +1415,16 @@ async fn api(req: Request) -> RoutingResult {
                             Permission::CA_ADMIN,
                             api_ta(req, &mut path).await
                         ),
+                        Some("webhooks") => aa!(
+                            req,
+                            Permission::CA_ADMIN,
+                            api_webhooks(req, &mut path).await
+                        ),
+                        Some("tasks") => aa!(
+                            req,
+                            Permission::CA_ADMIN,
+                            api_tasks(req, &mut path).await
+                        ),
                         _ => render_unknown_method(),
                     }
                 })
@@ -1278,6 +1448,19 @@ async fn api_authorized(req: Request) -> RoutingResult {
     )
 }
 
+/// Returns a backup of all data. The backup includes private keys kept by
+/// Krill, so this requires both CA and publication server admin rights.
+/// The keys are only encrypted if storage encryption is enabled, which is
+/// shown by `encrypted` in the manifest.
+async fn api_admin(req: Request, path: &mut RequestPath) -> RoutingResult {
+    match (req.method().clone(), path.next()) {
+        (Method::GET, Some("backup")) => {
+            render_json_res(req.state().backup())
+        }
+        _ => render_unknown_method(),
+    }
+}
+
 async fn api_bulk(req: Request, path: &mut RequestPath) -> RoutingResult {
     match path.full() {
         "/api/v1/bulk/cas/import" => api_cas_import(req).await,
@@ -1305,6 +1488,9 @@ async fn api_cas(req: Request, path: &mut RequestPath) -> RoutingResult {
                 Some("aspas") => api_ca_aspas(req, path, ca).await,
                 Some("bgpsec") => api_ca_bgpsec(req, path, ca).await,
                 Some("children") => api_ca_children(req, path, ca).await,
+                Some("ghostbusters") => {
+                    api_ca_ghostbusters(req, path, ca).await
+                }
                 Some("history") => api_ca_history(req, path, ca).await,
 
                 Some("id") => api_ca_id(req, path, ca).await,
@@ -1316,6 +1502,8 @@ async fn api_cas(req: Request, path: &mut RequestPath) -> RoutingResult {
                 Some("stats") => api_ca_stats(req, path, ca).await,
                 Some("sync") => api_ca_sync(req, path, ca).await,
 
+                Some("geofeed") => api_ca_geofeed(req, ca).await,
+                Some("rsc") => api_ca_rsc(req, ca).await,
                 Some("rta") => api_ca_rta(req, path, ca).await,
 
                 _ => render_unknown_method(),
@@ -1338,6 +1526,14 @@ async fn api_ca_keys(
         Method::POST => match path.next() {
             Some("roll_init") => api_ca_kr_init(req, ca).await,
             Some("roll_activate") => api_ca_kr_activate(req, ca).await,
+            Some("roll_emergency") => {
+                api_ca_kr_emergency(req, ca, path.path_arg()).await
+            }
+            Some("policy") => api_ca_kr_policy_update(req, ca).await,
+            _ => render_unknown_method(),
+        },
+        Method::DELETE => match path.next() {
+            Some("policy") => api_ca_kr_policy_remove(req, ca).await,
             _ => render_unknown_method(),
         },
         _ => render_unknown_method(),
@@ -1550,7 +1746,14 @@ pub async fn api_add_pbl(req: Request) -> RoutingResult {
     aa!(req, Permission::PUB_CREATE, {
         let actor = req.actor();
         let server = req.state().clone();
+        let dry_run = match req.dry_run() {
+            Ok(dry_run) => dry_run,
+            Err(e) => return render_error(e),
+        };
         match req.json().await {
+            Ok(pbl) if dry_run => {
+                render_json_res(server.add_publisher_dry_run(pbl, &actor))
+            }
             Ok(pbl) => render_json_res(server.add_publisher(pbl, &actor)),
             Err(e) => render_error(e),
         }
@@ -1566,7 +1769,15 @@ pub async fn api_remove_pbl(
 ) -> RoutingResult {
     aa!(req, Permission::PUB_DELETE, {
         let actor = req.actor();
-        render_empty_res(req.state().remove_publisher(publisher, &actor))
+        match req.dry_run() {
+            Ok(true) => render_json_res(
+                req.state().remove_publisher_dry_run(publisher, &actor),
+            ),
+            Ok(false) => render_empty_res(
+                req.state().remove_publisher(publisher, &actor),
+            ),
+            Err(e) => render_error(e),
+        }
     })
 }
 
@@ -1625,9 +1836,22 @@ pub async fn api_ca_add_child(req: Request, ca: CaHandle) -> RoutingResult {
     aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
         let actor = req.actor();
         let server = req.state().clone();
+        let version = match req.if_match() {
+            Ok(version) => version,
+            Err(e) => return render_error(e),
+        };
+        let dry_run = match req.dry_run() {
+            Ok(dry_run) => dry_run,
+            Err(e) => return render_error(e),
+        };
         match req.json().await {
+            Ok(child_req) if dry_run => render_json_res(
+                server
+                    .ca_add_child_dry_run(&ca, child_req, version, &actor)
+                    .await,
+            ),
             Ok(child_req) => render_json_res(
-                server.ca_add_child(&ca, child_req, &actor).await,
+                server.ca_add_child(&ca, child_req, version, &actor).await,
             ),
             Err(e) => render_error(e),
         }
@@ -1642,9 +1866,26 @@ async fn api_ca_child_update(
     aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
         let actor = req.actor();
         let server = req.state().clone();
+        let version = match req.if_match() {
+            Ok(version) => version,
+            Err(e) => return render_error(e),
+        };
+        let dry_run = match req.dry_run() {
+            Ok(dry_run) => dry_run,
+            Err(e) => return render_error(e),
+        };
         match req.json().await {
+            Ok(child_req) if dry_run => render_json_res(
+                server
+                    .ca_child_update_dry_run(
+                        &ca, child, child_req, version, &actor,
+                    )
+                    .await,
+            ),
             Ok(child_req) => render_empty_res(
-                server.ca_child_update(&ca, child, child_req, &actor).await,
+                server
+                    .ca_child_update(&ca, child, child_req, version, &actor)
+                    .await,
             ),
             Err(e) => render_error(e),
         }
@@ -1658,9 +1899,23 @@ pub async fn api_ca_child_remove(
 ) -> RoutingResult {
     aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
         let actor = req.actor();
-        render_empty_res(
-            req.state().ca_child_remove(&ca, child, &actor).await,
-        )
+        let version = match req.if_match() {
+            Ok(version) => version,
+            Err(e) => return render_error(e),
+        };
+        match req.dry_run() {
+            Ok(true) => render_json_res(
+                req.state()
+                    .ca_child_remove_dry_run(&ca, child, version, &actor)
+                    .await,
+            ),
+            Ok(false) => render_empty_res(
+                req.state()
+                    .ca_child_remove(&ca, child, version, &actor)
+                    .await,
+            ),
+            Err(e) => render_error(e),
+        }
     })
 }
 
@@ -1669,12 +1924,15 @@ async fn api_ca_child_show(
     ca: CaHandle,
     child: ChildHandle,
 ) -> RoutingResult {
-    aa!(
-        req,
-        Permission::CA_READ,
-        Handle::from(&ca),
-        render_json_res(req.state().ca_child_show(&ca, &child).await)
-    )
+    aa!(req, Permission::CA_READ, Handle::from(&ca), {
+        match req.state().ca_version(&ca).await {
+            Ok(version) => render_json_res_with_etag(
+                req.state().ca_child_show(&ca, &child).await,
+                version,
+            ),
+            Err(e) => render_error(e),
+        }
+    })
 }
 
 async fn api_ca_child_export(
@@ -1770,6 +2028,191 @@ async fn api_all_ca_issues(req: Request) -> RoutingResult {
     }
 }
 
+/// Returns the objects which expire within the period given by the
+/// optional 'within' query parameter, e.g. '?within=7d'.
+async fn api_expiry(req: Request) -> RoutingResult {
+    match *req.method() {
+        Method::GET => aa!(req, Permission::CA_READ, {
+            match req.path().query_param_parsed::<ExpiryWindow>("within") {
+                Ok(within) => {
+                    let actor = req.actor();
+                    render_json_res(
+                        req.state()
+                            .object_expiry_report(within, &actor)
+                            .await,
+                    )
+                }
+                Err(e) => render_error(e),
+            }
+        }),
+        _ => render_unknown_method(),
+    }
+}
+
+/// Returns the commands for all CAs, the publication server and the TA
+/// proxy that the actor may see, as JSON Lines or CSV depending on the
+/// 'format' query parameter. The 'actor', 'after', 'before', 'command' and
+/// 'handle' query parameters limit the commands included.
+async fn api_audit(req: Request) -> RoutingResult {
+    match *req.method() {
+        Method::GET => aa!(req, Permission::CA_READ, {
+            match audit_query(req.path()) {
+                Ok((crit, format)) => {
+                    let actor = req.actor();
+                    match req.state().audit_log(&crit, &actor) {
+                        Ok(log) => {
+                            let body = log.to_format(format).into_bytes();
+                            Ok(match format {
+                                AuditFormat::JsonLines => {
+                                    HttpResponse::json_lines(body)
+                                }
+                                AuditFormat::Csv => HttpResponse::csv(body),
+                            })
+                        }
+                        Err(e) => render_error(e),
+                    }
+                }
+                Err(e) => render_error(e),
+            }
+        }),
+        _ => render_unknown_method(),
+    }
+}
+
+fn audit_query(
+    path: &RequestPath,
+) -> KrillResult<(AuditCriteria, AuditFormat)> {
+    let crit = AuditCriteria {
+        actor: path.query_param_decoded("actor"),
+        after: path.query_param_decoded_parsed("after")?,
+        before: path.query_param_decoded_parsed("before")?,
+        command: path.query_param_decoded("command"),
+        handle: path.query_param_decoded_parsed("handle")?,
+    };
+    let format = path
+        .query_param_decoded_parsed("format")?
+        .unwrap_or_default();
+    Ok((crit, format))
+}
+
+/// Streams committed events as Server-Sent Events, optionally filtered by
+/// CA and by a comma separated list of event types. Events for which the
+/// actor lacks read permission are left out.
+async fn api_events(req: Request) -> RoutingResult {
+    match *req.method() {
+        Method::GET => aa!(req, Permission::CA_READ, {
+            match req.path().query_param_parsed::<CaHandle>("ca") {
+                Ok(ca) => {
+                    let filter = EventStreamFilter::new(
+                        ca,
+                        req.path().query_param("type"),
+                    );
+                    let receiver = req.state().event_stream().subscribe();
+                    Ok(HttpResponse::event_stream(event_messages(
+                        receiver,
+                        filter,
+                        req.actor(),
+                    )))
+                }
+                Err(e) => render_error(e),
+            }
+        }),
+        _ => render_unknown_method(),
+    }
+}
+
+fn event_messages(
+    receiver: broadcast::Receiver<Arc<StreamedEvent>>,
+    filter: EventStreamFilter,
+    actor: Actor,
+) -> impl futures_util::Stream<Item = String> {
+    futures_util::stream::unfold(
+        (receiver, filter, actor),
+        |(mut receiver, filter, actor)| async move {
+            loop {
+                let msg = match tokio::time::timeout(
+                    Duration::from_secs(EVENT_STREAM_KEEP_ALIVE_SECS),
+                    receiver.recv(),
+                )
+                .await
+                {
+                    Ok(Ok(event)) => {
+                        if !filter.matches(&event)
+                            || !event_allowed(&event, &actor)
+                        {
+                            continue;
+                        }
+                        event.to_sse_message()
+                    }
+                    Ok(Err(RecvError::Lagged(missed))) => {
+                        format!(": missed {} events\n\n", missed)
+                    }
+                    Ok(Err(RecvError::Closed)) => return None,
+                    Err(_) => ": keep-alive\n\n".to_string(),
+                };
+                return Some((msg, (receiver, filter, actor)));
+            }
+        },
+    )
+}
+
+fn event_allowed(event: &StreamedEvent, actor: &Actor) -> bool {
+    let allowed = match event.aggregate_type {
+        StreamedAggregateType::Ca => actor.is_allowed(
+            Permission::CA_READ,
+            Handle::from(&event.handle.convert()),
+        ),
+        StreamedAggregateType::TaProxy => {
+            actor.is_allowed(Permission::CA_ADMIN, NO_RESOURCE)
+        }
+        StreamedAggregateType::PubServer => {
+            actor.is_allowed(Permission::PUB_ADMIN, NO_RESOURCE)
+        }
+    };
+    matches!(allowed, Ok(true))
+}
+
+async fn api_webhooks(req: Request, path: &mut RequestPath) -> RoutingResult {
+    match path.path_arg::<WebhookName>() {
+        Some(name) => match (req.method().clone(), path.next()) {
+            (Method::DELETE, None) => {
+                render_empty_res(req.state().webhook_remove(&name))
+            }
+            (Method::POST, Some("test")) => {
+                render_empty_res(req.state().webhook_test(&name))
+            }
+            _ => render_unknown_method(),
+        },
+        None => match *req.method() {
+            Method::GET => render_json_res(req.state().webhooks_list()),
+            Method::POST => {
+                let state = req.state().clone();
+                match req.json().await {
+                    Ok(definition) => {
+                        render_empty_res(state.webhook_add(definition))
+                    }
+                    Err(e) => render_error(e),
+                }
+            }
+            _ => render_unknown_method(),
+        },
+    }
+}
+
+async fn api_tasks(req: Request, path: &mut RequestPath) -> RoutingResult {
+    match (req.method().clone(), path.next()) {
+        (Method::GET, None) => render_json_res(req.state().task_list()),
+        (Method::POST, Some("run")) => {
+            let state = req.state().clone();
+            match req.json().await {
+                Ok(task) => render_empty_res(state.task_run(task).await),
+                Err(e) => render_error(e),
+            }
+        }
+        _ => render_unknown_method(),
+    }
+}
+
 /// Returns the health (state) for a given CA.
 async fn api_ca_issues(req: Request, ca: CaHandle) -> RoutingResult {
     match *req.method() {
@@ -1828,23 +2271,39 @@ async fn api_ca_id(
     }
 }
 
+/// Returns the CA info, or the historic CA info if a point in its history
+/// is given using the 'at' query parameter.
 async fn api_ca_info(req: Request, handle: CaHandle) -> RoutingResult {
-    aa!(
-        req,
-        Permission::CA_READ,
-        Handle::from(&handle),
-        render_json_res(req.state().ca_info(&handle).await)
-    )
+    aa!(req, Permission::CA_READ, Handle::from(&handle), {
+        match req.path().query_param_parsed::<PointInTime>("at") {
+            Ok(None) => match req.state().ca_version(&handle).await {
+                Ok(version) => render_json_res_with_etag(
+                    req.state().ca_info(&handle).await,
+                    version,
+                ),
+                Err(e) => render_error(e),
+            },
+            Ok(Some(point)) => {
+                render_json_res(req.state().ca_info_at(&handle, point).await)
+            }
+            Err(e) => render_error(e),
+        }
+    })
 }
 
 async fn api_ca_delete(req: Request, handle: CaHandle) -> RoutingResult {
     let actor = req.actor();
-    aa!(
-        req,
-        Permission::CA_DELETE,
-        Handle::from(&handle),
-        render_json_res(req.state().ca_delete(&handle, &actor).await)
-    )
+    aa!(req, Permission::CA_DELETE, Handle::from(&handle), {
+        match req.dry_run() {
+            Ok(true) => {
+                render_json_res(req.state().ca_delete_dry_run(&handle).await)
+            }
+            Ok(false) => {
+                render_json_res(req.state().ca_delete(&handle, &actor).await)
+            }
+            Err(e) => render_error(e),
+        }
+    })
 }
 
 async fn api_ca_my_parent_contact(
@@ -1938,7 +2397,13 @@ async fn api_ca_bgpsec_definitions_show(
     ca: CaHandle,
 ) -> RoutingResult {
     aa!(req, Permission::BGPSEC_READ, Handle::from(&ca), {
-        render_json_res(req.state().ca_bgpsec_definitions_show(ca).await)
+        match req.state().ca_version(&ca).await {
+            Ok(version) => render_json_res_with_etag(
+                req.state().ca_bgpsec_definitions_show(ca).await,
+                version,
+            ),
+            Err(e) => render_error(e),
+        }
     })
 }
 
@@ -1949,10 +2414,27 @@ async fn api_ca_bgpsec_definitions_update(
     aa!(req, Permission::BGPSEC_UPDATE, Handle::from(&ca), {
         let actor = req.actor();
         let server = req.state().clone();
+        let version = match req.if_match() {
+            Ok(version) => version,
+            Err(e) => return render_error(e),
+        };
+        let dry_run = match req.dry_run() {
+            Ok(dry_run) => dry_run,
+            Err(e) => return render_error(e),
+        };
         match req.json().await {
+            Ok(updates) if dry_run => render_json_res(
+                server
+                    .ca_bgpsec_definitions_update_dry_run(
+                        ca, updates, version, &actor,
+                    )
+                    .await,
+            ),
             Ok(updates) => render_empty_res(
                 server
-                    .ca_bgpsec_definitions_update(ca, updates, &actor)
+                    .ca_bgpsec_definitions_update(
+                        ca, updates, version, &actor,
+                    )
                     .await,
             ),
             Err(e) => render_error(e),
@@ -1960,6 +2442,50 @@ async fn api_ca_bgpsec_definitions_update(
     })
 }
 
+async fn api_ca_ghostbusters(
+    req: Request,
+    path: &mut RequestPath,
+    ca: CaHandle,
+) -> RoutingResult {
+    // Handles /api/v1/cas/{ca}/ghostbusters/:
+    //    GET  /api/v1/cas/{ca}/ghostbusters/ -> List Ghostbuster definitions
+    //    POST /api/v1/cas/{ca}/ghostbusters/ -> Send
+    //    GhostbusterDefinitionUpdates
+    match path.next() {
+        None => match *req.method() {
+            Method::GET => api_ca_ghostbusters_show(req, ca).await,
+            Method::POST => api_ca_ghostbusters_update(req, ca).await,
+            _ => render_unknown_method(),
+        },
+        _ => render_unknown_method(),
+    }
+}
+
+async fn api_ca_ghostbusters_show(
+    req: Request,
+    ca: CaHandle,
+) -> RoutingResult {
+    aa!(req, Permission::GHOSTBUSTERS_READ, Handle::from(&ca), {
+        render_json_res(req.state().ca_ghostbusters_show(ca).await)
+    })
+}
+
+async fn api_ca_ghostbusters_update(
+    req: Request,
+    ca: CaHandle,
+) -> RoutingResult {
+    aa!(req, Permission::GHOSTBUSTERS_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        let server = req.state().clone();
+        match req.json().await {
+            Ok(updates) => render_empty_res(
+                server.ca_ghostbusters_update(ca, updates, &actor).await,
+            ),
+            Err(e) => render_error(e),
+        }
+    })
+}
+
 async fn api_ca_children(
     req: Request,
     path: &mut RequestPath,
@@ -2035,10 +2561,53 @@ async fn api_ca_history(
     match path.next() {
         Some("details") => api_ca_command_details(req, path, ca).await,
         Some("commands") => api_ca_history_commands(req, path, ca).await,
+        Some("revert") => api_ca_command_revert(req, path, ca).await,
         _ => render_unknown_method(),
     }
 }
 
+/// Reverts the definition changes made by an earlier command
+async fn api_ca_command_revert(
+    req: Request,
+    path: &mut RequestPath,
+    ca: CaHandle,
+) -> RoutingResult {
+    // /api/v1/cas/{ca}/history/revert/<command-key>
+    match path.path_arg() {
+        Some(reverted) => match *req.method() {
+            Method::POST => {
+                aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+                    let actor = req.actor();
+                    let state = req.state();
+                    let version = match req.if_match() {
+                        Ok(version) => version,
+                        Err(e) => return render_error(e),
+                    };
+                    match req.dry_run() {
+                        Ok(true) => render_json_res(
+                            state
+                                .ca_command_revert_dry_run(
+                                    &ca, reverted, version, &actor,
+                                )
+                                .await,
+                        ),
+                        Ok(false) => render_empty_res(
+                            state
+                                .ca_command_revert(
+                                    &ca, reverted, version, &actor,
+                                )
+                                .await,
+                        ),
+                        Err(e) => render_error(e),
+                    }
+                })
+            }
+            _ => render_unknown_method(),
+        },
+        None => render_unknown_resource(),
+    }
+}
+
 #[allow(clippy::redundant_clone)] // false positive
 async fn api_ca_command_details(
     req: Request,
@@ -2293,9 +2862,17 @@ async fn api_ca_remove_parent(
 ) -> RoutingResult {
     aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
         let actor = req.actor();
-        render_empty_res(
-            req.state().ca_parent_remove(ca, parent, &actor).await,
-        )
+        match req.dry_run() {
+            Ok(true) => render_json_res(
+                req.state()
+                    .ca_parent_remove_dry_run(ca, parent, &actor)
+                    .await,
+            ),
+            Ok(false) => render_empty_res(
+                req.state().ca_parent_remove(ca, parent, &actor).await,
+            ),
+            Err(e) => render_error(e),
+        }
     })
 }
 
@@ -2316,6 +2893,53 @@ async fn api_ca_kr_activate(req: Request, ca: CaHandle) -> RoutingResult {
     })
 }
 
+/// Start an emergency key roll for a compromised key in a resource class,
+/// or for all resource classes if none is specified.
+async fn api_ca_kr_emergency(
+    req: Request,
+    ca: CaHandle,
+    rcn: Option<ResourceClassName>,
+) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        render_empty_res(
+            req.state().ca_keyroll_emergency(ca, rcn, &actor).await,
+        )
+    })
+}
+
+/// Set the policy for automatic key rolls for a CA.
+async fn api_ca_kr_policy_update(
+    req: Request,
+    ca: CaHandle,
+) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        let server = req.state().clone();
+        match req.json().await {
+            Ok(policy) => render_empty_res(
+                server
+                    .ca_keyroll_policy_update(ca, Some(policy), &actor)
+                    .await,
+            ),
+            Err(e) => render_error(e),
+        }
+    })
+}
+
+/// Remove the policy for automatic key rolls for a CA.
+async fn api_ca_kr_policy_remove(
+    req: Request,
+    ca: CaHandle,
+) -> RoutingResult {
+    aa!(req, Permission::CA_UPDATE, Handle::from(&ca), {
+        let actor = req.actor();
+        render_empty_res(
+            req.state().ca_keyroll_policy_update(ca, None, &actor).await,
+        )
+    })
+}
+
 // -- ASPA functions
 
 /// List the current ASPA definitions for a CA
@@ -2325,7 +2949,13 @@ async fn api_ca_aspas_definitions_show(
 ) -> RoutingResult {
     aa!(req, Permission::ASPAS_READ, Handle::from(&ca), {
         let state = req.state().clone();
-        render_json_res(state.ca_aspas_definitions_show(ca).await)
+        match state.ca_version(&ca).await {
+            Ok(version) => render_json_res_with_etag(
+                state.ca_aspas_definitions_show(ca).await,
+                version,
+            ),
+            Err(e) => render_error(e),
+        }
     })
 }
 
@@ -2337,11 +2967,28 @@ async fn api_ca_aspas_definitions_update(
     aa!(req, Permission::ASPAS_UPDATE, Handle::from(&ca), {
         let actor = req.actor();
         let state = req.state().clone();
+        let version = match req.if_match() {
+            Ok(version) => version,
+            Err(e) => return render_error(e),
+        };
+        let dry_run = match req.dry_run() {
+            Ok(dry_run) => dry_run,
+            Err(e) => return render_error(e),
+        };
 
         match req.json().await {
             Err(e) => render_error(e),
+            Ok(updates) if dry_run => render_json_res(
+                state
+                    .ca_aspas_definitions_update_dry_run(
+                        ca, updates, version, &actor,
+                    )
+                    .await,
+            ),
             Ok(updates) => render_empty_res(
-                state.ca_aspas_definitions_update(ca, updates, &actor).await,
+                state
+                    .ca_aspas_definitions_update(ca, updates, version, &actor)
+                    .await,
             ),
         }
     })
@@ -2357,12 +3004,29 @@ async fn api_ca_aspas_update_aspa(
     aa!(req, Permission::ASPAS_UPDATE, Handle::from(&ca), {
         let actor = req.actor();
         let state = req.state().clone();
+        let version = match req.if_match() {
+            Ok(version) => version,
+            Err(e) => return render_error(e),
+        };
+        let dry_run = match req.dry_run() {
+            Ok(dry_run) => dry_run,
+            Err(e) => return render_error(e),
+        };
 
         match req.json().await {
             Err(e) => render_error(e),
+            Ok(update) if dry_run => render_json_res(
+                state
+                    .ca_aspas_update_aspa_dry_run(
+                        ca, customer, update, version, &actor,
+                    )
+                    .await,
+            ),
             Ok(update) => render_empty_res(
                 state
-                    .ca_aspas_update_aspa(ca, customer, update, &actor)
+                    .ca_aspas_update_aspa(
+                        ca, customer, update, version, &actor,
+                    )
                     .await,
             ),
         }
@@ -2380,9 +3044,25 @@ async fn api_ca_aspas_delete(
         let state = req.state().clone();
 
         let updates = AspaDefinitionUpdates::new(vec![], vec![customer]);
-        render_empty_res(
-            state.ca_aspas_definitions_update(ca, updates, &actor).await,
-        )
+        let version = match req.if_match() {
+            Ok(version) => version,
+            Err(e) => return render_error(e),
+        };
+        match req.dry_run() {
+            Ok(true) => render_json_res(
+                state
+                    .ca_aspas_definitions_update_dry_run(
+                        ca, updates, version, &actor,
+                    )
+                    .await,
+            ),
+            Ok(false) => render_empty_res(
+                state
+                    .ca_aspas_definitions_update(ca, updates, version, &actor)
+                    .await,
+            ),
+            Err(e) => render_error(e),
+        }
     })
 }
 
@@ -2391,11 +3071,25 @@ async fn api_ca_routes_update(req: Request, ca: CaHandle) -> RoutingResult {
     aa!(req, Permission::ROUTES_UPDATE, Handle::from(&ca), {
         let actor = req.actor();
         let state = req.state().clone();
+        let version = match req.if_match() {
+            Ok(version) => version,
+            Err(e) => return render_error(e),
+        };
+
+        let dry_run = match req.dry_run() {
+            Ok(dry_run) => dry_run,
+            Err(e) => return render_error(e),
+        };
 
         match req.json().await {
             Err(e) => render_error(e),
+            Ok(updates) if dry_run => render_json_res(
+                state
+                    .ca_routes_update_dry_run(ca, updates, version, &actor)
+                    .await,
+            ),
             Ok(updates) => render_empty_res(
-                state.ca_routes_update(ca, updates, &actor).await,
+                state.ca_routes_update(ca, updates, version, &actor).await,
             ),
         }
     })
@@ -2411,6 +3105,10 @@ async fn api_ca_routes_try_update(
     aa!(req, Permission::ROUTES_UPDATE, Handle::from(&ca), {
         let actor = req.actor();
         let state = req.state().clone();
+        let version = match req.if_match() {
+            Ok(version) => version,
+            Err(e) => return render_error(e),
+        };
 
         match req.json::<RoaConfigurationUpdates>().await {
             Err(e) => render_error(e),
@@ -2427,7 +3125,9 @@ async fn api_ca_routes_try_update(
                             // no issues found, apply
                             render_empty_res(
                                 server
-                                    .ca_routes_update(ca, updates, &actor)
+                                    .ca_routes_update(
+                                        ca, updates, version, &actor,
+                                    )
                                     .await,
                             )
                         } else {
@@ -2460,8 +3160,12 @@ async fn api_ca_routes_try_update(
 /// show the route authorizations for this CA
 async fn api_ca_routes_show(req: Request, ca: CaHandle) -> RoutingResult {
     aa!(req, Permission::ROUTES_READ, Handle::from(&ca), {
-        match req.state().ca_routes_show(&ca).await {
-            Ok(roas) => render_json(roas),
+        let state = req.state();
+        match state.ca_version(&ca).await {
+            Ok(version) => match state.ca_routes_show(&ca).await {
+                Ok(roas) => Ok(HttpResponse::json(&roas).with_etag(version)),
+                Err(_) => render_unknown_resource(),
+            },
             Err(_) => render_unknown_resource(),
         }
     })
@@ -2584,6 +3288,46 @@ async fn rrdp(req: Request) -> RoutingResult {
     }
 }
 
+//------------ Support Geofeed authentication (RFC 9632) -------------------
+
+/// Sign the geofeed in the request. The geofeed with its signature block
+/// appended is returned, but it is not kept by Krill.
+async fn api_ca_geofeed(req: Request, ca: CaHandle) -> RoutingResult {
+    match *req.method() {
+        Method::POST => {
+            aa!(req, Permission::GEOFEED_SIGN, Handle::from(&ca), {
+                let state = req.state().clone();
+                match req.json().await {
+                    Err(e) => render_error(e),
+                    Ok(request) => {
+                        render_json_res(state.geofeed_sign(ca, request).await)
+                    }
+                }
+            })
+        }
+        _ => render_unknown_method(),
+    }
+}
+
+//------------ Support RPKI Signed Checklists (RSC) -----------------------
+
+/// Sign an RSC for the checklist in the request. The RSC is returned, but
+/// it is not kept by Krill.
+async fn api_ca_rsc(req: Request, ca: CaHandle) -> RoutingResult {
+    match *req.method() {
+        Method::POST => aa!(req, Permission::RSC_SIGN, Handle::from(&ca), {
+            let state = req.state().clone();
+            match req.json().await {
+                Err(e) => render_error(e),
+                Ok(request) => {
+                    render_json_res(state.rsc_sign(ca, request).await)
+                }
+            }
+        }),
+        _ => render_unknown_method(),
+    }
+}
+
 //------------ Support Resource Tagged Attestations (RTA)
 //------------ ----------------------
 
@@ -2843,12 +3587,20 @@ async fn api_ta(req: Request, path: &mut RequestPath) -> RoutingResult {
                         }
                     }
                     None => match *req.method() {
+                        Method::GET => render_json_res(
+                            req.state().ta_proxy_children_show(&child).await,
+                        ),
                         Method::POST => render_error(Error::custom(
                             "update TA child not yet supported",
                         )),
-                        Method::DELETE => render_error(Error::custom(
-                            "remove TA child not yet supported",
-                        )),
+                        Method::DELETE => {
+                            let actor = req.actor();
+                            render_empty_res(
+                                req.state()
+                                    .ta_proxy_children_remove(child, &actor)
+                                    .await,
+                            )
+                        }
                         _ => render_unknown_method(),
                     },
                     _ => render_unknown_method(),
@@ -11,7 +11,7 @@ pub async fn statics(req: Request) -> RoutingResult {
                 hyper::Response::builder()
                     .status(StatusCode::FOUND)
                     .header("location", "/ui")
-                    .body(Either::Left(Empty::new()))
+                    .body(Either::Left(Either::Left(Empty::new())))
                     .unwrap(),
             )),
             "/ui" => Ok(HttpResponse::html(INDEX)),
@@ -10,6 +10,7 @@ use rpki::{
     ca::{
         idexchange,
         idexchange::{CaHandle, ChildHandle, ParentHandle, PublisherHandle},
+        provisioning::ResourceClassName,
     },
     repository::resources::ResourceSet,
     uri,
@@ -22,37 +23,51 @@ use crate::{
             self,
             import::{ExportChild, ImportChild},
             AddChildRequest, AllCertAuthIssues, AspaDefinitionList,
-            AspaDefinitionUpdates, AspaProvidersUpdate, BgpSecCsrInfoList,
+            AspaDefinitionUpdates, AspaProvidersUpdate, AuditCriteria,
+            AuditLog, AuditSource, BgpSecCsrInfoList,
             BgpSecDefinitionUpdates, CaCommandDetails, CaRepoDetails,
             CertAuthInfo, CertAuthInit, CertAuthIssues, CertAuthList,
             CertAuthStats, ChildCaInfo, ChildrenConnectionStats,
-            CommandHistory, CommandHistoryCriteria, ConfiguredRoa,
-            CustomerAsn, IdCertInfo, ParentCaContact, ParentCaReq,
+            CommandDryRun, CommandHistory, CommandHistoryCriteria,
+            ConfiguredRoa, CustomerAsn, ExpiryWindow,
+            GhostbusterDefinitionList, GhostbusterDefinitionUpdates,
+            HistoricCertAuthInfo, IdCertInfo, KeyRollPolicy, NodeRole,
+            ObjectExpiryReport, ParentCaContact, ParentCaReq, PointInTime,
             PublicationServerUris, PublisherDetails, ReceivedCert,
             RepoFileDeleteCriteria, RepositoryContact, RoaConfiguration,
             RoaConfigurationUpdates, RoaPayload, RtaList, RtaName,
             RtaPrepResponse, ServerInfo, Timestamp, UpdateChildRequest,
+            WebhookDefinition, WebhookList, WebhookName,
         },
         bgp::{BgpAnalyser, BgpAnalysisReport, BgpAnalysisSuggestion},
         crypto::KrillSignerBuilder,
         error::Error,
+        eventsourcing::Aggregate,
         KrillEmptyResult, KrillResult,
     },
     constants::*,
     daemon::{
-        auth::{providers::AdminTokenAuthProvider, Authorizer, LoggedInUser},
+        auth::{
+            common::permissions::Permission,
+            providers::AdminTokenAuthProvider, Authorizer, LoggedInUser,
+        },
+        backup::KrillBackup,
         ca::{
-            self, testbed_ca_handle, CaManager, CaStatus,
-            ResourceTaggedAttestation, RtaContentRequest, RtaPrepareRequest,
+            self, testbed_ca_handle, CaManager, CaStatus, GeofeedSignRequest,
+            ResourceTaggedAttestation, RscSignRequest, RtaContentRequest,
+            RtaPrepareRequest, SignedChecklist, SignedGeofeed,
         },
         config::{AuthType, Config},
+        eventstream::EventStream,
+        ha::{HaManager, RoleListener},
         http::{HttpResponse, HyperRequest},
-        mq::{now, Task, TaskQueue},
+        mq::{Task, TaskList, TaskQueue, TaskRunRequest},
         scheduler::Scheduler,
+        webhooks::WebhookManager,
     },
     pubd::{RepoStats, RepositoryManager},
     ta::{
-        ta_handle, TaCertDetails, TrustAnchorSignedRequest,
+        ta_handle, TaCertDetails, TrustAnchorChild, TrustAnchorSignedRequest,
         TrustAnchorSignedResponse, TrustAnchorSignerInfo, TA_NAME,
     },
 };
@@ -86,6 +101,15 @@ pub struct KrillServer {
     // Shared message queue
     mq: Arc<TaskQueue>,
 
+    // Passes on committed events to API subscribers
+    event_stream: Arc<EventStream>,
+
+    // Configured webhooks, notified of CA events and issues
+    webhooks: Arc<WebhookManager>,
+
+    // The role of this node in an active/passive set up
+    ha: Arc<HaManager>,
+
     // Time this server was started
     started: Timestamp,
 
@@ -103,7 +127,10 @@ pub struct KrillServer {
 impl KrillServer {
     /// Creates a new publication server. Note that state is preserved
     /// in the data storage.
-    pub async fn build(config: Arc<Config>) -> KrillResult<Self> {
+    pub async fn build(
+        config: Arc<Config>,
+        ha: Arc<HaManager>,
+    ) -> KrillResult<Self> {
         let service_uri = config.service_uri();
 
         info!("Starting {} v{}", KRILL_SERVER_APP, KRILL_VERSION);
@@ -164,6 +191,17 @@ impl KrillServer {
         // scheduler.
         let mq = Arc::new(TaskQueue::new(&config.storage_uri)?);
 
+        // Committed events for CAs, the Publication Server and the TA Proxy
+        // are passed on to subscribers of this stream.
+        let event_stream = Arc::new(EventStream::default());
+
+        // Webhooks are notified of CA events and issues, deliveries are
+        // planned in the task queue.
+        let webhooks = Arc::new(WebhookManager::create(
+            &config.storage_uri,
+            mq.clone(),
+        )?);
+
         // for now, support that existing embedded repositories are still
         // supported. this should be removed in future after people
         // have had a chance to separate.
@@ -171,6 +209,7 @@ impl KrillServer {
             config.clone(),
             mq.clone(),
             signer.clone(),
+            event_stream.clone(),
         )?);
 
         let ca_manager = Arc::new(
@@ -179,23 +218,23 @@ impl KrillServer {
                 mq.clone(),
                 signer,
                 system_actor.clone(),
+                event_stream.clone(),
+                webhooks.clone(),
             )
             .await?,
         );
 
+        // The CA status is cached only while this node is active, and
+        // reloaded when it becomes active.
+        ha.add_listener(ca_manager.clone());
+        ca_manager.active_changed(ha.is_active())?;
+
         let bgp_analyser = Arc::new(BgpAnalyser::new(
             config.bgp_risdumps_enabled,
             &config.bgp_risdumps_v4_uri,
             &config.bgp_risdumps_v6_uri,
         ));
 
-        // When multi-node set ups with a shared queue are
-        // supported then we can no longer safely reschedule
-        // ALL running tests. See issue: #1112
-        mq.reschedule_tasks_at_startup()?;
-
-        mq.schedule(Task::QueueStartTasks, now())?;
-
         let server = KrillServer {
             service_uri,
             authorizer,
@@ -203,6 +242,9 @@ impl KrillServer {
             ca_manager,
             bgp_analyser,
             mq,
+            event_stream,
+            webhooks,
+            ha,
             started: Timestamp::now(),
             #[cfg(feature = "multi-user")]
             login_session_cache,
@@ -213,7 +255,10 @@ impl KrillServer {
         // Check if we need to do any testbed or benchmarking set up.
         let testbed_handle = testbed_ca_handle();
 
-        if let Some(testbed) = config.testbed() {
+        // Only the active node can set things up.
+        if let Some(testbed) =
+            config.testbed().filter(|_| server.ha.is_active())
+        {
             if server.ca_manager.has_ca(&testbed_handle)? {
                 if config.benchmark.is_some() {
                     info!("Resuming BENCHMARK mode - will NOT recreate CAs. If you wanted this, then wipe the data dir and restart.");
@@ -315,6 +360,8 @@ impl KrillServer {
             self.ca_manager.clone(),
             self.repo_manager.clone(),
             self.bgp_analyser.clone(),
+            self.webhooks.clone(),
+            self.ha.clone(),
             #[cfg(feature = "multi-user")]
             self.login_session_cache.clone(),
             self.config.clone(),
@@ -327,7 +374,27 @@ impl KrillServer {
     }
 
     pub fn server_info(&self) -> ServerInfo {
-        ServerInfo::new(KRILL_VERSION, self.started)
+        ServerInfo::new(
+            KRILL_VERSION,
+            self.started,
+            self.ha.node().cloned(),
+            self.ha.role(),
+        )
+    }
+
+    /// Returns the role of this node in an active/passive set up.
+    pub fn node_role(&self) -> NodeRole {
+        self.ha.role()
+    }
+
+    /// Returns an error if this node is a standby, because then it may
+    /// not make any changes.
+    pub fn verify_active(&self) -> KrillResult<()> {
+        if self.ha.is_active() {
+            Ok(())
+        } else {
+            Err(Error::NodeIsStandby(self.ha.leader()))
+        }
     }
 }
 
@@ -405,6 +472,24 @@ impl KrillServer {
         self.repo_manager.remove_publisher(publisher, actor)
     }
 
+    /// Reports what adding a publisher would do, without adding it.
+    pub fn add_publisher_dry_run(
+        &self,
+        req: idexchange::PublisherRequest,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.repo_manager.create_publisher_dry_run(req, actor)
+    }
+
+    /// Reports what removing a publisher would do, without removing it.
+    pub fn remove_publisher_dry_run(
+        &self,
+        publisher: PublisherHandle,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.repo_manager.remove_publisher_dry_run(publisher, actor)
+    }
+
     /// Removes a publisher, blows up if it didn't exist.
     pub fn delete_matching_files(
         &self,
@@ -524,11 +609,27 @@ impl KrillServer {
                 &ta_handle().convert(),
                 child_request,
                 &self.config.service_uri(),
+                None,
                 actor,
             )
             .await
     }
 
+    pub async fn ta_proxy_children_show(
+        &self,
+        child: &ChildHandle,
+    ) -> KrillResult<TrustAnchorChild> {
+        self.ca_manager.ta_proxy_children_show(child).await
+    }
+
+    pub async fn ta_proxy_children_remove(
+        &self,
+        child: ChildHandle,
+        actor: &Actor,
+    ) -> KrillResult<()> {
+        self.ca_manager.ta_proxy_children_remove(child, actor).await
+    }
+
     pub async fn ta_cert_details(&self) -> KrillResult<TaCertDetails> {
         let proxy = self.ca_manager.get_trust_anchor_proxy().await?;
         Ok(proxy.get_ta_details()?.clone())
@@ -550,10 +651,24 @@ impl KrillServer {
         &self,
         ca: &CaHandle,
         req: AddChildRequest,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillResult<idexchange::ParentResponse> {
         self.ca_manager
-            .ca_add_child(ca, req, &self.service_uri, actor)
+            .ca_add_child(ca, req, &self.service_uri, version, actor)
+            .await
+    }
+
+    /// Reports what adding a child would do, without adding it.
+    pub async fn ca_add_child_dry_run(
+        &self,
+        ca: &CaHandle,
+        req: AddChildRequest,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_manager
+            .ca_add_child_dry_run(ca, req, version, actor)
             .await
     }
 
@@ -585,9 +700,26 @@ impl KrillServer {
         ca: &CaHandle,
         child: ChildHandle,
         req: UpdateChildRequest,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillEmptyResult {
-        self.ca_manager.ca_child_update(ca, child, req, actor).await
+        self.ca_manager
+            .ca_child_update(ca, child, req, version, actor)
+            .await
+    }
+
+    /// Reports what updating a child would do, without updating it.
+    pub async fn ca_child_update_dry_run(
+        &self,
+        ca: &CaHandle,
+        child: ChildHandle,
+        req: UpdateChildRequest,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_manager
+            .ca_child_update_dry_run(ca, child, req, version, actor)
+            .await
     }
 
     /// Update IdCert or resources of a child.
@@ -595,12 +727,28 @@ impl KrillServer {
         &self,
         ca: &CaHandle,
         child: ChildHandle,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillEmptyResult {
-        self.ca_manager.ca_child_remove(ca, child, actor).await?;
+        self.ca_manager
+            .ca_child_remove(ca, child, version, actor)
+            .await?;
         Ok(())
     }
 
+    /// Reports what removing a child would do, without removing it.
+    pub async fn ca_child_remove_dry_run(
+        &self,
+        ca: &CaHandle,
+        child: ChildHandle,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_manager
+            .ca_child_remove_dry_run(ca, child, version, actor)
+            .await
+    }
+
     /// Show details for a child under the CA.
     pub async fn ca_child_show(
         &self,
@@ -692,6 +840,17 @@ impl KrillServer {
             .await
     }
 
+    pub async fn ca_parent_remove_dry_run(
+        &self,
+        handle: CaHandle,
+        parent: ParentHandle,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_manager
+            .ca_parent_remove_dry_run(handle, parent, actor)
+            .await
+    }
+
     pub async fn ca_parent_revoke(
         &self,
         handle: &CaHandle,
@@ -701,6 +860,78 @@ impl KrillServer {
     }
 }
 
+/// # Event stream
+impl KrillServer {
+    pub fn event_stream(&self) -> &EventStream {
+        &self.event_stream
+    }
+}
+
+/// # Webhooks
+impl KrillServer {
+    pub fn webhooks_list(&self) -> KrillResult<WebhookList> {
+        self.webhooks.list()
+    }
+
+    pub fn webhook_add(
+        &self,
+        definition: WebhookDefinition,
+    ) -> KrillResult<()> {
+        self.webhooks.add(definition)
+    }
+
+    pub fn webhook_remove(&self, name: &WebhookName) -> KrillResult<()> {
+        self.webhooks.remove(name)
+    }
+
+    pub fn webhook_test(&self, name: &WebhookName) -> KrillResult<()> {
+        self.webhooks.test(name)
+    }
+}
+
+/// # Backup
+impl KrillServer {
+    pub fn backup(&self) -> KrillResult<KrillBackup> {
+        KrillBackup::create(&self.config.storage_uri)
+    }
+}
+
+/// # Task Queue
+impl KrillServer {
+    pub fn task_list(&self) -> KrillResult<TaskList> {
+        self.mq.list()
+    }
+
+    /// Schedules a task to run now. Tasks for a CA are only accepted if
+    /// the CA, and parent if applicable, exist.
+    pub async fn task_run(&self, request: TaskRunRequest) -> KrillResult<()> {
+        let task = request.into_task()?;
+        match &task {
+            Task::SyncParent {
+                ca_handle, parent, ..
+            } => {
+                let ca = self.ca_manager.get_ca(ca_handle).await?;
+                if !ca.parent_known(parent) {
+                    return Err(Error::CaParentUnknown(
+                        ca_handle.clone(),
+                        parent.clone(),
+                    ));
+                }
+            }
+            Task::SyncRepo { ca_handle, .. }
+            | Task::SuspendChildrenIfNeeded { ca_handle }
+            | Task::KeyRollInitIfNeeded { ca_handle }
+            | Task::KeyRollActivateIfNeeded { ca_handle } => {
+                if ca_handle != &ta_handle() {
+                    self.ca_manager.get_ca(ca_handle).await?;
+                }
+            }
+            _ => {}
+        }
+        self.mq.run_now(task)
+    }
+}
+
 /// # Stats and status of CAS
 impl KrillServer {
     pub async fn cas_stats(
@@ -725,12 +956,19 @@ impl KrillServer {
                         .await
                 };
 
+                let key_ages = ca
+                    .current_key_ages()
+                    .into_iter()
+                    .map(|(rcn, age)| (rcn, age.num_seconds()))
+                    .collect();
+
                 res.insert(
                     ca.handle().clone(),
                     CertAuthStats::new(
                         roa_count,
                         child_count,
                         bgp_report.into(),
+                        key_ages,
                     ),
                 );
             }
@@ -923,6 +1161,7 @@ impl KrillServer {
                         &parent.convert(),
                         child_req,
                         &service_uri,
+                        None,
                         &actor,
                     )
                     .await?
@@ -967,7 +1206,7 @@ impl KrillServer {
         // Add ROA definitions
         let roa_updates = RoaConfigurationUpdates::new(roas, vec![]);
         ca_manager
-            .ca_routes_update(ca_handle, roa_updates, &actor)
+            .ca_routes_update(ca_handle, roa_updates, None, &actor)
             .await?;
 
         Ok(())
@@ -988,6 +1227,21 @@ impl KrillServer {
         Ok(all_issues)
     }
 
+    /// Returns the objects which expire within the given window, or all
+    /// objects if no window is given, for the CAs (and TA) that the actor
+    /// is permitted to see.
+    pub async fn object_expiry_report(
+        &self,
+        within: Option<ExpiryWindow>,
+        actor: &Actor,
+    ) -> KrillResult<ObjectExpiryReport> {
+        let mut report = self.ca_manager.object_expiry_report(actor).await?;
+        if let Some(window) = within {
+            report.retain_within(window);
+        }
+        Ok(report)
+    }
+
     pub async fn ca_issues(
         &self,
         ca: &CaHandle,
@@ -1057,12 +1311,42 @@ impl KrillServer {
         self.ca_manager.ca_list(actor)
     }
 
+    /// Returns the current version of a CA, for use as an ETag.
+    pub async fn ca_version(&self, ca: &CaHandle) -> KrillResult<u64> {
+        self.ca_manager.get_ca(ca).await.map(|ca| ca.version())
+    }
+
     /// Returns the public CA info for a CA, or NONE if the CA cannot be
     /// found.
     pub async fn ca_info(&self, ca: &CaHandle) -> KrillResult<CertAuthInfo> {
         self.ca_manager.get_ca(ca).await.map(|ca| ca.as_ca_info())
     }
 
+    /// Returns the CA info, ROAs, ASPAs and children for a CA as they were
+    /// at the given point in its history.
+    pub async fn ca_info_at(
+        &self,
+        ca: &CaHandle,
+        point: PointInTime,
+    ) -> KrillResult<HistoricCertAuthInfo> {
+        let (ca, time) = self.ca_manager.get_ca_at(ca, point).await?;
+
+        let mut children = HashMap::new();
+        for child in ca.children() {
+            let details = ca.get_child(child)?;
+            children.insert(child.clone(), details.clone().into());
+        }
+
+        Ok(HistoricCertAuthInfo::new(
+            ca.version(),
+            time.into(),
+            ca.as_ca_info(),
+            ca.configured_roas(),
+            ca.aspas_definitions_show(),
+            children,
+        ))
+    }
+
     /// Returns the CA status, or an error if none can be found.
     pub async fn ca_status(&self, ca: &CaHandle) -> KrillResult<CaStatus> {
         self.ca_manager.get_ca_status(ca).await
@@ -1082,6 +1366,14 @@ impl KrillServer {
             .await
     }
 
+    /// Reports what deleting a CA would do, without deleting it.
+    pub async fn ca_delete_dry_run(
+        &self,
+        ca: &CaHandle,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_manager.delete_ca_dry_run(ca).await
+    }
+
     /// Returns the parent contact for a CA and parent, or NONE if either the
     /// CA or the parent cannot be found.
     pub async fn ca_my_parent_contact(
@@ -1110,6 +1402,54 @@ impl KrillServer {
         self.ca_manager.ca_command_details(ca, version)
     }
 
+    pub async fn ca_command_revert(
+        &self,
+        ca: &CaHandle,
+        reverted: u64,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager
+            .ca_command_revert(ca, reverted, version, actor)
+            .await
+    }
+
+    pub async fn ca_command_revert_dry_run(
+        &self,
+        ca: &CaHandle,
+        reverted: u64,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_manager
+            .ca_command_revert_dry_run(ca, reverted, version, actor)
+            .await
+    }
+
+    /// Returns the commands for all CAs, the publication server and the TA
+    /// proxy that the actor may see, in chronological order.
+    pub fn audit_log(
+        &self,
+        crit: &AuditCriteria,
+        actor: &Actor,
+    ) -> KrillResult<AuditLog> {
+        let mut log = AuditLog::default();
+        self.ca_manager.add_audit_records(&mut log, crit, actor)?;
+
+        if matches!(
+            actor.is_allowed(Permission::PUB_ADMIN, NO_RESOURCE),
+            Ok(true)
+        ) {
+            log.add(
+                AuditSource::Pubd,
+                self.repo_manager.audit_records(crit)?,
+            );
+        }
+
+        log.sort();
+        Ok(log)
+    }
+
     /// Returns the publisher request for a CA, or NONE of the CA cannot be
     /// found.
     pub async fn ca_publisher_req(
@@ -1179,6 +1519,26 @@ impl KrillServer {
             .await
     }
 
+    pub async fn ca_keyroll_emergency(
+        &self,
+        ca: CaHandle,
+        rcn: Option<ResourceClassName>,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager.ca_keyroll_emergency(ca, rcn, actor).await
+    }
+
+    pub async fn ca_keyroll_policy_update(
+        &self,
+        ca: CaHandle,
+        policy: Option<KeyRollPolicy>,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager
+            .ca_keyroll_policy_update(ca, policy, actor)
+            .await
+    }
+
     pub async fn rfc6492(
         &self,
         ca: CaHandle,
@@ -1205,10 +1565,23 @@ impl KrillServer {
         &self,
         ca: CaHandle,
         updates: AspaDefinitionUpdates,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillEmptyResult {
         self.ca_manager
-            .ca_aspas_definitions_update(ca, updates, actor)
+            .ca_aspas_definitions_update(ca, updates, version, actor)
+            .await
+    }
+
+    pub async fn ca_aspas_definitions_update_dry_run(
+        &self,
+        ca: CaHandle,
+        updates: AspaDefinitionUpdates,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_manager
+            .ca_aspas_definitions_update_dry_run(ca, updates, version, actor)
             .await
     }
 
@@ -1217,10 +1590,26 @@ impl KrillServer {
         ca: CaHandle,
         customer: CustomerAsn,
         update: AspaProvidersUpdate,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillEmptyResult {
         self.ca_manager
-            .ca_aspas_update_aspa(ca, customer, update, actor)
+            .ca_aspas_update_aspa(ca, customer, update, version, actor)
+            .await
+    }
+
+    pub async fn ca_aspas_update_aspa_dry_run(
+        &self,
+        ca: CaHandle,
+        customer: CustomerAsn,
+        update: AspaProvidersUpdate,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_manager
+            .ca_aspas_update_aspa_dry_run(
+                ca, customer, update, version, actor,
+            )
             .await
     }
 }
@@ -1238,10 +1627,44 @@ impl KrillServer {
         &self,
         ca: CaHandle,
         updates: BgpSecDefinitionUpdates,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillResult<()> {
         self.ca_manager
-            .ca_bgpsec_definitions_update(ca, updates, actor)
+            .ca_bgpsec_definitions_update(ca, updates, version, actor)
+            .await
+    }
+
+    pub async fn ca_bgpsec_definitions_update_dry_run(
+        &self,
+        ca: CaHandle,
+        updates: BgpSecDefinitionUpdates,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_manager
+            .ca_bgpsec_definitions_update_dry_run(ca, updates, version, actor)
+            .await
+    }
+}
+
+/// # Handle Ghostbuster requests
+impl KrillServer {
+    pub async fn ca_ghostbusters_show(
+        &self,
+        ca: CaHandle,
+    ) -> KrillResult<GhostbusterDefinitionList> {
+        self.ca_manager.ca_ghostbusters_show(ca).await
+    }
+
+    pub async fn ca_ghostbusters_update(
+        &self,
+        ca: CaHandle,
+        updates: GhostbusterDefinitionUpdates,
+        actor: &Actor,
+    ) -> KrillEmptyResult {
+        self.ca_manager
+            .ca_ghostbusters_update(ca, updates, actor)
             .await
     }
 }
@@ -1252,9 +1675,24 @@ impl KrillServer {
         &self,
         ca: CaHandle,
         updates: RoaConfigurationUpdates,
+        version: Option<u64>,
         actor: &Actor,
     ) -> KrillEmptyResult {
-        self.ca_manager.ca_routes_update(ca, updates, actor).await
+        self.ca_manager
+            .ca_routes_update(ca, updates, version, actor)
+            .await
+    }
+
+    pub async fn ca_routes_update_dry_run(
+        &self,
+        ca: CaHandle,
+        updates: RoaConfigurationUpdates,
+        version: Option<u64>,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.ca_manager
+            .ca_routes_update_dry_run(ca, updates, version, actor)
+            .await
     }
 
     pub async fn ca_routes_show(
@@ -1379,6 +1817,24 @@ impl KrillServer {
         self.ca_manager.rta_sign(ca, name, request, actor).await
     }
 
+    /// Sign an RPKI Signed Checklist
+    pub async fn rsc_sign(
+        &self,
+        ca: CaHandle,
+        request: RscSignRequest,
+    ) -> KrillResult<SignedChecklist> {
+        self.ca_manager.rsc_sign(&ca, request).await
+    }
+
+    /// Sign a geofeed
+    pub async fn geofeed_sign(
+        &self,
+        ca: CaHandle,
+        request: GeofeedSignRequest,
+    ) -> KrillResult<SignedGeofeed> {
+        self.ca_manager.geofeed_sign(&ca, request).await
+    }
+
     /// Prepare a multi
     pub async fn rta_multi_prep(
         &self,
@@ -1,8 +1,13 @@
 pub mod auth;
+pub mod backup;
 pub mod ca;
 pub mod config;
+pub mod encryption;
+pub mod eventstream;
+pub mod ha;
 pub mod http;
 pub mod krillserver;
 pub mod mq;
 pub mod properties;
 pub mod scheduler;
+pub mod webhooks;
@@ -3,14 +3,17 @@
 //! signed material, or asking a newly added parent for resource
 //! entitlements.
 
-use std::{fmt, str::FromStr};
+use std::{
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::RwLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use url::Url;
 
-use kvx::{
-    queue::{Queue, RunningTask, ScheduleMode},
-    segment, Segment, SegmentBuf,
-};
+use kvx::KeyValueStoreBackend;
 
 use rpki::{
     ca::{
@@ -21,9 +24,15 @@ use rpki::{
 };
 
 use crate::{
-    commons::api::Timestamp,
+    commons::api::{Timestamp, WebhookName, WebhookNotification},
     commons::eventsourcing,
-    commons::{eventsourcing::Aggregate, Error, KrillResult},
+    commons::{
+        eventsourcing::{
+            segment, Aggregate, Key, KeyValueStore, Scope, Segment,
+            SegmentBuf,
+        },
+        Error, KrillResult,
+    },
     constants::TASK_QUEUE_NS,
     daemon::ca::{CertAuth, CertAuthEvent},
     ta::{ta_handle, TrustAnchorProxy, TrustAnchorProxyEvent},
@@ -84,6 +93,18 @@ pub enum Task {
         ca_handle: CaHandle,
     },
 
+    // Initiates a key roll for the CA if this is due according to its
+    // key roll policy, and plans the next check.
+    KeyRollInitIfNeeded {
+        ca_handle: CaHandle,
+    },
+
+    // Activates new keys for the CA if they have been staged for the
+    // period required by its key roll policy.
+    KeyRollActivateIfNeeded {
+        ca_handle: CaHandle,
+    },
+
     RenewTestbedTa,
 
     RepublishIfNeeded,
@@ -93,8 +114,18 @@ pub enum Task {
 
     UpdateSnapshots,
 
+    ArchiveCommands,
+
     RrdpUpdateIfNeeded,
 
+    // Delivers a notification to a webhook. Failed deliveries are retried
+    // as a follow-up task with an increased attempt count.
+    DeliverWebhook {
+        webhook: WebhookName,
+        notification: WebhookNotification,
+        attempt: u32,
+    },
+
     #[cfg(feature = "multi-user")]
     SweepLoginCache,
 }
@@ -119,6 +150,18 @@ impl Task {
                     ca
                 ))
             }
+            Task::KeyRollInitIfNeeded { ca_handle: ca } => {
+                SegmentBuf::from_str(&format!(
+                    "keyroll_init_if_needed_{}",
+                    ca
+                ))
+            }
+            Task::KeyRollActivateIfNeeded { ca_handle: ca } => {
+                SegmentBuf::from_str(&format!(
+                    "keyroll_activate_if_needed_{}",
+                    ca
+                ))
+            }
             Task::RepublishIfNeeded => {
                 Ok(segment!("all_cas_republish_if_needed").to_owned())
             }
@@ -151,9 +194,20 @@ impl Task {
             Task::UpdateSnapshots => {
                 Ok(segment!("update_stored_snapshots").to_owned())
             }
+            Task::ArchiveCommands => {
+                Ok(segment!("archive_commands").to_owned())
+            }
             Task::RrdpUpdateIfNeeded => {
                 Ok(segment!("update_rrdp_if_needed").to_owned())
             }
+            Task::DeliverWebhook {
+                webhook,
+                notification,
+                ..
+            } => SegmentBuf::from_str(&format!(
+                "deliver_webhook_{}_{}",
+                webhook, notification.id
+            )),
             #[cfg(feature = "multi-user")]
             Task::SweepLoginCache => {
                 Ok(segment!("sweep_login_cache").to_owned())
@@ -193,6 +247,12 @@ impl fmt::Display for Task {
             Task::SuspendChildrenIfNeeded { ca_handle: ca } => {
                 write!(f, "verify if CA '{}' has children to suspend", ca)
             }
+            Task::KeyRollInitIfNeeded { ca_handle: ca } => {
+                write!(f, "verify if CA '{}' should roll its keys", ca)
+            }
+            Task::KeyRollActivateIfNeeded { ca_handle: ca } => {
+                write!(f, "verify if CA '{}' should activate new keys", ca)
+            }
             Task::RepublishIfNeeded => {
                 write!(f, "let CAs republish their mft/crls if needed")
             }
@@ -205,9 +265,23 @@ impl fmt::Display for Task {
             Task::UpdateSnapshots => {
                 write!(f, "update repository content snapshot on disk")
             }
+            Task::ArchiveCommands => {
+                write!(f, "archive old commands of CAs")
+            }
             Task::RrdpUpdateIfNeeded => {
                 write!(f, "create new RRDP delta, if needed")
             }
+            Task::DeliverWebhook {
+                webhook,
+                notification,
+                ..
+            } => {
+                write!(
+                    f,
+                    "deliver '{}' notification to webhook '{}'",
+                    notification.event, webhook
+                )
+            }
 
             #[cfg(feature = "multi-user")]
             Task::SweepLoginCache => write!(f, "sweep up expired logins"),
@@ -231,26 +305,423 @@ pub enum TaskResult {
     Done,                     // finished, nothing more to do
     FollowUp(Task, Priority), // finished, follow-up should be scheduled
     Reschedule(Priority),     // not finished, should be rescheduled
+    Handover, // continues in the background, which finishes or reschedules
+}
+
+//------------ TaskList -----------------------------------------------------
+
+/// The pending and running tasks in the queue, and the outcome of the last
+/// run of each task that was processed by this server since it started.
+///
+/// The last runs are kept in memory only, by the node that ran the tasks.
+/// They are empty after a restart, and on a standby node, which does not run
+/// tasks. The time since which they were recorded is included.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaskList {
+    tasks: Vec<TaskInfo>,
+    last_runs: Vec<TaskRun>,
+    last_runs_since: Timestamp,
+}
+
+impl TaskList {
+    pub fn tasks(&self) -> &Vec<TaskInfo> {
+        &self.tasks
+    }
+
+    pub fn last_runs(&self) -> &Vec<TaskRun> {
+        &self.last_runs
+    }
+
+    pub fn last_runs_since(&self) -> Timestamp {
+        self.last_runs_since
+    }
+}
+
+impl fmt::Display for TaskList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.tasks.is_empty() {
+            writeln!(f, "no tasks in the queue")?;
+        } else {
+            writeln!(f, "Tasks:")?;
+            for task in &self.tasks {
+                writeln!(
+                    f,
+                    "  {} {:<7} {} ({})",
+                    task.due, task.state, task.key, task.description
+                )?;
+            }
+        }
+
+        writeln!(f)?;
+        if self.last_runs.is_empty() {
+            writeln!(
+                f,
+                "no runs on this server since {}",
+                self.last_runs_since.to_rfc3339()
+            )?;
+        } else {
+            writeln!(
+                f,
+                "Last runs on this server since {}:",
+                self.last_runs_since.to_rfc3339()
+            )?;
+            for run in &self.last_runs {
+                writeln!(
+                    f,
+                    "  {} {} {}",
+                    run.time.to_rfc3339(),
+                    run.key,
+                    run.outcome
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A task in the queue. The priority of a task is the time it is due, the
+/// task that is due soonest is picked up first. For running tasks this is
+/// the time they were picked up.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaskInfo {
+    key: String,
+    state: TaskState,
+    priority: Priority,
+    due: String,
+    description: String,
+    task: Task,
+}
+
+impl TaskInfo {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn state(&self) -> TaskState {
+        self.state
+    }
+
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    pub fn task(&self) -> &Task {
+        &self.task
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Pending,
+    Running,
+}
+
+impl fmt::Display for TaskState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaskState::Pending => write!(f, "pending"),
+            TaskState::Running => write!(f, "running"),
+        }
+    }
+}
+
+/// The outcome of the last run of a task, by task key.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaskRun {
+    key: String,
+    time: Timestamp,
+    outcome: TaskOutcome,
+}
+
+impl TaskRun {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn time(&self) -> Timestamp {
+        self.time
+    }
+
+    pub fn outcome(&self) -> &TaskOutcome {
+        &self.outcome
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TaskOutcome {
+    Done,
+    FollowUp { task: String, due: Timestamp },
+    Rescheduled { due: Timestamp },
+}
+
+impl TaskOutcome {
+    /// Returns the outcome of a task, unless it continues in the
+    /// background.
+    fn for_result(result: &TaskResult) -> Option<Self> {
+        match result {
+            TaskResult::Done => Some(TaskOutcome::Done),
+            TaskResult::FollowUp(task, priority) => {
+                Some(TaskOutcome::FollowUp {
+                    task: task
+                        .name()
+                        .map(|name| name.to_string())
+                        .unwrap_or_else(|_| task.to_string()),
+                    due: priority.into(),
+                })
+            }
+            TaskResult::Reschedule(priority) => {
+                Some(TaskOutcome::Rescheduled {
+                    due: priority.into(),
+                })
+            }
+            TaskResult::Handover => None,
+        }
+    }
+}
+
+impl fmt::Display for TaskOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TaskOutcome::Done => write!(f, "done"),
+            TaskOutcome::FollowUp { task, due } => {
+                write!(f, "done, follow-up {} due {}", task, due.to_rfc3339())
+            }
+            TaskOutcome::Rescheduled { due } => {
+                write!(f, "rescheduled, due {}", due.to_rfc3339())
+            }
+        }
+    }
+}
+
+//------------ TaskRunRequest -----------------------------------------------
+
+/// A request to run a task now. Only the name of the task, and the CA and
+/// parent it is for, are given. The task itself is created by the server,
+/// so that clients cannot set any other values, such as the CA version.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaskRunRequest {
+    task: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ca: Option<CaHandle>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent: Option<ParentHandle>,
+}
+
+impl TaskRunRequest {
+    /// The names of the tasks that can be run this way. Tasks that Krill
+    /// only creates itself in reaction to events, or at startup, cannot.
+    pub const RUNNABLE_TASKS: &'static [&'static str] = &[
+        "sync_repo",
+        "sync_parent",
+        "suspend_children_if_needed",
+        "key_roll_init_if_needed",
+        "key_roll_activate_if_needed",
+        "sync_trust_anchor_proxy_signer_if_possible",
+        "renew_testbed_ta",
+        "republish_if_needed",
+        "renew_objects_if_needed",
+        "refresh_announcements_info",
+        "update_snapshots",
+        "archive_commands",
+        "rrdp_update_if_needed",
+    ];
+
+    pub fn new(
+        task: String,
+        ca: Option<CaHandle>,
+        parent: Option<ParentHandle>,
+    ) -> Self {
+        TaskRunRequest { task, ca, parent }
+    }
+
+    pub fn task(&self) -> &str {
+        &self.task
+    }
+
+    pub fn ca(&self) -> Option<&CaHandle> {
+        self.ca.as_ref()
+    }
+
+    pub fn parent(&self) -> Option<&ParentHandle> {
+        self.parent.as_ref()
+    }
+
+    /// Creates the task to run. Tasks that depend on the CA version are
+    /// created with version 0, so that they run against the current CA.
+    pub fn into_task(self) -> KrillResult<Task> {
+        let task = match self.task.as_str() {
+            "sync_repo" => Task::SyncRepo {
+                ca_handle: self.ca_for_task()?,
+                ca_version: 0,
+            },
+            "sync_parent" => Task::SyncParent {
+                ca_handle: self.ca_for_task()?,
+                ca_version: 0,
+                parent: self.parent.clone().ok_or_else(|| {
+                    Error::TaskRunInvalid(
+                        "task 'sync_parent' needs a parent".to_string(),
+                    )
+                })?,
+            },
+            "suspend_children_if_needed" => Task::SuspendChildrenIfNeeded {
+                ca_handle: self.ca_for_task()?,
+            },
+            "key_roll_init_if_needed" => Task::KeyRollInitIfNeeded {
+                ca_handle: self.ca_for_task()?,
+            },
+            "key_roll_activate_if_needed" => Task::KeyRollActivateIfNeeded {
+                ca_handle: self.ca_for_task()?,
+            },
+            "sync_trust_anchor_proxy_signer_if_possible" => {
+                Task::SyncTrustAnchorProxySignerIfPossible
+            }
+            "renew_testbed_ta" => Task::RenewTestbedTa,
+            "republish_if_needed" => Task::RepublishIfNeeded,
+            "renew_objects_if_needed" => Task::RenewObjectsIfNeeded,
+            "refresh_announcements_info" => Task::RefreshAnnouncementsInfo,
+            "update_snapshots" => Task::UpdateSnapshots,
+            "archive_commands" => Task::ArchiveCommands,
+            "rrdp_update_if_needed" => Task::RrdpUpdateIfNeeded,
+            _ => return Err(Error::TaskNotRunnable(self.task)),
+        };
+        Ok(task)
+    }
+
+    fn ca_for_task(&self) -> KrillResult<CaHandle> {
+        self.ca.clone().ok_or_else(|| {
+            Error::TaskRunInvalid(format!("task '{}' needs a CA", self.task))
+        })
+    }
+}
+
+//------------ RunningTask --------------------------------------------------
+
+/// A task that was claimed from the queue to be run.
+#[derive(Clone, Debug)]
+pub struct RunningTask {
+    pub name: SegmentBuf,
+    pub timestamp_millis: u128,
+    pub value: serde_json::Value,
+}
+
+impl RunningTask {
+    /// Returns the key of this task in the queue, so that it can be
+    /// finished or rescheduled.
+    pub fn key(&self) -> Key {
+        TaskQueue::task_key(RUNNING, self.timestamp_millis, &self.name)
+    }
+}
+
+//------------ ScheduleMode -------------------------------------------------
+
+/// Defines what happens when a task by the same name already exists.
+#[derive(Clone, Copy, Debug)]
+enum ScheduleMode {
+    /// Replace an existing pending task, using the soonest of the two
+    /// priorities. Do not finish an existing running task.
+    ReplaceExistingSoonest,
+
+    /// Replace an existing pending task, using the soonest of the two
+    /// priorities, and finish an existing running task.
+    FinishOrReplaceExistingSoonest,
+
+    /// Keep an existing pending or running task, and in that case do not
+    /// add the new task.
+    IfMissing,
 }
 
 //------------ TaskQueue ----------------------------------------------------
 
+/// The scope of the tasks that are waiting to be run.
+const PENDING: &Segment = segment!("pending");
+
+/// The scope of the tasks that are being run.
+const RUNNING: &Segment = segment!("running");
+
+/// The queue of tasks, kept in its own namespace.
+///
+/// Tasks are kept under the "pending" and "running" scopes, with the time
+/// they are due, or were claimed, in milliseconds and their name as the key,
+/// e.g. "pending/1700000000000-sync_repo_CA". This is the same layout that
+/// the queue of kvx uses, so that existing queues can still be read. All
+/// changes are made under the lock for the global scope.
 #[derive(Debug)]
 pub struct TaskQueue {
-    q: kvx::KeyValueStore,
+    q: KeyValueStore,
+
+    // The outcome of the last run of each task, by task key. This is kept
+    // in memory only, so it covers the tasks run by this node since it
+    // was started.
+    last_runs: RwLock<HashMap<String, TaskRun>>,
+    last_runs_since: Timestamp,
 }
 
 impl TaskQueue {
     pub fn new(storage_uri: &Url) -> KrillResult<Self> {
-        kvx::KeyValueStore::new(storage_uri, TASK_QUEUE_NS)
-            .map(|q| TaskQueue { q })
-            .map_err(Error::from)
+        Ok(TaskQueue {
+            q: KeyValueStore::create(storage_uri, TASK_QUEUE_NS)?,
+            last_runs: RwLock::new(HashMap::new()),
+            last_runs_since: Timestamp::now(),
+        })
+    }
+
+    /// Returns the key for a task in the given scope.
+    fn task_key(scope: &Segment, millis: u128, name: &Segment) -> Key {
+        // The name is a segment, and the timestamp only has digits, so
+        // this is always a valid segment.
+        let name = Segment::parse(&format!("{}-{}", millis, name))
+            .unwrap()
+            .to_owned();
+        Key::new_scoped(Scope::from_segment(scope), name)
+    }
+
+    /// Returns the timestamp and the task name from the key of a pending
+    /// or running task.
+    fn parse_key(key: &Key) -> Option<(u128, SegmentBuf)> {
+        let (millis, name) = key.name().as_str().split_once('-')?;
+        Some((millis.parse().ok()?, Segment::parse(name).ok()?.to_owned()))
+    }
+
+    /// Returns the pending or running tasks, with their parsed keys.
+    fn tasks_in(
+        kv: &dyn KeyValueStoreBackend,
+        scope: &Segment,
+    ) -> Result<Vec<(Key, u128, SegmentBuf)>, kvx::Error> {
+        Ok(kv
+            .list_keys(&Scope::from_segment(scope))?
+            .into_iter()
+            .filter_map(|key| {
+                let (millis, name) = Self::parse_key(&key)?;
+                Some((key, millis, name))
+            })
+            .collect())
+    }
+
+    /// Returns the current time in milliseconds. Priorities only have a
+    /// granularity of seconds, which is too coarse for running tasks.
+    fn now_millis() -> u128 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis())
+            .unwrap_or_default()
+    }
+
+    fn execute<T>(
+        &self,
+        op: impl FnMut(&dyn KeyValueStoreBackend) -> Result<T, kvx::Error>,
+    ) -> KrillResult<T> {
+        self.q.execute(&Scope::global(), op).map_err(Error::from)
     }
 }
+
 impl TaskQueue {
     pub fn pop(&self) -> Option<RunningTask> {
         trace!("Try to get a task off the queue");
-        match self.q.claim_scheduled_pending_task() {
+        match self.claim_scheduled_pending_task() {
             Err(e) => {
                 // Log error and return nothing.
                 // We do this, because the key value store - if in future
@@ -275,6 +746,44 @@ impl TaskQueue {
         }
     }
 
+    /// Claims the pending task that is due first, if any is due, and moves
+    /// it to the running tasks.
+    fn claim_scheduled_pending_task(
+        &self,
+    ) -> KrillResult<Option<RunningTask>> {
+        self.execute(|kv| {
+            let tasks_before = Self::now_millis();
+
+            let due = Self::tasks_in(kv, PENDING)?
+                .into_iter()
+                .filter(|(_, millis, _)| *millis <= tasks_before)
+                .min_by_key(|(_, millis, _)| *millis);
+            let (pending_key, _, name) = match due {
+                Some(due) => due,
+                None => return Ok(None),
+            };
+            let value = match kv.get(&pending_key)? {
+                Some(value) => value,
+                None => return Ok(None),
+            };
+
+            let mut running = RunningTask {
+                name,
+                timestamp_millis: tasks_before,
+                value,
+            };
+            if kv.has(&running.key())? {
+                // It's not pretty to sleep blocking, even if it's for 1 ms,
+                // but if we don't then we get a name collision with an
+                // existing running task.
+                std::thread::sleep(std::time::Duration::from_millis(1));
+                running.timestamp_millis = Self::now_millis();
+            }
+            kv.move_value(&pending_key, &running.key())?;
+            Ok(Some(running))
+        })
+    }
+
     /// Schedules a task for the given priority. If the equivalent task
     /// was already present, then it will get the highest of the two
     /// priorities.
@@ -335,42 +844,114 @@ impl TaskQueue {
             ))
         })?;
 
-        self.q
-            .schedule_task(task_name, json, Some(priority.to_millis()), mode)
-            .map_err(Error::from)
+        self.execute(|kv| {
+            let running = Self::tasks_in(kv, RUNNING)?
+                .into_iter()
+                .find(|(_, _, name)| name == &task_name);
+            let pending = Self::tasks_in(kv, PENDING)?
+                .into_iter()
+                .find(|(_, _, name)| name == &task_name);
+
+            let mut millis = priority.to_millis();
+            match mode {
+                ScheduleMode::IfMissing => {
+                    if pending.is_some() || running.is_some() {
+                        return Ok(());
+                    }
+                }
+                ScheduleMode::ReplaceExistingSoonest
+                | ScheduleMode::FinishOrReplaceExistingSoonest => {
+                    if let Some((key, existing, _)) = pending {
+                        millis = millis.min(existing);
+                        kv.delete(&key)?;
+                    }
+                    if let ScheduleMode::FinishOrReplaceExistingSoonest = mode
+                    {
+                        if let Some((key, _, _)) = running {
+                            kv.delete(&key)?;
+                        }
+                    }
+                }
+            }
+
+            kv.store(
+                &Self::task_key(PENDING, millis, &task_name),
+                json.clone(),
+            )
+        })
     }
 
     /// Finish a running task, without rescheduling it.
-    pub fn finish(&self, task: &kvx::Key) -> KrillResult<()> {
+    pub fn finish(&self, task: &Key) -> KrillResult<()> {
         debug!("Finish task: {}", task);
-        self.q.finish_running_task(task).map_err(Error::from)
+        let running = self.execute(|kv| {
+            let running = kv.has(task)?;
+            if running {
+                kv.delete(task)?;
+            }
+            Ok(running)
+        })?;
+        Self::check_running(task, running)
     }
 
     /// Reschedule a running task, without finishing it.
     pub fn reschedule(
         &self,
-        task: &kvx::Key,
+        task: &Key,
         priority: Priority,
     ) -> KrillResult<()> {
         debug!("Reschedule task: {} to: {}", task, priority);
-        self.q
-            .reschedule_running_task(task, Some(priority.to_millis()))
-            .map_err(Error::from)
+        self.reschedule_running_task(task, priority.to_millis())
+    }
+
+    fn reschedule_running_task(
+        &self,
+        task: &Key,
+        millis: u128,
+    ) -> KrillResult<()> {
+        let (_, name) = Self::parse_key(task).ok_or_else(|| {
+            Error::Custom(format!("invalid task key: {}", task))
+        })?;
+        let pending = Self::task_key(PENDING, millis, &name);
+        let running = self.execute(|kv| {
+            let running = kv.has(task)?;
+            if running {
+                kv.move_value(task, &pending)?;
+            }
+            Ok(running)
+        })?;
+        Self::check_running(task, running)
+    }
+
+    /// Fails if the task was not running. This is checked after the
+    /// change, because some backends keep the scope locked if the change
+    /// itself fails.
+    fn check_running(task: &Key, running: bool) -> KrillResult<()> {
+        if running {
+            Ok(())
+        } else {
+            Err(Error::Custom(format!(
+                "Cannot finish or reschedule task {}. It is not running.",
+                task
+            )))
+        }
     }
 
     /// Reschedule all running tasks to pending. This assumes that we only
-    /// have a single active node. See issue #1112
+    /// have a single active node, i.e. that this is the only node or the
+    /// leader node. See issue #1112
     pub fn reschedule_tasks_at_startup(&self) -> KrillResult<()> {
-        let keys = self.q.running_tasks_keys()?;
+        let keys =
+            self.execute(|kv| kv.list_keys(&Scope::from_segment(RUNNING)))?;
 
         let queue_started_key_name = Task::QueueStartTasks.name()?;
 
         if keys.len() > 1 {
-            warn!("Rescheduling running tasks at startup, note that multi-node Krill servers are not yet supported.");
+            warn!("Rescheduling running tasks left by a previous run, or a previous leader node.");
             for key in keys {
                 if key.name() != queue_started_key_name.as_ref() {
                     warn!("  - rescheduling: {}", key.name());
-                    self.q.reschedule_running_task(&key, None)?;
+                    self.reschedule_running_task(&key, Self::now_millis())?;
                 }
             }
         }
@@ -379,6 +960,90 @@ impl TaskQueue {
     }
 }
 
+/// Implement introspection and manual triggering of tasks.
+impl TaskQueue {
+    /// Lists the pending and running tasks, ordered by priority, and the
+    /// outcome of the last run of each task.
+    pub fn list(&self) -> KrillResult<TaskList> {
+        let entries = self.execute(|kv| {
+            let mut entries = vec![];
+            for (state, scope) in
+                [(TaskState::Running, RUNNING), (TaskState::Pending, PENDING)]
+            {
+                for key in kv.list_keys(&Scope::from_segment(scope))? {
+                    if let Some(value) = kv.get(&key)? {
+                        entries.push((state, key, value));
+                    }
+                }
+            }
+            Ok(entries)
+        })?;
+
+        let mut tasks = vec![];
+        for (state, key, value) in entries {
+            let (millis, name) = match Self::parse_key(&key) {
+                Some(parsed) => parsed,
+                None => {
+                    warn!("Ignoring task with unexpected key: {}", key);
+                    continue;
+                }
+            };
+            let task: Task = match serde_json::from_value(value) {
+                Ok(task) => task,
+                Err(e) => {
+                    warn!(
+                        "Ignoring task {} that cannot be parsed: {}",
+                        key, e
+                    );
+                    continue;
+                }
+            };
+
+            let priority = Priority::from_timestamp_ms(millis);
+            tasks.push(TaskInfo {
+                key: name.to_string(),
+                state,
+                priority,
+                due: priority.to_string(),
+                description: task.to_string(),
+                task,
+            });
+        }
+        tasks.sort_by_key(|task| task.priority.to_millis());
+
+        let mut last_runs: Vec<TaskRun> =
+            self.last_runs.read().unwrap().values().cloned().collect();
+        last_runs.sort_by(|a, b| a.key.cmp(&b.key));
+
+        Ok(TaskList {
+            tasks,
+            last_runs,
+            last_runs_since: self.last_runs_since,
+        })
+    }
+
+    /// Remembers the outcome of a run of the task with the given key.
+    /// Tasks that continue in the background, i.e. webhook deliveries
+    /// which each have a unique key, are not remembered.
+    pub fn record_run(&self, key: &str, result: &TaskResult) {
+        if let Some(outcome) = TaskOutcome::for_result(result) {
+            let run = TaskRun {
+                key: key.to_string(),
+                time: Timestamp::now(),
+                outcome,
+            };
+            self.last_runs.write().unwrap().insert(run.key.clone(), run);
+        }
+    }
+
+    /// Schedules the given task to run now. If the task was already
+    /// scheduled for later, it is moved forward.
+    pub fn run_now(&self, task: Task) -> KrillResult<()> {
+        info!("Manually triggered task: {}", task);
+        self.schedule(task, now())
+    }
+}
+
 /// Implement listening for CertAuth events.
 impl TaskQueue {
     fn schedule_for_ca_event(
@@ -399,6 +1064,7 @@ impl TaskQueue {
             | CertAuthEvent::AspaObjectsUpdated { .. }
             | CertAuthEvent::ChildCertificatesUpdated { .. }
             | CertAuthEvent::BgpSecCertificatesUpdated { .. }
+            | CertAuthEvent::GhostbusterObjectsUpdated { .. }
             | CertAuthEvent::ChildKeyRevoked { .. }
             | CertAuthEvent::KeyPendingToNew { .. }
             | CertAuthEvent::KeyPendingToActive { .. }
@@ -568,11 +1234,46 @@ impl eventsourcing::PreSaveEventListener<CertAuth> for TaskQueue {
 /// Implement post-save listening for CertAuth events.
 ///
 /// Used for best effort signaling to local child CAs that a sync with
-/// their parent is needed.
+/// their parent is needed, and to plan automatic key roll steps. The
+/// latter are done here, rather than before saving, so that the tasks
+/// will see the updated CA when they are picked up.
 impl eventsourcing::PostSaveEventListener<CertAuth> for TaskQueue {
     fn listen(&self, ca: &CertAuth, events: &[CertAuthEvent]) {
         for event in events {
             match event {
+                CertAuthEvent::KeyRollPolicyUpdated { policy: Some(_) }
+                | CertAuthEvent::KeyRollFinished { .. } => {
+                    if ca.keyroll_policy().is_some() {
+                        if let Err(e) = self.schedule(
+                            Task::KeyRollInitIfNeeded {
+                                ca_handle: ca.handle().clone(),
+                            },
+                            now(),
+                        ) {
+                            error!(
+                                "Could not schedule key roll check for CA {}. Restart Krill to retry. Error was: {}",
+                                ca.handle(),
+                                e
+                            );
+                        }
+                    }
+                }
+                CertAuthEvent::KeyPendingToNew { .. } => {
+                    if ca.keyroll_policy().is_some() {
+                        if let Err(e) = self.schedule(
+                            Task::KeyRollActivateIfNeeded {
+                                ca_handle: ca.handle().clone(),
+                            },
+                            now(),
+                        ) {
+                            error!(
+                                "Could not schedule key activation check for CA {}. Restart Krill to retry. Error was: {}",
+                                ca.handle(),
+                                e
+                            );
+                        }
+                    }
+                }
                 CertAuthEvent::ChildUpdatedResources { child, .. }
                 | CertAuthEvent::ChildKeyRevoked { child, .. } => {
                     debug!("Schedule a sync from the child to this CA as their parent. This will be a no-op for remote children.");
@@ -612,10 +1313,8 @@ impl eventsourcing::PreSaveEventListener<TrustAnchorProxy> for TaskQueue {
         for event in events {
             trace!("Seen TrustAnchorProxy event '{}'", event);
             match event {
-                TrustAnchorProxyEvent::ChildRequestAdded(
-                    _child,
-                    _request,
-                ) => {
+                TrustAnchorProxyEvent::ChildRequestAdded(_, _)
+                | TrustAnchorProxyEvent::ChildRemovalScheduled(_) => {
                     // schedule proxy -> signer sync
                     self.schedule(
                         Task::SyncTrustAnchorProxySignerIfPossible,
@@ -636,7 +1335,8 @@ impl eventsourcing::PreSaveEventListener<TrustAnchorProxy> for TaskQueue {
                 | TrustAnchorProxyEvent::SignerAdded(_)
                 | TrustAnchorProxyEvent::SignerRequestMade(_)
                 | TrustAnchorProxyEvent::ChildAdded(_)
-                | TrustAnchorProxyEvent::ChildResponseGiven(_, _) => {
+                | TrustAnchorProxyEvent::ChildResponseGiven(_, _)
+                | TrustAnchorProxyEvent::ChildRemoved(_) => {
                     // No triggered actions needed
                 }
             }
@@ -766,3 +1466,78 @@ impl From<Time> for Priority {
         Priority(time.timestamp())
     }
 }
+
+//------------ Tests ---------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::test;
+
+    fn sync_repo(ca: &str) -> Task {
+        Task::SyncRepo {
+            ca_handle: CaHandle::from_str(ca).unwrap(),
+            ca_version: 0,
+        }
+    }
+
+    #[test]
+    fn schedule_claim_and_finish() {
+        let queue = TaskQueue::new(&test::mem_storage()).unwrap();
+
+        // A task that is not due yet is not claimed.
+        queue.schedule(sync_repo("ca"), in_hours(1)).unwrap();
+        assert!(queue.pop().is_none());
+
+        // Scheduling it again keeps the soonest priority, ...
+        queue.schedule(sync_repo("ca"), now()).unwrap();
+        queue.schedule(sync_repo("ca"), in_hours(2)).unwrap();
+        let list = queue.list().unwrap();
+        assert_eq!(1, list.tasks().len());
+        assert_eq!(TaskState::Pending, list.tasks()[0].state());
+
+        // ... so it can be claimed now, but only once.
+        let running = queue.pop().unwrap();
+        assert_eq!(
+            sync_repo("ca"),
+            serde_json::from_value(running.value.clone()).unwrap()
+        );
+        assert!(queue.pop().is_none());
+        assert_eq!(
+            TaskState::Running,
+            queue.list().unwrap().tasks()[0].state()
+        );
+
+        // A missing task is not added while the task is running.
+        queue.schedule_missing(sync_repo("ca"), now()).unwrap();
+        assert_eq!(1, queue.list().unwrap().tasks().len());
+
+        queue.finish(&running.key()).unwrap();
+        assert!(queue.finish(&running.key()).is_err());
+        assert!(queue.list().unwrap().tasks().is_empty());
+    }
+
+    #[test]
+    fn reschedule_and_finish_existing() {
+        let queue = TaskQueue::new(&test::mem_storage()).unwrap();
+
+        queue.schedule(sync_repo("ca"), now()).unwrap();
+        let running = queue.pop().unwrap();
+        queue.reschedule(&running.key(), now()).unwrap();
+        assert!(queue.finish(&running.key()).is_err());
+        let running = queue.pop().unwrap();
+
+        // Scheduling a task while it runs adds it as pending, unless the
+        // running task is finished.
+        queue.schedule(sync_repo("ca"), in_hours(1)).unwrap();
+        assert_eq!(2, queue.list().unwrap().tasks().len());
+        queue
+            .schedule_and_finish_existing(sync_repo("ca"), in_hours(1))
+            .unwrap();
+        let list = queue.list().unwrap();
+        assert_eq!(1, list.tasks().len());
+        assert_eq!(TaskState::Pending, list.tasks()[0].state());
+        assert!(queue.finish(&running.key()).is_err());
+    }
+}
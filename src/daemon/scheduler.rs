@@ -6,9 +6,12 @@ use std::{collections::HashMap, sync::Arc, time::Duration};
 use kvx::Namespace;
 use tokio::time::sleep;
 
-use rpki::ca::{
-    idexchange::{CaHandle, ParentHandle},
-    provisioning::{ResourceClassName, RevocationRequest},
+use rpki::{
+    ca::{
+        idexchange::{CaHandle, ParentHandle},
+        provisioning::{ResourceClassName, RevocationRequest},
+    },
+    repository::x509::Time,
 };
 use url::Url;
 
@@ -31,10 +34,13 @@ use crate::{
     daemon::{
         ca::{CaManager, CertAuth},
         config::Config,
+        ha::HaManager,
         mq::{
-            in_hours, in_minutes, in_seconds, in_weeks, now, Task, TaskQueue,
+            in_hours, in_minutes, in_seconds, in_weeks, now, RunningTask,
+            Task, TaskQueue,
         },
         properties::Properties,
+        webhooks::WebhookManager,
     },
     pubd::{RepositoryAccess, RepositoryContent, RepositoryManager},
 };
@@ -49,6 +55,8 @@ pub struct Scheduler {
     ca_manager: Arc<CaManager>,
     repo_manager: Arc<RepositoryManager>,
     bgp_analyser: Arc<BgpAnalyser>,
+    webhooks: Arc<WebhookManager>,
+    ha: Arc<HaManager>,
     #[cfg(feature = "multi-user")]
     // Responsible for purging expired cached login tokens
     login_session_cache: Arc<LoginSessionCache>,
@@ -58,11 +66,14 @@ pub struct Scheduler {
 }
 
 impl Scheduler {
+    #[allow(clippy::too_many_arguments)]
     pub fn build(
         tasks: Arc<TaskQueue>,
         ca_manager: Arc<CaManager>,
         repo_manager: Arc<RepositoryManager>,
         bgp_analyser: Arc<BgpAnalyser>,
+        webhooks: Arc<WebhookManager>,
+        ha: Arc<HaManager>,
         #[cfg(feature = "multi-user")] login_session_cache: Arc<
             LoginSessionCache,
         >,
@@ -74,6 +85,8 @@ impl Scheduler {
             ca_manager,
             repo_manager,
             bgp_analyser,
+            webhooks,
+            ha,
             #[cfg(feature = "multi-user")]
             login_session_cache,
             config,
@@ -84,11 +97,38 @@ impl Scheduler {
 
     /// Run the scheduler in the background. It will sweep the message queue
     /// for tasks and re-schedule new tasks as needed.
+    ///
+    /// Tasks are only processed while this node is active, i.e. when it is
+    /// the only node, or the leader in an active/passive set up.
     pub async fn run(&self) {
+        let mut active = false;
         loop {
-            while let Some(running_task) = self.tasks.pop() {
+            if !self.ha.is_active() {
+                if active {
+                    warn!("This node is no longer the leader, background tasks are paused");
+                    active = false;
+                }
+                sleep(Duration::from_millis(500)).await;
+                continue;
+            }
+
+            if !active {
+                // Tasks that were running when Krill, or the previous
+                // leader, stopped need to be picked up again.
+                if let Err(e) =
+                    self.tasks.reschedule_tasks_at_startup().and_then(|_| {
+                        self.tasks.schedule(Task::QueueStartTasks, now())
+                    })
+                {
+                    error!("Could not queue the start tasks. Krill will stop as there is no good way to recover from this. Error was: {}", e);
+                    std::process::exit(1);
+                }
+                active = true;
+            }
+
+            while let Some(running_task) = self.next_task() {
                 // remember the key so we can finish or re-schedule the task.
-                let task_key = kvx::Key::from(&running_task);
+                let task_key = running_task.key();
 
                 match serde_json::from_value(running_task.value) {
                     Err(e) => {
@@ -106,30 +146,38 @@ impl Scheduler {
                         error!("Fatal error parsing task: {}. Krill will now stop! This may be because this task is not for this Krill version ({}). If this issue persists, then try deleting this task from storage, it will appear in the 'tasks' dir if you use disk storage. The error was {}", task_key, KrillVersion::code_version(), e);
                         std::process::exit(1);
                     }
-                    Ok(task) => match self.process_task(task).await {
-                        Ok(result) => {
-                            if let Err(e) = match result {
-                                TaskResult::Done => {
-                                    self.tasks.finish(&task_key)
+                    Ok(task) => {
+                        match self.process_task(task, &task_key).await {
+                            Ok(result) => {
+                                self.tasks.record_run(
+                                    running_task.name.as_str(),
+                                    &result,
+                                );
+                                if let Err(e) = match result {
+                                    TaskResult::Done => {
+                                        self.tasks.finish(&task_key)
+                                    }
+                                    TaskResult::FollowUp(task, priority) => {
+                                        self.tasks
+                                            .schedule_and_finish_existing(
+                                                task, priority,
+                                            )
+                                    }
+                                    TaskResult::Reschedule(priority) => self
+                                        .tasks
+                                        .reschedule(&task_key, priority),
+                                    TaskResult::Handover => Ok(()),
+                                } {
+                                    error!("Error finishing / scheduling task {}. Krill will stop as there is no good way to recover from this. When Krill starts it will try to reschedule any missing tasks. Error was: {}", task_key, e);
+                                    std::process::exit(1);
                                 }
-                                TaskResult::FollowUp(task, priority) => {
-                                    self.tasks.schedule_and_finish_existing(
-                                        task, priority,
-                                    )
-                                }
-                                TaskResult::Reschedule(priority) => {
-                                    self.tasks.reschedule(&task_key, priority)
-                                }
-                            } {
-                                error!("Error finishing / scheduling task {}. Krill will stop as there is no good way to recover from this. When Krill starts it will try to reschedule any missing tasks. Error was: {}", task_key, e);
+                            }
+                            Err(e) => {
+                                error!("Error processing task: {}. Tasks are only allowed to return fatal errors. Krill will stop as there is no good way to recover from this. When Krill starts it will try to reschedule any missing tasks. Error was: {}", task_key, e);
                                 std::process::exit(1);
                             }
                         }
-                        Err(e) => {
-                            error!("Error processing task: {}. Tasks are only allowed to return fatal errors. Krill will stop as there is no good way to recover from this. When Krill starts it will try to reschedule any missing tasks. Error was: {}", task_key, e);
-                            std::process::exit(1);
-                        }
-                    },
+                    }
                 }
             }
 
@@ -137,6 +185,15 @@ impl Scheduler {
         }
     }
 
+    /// Returns the next task to run, unless this node is no longer active.
+    fn next_task(&self) -> Option<RunningTask> {
+        if self.ha.is_active() {
+            self.tasks.pop()
+        } else {
+            None
+        }
+    }
+
     /// Process a single task
     ///
     /// May only return fatal errors. Temporary, or suspected temporary,
@@ -145,6 +202,7 @@ impl Scheduler {
     async fn process_task(
         &self,
         task: Task,
+        task_key: &kvx::Key,
     ) -> Result<TaskResult, FatalError> {
         match task {
             Task::QueueStartTasks => self.queue_start_tasks().await, /* return error and stop server on failure */
@@ -170,6 +228,14 @@ impl Scheduler {
                 self.suspend_children_if_needed(ca).await
             }
 
+            Task::KeyRollInitIfNeeded { ca_handle: ca } => {
+                self.keyroll_init_if_needed(ca).await
+            }
+
+            Task::KeyRollActivateIfNeeded { ca_handle: ca } => {
+                self.keyroll_activate_if_needed(ca).await
+            }
+
             Task::RepublishIfNeeded => self.republish_if_needed().await,
 
             Task::RenewObjectsIfNeeded => {
@@ -185,6 +251,8 @@ impl Scheduler {
 
             Task::UpdateSnapshots => self.update_snapshots(),
 
+            Task::ArchiveCommands => self.archive_commands(),
+
             Task::RrdpUpdateIfNeeded => self.update_rrdp_if_needed(),
 
             Task::ResourceClassRemoved {
@@ -213,6 +281,22 @@ impl Scheduler {
                 self.unexpected_key(ca, ca_version, rcn, revocation_request)
                     .await
             }
+
+            Task::DeliverWebhook {
+                webhook,
+                notification,
+                attempt,
+            } => {
+                // Deliveries can take long if a webhook is slow or
+                // unreachable, so they are done in the background.
+                self.webhooks.deliver_in_background(
+                    task_key.clone(),
+                    webhook,
+                    notification,
+                    attempt,
+                );
+                Ok(TaskResult::Handover)
+            }
         }
     }
 
@@ -313,6 +397,31 @@ impl Scheduler {
                     )
                     .map_err(FatalError)?;
             }
+
+            // Plan automatic key roll checks for CAs that have a key roll
+            // policy. These tasks will keep re-scheduling themselves
+            // while the policy is in place.
+            if ca.keyroll_policy().is_some() {
+                self.tasks
+                    .schedule_missing(
+                        Task::KeyRollInitIfNeeded {
+                            ca_handle: ca_handle.clone(),
+                        },
+                        now(),
+                    )
+                    .map_err(FatalError)?;
+
+                if ca.next_keyroll_activation().is_some() {
+                    self.tasks
+                        .schedule_missing(
+                            Task::KeyRollActivateIfNeeded {
+                                ca_handle: ca_handle.clone(),
+                            },
+                            now(),
+                        )
+                        .map_err(FatalError)?;
+                }
+            }
         }
 
         self.tasks
@@ -343,6 +452,12 @@ impl Scheduler {
             .schedule_missing(Task::UpdateSnapshots, now())
             .map_err(FatalError)?;
 
+        if self.config.history_archive_days.is_some() {
+            self.tasks
+                .schedule_missing(Task::ArchiveCommands, in_minutes(5))
+                .map_err(FatalError)?;
+        }
+
         if self.config.testbed().is_some() {
             self.tasks
                 .schedule_missing(Task::RenewTestbedTa, now())
@@ -492,6 +607,99 @@ impl Scheduler {
         }
     }
 
+    /// Initiate a key roll for a CA if this is due according to its key
+    /// roll policy. Plans the next check for the time that the next key
+    /// roll is due, or in a day if a key roll is in progress.
+    async fn keyroll_init_if_needed(
+        &self,
+        ca_handle: CaHandle,
+    ) -> Result<TaskResult, FatalError> {
+        if !self.ca_manager.has_ca(&ca_handle).map_err(FatalError)? {
+            debug!("Drop key roll task for removed CA {ca_handle}");
+            return Ok(TaskResult::Done);
+        }
+
+        if let Err(e) = self
+            .ca_manager
+            .ca_keyroll_init_if_needed(&ca_handle, &self.system_actor)
+            .await
+        {
+            error!(
+                "Could not initiate key roll for CA '{}'. Will retry in an hour. Error: {}",
+                ca_handle, e
+            );
+            return Ok(TaskResult::Reschedule(in_hours(1)));
+        }
+
+        let ca = self
+            .ca_manager
+            .get_ca(&ca_handle)
+            .await
+            .map_err(FatalError)?;
+
+        if ca.keyroll_policy().is_none() {
+            debug!("Drop key roll task for CA {ca_handle} without policy");
+            return Ok(TaskResult::Done);
+        }
+
+        let next = match ca.next_keyroll() {
+            Some(next) if next > Time::now() => next.into(),
+            // A key roll is in progress, or could not be initiated yet
+            // because the CA has no certified key.
+            _ => in_hours(24),
+        };
+
+        Ok(TaskResult::FollowUp(
+            Task::KeyRollInitIfNeeded { ca_handle },
+            next,
+        ))
+    }
+
+    /// Activate new keys for a CA if they have been staged for the period
+    /// required by its key roll policy. Plans the next check if there are
+    /// new keys that still need to be staged for longer.
+    async fn keyroll_activate_if_needed(
+        &self,
+        ca_handle: CaHandle,
+    ) -> Result<TaskResult, FatalError> {
+        if !self.ca_manager.has_ca(&ca_handle).map_err(FatalError)? {
+            debug!("Drop key activation task for removed CA {ca_handle}");
+            return Ok(TaskResult::Done);
+        }
+
+        if let Err(e) = self
+            .ca_manager
+            .ca_keyroll_activate_if_needed(&ca_handle, &self.system_actor)
+            .await
+        {
+            error!(
+                "Could not activate new key for CA '{}'. Will retry in an hour. Error: {}",
+                ca_handle, e
+            );
+            return Ok(TaskResult::Reschedule(in_hours(1)));
+        }
+
+        let ca = self
+            .ca_manager
+            .get_ca(&ca_handle)
+            .await
+            .map_err(FatalError)?;
+
+        match ca.next_keyroll_activation() {
+            Some(next) if next > Time::now() => Ok(TaskResult::FollowUp(
+                Task::KeyRollActivateIfNeeded { ca_handle },
+                next.into(),
+            )),
+            Some(_) => {
+                // The key is due, but was not activated. This can happen
+                // if there are pending requests to the parent. Try again
+                // in a while.
+                Ok(TaskResult::Reschedule(in_minutes(10)))
+            }
+            None => Ok(TaskResult::Done),
+        }
+    }
+
     /// Let CAs that need it republish their CRL/MFT
     async fn republish_if_needed(&self) -> Result<TaskResult, FatalError> {
         // Note that CRL/MFT re-issuance is handled by the `CaObjects`
@@ -661,6 +869,23 @@ impl Scheduler {
         Ok(TaskResult::FollowUp(Task::UpdateSnapshots, in_hours(24)))
     }
 
+    /// Moves old commands of CAs to the archive, if configured.
+    fn archive_commands(&self) -> Result<TaskResult, FatalError> {
+        let days = match self.config.history_archive_days {
+            Some(days) => days,
+            None => return Ok(TaskResult::Done),
+        };
+
+        if let Err(e) = self.ca_manager.ca_archive_commands(days) {
+            error!(
+                "Could not archive old commands, will try again in 24 hours. Error: {}",
+                e
+            );
+        }
+
+        Ok(TaskResult::FollowUp(Task::ArchiveCommands, in_hours(24)))
+    }
+
     fn update_rrdp_if_needed(&self) -> Result<TaskResult, FatalError> {
         match self.repo_manager.update_rrdp_if_needed() {
             Err(e) => {
@@ -0,0 +1,440 @@
+//! Outbound webhooks, used to notify external systems of CA events and
+//! issues.
+//!
+//! Notifications are derived from committed CA events, and from new issues
+//! in the status of CAs. They are not sent directly, but a delivery task is
+//! planned in the [`TaskQueue`] for each interested webhook. This way
+//! deliveries survive restarts, and failed deliveries can be retried.
+//!
+//! The scheduler hands delivery tasks over to the [`WebhookManager`], which
+//! POSTs the notifications in the background and then finishes or
+//! reschedules the tasks. A slow or unreachable webhook therefore does not
+//! hold up the other tasks of the scheduler.
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::Semaphore;
+
+use openssl::{hash::MessageDigest, pkey::PKey, sign::Signer};
+use reqwest::header::CONTENT_TYPE;
+use rpki::ca::idexchange::CaHandle;
+use url::Url;
+
+use crate::{
+    commons::{
+        api::{
+            WebhookDefinition, WebhookEventType, WebhookList, WebhookName,
+            WebhookNotification,
+        },
+        error::Error,
+        eventsourcing::{
+            self, Key, KeyValueStore, Scope, Segment, SegmentExt,
+        },
+        util::httpclient,
+        KrillResult,
+    },
+    constants::{
+        WEBHOOKS_NS, WEBHOOK_MAX_ATTEMPTS, WEBHOOK_MAX_CONCURRENT_DELIVERIES,
+        WEBHOOK_RETRY_DELAY_MAX_SECS, WEBHOOK_RETRY_DELAY_SECS,
+        WEBHOOK_TIMEOUT_SECS,
+    },
+    daemon::{
+        ca::{CertAuth, CertAuthEvent, StatusIssue, StatusListener},
+        mq::{in_seconds, now, Task, TaskQueue},
+    },
+};
+
+/// The HTTP header with the event type of a notification.
+pub const WEBHOOK_EVENT_HEADER: &str = "X-Krill-Event";
+
+/// The HTTP header with the unique id of a notification.
+pub const WEBHOOK_DELIVERY_HEADER: &str = "X-Krill-Delivery";
+
+/// The HTTP header with the signature of a notification, as
+/// "sha256=<hex encoded HMAC-SHA256 of the body>".
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Krill-Signature";
+
+//------------ WebhookManager ----------------------------------------------
+
+/// Manages the configured webhooks, and plans and performs the delivery of
+/// notifications to them.
+pub struct WebhookManager {
+    store: KeyValueStore,
+    tasks: Arc<TaskQueue>,
+
+    // Limits the number of deliveries that are done at the same time.
+    deliveries: Arc<Semaphore>,
+}
+
+impl WebhookManager {
+    pub fn create(
+        storage_uri: &Url,
+        tasks: Arc<TaskQueue>,
+    ) -> KrillResult<Self> {
+        let store = KeyValueStore::create(storage_uri, WEBHOOKS_NS)?;
+        let deliveries =
+            Arc::new(Semaphore::new(WEBHOOK_MAX_CONCURRENT_DELIVERIES));
+        Ok(WebhookManager {
+            store,
+            tasks,
+            deliveries,
+        })
+    }
+
+    fn key(name: &WebhookName) -> Key {
+        // webhook names are always valid segments
+        Key::new_global(Segment::parse_lossy(&format!("{}.json", name)))
+    }
+}
+
+/// # Manage webhooks
+impl WebhookManager {
+    pub fn list(&self) -> KrillResult<WebhookList> {
+        let mut webhooks: Vec<_> =
+            self.definitions()?.iter().map(|def| def.info()).collect();
+        webhooks.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(WebhookList::new(webhooks))
+    }
+
+    pub fn add(&self, definition: WebhookDefinition) -> KrillResult<()> {
+        let name = definition.name().clone();
+        definition
+            .verify()
+            .map_err(|msg| Error::WebhookInvalid(name.clone(), msg))?;
+
+        let key = Self::key(&name);
+        if self.store.has(&key)? {
+            return Err(Error::WebhookDuplicate(name));
+        }
+        self.store.store(&key, &definition)?;
+
+        info!("Added webhook '{}' for {}", name, definition.url());
+        if !self.store.is_encrypted() {
+            warn!(
+                "The secret of webhook '{}' is stored unencrypted, enable 'storage_encryption' to encrypt it at rest",
+                name
+            );
+        }
+        Ok(())
+    }
+
+    pub fn remove(&self, name: &WebhookName) -> KrillResult<()> {
+        let key = Self::key(name);
+        if !self.store.has(&key)? {
+            return Err(Error::WebhookUnknown(name.clone()));
+        }
+        self.store.drop_key(&key)?;
+
+        info!("Removed webhook '{}'", name);
+        Ok(())
+    }
+
+    /// Plans the delivery of a test notification to the webhook.
+    pub fn test(&self, name: &WebhookName) -> KrillResult<()> {
+        if !self.store.has(&Self::key(name))? {
+            return Err(Error::WebhookUnknown(name.clone()));
+        }
+        let notification = WebhookNotification::new(
+            WebhookEventType::Test,
+            None,
+            format!("Test notification for webhook '{}'", name),
+        );
+        self.schedule_delivery(name.clone(), notification)
+    }
+
+    fn get(
+        &self,
+        name: &WebhookName,
+    ) -> KrillResult<Option<WebhookDefinition>> {
+        self.store.get(&Self::key(name)).map_err(Error::from)
+    }
+
+    fn definitions(&self) -> KrillResult<Vec<WebhookDefinition>> {
+        let mut definitions = vec![];
+        for key in self.store.keys(&Scope::global(), ".json")? {
+            if let Some(definition) = self.store.get(&key)? {
+                definitions.push(definition);
+            }
+        }
+        Ok(definitions)
+    }
+}
+
+/// # Deliver notifications
+impl WebhookManager {
+    /// Plans the delivery of the notification to all webhooks that want
+    /// it. This is best effort: errors are logged, but do not fail the
+    /// change that led to the notification.
+    pub fn notify(&self, notification: WebhookNotification) {
+        let definitions = match self.definitions() {
+            Ok(definitions) => definitions,
+            Err(e) => {
+                error!(
+                    "Could not read webhooks, '{}' notification is not sent. Error: {}",
+                    notification.event, e
+                );
+                return;
+            }
+        };
+
+        for definition in definitions {
+            if definition.wants(notification.event) {
+                if let Err(e) = self.schedule_delivery(
+                    definition.name().clone(),
+                    notification.clone(),
+                ) {
+                    error!(
+                        "Could not schedule '{}' notification for webhook '{}'. Error: {}",
+                        notification.event,
+                        definition.name(),
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    fn schedule_delivery(
+        &self,
+        webhook: WebhookName,
+        notification: WebhookNotification,
+    ) -> KrillResult<()> {
+        self.tasks.schedule(
+            Task::DeliverWebhook {
+                webhook,
+                notification,
+                attempt: 0,
+            },
+            now(),
+        )
+    }
+
+    /// Delivers the notification of a running delivery task in the
+    /// background, and then finishes the task, or reschedules it with an
+    /// exponential back-off if the delivery failed. Gives up after the
+    /// maximum number of attempts.
+    pub fn deliver_in_background(
+        self: &Arc<Self>,
+        task_key: Key,
+        webhook: WebhookName,
+        notification: WebhookNotification,
+        attempt: u32,
+    ) {
+        let manager = self.clone();
+        tokio::spawn(async move {
+            let result =
+                match manager.deliveries.clone().acquire_owned().await {
+                    Ok(_permit) => {
+                        manager.deliver(&webhook, &notification).await
+                    }
+                    Err(_) => return, // the semaphore is never closed
+                };
+            manager.delivery_done(
+                task_key,
+                webhook,
+                notification,
+                attempt,
+                result,
+            );
+        });
+    }
+
+    fn delivery_done(
+        &self,
+        task_key: Key,
+        webhook: WebhookName,
+        notification: WebhookNotification,
+        attempt: u32,
+        result: KrillResult<()>,
+    ) {
+        let attempt = attempt + 1;
+        let res = match result {
+            Ok(()) => self.tasks.finish(&task_key),
+            Err(e) if attempt >= WEBHOOK_MAX_ATTEMPTS => {
+                error!(
+                    "Could not deliver '{}' notification to webhook '{}' after {} attempts, giving up. Error: {}",
+                    notification.event, webhook, attempt, e
+                );
+                self.tasks.finish(&task_key)
+            }
+            Err(e) => {
+                let delay = WEBHOOK_RETRY_DELAY_SECS
+                    .saturating_mul(1 << (attempt - 1).min(16))
+                    .min(WEBHOOK_RETRY_DELAY_MAX_SECS);
+                warn!(
+                    "Could not deliver '{}' notification to webhook '{}', will retry in {} seconds. Error: {}",
+                    notification.event, webhook, delay, e
+                );
+                self.tasks.schedule_and_finish_existing(
+                    Task::DeliverWebhook {
+                        webhook,
+                        notification,
+                        attempt,
+                    },
+                    in_seconds(delay),
+                )
+            }
+        };
+        if let Err(e) = res {
+            // The task stays in the running state, and is picked up again
+            // when Krill restarts.
+            error!(
+                "Could not finish webhook delivery task {}: {}",
+                task_key, e
+            );
+        }
+    }
+
+    /// POSTs the notification to the webhook. Returns an error if the
+    /// webhook could not be reached, or did not return a success status.
+    /// Does nothing if the webhook was removed.
+    pub async fn deliver(
+        &self,
+        name: &WebhookName,
+        notification: &WebhookNotification,
+    ) -> KrillResult<()> {
+        let definition = match self.get(name)? {
+            Some(definition) => definition,
+            None => {
+                debug!(
+                    "Webhook '{}' was removed, dropping notification {}",
+                    name, notification.id
+                );
+                return Ok(());
+            }
+        };
+
+        let body =
+            serde_json::to_vec(notification).map_err(Error::JsonError)?;
+        let signature = Self::signature(definition.secret(), &body)?;
+        let url = definition.url().as_str();
+
+        let client = httpclient::client_with_tweaks(
+            url,
+            Duration::from_secs(WEBHOOK_TIMEOUT_SECS),
+            false,
+        )
+        .map_err(Error::HttpClientError)?;
+
+        let res = client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_EVENT_HEADER, notification.event.to_string())
+            .header(WEBHOOK_DELIVERY_HEADER, notification.id.as_str())
+            .header(WEBHOOK_SIGNATURE_HEADER, format!("sha256={}", signature))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| {
+                Error::HttpClientError(httpclient::Error::execute(url, e))
+            })?;
+
+        if res.status().is_success() {
+            Ok(())
+        } else {
+            Err(Error::HttpClientError(httpclient::Error::Response(
+                url.to_string(),
+                httpclient::Error::unexpected_status(res.status()),
+            )))
+        }
+    }
+
+    /// Returns the hex encoded HMAC-SHA256 of the body, using the secret
+    /// as the key.
+    pub fn signature(secret: &str, body: &[u8]) -> KrillResult<String> {
+        let sign = || -> Result<Vec<u8>, openssl::error::ErrorStack> {
+            let key = PKey::hmac(secret.as_bytes())?;
+            let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+            signer.update(body)?;
+            signer.sign_to_vec()
+        };
+        sign().map(hex::encode).map_err(|e| {
+            Error::Custom(format!(
+                "Could not sign webhook notification: {}",
+                e
+            ))
+        })
+    }
+}
+
+/// Derives notifications from committed CA events.
+impl eventsourcing::PostSaveEventListener<CertAuth> for WebhookManager {
+    fn listen(&self, ca: &CertAuth, events: &[CertAuthEvent]) {
+        for event in events {
+            let event_type = match event {
+                CertAuthEvent::RoasUpdated { .. } => {
+                    WebhookEventType::RoasUpdated
+                }
+                CertAuthEvent::KeyRollPendingKeyAdded { .. }
+                | CertAuthEvent::KeyRollEmergencyInitiated { .. } => {
+                    WebhookEventType::KeyRollStarted
+                }
+                CertAuthEvent::KeyRollFinished { .. } => {
+                    WebhookEventType::KeyRollFinished
+                }
+                CertAuthEvent::ChildSuspended { .. } => {
+                    WebhookEventType::ChildSuspended
+                }
+                _ => continue,
+            };
+
+            self.notify(WebhookNotification::new(
+                event_type,
+                Some(ca.handle().clone()),
+                event.to_string(),
+            ));
+        }
+    }
+}
+
+/// Derives notifications from new CA issues.
+impl StatusListener for WebhookManager {
+    fn issue_found(&self, ca: &CaHandle, issue: StatusIssue) {
+        let notification = match issue {
+            StatusIssue::ParentSyncFailed { parent, error } => {
+                WebhookNotification::new(
+                    WebhookEventType::ParentSyncFailed,
+                    Some(ca.clone()),
+                    format!(
+                        "CA '{}' could not synchronise with parent '{}': {}",
+                        ca,
+                        parent,
+                        error.msg()
+                    ),
+                )
+            }
+            StatusIssue::RepoSyncFailed { error } => {
+                WebhookNotification::new(
+                    WebhookEventType::RepoSyncFailed,
+                    Some(ca.clone()),
+                    format!(
+                    "CA '{}' could not synchronise with its repository: {}",
+                    ca,
+                    error.msg()
+                ),
+                )
+            }
+        };
+        self.notify(notification);
+    }
+}
+
+//------------ Tests --------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature() {
+        // Test vector from RFC 4231, section 4.3
+        let signature = WebhookManager::signature(
+            "Jefe",
+            b"what do ya want for nothing?",
+        )
+        .unwrap();
+        assert_eq!(
+            signature,
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -16,6 +16,7 @@ use crate::{
     commons::{
         actor::Actor,
         api::{
+            AuditCriteria, CommandDryRun, CommandHistoryRecord,
             PublicationServerUris, PublisherDetails, RepoFileDeleteCriteria,
         },
         crypto::KrillSigner,
@@ -25,6 +26,7 @@ use crate::{
     },
     daemon::{
         config::Config,
+        eventstream::EventStream,
         mq::{now, Task, TaskQueue},
     },
     pubd::{RepoStats, RepositoryAccessProxy, RepositoryContentProxy},
@@ -58,8 +60,10 @@ impl RepositoryManager {
         config: Arc<Config>,
         tasks: Arc<TaskQueue>,
         signer: Arc<KrillSigner>,
+        event_stream: Arc<EventStream>,
     ) -> Result<Self, Error> {
-        let access_proxy = Arc::new(RepositoryAccessProxy::create(&config)?);
+        let access_proxy =
+            Arc::new(RepositoryAccessProxy::create(&config, event_stream)?);
         let content_proxy =
             Arc::new(RepositoryContentProxy::create(&config)?);
 
@@ -254,6 +258,15 @@ impl RepositoryManager {
         self.content.stats()
     }
 
+    /// Returns the commands for the publication server that match the
+    /// audit criteria.
+    pub fn audit_records(
+        &self,
+        crit: &AuditCriteria,
+    ) -> KrillResult<Vec<CommandHistoryRecord>> {
+        self.access.audit_records(crit)
+    }
+
     /// Returns a list reply for a known publisher in a repository.
     pub fn list(
         &self,
@@ -322,6 +335,39 @@ impl RepositoryManager {
 
         self.tasks.schedule(Task::RrdpUpdateIfNeeded, now())
     }
+
+    /// Reports what adding a publisher would do, without adding it.
+    pub fn create_publisher_dry_run(
+        &self,
+        req: idexchange::PublisherRequest,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        self.access.add_publisher_dry_run(req, actor)
+    }
+
+    /// Reports what removing a publisher would do, without removing it:
+    /// all of its content would be withdrawn.
+    pub fn remove_publisher_dry_run(
+        &self,
+        name: PublisherHandle,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        let mut withdrawn: Vec<_> = self
+            .content
+            .current_objects(&name)?
+            .try_into_publish_elements()?
+            .into_iter()
+            .map(|el| el.unpack().0)
+            .collect();
+        withdrawn.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+        let mut dry_run =
+            self.access.remove_publisher_dry_run(name, actor)?;
+        for uri in withdrawn {
+            dry_run.add_withdrawn(uri);
+        }
+        Ok(dry_run)
+    }
 }
 
 /// # Publishing RRDP and rsync
@@ -441,8 +487,13 @@ mod tests {
         let signer = Arc::new(signer);
         let config = Arc::new(config);
         let mq = Arc::new(TaskQueue::new(&config.storage_uri).unwrap());
-        let repository_manager =
-            RepositoryManager::build(config, mq, signer).unwrap();
+        let repository_manager = RepositoryManager::build(
+            config,
+            mq,
+            signer,
+            Arc::new(EventStream::default()),
+        )
+        .unwrap();
 
         let rsync_base = rsync("rsync://localhost/repo/");
         let rrdp_base = https("https://localhost/repo/rrdp/");
@@ -32,7 +32,10 @@ use crate::{
             },
             IdCertInfo,
         },
-        api::{PublicationServerUris, StorableRepositoryCommand},
+        api::{
+            AuditCriteria, CommandDryRun, CommandHistoryRecord,
+            PublicationServerUris, StorableRepositoryCommand,
+        },
         crypto::KrillSigner,
         error::{Error, KrillIoError},
         eventsourcing::{
@@ -50,6 +53,7 @@ use crate::{
     daemon::{
         ca::Rfc8183Id,
         config::{Config, RrdpUpdatesConfig},
+        eventstream::EventStream,
     },
     pubd::{
         publishers::Publisher, RepositoryAccessCommand,
@@ -1834,12 +1838,16 @@ pub struct RepositoryAccessProxy {
 }
 
 impl RepositoryAccessProxy {
-    pub fn create(config: &Config) -> KrillResult<Self> {
-        let store = AggregateStore::<RepositoryAccess>::create(
+    pub fn create(
+        config: &Config,
+        event_stream: Arc<EventStream>,
+    ) -> KrillResult<Self> {
+        let mut store = AggregateStore::<RepositoryAccess>::create(
             &config.storage_uri,
             PUBSERVER_NS,
             config.use_history_cache,
         )?;
+        store.add_post_save_listener(event_stream);
         let key = MyHandle::from_str(PUBSERVER_DFLT).unwrap();
 
         if store.has(&key)? {
@@ -1927,6 +1935,19 @@ impl RepositoryAccessProxy {
         Ok(self.read()?.publishers())
     }
 
+    /// Returns the commands for the publication server that match the
+    /// audit criteria.
+    pub fn audit_records(
+        &self,
+        crit: &AuditCriteria,
+    ) -> KrillResult<Vec<CommandHistoryRecord>> {
+        if !self.initialized()? || !crit.matches_handle(&self.key) {
+            Ok(vec![])
+        } else {
+            Ok(self.store.audit_records(&self.key, crit)?)
+        }
+    }
+
     pub fn get_publisher(
         &self,
         name: &PublisherHandle,
@@ -1970,6 +1991,52 @@ impl RepositoryAccessProxy {
         }
     }
 
+    /// Reports what adding a publisher would do, without adding it.
+    pub fn add_publisher_dry_run(
+        &self,
+        req: idexchange::PublisherRequest,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        let name = req.publisher_handle().clone();
+        let id_cert = req.validate().map_err(Error::rfc8183)?;
+        let base_uri = self.read()?.base_uri_for(&name)?;
+
+        let cmd = RepositoryAccessCommandDetails::add_publisher(
+            &self.key,
+            id_cert.into(),
+            name,
+            base_uri,
+            actor,
+        );
+        self.dry_run(cmd)
+    }
+
+    /// Reports what removing a publisher would do, without removing it.
+    pub fn remove_publisher_dry_run(
+        &self,
+        name: PublisherHandle,
+        actor: &Actor,
+    ) -> KrillResult<CommandDryRun> {
+        let cmd = RepositoryAccessCommandDetails::remove_publisher(
+            &self.key, name, actor,
+        );
+        self.dry_run(cmd)
+    }
+
+    fn dry_run(
+        &self,
+        cmd: RepositoryAccessCommand,
+    ) -> KrillResult<CommandDryRun> {
+        let version = self.read()?.version();
+        let (_, events) = self.store.dry_run(&self.key, vec![cmd])?;
+
+        let mut dry_run = CommandDryRun::new(version);
+        for event in &events {
+            dry_run.add_event(event);
+        }
+        Ok(dry_run)
+    }
+
     /// Returns the repository URI information for a publisher.
     pub fn repo_info_for(
         &self,
@@ -9,7 +9,7 @@ use std::{
 use bytes::Bytes;
 use rpki::{
     ca::{
-        idexchange::{ChildHandle, RecipientHandle, SenderHandle},
+        idexchange::{CaHandle, ChildHandle, RecipientHandle, SenderHandle},
         provisioning,
         publication::Base64,
         sigmsg::SignedMessage,
@@ -23,7 +23,8 @@ use serde::Serialize;
 use crate::{
     commons::{
         api::{
-            IdCertInfo, IssuedCertificate, ObjectName, ReceivedCert,
+            ExpiringObject, ExpiringObjectType, IdCertInfo,
+            IssuedCertificate, ObjectExpiryReport, ObjectName, ReceivedCert,
             Revocations,
         },
         crypto::KrillSigner,
@@ -70,6 +71,15 @@ pub struct TrustAnchorObjects {
     // Certificates issued to children. We use a map to avoid having
     // to loop. (yes, even if typically the list would be very short)
     issued: HashMap<KeyIdentifier, IssuedCertificate>,
+
+    // The signed TAL (RFC 9691), published while a key roll is in progress.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tak: Option<PublishedObject>,
+
+    // The objects published under the successor key while a key roll is
+    // in progress.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    successor: Option<Box<TrustAnchorObjects>>,
 }
 
 impl TrustAnchorObjects {
@@ -113,6 +123,8 @@ impl TrustAnchorObjects {
             crl,
             manifest,
             issued: HashMap::new(),
+            tak: None,
+            successor: None,
         })
     }
 
@@ -132,8 +144,8 @@ impl TrustAnchorObjects {
         let signing_key = signing_cert.key_identifier();
 
         if signing_key != self.key_identifier {
-            // This would be a bug.. we will need to re-think this when
-            // implementing signed TALs and TA key rollovers.
+            // This would be a bug.. when the TA key is rolled the objects
+            // for the successor key take the place of these objects.
             Err(Error::custom("TA key changed when republishing"))
         } else {
             let issuer = signing_cert.subject().clone();
@@ -147,7 +159,7 @@ impl TrustAnchorObjects {
             )?;
 
             self.manifest = ManifestBuilder::new(self.revision)
-                .with_objects(&self.crl, &self.issued_certs_objects())
+                .with_objects(&self.crl, &self.published_objects())
                 .build_new_mft(signing_cert, signer)
                 .map(|m| m.into())?;
 
@@ -172,25 +184,86 @@ impl TrustAnchorObjects {
             .map_err(|e| Error::Custom(format!("Cannot make uri: {}", e)))?;
         res.push(self.crl.publish_element(crl_uri));
 
-        for (name, object) in self.issued_certs_objects() {
-            let cert_uri =
+        for (name, object) in self.published_objects() {
+            let object_uri =
                 self.base_uri.join(name.as_ref()).map_err(|e| {
                     Error::Custom(format!("Cannot make uri: {}", e))
                 })?;
-            res.push(object.publish_element(cert_uri));
+            res.push(object.publish_element(object_uri));
+        }
+
+        if let Some(successor) = &self.successor {
+            res.append(&mut successor.publish_elements()?);
         }
+
         Ok(res)
     }
 
-    fn issued_certs_objects(&self) -> HashMap<ObjectName, PublishedObject> {
-        self.issued
+    /// Adds the manifest, CRL and published objects, including those for
+    /// the successor key if there is one, to the expiry report.
+    pub fn add_expiring_objects(
+        &self,
+        ta: &CaHandle,
+        report: &mut ObjectExpiryReport,
+    ) -> KrillResult<()> {
+        let uri_for_name = |name: &ObjectName| {
+            self.base_uri
+                .join(name.as_ref())
+                .map_err(|e| Error::Custom(format!("Cannot make uri: {}", e)))
+        };
+
+        let next_update = Some(self.revision.next_update());
+
+        report.add(ExpiringObject::new(
+            ta.clone(),
+            ExpiringObjectType::Manifest,
+            uri_for_name(&ObjectName::mft_for_key(&self.key_identifier))?,
+            self.manifest.expires(),
+            next_update,
+        ));
+
+        report.add(ExpiringObject::new(
+            ta.clone(),
+            ExpiringObjectType::Crl,
+            uri_for_name(&ObjectName::crl_for_key(&self.key_identifier))?,
+            self.crl.expires(),
+            next_update,
+        ));
+
+        for (name, object) in self.published_objects() {
+            report.add(ExpiringObject::new(
+                ta.clone(),
+                ExpiringObjectType::from(&name),
+                uri_for_name(&name)?,
+                object.expires(),
+                None,
+            ));
+        }
+
+        if let Some(successor) = &self.successor {
+            successor.add_expiring_objects(ta, report)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the objects to include on the manifest, other than the CRL.
+    fn published_objects(&self) -> HashMap<ObjectName, PublishedObject> {
+        let mut res: HashMap<ObjectName, PublishedObject> = self
+            .issued
             .iter()
             .map(|(ki, cert)| {
                 let object = PublishedObject::for_cert_info(cert);
                 let name = ObjectName::cer_for_key(ki);
                 (name, object)
             })
-            .collect()
+            .collect();
+
+        if let Some(tak) = &self.tak {
+            res.insert(tak.name().clone(), tak.clone());
+        }
+
+        res
     }
 
     pub fn manifest(&self) -> &PublishedManifest {
@@ -201,6 +274,38 @@ impl TrustAnchorObjects {
         &self.revision
     }
 
+    pub fn key_identifier(&self) -> KeyIdentifier {
+        self.key_identifier
+    }
+
+    /// Sets the signed TAL to publish, or removes it. A replaced signed
+    /// TAL is revoked. Takes effect when the objects are republished.
+    pub fn set_tak(&mut self, tak: Option<PublishedObject>) {
+        if let Some(previous) = std::mem::replace(&mut self.tak, tak) {
+            self.revocations.add(previous.revoke());
+            self.revocations.remove_expired();
+        }
+    }
+
+    pub fn tak(&self) -> Option<&PublishedObject> {
+        self.tak.as_ref()
+    }
+
+    /// Returns the objects for the successor key, if a key roll is in
+    /// progress.
+    pub fn successor(&self) -> Option<&TrustAnchorObjects> {
+        self.successor.as_deref()
+    }
+
+    pub fn set_successor(&mut self, successor: Option<TrustAnchorObjects>) {
+        self.successor = successor.map(Box::new);
+    }
+
+    /// Returns all current certificates issued to children.
+    pub fn issued(&self) -> impl Iterator<Item = &IssuedCertificate> {
+        self.issued.values()
+    }
+
     pub fn this_update() -> Time {
         Time::five_minutes_ago()
     }
@@ -673,6 +778,11 @@ pub struct TrustAnchorSignerResponse {
     pub objects: TrustAnchorObjects,
     pub child_responses:
         HashMap<ChildHandle, HashMap<KeyIdentifier, ProvisioningResponse>>,
+    // The current TA certificate and TAL, so that the proxy learns about a
+    // re-issued TA certificate or a newly activated key. Not included in
+    // responses by older signers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ta_cert_details: Option<TaCertDetails>,
 }
 
 impl TrustAnchorSignerResponse {
@@ -703,6 +813,25 @@ impl fmt::Display for TrustAnchorSignerResponse {
         writeln!(f)?;
         writeln!(f, "{}", self.objects)?;
         writeln!(f)?;
+        if let Some(details) = &self.ta_cert_details {
+            writeln!(f, "-------------------------------")?;
+            writeln!(f, "     TA certificate and TAL")?;
+            writeln!(f, "-------------------------------")?;
+            writeln!(
+                f,
+                "key:           {}",
+                details.cert().key_identifier()
+            )?;
+            writeln!(f, "resources:     {}", details.resources())?;
+            writeln!(
+                f,
+                "not after:     {}",
+                details.cert().validity().not_after().to_rfc3339()
+            )?;
+            writeln!(f)?;
+            writeln!(f, "{}", details.tal())?;
+            writeln!(f)?;
+        }
         for (child, responses) in &self.child_responses {
             writeln!(f, "-------------------------------")?;
             writeln!(f, "          child response")?;
@@ -729,6 +858,208 @@ impl fmt::Display for TrustAnchorSignerResponse {
     }
 }
 
+//------------ TrustAnchorSignerResponseDiff -------------------------------
+
+/// Shows what a signer response would change compared to the current state
+/// of the signer. This is used to preview a response before the request is
+/// processed for real.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TrustAnchorSignerResponseDiff {
+    pub nonce: Nonce,
+    // The TA key used for the manifest and CRL
+    pub signing_key: KeyIdentifier,
+    pub manifest_number: TaObjectNumberChange,
+    pub crl_number: TaObjectNumberChange,
+    pub this_update: Time,
+    pub next_update: Time,
+    // Certificates for keys that did not have a certificate before
+    pub issued: Vec<TaChildCertDiff>,
+    // Certificates replacing a previous certificate for the same key
+    pub reissued: Vec<TaChildCertDiff>,
+    // Certificates that are revoked and no longer published
+    pub revoked: Vec<TaChildCertDiff>,
+    // The TA certificate and TAL, if they differ from the current ones
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ta_cert_details: Option<TaCertDetails>,
+}
+
+impl TrustAnchorSignerResponseDiff {
+    pub fn new(
+        current_objects: &TrustAnchorObjects,
+        current_ta_cert_details: &TaCertDetails,
+        response: &TrustAnchorSignerResponse,
+    ) -> Self {
+        let objects = &response.objects;
+
+        // Certificates issued to children, or revoked, in response to
+        // their requests. Other certificates may be re-issued or revoked
+        // after a change to the TA certificate.
+        let child_for_key = |key: &KeyIdentifier| {
+            response
+                .child_responses
+                .iter()
+                .find(|(_, responses)| responses.contains_key(key))
+                .map(|(child, _)| child.clone())
+        };
+
+        let mut issued = vec![];
+        let mut reissued = vec![];
+        for cert in objects.issued() {
+            let key = cert.key_identifier();
+            match current_objects.get_issued(&key) {
+                None => issued
+                    .push(TaChildCertDiff::new(child_for_key(&key), cert)),
+                Some(previous) if previous != cert => reissued
+                    .push(TaChildCertDiff::new(child_for_key(&key), cert)),
+                Some(_) => {}
+            }
+        }
+
+        let revoked = current_objects
+            .issued()
+            .filter(|cert| {
+                objects.get_issued(&cert.key_identifier()).is_none()
+            })
+            .map(|cert| {
+                TaChildCertDiff::new(
+                    child_for_key(&cert.key_identifier()),
+                    cert,
+                )
+            })
+            .collect();
+
+        let ta_cert_details = response
+            .ta_cert_details
+            .as_ref()
+            .filter(|details| *details != current_ta_cert_details)
+            .cloned();
+
+        let number_change = TaObjectNumberChange {
+            current: current_objects.revision().number(),
+            next: objects.revision().number(),
+        };
+
+        TrustAnchorSignerResponseDiff {
+            nonce: response.nonce.clone(),
+            signing_key: objects.key_identifier(),
+            manifest_number: number_change,
+            crl_number: number_change,
+            this_update: objects.revision().this_update(),
+            next_update: objects.revision().next_update(),
+            issued,
+            reissued,
+            revoked,
+            ta_cert_details,
+        }
+    }
+}
+
+impl fmt::Display for TrustAnchorSignerResponseDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "-------------------------------")?;
+        writeln!(f, "nonce: {}", self.nonce)?;
+        writeln!(f, "-------------------------------")?;
+        writeln!(f)?;
+        writeln!(f, "signing key:      {}", self.signing_key)?;
+        writeln!(f, "manifest number:  {}", self.manifest_number)?;
+        writeln!(f, "CRL number:       {}", self.crl_number)?;
+        writeln!(f, "this update:      {}", self.this_update.to_rfc3339())?;
+        writeln!(f, "next update:      {}", self.next_update.to_rfc3339())?;
+        writeln!(f)?;
+
+        if let Some(details) = &self.ta_cert_details {
+            writeln!(f, "-------------------------------")?;
+            writeln!(f, "   updated TA certificate")?;
+            writeln!(f, "-------------------------------")?;
+            writeln!(
+                f,
+                "key:           {}",
+                details.cert().key_identifier()
+            )?;
+            writeln!(f, "resources:     {}", details.resources())?;
+            writeln!(
+                f,
+                "not after:     {}",
+                details.cert().validity().not_after().to_rfc3339()
+            )?;
+            writeln!(f)?;
+            writeln!(f, "{}", details.tal())?;
+            writeln!(f)?;
+        }
+
+        for (label, certs) in [
+            ("issued", &self.issued),
+            ("re-issued", &self.reissued),
+            ("revoked", &self.revoked),
+        ] {
+            writeln!(f, "-------------------------------")?;
+            writeln!(f, "  child certificates {}", label)?;
+            writeln!(f, "-------------------------------")?;
+            if certs.is_empty() {
+                writeln!(f, "none")?;
+            }
+            for cert in certs {
+                writeln!(f, "{}", cert)?;
+            }
+            writeln!(f)?;
+        }
+
+        writeln!(
+            f,
+            "NOTE: nothing was saved, serial numbers and signatures will differ when the request is processed."
+        )?;
+
+        Ok(())
+    }
+}
+
+/// The current and next number of a manifest or CRL.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaObjectNumberChange {
+    pub current: u64,
+    pub next: u64,
+}
+
+impl fmt::Display for TaObjectNumberChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}", self.current, self.next)
+    }
+}
+
+/// A certificate issued to a child, as shown in a response diff. The child
+/// is only known if the change was requested by the child.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaChildCertDiff {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub child: Option<ChildHandle>,
+    pub key: KeyIdentifier,
+    pub resources: ResourceSet,
+    pub not_after: Time,
+}
+
+impl TaChildCertDiff {
+    fn new(child: Option<ChildHandle>, cert: &IssuedCertificate) -> Self {
+        TaChildCertDiff {
+            child,
+            key: cert.key_identifier(),
+            resources: cert.resources().clone(),
+            not_after: cert.validity().not_after(),
+        }
+    }
+}
+
+impl fmt::Display for TaChildCertDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.child {
+            Some(child) => writeln!(f, "child:         {}", child)?,
+            None => writeln!(f, "child:         <not in request>")?,
+        }
+        writeln!(f, "key:           {}", self.key)?;
+        writeln!(f, "resources:     {}", self.resources)?;
+        write!(f, "not after:     {}", self.not_after.to_rfc3339())
+    }
+}
+
 //------------ TrustAnchorChild --------------------------------------------
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
@@ -739,6 +1070,10 @@ pub struct TrustAnchorChild {
     pub used_keys: HashMap<KeyIdentifier, UsedKeyState>,
     pub open_requests: HashMap<KeyIdentifier, ProvisioningRequest>,
     pub open_responses: HashMap<KeyIdentifier, ProvisioningResponse>,
+    // Set when the child is removed. The child is dropped when the signer
+    // response with the revocations for its keys is processed.
+    #[serde(default)]
+    pub removal_pending: bool,
 }
 
 impl TrustAnchorChild {
@@ -754,7 +1089,37 @@ impl TrustAnchorChild {
             used_keys: HashMap::new(),
             open_requests: HashMap::new(),
             open_responses: HashMap::new(),
+            removal_pending: false,
+        }
+    }
+}
+
+impl fmt::Display for TrustAnchorChild {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "child:         {}", self.handle)?;
+        writeln!(f, "resources:     {}", self.resources)?;
+        if self.removal_pending {
+            writeln!(
+                f,
+                "status:        removal pending until the next signer response is processed"
+            )?;
         }
+        writeln!(f)?;
+        writeln!(f, "keys:")?;
+        for (key, state) in &self.used_keys {
+            match state {
+                UsedKeyState::InUse(_) => writeln!(f, "  {}  in use", key)?,
+                UsedKeyState::Revoked => writeln!(f, "  {}  revoked", key)?,
+            }
+        }
+        if !self.open_requests.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "open requests:")?;
+            for request in self.open_requests.values() {
+                writeln!(f, "  {}", request)?;
+            }
+        }
+        Ok(())
     }
 }
 
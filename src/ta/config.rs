@@ -20,6 +20,7 @@ const DFLT_TA_ISSUED_CERTIFICATE_VALIDITY_WEEKS: i64 = 52;
 const DFLT_TA_ISSUED_CERTIFICATE_REISSUE_WEEKS_BEFORE: i64 = 26;
 const DFLT_TA_MFT_NEXT_UPDATE_WEEKS: i64 = 12;
 const DFLT_TA_SIGNED_MESSAGE_VALIDITY_DAYS: i64 = 14;
+const DFLT_TA_KEY_ROLL_ACTIVATION_DAYS: i64 = 30;
 
 //------------------------ TaTimingConfig
 //------------------------ ---------------------------------------
@@ -46,6 +47,9 @@ pub struct TaTimingConfig {
         default = "TaTimingConfig::dflt_ta_signed_message_validity_days"
     )]
     pub signed_message_validity_days: i64,
+
+    #[serde(default = "TaTimingConfig::dflt_ta_key_roll_activation_days")]
+    pub key_roll_activation_days: i64,
 }
 
 impl Default for TaTimingConfig {
@@ -59,6 +63,7 @@ impl Default for TaTimingConfig {
             mft_next_update_weeks: DFLT_TA_MFT_NEXT_UPDATE_WEEKS,
             signed_message_validity_days:
                 DFLT_TA_SIGNED_MESSAGE_VALIDITY_DAYS,
+            key_roll_activation_days: DFLT_TA_KEY_ROLL_ACTIVATION_DAYS,
         }
     }
 }
@@ -83,6 +88,10 @@ impl TaTimingConfig {
     fn dflt_ta_signed_message_validity_days() -> i64 {
         DFLT_TA_SIGNED_MESSAGE_VALIDITY_DAYS
     }
+
+    fn dflt_ta_key_roll_activation_days() -> i64 {
+        DFLT_TA_KEY_ROLL_ACTIVATION_DAYS
+    }
 }
 
 //------------------------ Config -----------------------------------------------
@@ -13,6 +13,9 @@ pub use self::proxy::*;
 mod signer;
 pub use self::signer::*;
 
+mod tak;
+pub use self::tak::*;
+
 pub const TA_NAME: &str = "ta"; // reserved for TA
 
 //------------ TrustAnchor Handle Types ------------------------------------
@@ -31,19 +34,26 @@ fn ta_resource_class_name() -> rpki::ca::provisioning::ResourceClassName {
 //----------------- TESTS --------------------------------------------------------------
 #[cfg(test)]
 mod tests {
-    use rpki::ca::idexchange::{RepoInfo, ServiceUri};
+    use rpki::ca::{
+        idexchange::{ChildHandle, RepoInfo, ServiceUri},
+        provisioning::{IssuanceRequest, RequestResourceLimit},
+    };
 
     use super::*;
 
-    use std::{sync::Arc, time::Duration};
+    use std::{str::FromStr, sync::Arc, time::Duration};
 
     use crate::{
         commons::{
-            api::{PublicationServerInfo, RepositoryContact},
+            api::{
+                AddChildRequest, PublicationServerInfo, RepositoryContact,
+            },
             crypto::KrillSignerBuilder,
-            eventsourcing::{namespace, AggregateStore, Namespace},
+            eventsourcing::{
+                namespace, Aggregate, AggregateStore, Namespace,
+            },
         },
-        daemon::config::ConfigDefaults,
+        daemon::{ca::Rfc8183Id, config::ConfigDefaults},
         test,
     };
 
@@ -180,13 +190,36 @@ mod tests {
                 proxy.get_signer_request(timing, &signer).unwrap();
             let request_nonce = signed_request.content().nonce.clone();
 
+            // A dry run shows the changes without saving anything.
+            let diff = ta_signer
+                .dry_run_signer_request(
+                    signed_request.clone(),
+                    timing,
+                    Some(55),
+                    &signer,
+                )
+                .unwrap();
+            assert_eq!(diff.nonce, request_nonce);
+            assert_eq!(diff.manifest_number.current, 42);
+            assert_eq!(diff.manifest_number.next, 55);
+            assert!(diff.issued.is_empty() && diff.revoked.is_empty());
+            assert!(diff.ta_cert_details.is_none());
+            assert_eq!(
+                ta_signer_store
+                    .get_latest(&signer_handle)
+                    .unwrap()
+                    .version(),
+                ta_signer.version()
+            );
+            assert!(ta_signer.get_exchange(&request_nonce).is_none());
+
             let ta_signer_process_request_command =
                 TrustAnchorSignerCommand::make_process_request_command(
                     &signer_handle,
                     signed_request,
                     timing,
                     Some(55), // override the next manifest number again
-                    signer,
+                    signer.clone(),
                     &actor,
                 );
             ta_signer = ta_signer_store
@@ -211,6 +244,257 @@ mod tests {
             let ta_objects = proxy.get_trust_anchor_objects().unwrap();
             assert_eq!(ta_objects.revision().number(), 55);
 
+            // Roll the TA key. Use an activation period of 0 days, so that
+            // the successor key is activated in the second exchange.
+            let timing = TaTimingConfig {
+                key_roll_activation_days: 0,
+                ..timing
+            };
+
+            let exchange = || {
+                let make_request_cmd =
+                    TrustAnchorProxyCommand::make_signer_request(
+                        &proxy_handle,
+                        &actor,
+                    );
+                let proxy = ta_proxy_store.command(make_request_cmd).unwrap();
+                let signed_request =
+                    proxy.get_signer_request(timing, &signer).unwrap();
+
+                let process_request_cmd =
+                    TrustAnchorSignerCommand::make_process_request_command(
+                        &signer_handle,
+                        signed_request,
+                        timing,
+                        None,
+                        signer.clone(),
+                        &actor,
+                    );
+                let ta_signer =
+                    ta_signer_store.command(process_request_cmd).unwrap();
+
+                let response =
+                    ta_signer.get_latest_exchange().unwrap().response.clone();
+                let process_response_cmd =
+                    TrustAnchorProxyCommand::process_signer_response(
+                        &proxy_handle,
+                        response,
+                        &actor,
+                    );
+                (
+                    ta_proxy_store.command(process_response_cmd).unwrap(),
+                    ta_signer,
+                )
+            };
+
+            // The successor certificate cannot use the current URIs.
+            let reuse_uris_cmd =
+                TrustAnchorSignerCommand::make_key_roll_init_command(
+                    &signer_handle,
+                    tal_https.clone(),
+                    tal_rsync.clone(),
+                    timing,
+                    signer.clone(),
+                    &actor,
+                );
+            assert!(ta_signer_store.command(reuse_uris_cmd).is_err());
+
+            let key_roll_init_cmd =
+                TrustAnchorSignerCommand::make_key_roll_init_command(
+                    &signer_handle,
+                    vec![test::https(
+                        "https://example.krill.cloud/ta/ta-successor.cer",
+                    )],
+                    test::rsync(
+                        "rsync://example.krill.cloud/ta/ta-successor.cer",
+                    ),
+                    timing,
+                    signer.clone(),
+                    &actor,
+                );
+            ta_signer = ta_signer_store.command(key_roll_init_cmd).unwrap();
+
+            // The TA certificate cannot be re-issued during a key roll.
+            let reissue_cmd =
+                TrustAnchorSignerCommand::make_reissue_ta_cert_command(
+                    &signer_handle,
+                    TaCertReissueRequest::default(),
+                    timing,
+                    signer.clone(),
+                    &actor,
+                );
+            assert!(ta_signer_store.command(reissue_cmd).is_err());
+
+            let current = ta_signer.get_signer_info().ta_cert_details;
+            let successor =
+                ta_signer.get_key_roll().unwrap().successor().clone();
+            assert!(ta_signer
+                .get_key_roll()
+                .unwrap()
+                .activate_after()
+                .is_none());
+            assert!(proxy
+                .get_trust_anchor_objects()
+                .unwrap()
+                .tak()
+                .is_none());
+
+            // The next exchange publishes the signed TALs and the objects for
+            // the successor key.
+            let (proxy, ta_signer) = exchange();
+            assert!(ta_signer
+                .get_key_roll()
+                .unwrap()
+                .activate_after()
+                .is_some());
+            assert_eq!(proxy.get_ta_details().unwrap(), &current);
+
+            let ta_objects = proxy.get_trust_anchor_objects().unwrap();
+            let successor_objects = ta_objects.successor().unwrap();
+            assert_eq!(
+                successor_objects.key_identifier(),
+                successor.cert().key_identifier()
+            );
+            assert!(successor_objects.tak().is_some());
+
+            // mft, crl and tak for both keys
+            let elements = ta_objects.publish_elements().unwrap();
+            assert_eq!(elements.len(), 6);
+
+            let tak_uri = current
+                .cert()
+                .uri_for_name(ta_objects.tak().unwrap().name());
+            let tak_element =
+                elements.iter().find(|el| el.uri() == &tak_uri).unwrap();
+            let tak_object = rpki::repository::sigobj::SignedObject::decode(
+                tak_element.base64().to_bytes(),
+                true,
+            )
+            .unwrap();
+            assert_eq!(
+                tak_object.content().to_bytes(),
+                Tak::with_successor(&current, &successor).to_bytes()
+            );
+
+            // The activation period has passed, so the next exchange
+            // activates the successor key.
+            let (proxy, ta_signer) = exchange();
+            assert!(ta_signer.get_key_roll().is_none());
+            assert_eq!(proxy.get_ta_details().unwrap(), &successor);
+
+            let ta_objects = proxy.get_trust_anchor_objects().unwrap();
+            assert_eq!(
+                ta_objects.key_identifier(),
+                successor.cert().key_identifier()
+            );
+            assert!(ta_objects.tak().is_none());
+            assert!(ta_objects.successor().is_none());
+            assert_eq!(ta_objects.publish_elements().unwrap().len(), 2);
+
+            // Re-issue the TA certificate with fewer resources. The proxy
+            // picks up the new certificate with the next exchange.
+            let resources =
+                test::resources("AS65000-AS65535", "10.0.0.0/8", "");
+            let reissue_cmd =
+                TrustAnchorSignerCommand::make_reissue_ta_cert_command(
+                    &signer_handle,
+                    TaCertReissueRequest {
+                        resources: Some(resources.clone()),
+                        ..Default::default()
+                    },
+                    timing,
+                    signer.clone(),
+                    &actor,
+                );
+            let ta_signer = ta_signer_store.command(reissue_cmd).unwrap();
+            let reissued = ta_signer.get_signer_info().ta_cert_details;
+            assert_eq!(reissued.resources(), &resources);
+            assert_eq!(
+                reissued.cert().key_identifier(),
+                successor.cert().key_identifier()
+            );
+            assert_eq!(reissued.tal(), successor.tal());
+
+            let (proxy, _) = exchange();
+            assert_eq!(proxy.get_ta_details().unwrap(), &reissued);
+
+            // Add a child and let it request a certificate.
+            let child_handle = ChildHandle::from_str("child").unwrap();
+            let child_id = Rfc8183Id::generate(&signer).unwrap();
+            let add_child_cmd = TrustAnchorProxyCommand::add_child(
+                &proxy_handle,
+                AddChildRequest::new(
+                    child_handle.clone(),
+                    resources.clone(),
+                    child_id.cert().try_into().unwrap(),
+                ),
+                &actor,
+            );
+            ta_proxy_store.command(add_child_cmd).unwrap();
+
+            let child_key = signer.create_key().unwrap();
+            let csr = signer
+                .sign_csr(
+                    &RepoInfo::new(
+                        test::rsync(
+                            "rsync://example.krill.cloud/repo/child/",
+                        ),
+                        None,
+                    ),
+                    "",
+                    &child_key,
+                )
+                .unwrap();
+            let add_child_request_cmd =
+                TrustAnchorProxyCommand::add_child_request(
+                    &proxy_handle,
+                    child_handle.clone(),
+                    ProvisioningRequest::Issuance(IssuanceRequest::new(
+                        ta_resource_class_name(),
+                        RequestResourceLimit::default(),
+                        csr,
+                    )),
+                    &actor,
+                );
+            ta_proxy_store.command(add_child_request_cmd).unwrap();
+
+            let (proxy, _) = exchange();
+            assert!(proxy
+                .get_trust_anchor_objects()
+                .unwrap()
+                .get_issued(&child_key)
+                .is_some());
+
+            // Removing the child schedules the revocation of its key. The
+            // child is kept until the signer response is processed.
+            let remove_child_cmd = TrustAnchorProxyCommand::remove_child(
+                &proxy_handle,
+                child_handle.clone(),
+                &actor,
+            );
+            let proxy = ta_proxy_store.command(remove_child_cmd).unwrap();
+            let child = proxy.get_child(&child_handle).unwrap();
+            assert!(child.removal_pending);
+            assert!(matches!(
+                child.open_requests.get(&child_key),
+                Some(ProvisioningRequest::Revocation(_))
+            ));
+
+            let remove_child_cmd = TrustAnchorProxyCommand::remove_child(
+                &proxy_handle,
+                child_handle.clone(),
+                &actor,
+            );
+            assert!(ta_proxy_store.command(remove_child_cmd).is_err());
+
+            let (proxy, _) = exchange();
+            assert!(proxy.get_child(&child_handle).is_err());
+            assert!(proxy
+                .get_trust_anchor_objects()
+                .unwrap()
+                .get_issued(&child_key)
+                .is_none());
+
             // We still need to test some higher order functions:
             // - add child
             // - let the child request a certificate
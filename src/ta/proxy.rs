@@ -11,7 +11,7 @@ use chrono::Duration;
 use rpki::{
     ca::{
         idexchange::{self, ChildHandle, MyHandle},
-        provisioning::{ResourceClassEntitlements, SigningCert},
+        provisioning::{self, ResourceClassEntitlements, SigningCert},
     },
     crypto::KeyIdentifier,
     repository::x509::Time,
@@ -164,6 +164,8 @@ pub enum TrustAnchorProxyEvent {
     ChildAdded(TrustAnchorChild),
     ChildRequestAdded(ChildHandle, ProvisioningRequest),
     ChildResponseGiven(ChildHandle, KeyIdentifier),
+    ChildRemovalScheduled(ChildHandle),
+    ChildRemoved(ChildHandle),
 }
 
 impl Event for TrustAnchorProxyEvent {}
@@ -225,6 +227,16 @@ impl fmt::Display for TrustAnchorProxyEvent {
                     child_handle, key
                 )
             }
+            TrustAnchorProxyEvent::ChildRemovalScheduled(child_handle) => {
+                write!(
+                    f,
+                    "Scheduled revocation of all keys for removed child: {}",
+                    child_handle
+                )
+            }
+            TrustAnchorProxyEvent::ChildRemoved(child_handle) => {
+                write!(f, "Removed child: {}", child_handle)
+            }
         }
     }
 }
@@ -249,6 +261,7 @@ pub enum TrustAnchorProxyCommandDetails {
     AddChild(AddChildRequest),
     AddChildRequest(ChildHandle, ProvisioningRequest),
     GiveChildResponse(ChildHandle, KeyIdentifier),
+    RemoveChild(ChildHandle),
 }
 
 impl fmt::Display for TrustAnchorProxyCommandDetails {
@@ -313,6 +326,9 @@ impl fmt::Display for TrustAnchorProxyCommandDetails {
                     child_handle, key
                 )
             }
+            TrustAnchorProxyCommandDetails::RemoveChild(child_handle) => {
+                write!(f, "Remove child: {}", child_handle)
+            }
         }
     }
 }
@@ -404,6 +420,13 @@ impl eventsourcing::WithStorableDetails for TrustAnchorProxyCommandDetails {
                 self,
             )
             .with_child(child_handle),
+            TrustAnchorProxyCommandDetails::RemoveChild(child_handle) => {
+                crate::commons::api::CommandSummary::new(
+                    "cmd-ta-proxy-child-remove",
+                    self,
+                )
+                .with_child(child_handle)
+            }
         }
     }
 
@@ -504,6 +527,19 @@ impl TrustAnchorProxyCommand {
             actor,
         )
     }
+
+    pub fn remove_child(
+        id: &TrustAnchorHandle,
+        child: ChildHandle,
+        actor: &Actor,
+    ) -> Self {
+        TrustAnchorProxyCommand::new(
+            id,
+            None,
+            TrustAnchorProxyCommandDetails::RemoveChild(child),
+            actor,
+        )
+    }
 }
 
 impl eventsourcing::CommandDetails for TrustAnchorProxyCommandDetails {
@@ -609,7 +645,33 @@ impl eventsourcing::Aggregate for TrustAnchorProxy {
                 }
                 // We cannot have an accepted response if we did not have a
                 // signer
-                self.signer.as_mut().unwrap().objects = content.objects;
+                let signer = self.signer.as_mut().unwrap();
+                signer.objects = content.objects;
+
+                // The signer may also revoke certificates if the TA no
+                // longer holds any of their resources.
+                for child_details in self.child_details.values_mut() {
+                    for (key_id, state) in child_details.used_keys.iter_mut()
+                    {
+                        if matches!(state, UsedKeyState::InUse(_))
+                            && signer.objects.get_issued(key_id).is_none()
+                        {
+                            *state = UsedKeyState::Revoked;
+                        }
+                    }
+                }
+
+                // Children that are being removed are dropped now that
+                // their keys are revoked.
+                self.child_details.retain(|_, child_details| {
+                    !child_details.removal_pending
+                });
+
+                // The TA certificate may have been re-issued, or a new key
+                // may have been activated as part of a key roll.
+                if let Some(ta_cert_details) = content.ta_cert_details {
+                    signer.ta_cert_details = ta_cert_details;
+                }
                 self.open_signer_request = None;
             }
 
@@ -636,6 +698,34 @@ impl eventsourcing::Aggregate for TrustAnchorProxy {
                     .open_responses
                     .remove(&key);
             }
+            TrustAnchorProxyEvent::ChildRemovalScheduled(child_handle) => {
+                let child_details =
+                    self.child_details.get_mut(&child_handle).unwrap(); // safe - we can only have an event for this child if it
+                                                                        // exists
+
+                // Replace any open requests with revocations for all keys
+                // that are still in use.
+                child_details.open_requests = child_details
+                    .used_keys
+                    .iter()
+                    .filter_map(|(key_id, state)| match state {
+                        UsedKeyState::InUse(rcn) => Some((
+                            *key_id,
+                            ProvisioningRequest::Revocation(
+                                provisioning::RevocationRequest::new(
+                                    rcn.clone(),
+                                    *key_id,
+                                ),
+                            ),
+                        )),
+                        UsedKeyState::Revoked => None,
+                    })
+                    .collect();
+                child_details.removal_pending = true;
+            }
+            TrustAnchorProxyEvent::ChildRemoved(child_handle) => {
+                self.child_details.remove(&child_handle);
+            }
         }
     }
 
@@ -695,6 +785,9 @@ impl eventsourcing::Aggregate for TrustAnchorProxy {
                 child_handle,
                 key,
             ) => self.process_give_child_response(child_handle, key),
+            TrustAnchorProxyCommandDetails::RemoveChild(child_handle) => {
+                self.process_remove_child(child_handle)
+            }
         }
     }
 }
@@ -817,6 +910,10 @@ impl TrustAnchorProxy {
         let child = self.get_child_details(&child_handle)?;
         let ta_resource_class_name = ta_resource_class_name();
 
+        if child.removal_pending {
+            return Err(Error::TaProxyChildRemovalPending(child_handle));
+        }
+
         match &request {
             ProvisioningRequest::Issuance(issuance) => {
                 if issuance.class_name() != &ta_resource_class_name {
@@ -825,7 +922,12 @@ impl TrustAnchorProxy {
                         issuance.class_name()
                     )));
                 }
-                issuance.limit().apply_to(&child.resources)?; // Errors if request exceeds
+                // Errors if request exceeds the resources entitled to the
+                // child and held by the TA
+                let ta_resources = self.get_ta_details()?.resources();
+                issuance
+                    .limit()
+                    .apply_to(&child.resources.intersection(ta_resources))?;
                 CsrInfo::try_from(issuance.csr())?; // Errors if the CSR is
                                                     // invalid
             }
@@ -872,6 +974,32 @@ impl TrustAnchorProxy {
             )))
         }
     }
+
+    fn process_remove_child(
+        &self,
+        child_handle: ChildHandle,
+    ) -> KrillResult<Vec<TrustAnchorProxyEvent>> {
+        let child = self.get_child_details(&child_handle)?;
+
+        if child.removal_pending {
+            Err(Error::TaProxyChildRemovalPending(child_handle))
+        } else if self.open_signer_request.is_some() {
+            // The revocations must be included in the next signer request,
+            // so the open request needs to be processed first.
+            Err(Error::TaProxyHasRequest)
+        } else if child
+            .used_keys
+            .values()
+            .any(|state| matches!(state, UsedKeyState::InUse(_)))
+        {
+            Ok(vec![TrustAnchorProxyEvent::ChildRemovalScheduled(
+                child_handle,
+            )])
+        } else {
+            // Nothing to revoke, so the child can be removed right away.
+            Ok(vec![TrustAnchorProxyEvent::ChildRemoved(child_handle)])
+        }
+    }
 }
 
 impl TrustAnchorProxy {
@@ -1019,7 +1147,9 @@ impl TrustAnchorProxy {
 
         Ok(ResourceClassEntitlements::new(
             ta_resource_class_name(),
-            child.resources.clone(),
+            child
+                .resources
+                .intersection(signer.ta_cert_details.resources()),
             not_after,
             issued_certs,
             signing_cert,
@@ -20,7 +20,7 @@ use rpki::{
     repository::{
         cert::{KeyUsage, Overclaim, TbsCert},
         resources::ResourceSet,
-        x509::{Serial, Time},
+        x509::{Serial, Time, Validity},
     },
     uri,
 };
@@ -28,7 +28,7 @@ use rpki::{
 use crate::{
     commons::{
         actor::Actor,
-        api::{IdCertInfo, ObjectName, ReceivedCert},
+        api::{IdCertInfo, IssuedCertificate, ObjectName, ReceivedCert},
         crypto::{CsrInfo, KrillSigner, SignSupport},
         error::Error,
         eventsourcing::{
@@ -64,6 +64,99 @@ pub struct TrustAnchorSigner {
 
     // Proxy Signer Exchanges
     exchanges: TrustAnchorProxySignerExchanges,
+
+    // Staged key roll, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key_roll: Option<TaKeyRoll>,
+}
+
+//------------ TaCertReissueRequest ----------------------------------------
+
+/// Changes to apply when the TA certificate is re-issued. Values which are
+/// not set are kept as they are, except for the validity which is renewed
+/// using the configured number of years by default.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaCertReissueRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceSet>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tal_https: Option<Vec<uri::Https>>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tal_rsync: Option<uri::Rsync>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity_years: Option<i32>,
+}
+
+impl fmt::Display for TaCertReissueRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Re-issue TA certificate")?;
+        if let Some(resources) = &self.resources {
+            write!(f, ", resources: {}", resources)?;
+        }
+        if let Some(tal_rsync) = &self.tal_rsync {
+            write!(f, ", rsync uri: {}", tal_rsync)?;
+        }
+        if let Some(tal_https) = &self.tal_https {
+            for uri in tal_https {
+                write!(f, ", https uri: {}", uri)?;
+            }
+        }
+        if let Some(years) = self.validity_years {
+            write!(f, ", validity years: {}", years)?;
+        }
+        Ok(())
+    }
+}
+
+//------------ TaKeyRoll ---------------------------------------------------
+
+/// A staged Trust Anchor key roll, see RFC 9691.
+///
+/// The successor key and TA certificate are created when the key roll is
+/// initiated. The signed TALs for the current and successor key, and the
+/// objects for the successor key, are published as part of the next proxy
+/// signer exchange. The successor key is activated in the first exchange
+/// after the configured activation period has passed.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TaKeyRoll {
+    successor: TaCertDetails,
+
+    // Set when the signed TAL is first published.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    activate_after: Option<Time>,
+}
+
+impl TaKeyRoll {
+    pub fn successor(&self) -> &TaCertDetails {
+        &self.successor
+    }
+
+    pub fn activate_after(&self) -> Option<Time> {
+        self.activate_after
+    }
+}
+
+impl fmt::Display for TaKeyRoll {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.activate_after {
+            None => writeln!(
+                f,
+                "Key roll staged. The signed TAL will be published with the next proxy request."
+            )?,
+            Some(time) => writeln!(
+                f,
+                "Signed TAL published. Successor key will be activated with the first proxy request after: {}",
+                time.to_rfc3339_opts(SecondsFormat::Secs, false)
+            )?,
+        }
+        writeln!(f)?;
+        writeln!(f, "Successor TAL:")?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.successor.tal())
+    }
 }
 
 //------------ TrustAnchorSigner: Commands and Events ----------------------
@@ -121,8 +214,13 @@ impl fmt::Display for TrustAnchorSignerInitEvent {
 
 // Events
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[allow(clippy::large_enum_variant)]
 pub enum TrustAnchorSignerEvent {
     ProxySignerExchangeDone(TrustAnchorProxySignerExchange),
+    KeyRollStaged { successor: TaCertDetails },
+    KeyRollPublished { activate_after: Time },
+    KeyRollActivated,
+    TaCertReissued { ta_cert_details: TaCertDetails },
 }
 
 impl Event for TrustAnchorSignerEvent {}
@@ -138,6 +236,30 @@ impl fmt::Display for TrustAnchorSignerEvent {
                     exchange.request.content().nonce
                 )
             }
+            TrustAnchorSignerEvent::KeyRollStaged { successor } => {
+                write!(
+                    f,
+                    "Key roll staged for successor key: {}",
+                    successor.cert().key_identifier()
+                )
+            }
+            TrustAnchorSignerEvent::KeyRollPublished { activate_after } => {
+                write!(
+                    f,
+                    "Key roll published, activate after: {}",
+                    activate_after.to_rfc3339()
+                )
+            }
+            TrustAnchorSignerEvent::KeyRollActivated => {
+                write!(f, "Key roll activated successor key")
+            }
+            TrustAnchorSignerEvent::TaCertReissued { ta_cert_details } => {
+                write!(
+                    f,
+                    "TA certificate re-issued with resources: {}",
+                    ta_cert_details.resources()
+                )
+            }
         }
     }
 }
@@ -151,6 +273,17 @@ pub enum TrustAnchorSignerCommandDetails {
         ta_mft_number_override: Option<u64>,
         signer: Arc<KrillSigner>,
     },
+    KeyRollInit {
+        tal_https: Vec<uri::Https>,
+        tal_rsync: uri::Rsync,
+        ta_timing_config: TaTimingConfig,
+        signer: Arc<KrillSigner>,
+    },
+    ReissueTaCert {
+        request: TaCertReissueRequest,
+        ta_timing_config: TaTimingConfig,
+        signer: Arc<KrillSigner>,
+    },
 }
 
 impl eventsourcing::CommandDetails for TrustAnchorSignerCommandDetails {
@@ -189,6 +322,46 @@ impl TrustAnchorSignerCommand {
             actor,
         )
     }
+
+    pub fn make_key_roll_init_command(
+        id: &TrustAnchorHandle,
+        tal_https: Vec<uri::Https>,
+        tal_rsync: uri::Rsync,
+        ta_timing_config: TaTimingConfig,
+        signer: Arc<KrillSigner>,
+        actor: &Actor,
+    ) -> TrustAnchorSignerCommand {
+        TrustAnchorSignerCommand::new(
+            id,
+            None,
+            TrustAnchorSignerCommandDetails::KeyRollInit {
+                tal_https,
+                tal_rsync,
+                ta_timing_config,
+                signer,
+            },
+            actor,
+        )
+    }
+
+    pub fn make_reissue_ta_cert_command(
+        id: &TrustAnchorHandle,
+        request: TaCertReissueRequest,
+        ta_timing_config: TaTimingConfig,
+        signer: Arc<KrillSigner>,
+        actor: &Actor,
+    ) -> TrustAnchorSignerCommand {
+        TrustAnchorSignerCommand::new(
+            id,
+            None,
+            TrustAnchorSignerCommandDetails::ReissueTaCert {
+                request,
+                ta_timing_config,
+                signer,
+            },
+            actor,
+        )
+    }
 }
 
 // Storable Commands (KrillSigner cannot be de-/serialized)
@@ -196,6 +369,8 @@ impl TrustAnchorSignerCommand {
 pub enum TrustAnchorSignerStorableCommand {
     Init,
     TrustAnchorSignerRequest(TrustAnchorSignedRequest),
+    KeyRollInit(uri::Rsync),
+    ReissueTaCert(TaCertReissueRequest),
 }
 
 impl From<&TrustAnchorSignerCommandDetails>
@@ -209,6 +384,18 @@ impl From<&TrustAnchorSignerCommandDetails>
             } => TrustAnchorSignerStorableCommand::TrustAnchorSignerRequest(
                 signed_request.clone(),
             ),
+            TrustAnchorSignerCommandDetails::KeyRollInit {
+                tal_rsync,
+                ..
+            } => TrustAnchorSignerStorableCommand::KeyRollInit(
+                tal_rsync.clone(),
+            ),
+            TrustAnchorSignerCommandDetails::ReissueTaCert {
+                request,
+                ..
+            } => TrustAnchorSignerStorableCommand::ReissueTaCert(
+                request.clone(),
+            ),
         }
     }
 }
@@ -229,6 +416,19 @@ impl eventsourcing::WithStorableDetails for TrustAnchorSignerStorableCommand {
                 self,
             )
             .with_arg("nonce", &request.content().nonce),
+            TrustAnchorSignerStorableCommand::KeyRollInit(tal_rsync) => {
+                crate::commons::api::CommandSummary::new(
+                    "cmd-ta-signer-key-roll-init",
+                    self,
+                )
+                .with_arg("tal_rsync", tal_rsync)
+            }
+            TrustAnchorSignerStorableCommand::ReissueTaCert(_) => {
+                crate::commons::api::CommandSummary::new(
+                    "cmd-ta-signer-reissue",
+                    self,
+                )
+            }
         }
     }
 
@@ -253,6 +453,16 @@ impl fmt::Display for TrustAnchorSignerStorableCommand {
                     req.content().nonce
                 )
             }
+            TrustAnchorSignerStorableCommand::KeyRollInit(tal_rsync) => {
+                write!(
+                    f,
+                    "Initiate key roll with successor certificate at: {}",
+                    tal_rsync
+                )
+            }
+            TrustAnchorSignerStorableCommand::ReissueTaCert(request) => {
+                request.fmt(f)
+            }
         }
     }
 }
@@ -275,6 +485,7 @@ impl eventsourcing::Aggregate for TrustAnchorSigner {
             ta_cert_details: event.ta_cert_details,
             objects: event.objects,
             exchanges: TrustAnchorProxySignerExchanges::default(),
+            key_roll: None,
         }
     }
 
@@ -334,6 +545,25 @@ impl eventsourcing::Aggregate for TrustAnchorSigner {
                 self.objects = exchange.response.content().objects.clone();
                 self.exchanges.0.push(exchange);
             }
+            TrustAnchorSignerEvent::KeyRollStaged { successor } => {
+                self.key_roll = Some(TaKeyRoll {
+                    successor,
+                    activate_after: None,
+                });
+            }
+            TrustAnchorSignerEvent::KeyRollPublished { activate_after } => {
+                if let Some(key_roll) = self.key_roll.as_mut() {
+                    key_roll.activate_after = Some(activate_after);
+                }
+            }
+            TrustAnchorSignerEvent::KeyRollActivated => {
+                if let Some(key_roll) = self.key_roll.take() {
+                    self.ta_cert_details = key_roll.successor;
+                }
+            }
+            TrustAnchorSignerEvent::TaCertReissued { ta_cert_details } => {
+                self.ta_cert_details = ta_cert_details;
+            }
         }
     }
 
@@ -362,6 +592,26 @@ impl eventsourcing::Aggregate for TrustAnchorSigner {
                 ta_mft_number_override,
                 &signer,
             ),
+            TrustAnchorSignerCommandDetails::KeyRollInit {
+                tal_https,
+                tal_rsync,
+                ta_timing_config,
+                signer,
+            } => self.process_key_roll_init(
+                tal_https,
+                tal_rsync,
+                ta_timing_config,
+                &signer,
+            ),
+            TrustAnchorSignerCommandDetails::ReissueTaCert {
+                request,
+                ta_timing_config,
+                signer,
+            } => self.process_reissue_ta_cert(
+                request,
+                ta_timing_config,
+                &signer,
+            ),
         }
     }
 }
@@ -378,6 +628,10 @@ impl TrustAnchorSigner {
     pub fn get_associated_proxy_id(&self) -> &IdCertInfo {
         &self.proxy_id
     }
+
+    pub fn get_key_roll(&self) -> Option<&TaKeyRoll> {
+        self.key_roll.as_ref()
+    }
 }
 
 impl TrustAnchorSigner {
@@ -394,8 +648,27 @@ impl TrustAnchorSigner {
             Some(pem) => signer.import_key(&pem),
         }?;
 
-        let resources = ResourceSet::all();
+        Self::make_ta_cert_details(
+            key,
+            repo_info,
+            ResourceSet::all(),
+            tal_https,
+            tal_rsync,
+            SignSupport::sign_validity_years(years),
+            signer,
+        )
+    }
 
+    /// Makes a self-signed TA certificate and TAL for an existing key.
+    fn make_ta_cert_details(
+        key: KeyIdentifier,
+        repo_info: RepoInfo,
+        resources: ResourceSet,
+        tal_https: Vec<uri::Https>,
+        tal_rsync: uri::Rsync,
+        validity: Validity,
+        signer: &KrillSigner,
+    ) -> KrillResult<TaCertDetails> {
         let cert = {
             let serial: Serial = signer.random_serial()?;
 
@@ -405,7 +678,7 @@ impl TrustAnchorSigner {
             let mut cert = TbsCert::new(
                 serial,
                 name.clone(),
-                SignSupport::sign_validity_years(years),
+                validity,
                 Some(name),
                 pub_key.clone(),
                 KeyUsage::Ca,
@@ -453,6 +726,252 @@ impl TrustAnchorSigner {
         Ok(TaCertDetails::new(rcvd_cert, tal))
     }
 
+    /// Initiate a key roll. This creates the successor key and TA
+    /// certificate. Nothing is published until the next request is
+    /// processed.
+    fn process_key_roll_init(
+        &self,
+        tal_https: Vec<uri::Https>,
+        tal_rsync: uri::Rsync,
+        ta_timing_config: TaTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<Vec<TrustAnchorSignerEvent>> {
+        if self.key_roll.is_some() {
+            return Err(Error::TaKeyRollInProgress);
+        }
+
+        // Relying parties fetch the successor certificate while the current
+        // certificate is still in use, so they cannot share URIs.
+        let current_tal = self.ta_cert_details.tal();
+        if tal_rsync == *current_tal.rsync_uri()
+            || tal_https.iter().any(|uri| current_tal.uris().contains(uri))
+        {
+            return Err(Error::TaKeyRollUrisReused);
+        }
+
+        // The successor publishes in the same repository as the current
+        // key.
+        let repo_info = {
+            let (ca_repository, _, rpki_notify, _) =
+                self.ta_cert_details.cert().csr_info().clone().unpack();
+            RepoInfo::new(ca_repository, rpki_notify)
+        };
+
+        let successor = Self::create_ta_cert_details(
+            repo_info,
+            tal_https,
+            tal_rsync,
+            None,
+            ta_timing_config.certificate_validity_years,
+            signer,
+        )?;
+
+        Ok(vec![TrustAnchorSignerEvent::KeyRollStaged { successor }])
+    }
+
+    /// Re-issue the TA certificate using the same key. Certificates issued
+    /// to children are updated when the next request is processed.
+    fn process_reissue_ta_cert(
+        &self,
+        request: TaCertReissueRequest,
+        ta_timing_config: TaTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<Vec<TrustAnchorSignerEvent>> {
+        // The successor certificate was made for the current resources and
+        // URIs, so they should not change while a key roll is in progress.
+        if self.key_roll.is_some() {
+            return Err(Error::TaKeyRollInProgress);
+        }
+
+        let current = &self.ta_cert_details;
+
+        let resources = request
+            .resources
+            .unwrap_or_else(|| current.resources().clone());
+        if resources.is_empty() {
+            return Err(Error::custom(
+                "Cannot re-issue TA certificate without resources",
+            ));
+        }
+
+        let tal_https = request
+            .tal_https
+            .unwrap_or_else(|| current.tal().uris().clone());
+        let tal_rsync = request
+            .tal_rsync
+            .unwrap_or_else(|| current.tal().rsync_uri().clone());
+        let validity = SignSupport::sign_validity_years(
+            request
+                .validity_years
+                .unwrap_or(ta_timing_config.certificate_validity_years),
+        );
+
+        let repo_info = {
+            let (ca_repository, _, rpki_notify, _) =
+                current.cert().csr_info().clone().unpack();
+            RepoInfo::new(ca_repository, rpki_notify)
+        };
+
+        let ta_cert_details = Self::make_ta_cert_details(
+            current.cert().key_identifier(),
+            repo_info,
+            resources,
+            tal_https,
+            tal_rsync,
+            validity,
+            signer,
+        )?;
+
+        Ok(vec![TrustAnchorSignerEvent::TaCertReissued {
+            ta_cert_details,
+        }])
+    }
+
+    /// Updates certificates issued to children after the TA certificate was
+    /// re-issued. Certificates are re-issued if they refer to another TA
+    /// certificate URI, or if they claim resources no longer held by the
+    /// TA. Certificates are revoked if none of their resources are held.
+    fn update_issued_for_signing_cert(
+        objects: &mut TrustAnchorObjects,
+        signing_cert: &ReceivedCert,
+        ta_timing_config: &TaTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<()> {
+        let issued: Vec<IssuedCertificate> =
+            objects.issued().cloned().collect();
+
+        for issued in issued {
+            let issuer_changed = issued
+                .to_cert()
+                .ok()
+                .and_then(|cert| cert.ca_issuer().cloned())
+                .as_ref()
+                != Some(signing_cert.uri());
+
+            let resources = match issued
+                .reduced_applicable_resources(signing_cert.resources())
+            {
+                Some(reduced) if reduced.is_empty() => {
+                    objects.revoke_issued(&issued.key_identifier());
+                    continue;
+                }
+                Some(reduced) => reduced,
+                None if issuer_changed => issued.resources().clone(),
+                None => continue,
+            };
+
+            let re_issued = SignSupport::make_issued_cert(
+                issued.csr_info().clone(),
+                &resources,
+                issued.limit().clone(),
+                signing_cert,
+                SignSupport::sign_validity_weeks(
+                    ta_timing_config.issued_certificate_validity_weeks,
+                ),
+                signer,
+            )?;
+            objects.add_issued(re_issued);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the objects for the successor key, with all certificates
+    /// issued to children re-issued under the successor key.
+    fn activate_successor_objects(
+        &self,
+        key_roll: &TaKeyRoll,
+        ta_timing_config: &TaTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<TrustAnchorObjects> {
+        let successor_cert = key_roll.successor.cert();
+
+        let mut objects = match self.objects.successor() {
+            Some(objects) => objects.clone(),
+            None => {
+                // The successor objects are always published before the
+                // key roll can be activated.
+                return Err(Error::custom(
+                    "No objects found for the TA successor key",
+                ));
+            }
+        };
+
+        let validity = SignSupport::sign_validity_weeks(
+            ta_timing_config.issued_certificate_validity_weeks,
+        );
+
+        for issued in self.objects.issued() {
+            let re_issued = SignSupport::make_issued_cert(
+                issued.csr_info().clone(),
+                issued.resources(),
+                issued.limit().clone(),
+                successor_cert,
+                validity,
+                signer,
+            )?;
+            objects.add_issued(re_issued);
+        }
+
+        // The signed TAL for the successor key listed the current key as
+        // its predecessor. It is no longer needed.
+        objects.set_tak(None);
+
+        Ok(objects)
+    }
+
+    /// Publishes the signed TALs for the current and successor key, and
+    /// the objects for the successor key.
+    fn publish_key_roll(
+        &self,
+        key_roll: &TaKeyRoll,
+        objects: &mut TrustAnchorObjects,
+        ta_timing_config: &TaTimingConfig,
+        signer: &KrillSigner,
+    ) -> KrillResult<()> {
+        let current = &self.ta_cert_details;
+        let successor = &key_roll.successor;
+
+        let tak_validity = SignSupport::sign_validity_weeks(
+            ta_timing_config.mft_next_update_weeks,
+        );
+
+        let mut successor_objects = match objects.successor() {
+            Some(successor_objects) => successor_objects.clone(),
+            None => TrustAnchorObjects::create(
+                successor.cert(),
+                1,
+                ta_timing_config.mft_next_update_weeks,
+                signer,
+            )?,
+        };
+
+        successor_objects.set_tak(Some(
+            Tak::with_predecessor(successor, current).make_object(
+                successor.cert(),
+                tak_validity,
+                signer,
+            )?,
+        ));
+        successor_objects.republish(
+            successor.cert(),
+            ta_timing_config.mft_next_update_weeks,
+            None,
+            signer,
+        )?;
+
+        objects.set_tak(Some(
+            Tak::with_successor(current, successor).make_object(
+                current.cert(),
+                tak_validity,
+                signer,
+            )?,
+        ));
+        objects.set_successor(Some(successor_objects));
+
+        Ok(())
+    }
+
     /// Process a request.
     fn process_signer_request(
         &self,
@@ -465,14 +984,43 @@ impl TrustAnchorSigner {
         // and the 'content' is not tampered with.
         signed_request.validate(&self.proxy_id)?;
 
-        let mut objects = self.objects.clone();
+        let mut events = vec![];
+
+        // If a key roll was published long enough ago, then activate the
+        // successor key now. Certificates issued in this request will then
+        // be signed under the successor key.
+        let activate_key_roll = self.key_roll.as_ref().filter(|key_roll| {
+            key_roll
+                .activate_after
+                .map(|after| after <= Time::now())
+                .unwrap_or(false)
+        });
+
+        let (mut objects, signing_cert) = match activate_key_roll {
+            Some(key_roll) => {
+                let objects = self.activate_successor_objects(
+                    key_roll,
+                    &ta_timing_config,
+                    signer,
+                )?;
+                events.push(TrustAnchorSignerEvent::KeyRollActivated);
+                (objects, key_roll.successor.cert())
+            }
+            None => (self.objects.clone(), self.ta_cert_details.cert()),
+        };
+
+        Self::update_issued_for_signing_cert(
+            &mut objects,
+            signing_cert,
+            &ta_timing_config,
+            signer,
+        )?;
 
         let mut child_responses: HashMap<
             ChildHandle,
             HashMap<KeyIdentifier, ProvisioningResponse>,
         > = HashMap::new();
 
-        let signing_cert = self.ta_cert_details.cert();
         let ta_rcn = ta_resource_class_name();
 
         for child_request in &signed_request.content().child_requests {
@@ -496,8 +1044,13 @@ impl TrustAnchorSigner {
                             ta_timing_config
                                 .issued_certificate_validity_weeks,
                         );
-                        let issue_resources =
-                            limit.apply_to(&child_request.resources)?;
+                        // Children cannot get resources that are no longer
+                        // held by the TA.
+                        let issue_resources = limit.apply_to(
+                            &child_request
+                                .resources
+                                .intersection(signing_cert.resources()),
+                        )?;
 
                         // Create issued certificate
                         let issued_cert = SignSupport::make_issued_cert(
@@ -576,6 +1129,27 @@ impl TrustAnchorSigner {
             child_responses.insert(child_request.child.clone(), responses);
         }
 
+        if activate_key_roll.is_none() {
+            if let Some(key_roll) = &self.key_roll {
+                self.publish_key_roll(
+                    key_roll,
+                    &mut objects,
+                    &ta_timing_config,
+                    signer,
+                )?;
+
+                if key_roll.activate_after.is_none() {
+                    let activate_after = Time::now()
+                        + chrono::Duration::days(
+                            ta_timing_config.key_roll_activation_days,
+                        );
+                    events.push(TrustAnchorSignerEvent::KeyRollPublished {
+                        activate_after,
+                    });
+                }
+            }
+        }
+
         objects.republish(
             signing_cert,
             ta_timing_config.mft_next_update_weeks,
@@ -583,10 +1157,17 @@ impl TrustAnchorSigner {
             signer,
         )?;
 
+        let ta_cert_details = Some(
+            activate_key_roll
+                .map(|key_roll| key_roll.successor.clone())
+                .unwrap_or_else(|| self.ta_cert_details.clone()),
+        );
+
         let response = TrustAnchorSignerResponse {
             nonce: signed_request.content().nonce.clone(),
             objects,
             child_responses,
+            ta_cert_details,
         }
         .sign(
             ta_timing_config.signed_message_validity_days,
@@ -600,9 +1181,40 @@ impl TrustAnchorSigner {
             response,
         };
 
-        Ok(vec![TrustAnchorSignerEvent::ProxySignerExchangeDone(
-            exchange,
-        )])
+        events
+            .push(TrustAnchorSignerEvent::ProxySignerExchangeDone(exchange));
+
+        Ok(events)
+    }
+
+    /// Process a request without changing this signer, and return how the
+    /// response would differ from the current state. The objects are
+    /// signed as usual, but then discarded.
+    pub fn dry_run_signer_request(
+        &self,
+        signed_request: TrustAnchorSignedRequest,
+        ta_timing_config: TaTimingConfig,
+        ta_mft_number_override: Option<u64>,
+        signer: &KrillSigner,
+    ) -> KrillResult<TrustAnchorSignerResponseDiff> {
+        self.process_signer_request(
+            signed_request,
+            ta_timing_config,
+            ta_mft_number_override,
+            signer,
+        )?
+        .into_iter()
+        .find_map(|event| match event {
+            TrustAnchorSignerEvent::ProxySignerExchangeDone(exchange) => {
+                Some(TrustAnchorSignerResponseDiff::new(
+                    &self.objects,
+                    &self.ta_cert_details,
+                    exchange.response.content(),
+                ))
+            }
+            _ => None,
+        })
+        .ok_or_else(|| Error::custom("No response for request"))
     }
 
     /// Get all exchanges
@@ -0,0 +1,174 @@
+//! Signed TALs, see RFC 9691.
+//!
+//! A Trust Anchor Key (TAK) object is published under a TA certificate to
+//! let relying parties know about the current key of the TA, and - during a
+//! key roll - about its successor or predecessor. Relying parties can then
+//! update their configured TALs automatically.
+
+use bcder::{
+    encode::{self, Values},
+    Mode, OctetString, Tag,
+};
+use bytes::Bytes;
+use rpki::{
+    ca::publication::Base64,
+    crypto::PublicKey,
+    repository::{
+        sigobj::SignedObjectBuilder,
+        x509::{Time, Validity},
+    },
+};
+
+use crate::{
+    commons::{
+        api::{ObjectName, ReceivedCert},
+        crypto::KrillSigner,
+        KrillResult,
+    },
+    daemon::ca::PublishedObject,
+};
+
+use super::TaCertDetails;
+
+//------------ TakKey --------------------------------------------------------
+
+/// A TA public key and the URIs where its certificate can be found. This
+/// corresponds to the TAKey structure in RFC 9691.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TakKey {
+    comments: Vec<String>,
+    certificate_uris: Vec<String>,
+    public_key: PublicKey,
+}
+
+impl TakKey {
+    fn encode_ref(&self) -> impl Values + '_ {
+        encode::sequence((
+            encode::sequence(encode::iter(self.comments.iter().map(|c| {
+                OctetString::encode_slice_as(c.as_bytes(), Tag::UTF8_STRING)
+            }))),
+            encode::sequence(encode::iter(self.certificate_uris.iter().map(
+                |uri| {
+                    OctetString::encode_slice_as(
+                        uri.as_bytes(),
+                        Tag::IA5_STRING,
+                    )
+                },
+            ))),
+            self.public_key.encode_ref(),
+        ))
+    }
+}
+
+impl From<&TaCertDetails> for TakKey {
+    fn from(details: &TaCertDetails) -> Self {
+        let tal = details.tal();
+
+        let mut certificate_uris: Vec<String> =
+            tal.uris().iter().map(|uri| uri.to_string()).collect();
+        certificate_uris.push(tal.rsync_uri().to_string());
+
+        TakKey {
+            comments: vec![],
+            certificate_uris,
+            public_key: details.cert().csr_info().key().clone(),
+        }
+    }
+}
+
+//------------ Tak -----------------------------------------------------------
+
+/// The content of a TAK object.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tak {
+    current: TakKey,
+    predecessor: Option<TakKey>,
+    successor: Option<TakKey>,
+}
+
+impl Tak {
+    /// Creates the TAK published under the current key during a key roll.
+    pub fn with_successor(
+        current: &TaCertDetails,
+        successor: &TaCertDetails,
+    ) -> Self {
+        Tak {
+            current: current.into(),
+            predecessor: None,
+            successor: Some(successor.into()),
+        }
+    }
+
+    /// Creates the TAK published under the successor key during a key roll.
+    pub fn with_predecessor(
+        current: &TaCertDetails,
+        predecessor: &TaCertDetails,
+    ) -> Self {
+        Tak {
+            current: current.into(),
+            predecessor: Some(predecessor.into()),
+            successor: None,
+        }
+    }
+
+    /// Returns the DER encoded content. The version is left out because
+    /// it uses the default value 0.
+    pub fn to_bytes(&self) -> Bytes {
+        encode::sequence((
+            self.current.encode_ref(),
+            self.predecessor
+                .as_ref()
+                .map(|key| encode::sequence_as(Tag::CTX_0, key.encode_ref())),
+            self.successor
+                .as_ref()
+                .map(|key| encode::sequence_as(Tag::CTX_1, key.encode_ref())),
+        ))
+        .to_captured(Mode::Der)
+        .into_bytes()
+    }
+
+    /// Signs this TAK as an object to be published under the given TA
+    /// certificate. The key of the certificate MUST be the current key.
+    pub fn make_object(
+        &self,
+        signing_cert: &ReceivedCert,
+        validity: Validity,
+        signer: &KrillSigner,
+    ) -> KrillResult<PublishedObject> {
+        let name = ObjectName::new(&signing_cert.key_identifier(), "tak");
+
+        let mut object_builder = SignedObjectBuilder::new(
+            signer.random_serial()?,
+            validity,
+            signing_cert.crl_uri(),
+            signing_cert.uri().clone(),
+            signing_cert.uri_for_name(&name),
+        );
+        object_builder.set_issuer(Some(signing_cert.subject().clone()));
+        object_builder.set_signing_time(Some(Time::now()));
+
+        // The TAK does not make any claims about resources, so the EE
+        // certificate uses "inherit" like Ghostbuster records do.
+        object_builder.set_v4_resources_inherit();
+        object_builder.set_v6_resources_inherit();
+        object_builder.set_as_resources_inherit();
+
+        let object = signer.sign_tak(
+            self.to_bytes(),
+            object_builder,
+            &signing_cert.key_identifier(),
+        )?;
+
+        let serial = object.cert().serial_number();
+        let base64 = Base64::from_content(
+            object.encode_ref().to_captured(Mode::Der).as_slice(),
+        );
+
+        Ok(PublishedObject::new(
+            name,
+            base64,
+            serial,
+            validity.not_after(),
+        ))
+    }
+}
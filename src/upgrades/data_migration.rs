@@ -2,6 +2,7 @@
 
 use std::{str::FromStr, sync::Arc};
 
+use fslock::LockFile;
 use kvx::{Namespace, Scope};
 use rpki::crypto::KeyIdentifier;
 use url::Url;
@@ -17,12 +18,16 @@ use crate::{
         },
     },
     constants::{
-        CASERVER_NS, KEYS_NS, PROPERTIES_NS, PUBSERVER_CONTENT_NS,
-        PUBSERVER_NS, SIGNERS_NS, TA_PROXY_SERVER_NS, TA_SIGNER_SERVER_NS,
+        CASERVER_NS, ENCRYPTION_NS, KEYS_NS, KRILL_DATA_NAMESPACES,
+        PROPERTIES_NS, PUBSERVER_CONTENT_NS, PUBSERVER_NS, SIGNERS_NS,
+        TA_PROXY_SERVER_NS, TA_SIGNER_SERVER_NS,
     },
     daemon::{
+        backup::NamespaceManifest,
         ca::{CaObjectsStore, CertAuth},
         config::Config,
+        encryption::enable_storage_encryption,
+        ha::HaManager,
         properties::{Properties, PropertiesManager},
     },
     pubd::{RepositoryAccess, RepositoryContent},
@@ -35,6 +40,9 @@ use crate::{
 
 use super::UpgradeResult;
 
+/// Copies all data from the `storage_uri` in the config to the target
+/// storage, and then upgrades the copy if it is for an older version of
+/// Krill. The source data is not modified.
 pub fn migrate(mut config: Config, target_storage: Url) -> UpgradeResult<()> {
     // Copy the source data from config unmodified into the target_storage
     info!("-----------------------------------------------------------");
@@ -49,6 +57,14 @@ pub fn migrate(mut config: Config, target_storage: Url) -> UpgradeResult<()> {
     info!("-----------------------------------------------------------");
     info!("");
 
+    check_source_not_in_use(&config)?;
+    if config.storage_uri == target_storage {
+        return Err(UpgradeError::custom(
+            "The source and target storage are the same.",
+        ));
+    }
+
+    enable_storage_encryption(&config)?;
     copy_data_for_migration(&config, &target_storage)?;
 
     // Update the config file with the new target_storage
@@ -238,34 +254,111 @@ fn check_ca_objects(
     Ok(())
 }
 
+/// Refuses to migrate data that is in use by a running Krill daemon. That
+/// daemon keeps its PID file locked, or holds the leader lease if there are
+/// several nodes. The source storage may have been given with `--from`, so
+/// the default PID file for local source storage is checked as well as the
+/// PID file from the config.
+fn check_source_not_in_use(config: &Config) -> UpgradeResult<()> {
+    let mut pid_files = vec![config.pid_file().clone()];
+    if let Some(pid_file) = config.storage_pid_file() {
+        if !pid_files.contains(&pid_file) {
+            pid_files.push(pid_file);
+        }
+    }
+
+    for pid_file in &pid_files {
+        if !pid_file.exists() {
+            continue;
+        }
+        let locked = LockFile::open(pid_file)
+            .and_then(|mut lock| lock.try_lock())
+            .map(|free| !free)
+            .map_err(|e| {
+                UpgradeError::Custom(format!(
+                    "Cannot check PID file {}. Error: {}",
+                    pid_file.display(),
+                    e
+                ))
+            })?;
+        if locked {
+            return Err(UpgradeError::Custom(format!(
+                "The data is in use by a running Krill daemon, which holds the lock on PID file {}. Stop Krill before migrating the data.",
+                pid_file.display()
+            )));
+        }
+    }
+
+    if let Some(node) = HaManager::active_leader(&config.storage_uri)? {
+        return Err(UpgradeError::Custom(format!(
+            "The data is in use by Krill node '{}', which holds the leader lease. Stop all Krill nodes before migrating the data.",
+            node
+        )));
+    }
+
+    Ok(())
+}
+
 fn copy_data_for_migration(
     config: &Config,
     target_storage: &Url,
 ) -> UpgradeResult<()> {
-    for ns in &[
-        "ca_objects",
-        "cas",
-        "keys",
-        "pubd",
-        "pubd_objects",
-        "signers",
-        "status",
-        "ta_proxy",
-        "ta_signer",
-    ] {
-        let namespace = Namespace::parse(ns).map_err(|_| {
-            UpgradeError::Custom(format!(
-                "Cannot parse namespace '{}'. This is a bug.",
-                ns
-            ))
-        })?;
+    // The wrapped data key and the signer mappings are never encrypted, and
+    // they are needed to enable storage encryption for the target.
+    copy_namespaces(config, target_storage, &[ENCRYPTION_NS, SIGNERS_NS])?;
+
+    let mut target_config = config.clone();
+    target_config.storage_uri = target_storage.clone();
+    enable_storage_encryption(&target_config)?;
+
+    copy_namespaces(config, target_storage, KRILL_DATA_NAMESPACES)
+}
+
+/// Copies the namespaces to the target storage, which must not have any
+/// data for them yet. The number of keys and the versions of all
+/// aggregates in each copy are checked against the source.
+fn copy_namespaces(
+    config: &Config,
+    target_storage: &Url,
+    namespaces: &[&Namespace],
+) -> UpgradeResult<()> {
+    for namespace in namespaces {
         let source_kv_store =
             KeyValueStore::create(&config.storage_uri, namespace)?;
-        if !source_kv_store.is_empty()? {
-            let target_kv_store =
-                KeyValueStore::create(target_storage, namespace)?;
-            target_kv_store.import(&source_kv_store)?;
+        if source_kv_store.is_empty()? {
+            continue;
+        }
+
+        let target_kv_store =
+            KeyValueStore::create(target_storage, namespace)?;
+        if !target_kv_store.is_empty()? {
+            return Err(UpgradeError::Custom(format!(
+                "The target storage already contains data for namespace '{}'.",
+                namespace
+            )));
         }
+        target_kv_store.import(&source_kv_store)?;
+
+        let source =
+            NamespaceManifest::for_storage(&config.storage_uri, namespace)?;
+        let target =
+            NamespaceManifest::for_storage(target_storage, namespace)?;
+        if source != target {
+            return Err(UpgradeError::Custom(format!(
+                "The copy of namespace '{}' has {} keys and {} aggregates, but the source has {} keys and {} aggregates, or different aggregate versions.",
+                namespace,
+                target.keys(),
+                target.aggregates().len(),
+                source.keys(),
+                source.aggregates().len(),
+            )));
+        }
+        info!(
+            "Copied namespace '{}': {} keys, {} aggregates",
+            namespace,
+            source.keys(),
+            source.aggregates().len()
+        );
     }
 
     Ok(())
@@ -307,4 +400,74 @@ pub mod tests {
 
         migrate(config, target_store).unwrap();
     }
+
+    #[test]
+    fn migration_refuses_target_with_data() {
+        let test_sources_url =
+            Url::parse("local://test-resources/migrations/v0_13_1/").unwrap();
+        let bogus_path = PathBuf::from("/dev/null");
+        let config = Config::test(
+            &test_sources_url,
+            Some(&bogus_path),
+            false,
+            false,
+            false,
+            false,
+        );
+
+        let target_store = test::mem_storage();
+        migrate(config.clone(), target_store.clone()).unwrap();
+        let err = migrate(config, target_store).unwrap_err();
+        assert!(err.to_string().contains("already contains data"));
+    }
+
+    #[test]
+    fn migration_refuses_source_with_leader() {
+        let source_store = test::mem_storage();
+        let bogus_path = PathBuf::from("/dev/null");
+        let mut config = Config::test(
+            &source_store,
+            Some(&bogus_path),
+            false,
+            false,
+            false,
+            false,
+        );
+        config.ha_node = Some("a".to_string());
+
+        let ha = HaManager::create(Arc::new(config.clone())).unwrap();
+        ha.heartbeat(false).unwrap();
+
+        let err = migrate(config, test::mem_storage()).unwrap_err();
+        assert!(err.to_string().contains("leader lease"));
+    }
+
+    #[test]
+    fn migration_refuses_source_with_locked_pid_file() {
+        // The config is for other storage, as if the source was given with
+        // --from, so only the PID file in the source dir is locked.
+        let (source_dir, cleanup) = test::tmp_dir();
+        std::fs::create_dir_all(&source_dir).unwrap();
+        let mut lock = LockFile::open(&source_dir.join("krill.pid")).unwrap();
+        assert!(lock.try_lock_with_pid().unwrap());
+
+        let source_store =
+            Url::parse(&format!("local://{}/", source_dir.display()))
+                .unwrap();
+        let bogus_path = PathBuf::from("/dev/null");
+        let config = Config::test(
+            &source_store,
+            Some(&bogus_path),
+            false,
+            false,
+            false,
+            false,
+        );
+
+        let err = migrate(config, test::mem_storage()).unwrap_err();
+        assert!(err.to_string().contains("krill.pid"));
+
+        drop(lock);
+        cleanup();
+    }
 }
@@ -0,0 +1,167 @@
+//! Verify the consistency of the data in storage.
+
+use std::fmt;
+
+use kvx::Namespace;
+use rpki::ca::idexchange::MyHandle;
+
+use crate::{
+    commons::{
+        crypto::dispatch::signerinfo::SignerInfo,
+        eventsourcing::{
+            Aggregate, AggregateStore, StorageIssue, WalStore, WalSupport,
+        },
+    },
+    constants::{
+        CASERVER_NS, PROPERTIES_NS, PUBSERVER_CONTENT_NS, PUBSERVER_NS,
+        SIGNERS_NS, TA_PROXY_SERVER_NS, TA_SIGNER_SERVER_NS,
+    },
+    daemon::{ca::CertAuth, config::Config, properties::Properties},
+    pubd::{RepositoryAccess, RepositoryContent},
+    ta::{TrustAnchorProxy, TrustAnchorSigner},
+};
+
+use super::UpgradeResult;
+
+/// Verifies all aggregates and WAL supporting instances in the storage
+/// used by the given config. The data is only read, so this can be done
+/// while Krill is running, although changes made during verification may
+/// then show up as issues.
+pub fn verify(config: &Config) -> UpgradeResult<DataVerificationReport> {
+    let mut report = DataVerificationReport::default();
+
+    verify_agg_store::<Properties>(
+        config,
+        PROPERTIES_NS,
+        "Properties",
+        &mut report,
+    )?;
+    verify_agg_store::<SignerInfo>(
+        config,
+        SIGNERS_NS,
+        "Signer",
+        &mut report,
+    )?;
+    verify_agg_store::<CertAuth>(config, CASERVER_NS, "CA", &mut report)?;
+    verify_agg_store::<RepositoryAccess>(
+        config,
+        PUBSERVER_NS,
+        "Publication Server Access",
+        &mut report,
+    )?;
+    verify_wal_store::<RepositoryContent>(
+        config,
+        PUBSERVER_CONTENT_NS,
+        "Publication Server Objects",
+        &mut report,
+    )?;
+    verify_agg_store::<TrustAnchorProxy>(
+        config,
+        TA_PROXY_SERVER_NS,
+        "TA Proxy",
+        &mut report,
+    )?;
+    verify_agg_store::<TrustAnchorSigner>(
+        config,
+        TA_SIGNER_SERVER_NS,
+        "TA Signer",
+        &mut report,
+    )?;
+
+    Ok(report)
+}
+
+fn verify_agg_store<A: Aggregate>(
+    config: &Config,
+    ns: &Namespace,
+    name: &'static str,
+    report: &mut DataVerificationReport,
+) -> UpgradeResult<()> {
+    let store: AggregateStore<A> =
+        AggregateStore::create(&config.storage_uri, ns, false)?;
+    for handle in store.list()? {
+        let issues = store.verify(&handle)?;
+        report.add(name, handle, issues);
+    }
+    Ok(())
+}
+
+fn verify_wal_store<W: WalSupport>(
+    config: &Config,
+    ns: &Namespace,
+    name: &'static str,
+    report: &mut DataVerificationReport,
+) -> UpgradeResult<()> {
+    let store: WalStore<W> = WalStore::create(&config.storage_uri, ns)?;
+    for handle in store.list()? {
+        let issues = store.verify(&handle)?;
+        report.add(name, handle, issues);
+    }
+    Ok(())
+}
+
+//------------ DataVerificationReport ----------------------------------------
+
+/// The issues found for each verified instance.
+#[derive(Clone, Debug, Default)]
+pub struct DataVerificationReport {
+    instances: Vec<InstanceVerification>,
+}
+
+impl DataVerificationReport {
+    fn add(
+        &mut self,
+        store: &'static str,
+        handle: MyHandle,
+        issues: Vec<StorageIssue>,
+    ) {
+        self.instances.push(InstanceVerification {
+            store,
+            handle,
+            issues,
+        })
+    }
+
+    /// Returns true if no issues were found.
+    pub fn is_ok(&self) -> bool {
+        self.instances.iter().all(|i| i.issues.is_empty())
+    }
+
+    /// Returns the issues found for the instance in the named store.
+    pub fn issues(
+        &self,
+        store: &str,
+        handle: &MyHandle,
+    ) -> Option<&Vec<StorageIssue>> {
+        self.instances
+            .iter()
+            .find(|i| i.store == store && &i.handle == handle)
+            .map(|i| &i.issues)
+    }
+}
+
+impl fmt::Display for DataVerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for instance in &self.instances {
+            if instance.issues.is_empty() {
+                writeln!(f, "{} '{}': ok", instance.store, instance.handle)?;
+            } else {
+                for issue in &instance.issues {
+                    writeln!(
+                        f,
+                        "{} '{}': {}",
+                        instance.store, instance.handle, issue
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct InstanceVerification {
+    store: &'static str,
+    handle: MyHandle,
+    issues: Vec<StorageIssue>,
+}
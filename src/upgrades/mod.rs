@@ -54,6 +54,8 @@ use self::{pre_0_13_0::OldRepositoryContent, pre_0_14_0::OldCommandKey};
 
 pub mod data_migration;
 
+pub mod data_verification;
+
 pub mod pre_0_10_0;
 
 #[allow(clippy::mutable_key_type)]
@@ -1239,6 +1241,7 @@ pub async fn post_start_upgrade(
             .ca_aspas_definitions_update(
                 ca,
                 aspa_updates,
+                None,
                 server.system_actor(),
             )
             .await?;
@@ -1247,6 +1250,16 @@ pub async fn post_start_upgrade(
     Ok(())
 }
 
+/// Returns whether the data in storage must be upgraded before this Krill
+/// version can use it. Nothing is changed. Returns an error if the data is
+/// newer than this Krill version.
+pub fn upgrade_needed(
+    config: &Config,
+    properties_manager: &PropertiesManager,
+) -> UpgradeResult<bool> {
+    upgrade_versions(config, properties_manager).map(|v| v.is_some())
+}
+
 /// Checks if we should upgrade:
 ///  - if the code is newer than the version used then we upgrade
 ///  - if the code is the same version then we do not upgrade
@@ -1379,6 +1392,10 @@ mod tests {
             &properties_manager,
         )
         .unwrap();
+
+        // The migrated data should be consistent.
+        let verification = data_verification::verify(&config).unwrap();
+        assert!(verification.is_ok(), "{}", verification);
     }
 
     #[test]
@@ -0,0 +1,130 @@
+//! Test the audit log of commands for all CAs, the publication server and
+//! the TA proxy.
+use rpki::repository::resources::ResourceSet;
+
+use krill::{
+    commons::api::{
+        AuditCriteria, AuditFormat, AuditSource, RoaConfigurationUpdates,
+    },
+    test::*,
+};
+
+#[tokio::test]
+async fn functional_audit() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    let testbed = ca_handle("testbed");
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(roa_configuration("10.0.0.0/24 => 64496"));
+    ca_route_authorizations_update(&testbed, updates).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# The audit log includes CAs, the publication server and the TA  #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let all = audit_log(AuditCriteria::default()).await;
+    for source in [AuditSource::Ca, AuditSource::Pubd, AuditSource::TaProxy] {
+        assert!(all.iter().any(|record| record.source == source));
+    }
+    assert!(all.windows(2).all(|pair| pair[0].time <= pair[1].time));
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Filter the audit log by command type, handle and actor         #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let roas_updated = audit_log(AuditCriteria {
+        command: Some("cmd-ca-roas-updated".to_string()),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(1, roas_updated.len());
+    let record = &roas_updated[0];
+    assert_eq!(AuditSource::Ca, record.source);
+    assert_eq!(testbed, record.handle);
+    assert_eq!("admin-token", record.actor);
+    assert_eq!("ok", record.result);
+
+    let by_handle = audit_log(AuditCriteria {
+        handle: Some(testbed.clone()),
+        ..Default::default()
+    })
+    .await;
+    assert!(!by_handle.is_empty());
+    assert!(by_handle.iter().all(|record| record.handle == testbed));
+
+    let by_actor = audit_log(AuditCriteria {
+        actor: Some("admin-token".to_string()),
+        ..Default::default()
+    })
+    .await;
+    assert!(by_actor.contains(record));
+    assert!(by_actor.iter().all(|record| record.actor == "admin-token"));
+
+    let after = audit_log(AuditCriteria {
+        after: Some(record.time),
+        ..Default::default()
+    })
+    .await;
+    assert!(after.contains(record));
+    assert!(after.iter().all(|r| r.time >= record.time));
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Export the audit log as CSV                                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let csv =
+        audit_log_text(AuditCriteria::default(), AuditFormat::Csv).await;
+    let mut lines = csv.lines();
+    assert_eq!(
+        Some("time,source,handle,version,actor,command,message,result,error"),
+        lines.next()
+    );
+    assert_eq!(all.len(), lines.count());
+
+    assert_eq!("text/csv", audit_log_content_type(AuditFormat::Csv).await);
+    assert_eq!(
+        "application/x-ndjson",
+        audit_log_content_type(AuditFormat::JsonLines).await
+    );
+
+    cleanup();
+}
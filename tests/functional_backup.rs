@@ -0,0 +1,126 @@
+//! Back up all data of a running Krill instance and restore it
+use krill::{
+    commons::{
+        api::RoaConfigurationUpdates, eventsourcing::DataKey, util::file,
+    },
+    daemon::{backup::KrillBackup, config::StorageEncryptionConfig},
+    test::*,
+    upgrades::data_verification::verify,
+};
+
+#[tokio::test]
+async fn functional_backup() {
+    let (data_dir, cleanup) = tmp_dir();
+    let storage_uri = mem_storage();
+    let config =
+        test_config(&storage_uri, Some(&data_dir), true, false, false, false);
+    start_krill(config.clone()).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Test backing up all data and restoring it into new storage.    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca = ca_handle("CA");
+    let ca_res = resources("", "10.0.0.0/16", "");
+
+    set_up_ca_with_repo(&ca).await;
+    set_up_ca_under_parent_with_resources(&ca, &testbed, &ca_res).await;
+
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(roa_configuration("10.0.0.0/16-16 => 64496"));
+    ca_route_authorizations_update(&ca, updates).await;
+
+    let backup_file = data_dir.join("krill-backup.json");
+    let manifest = admin_backup(&backup_file).await;
+
+    let cas = manifest
+        .namespaces()
+        .iter()
+        .find(|ns| ns.namespace() == "cas")
+        .unwrap();
+    assert!(cas.aggregates().contains_key("CA"));
+    assert!(cas.aggregates().contains_key("testbed"));
+
+    let backup: KrillBackup =
+        serde_json::from_slice(&file::read(&backup_file).unwrap()).unwrap();
+    assert_eq!(&manifest, backup.manifest());
+
+    assert!(!manifest.encrypted());
+
+    info("Restore the backup into empty storage");
+    let mut restored_config = config.clone();
+    restored_config.storage_uri = mem_storage();
+    backup.restore(&restored_config).unwrap();
+
+    info("Restoring again fails, because the storage is no longer empty");
+    assert!(backup.restore(&restored_config).is_err());
+
+    info("Restored data is consistent and has the same content");
+    let report = verify(&restored_config).unwrap();
+    assert!(report.is_ok(), "{}", report);
+    assert_eq!(Some(&vec![]), report.issues("CA", &ca.convert()));
+
+    let restored = KrillBackup::create(&restored_config.storage_uri).unwrap();
+    assert_eq!(manifest.namespaces(), restored.manifest().namespaces());
+
+    info(
+        "Restore the backup into storage that is configured to be encrypted",
+    );
+    let passphrase_file = data_dir.join("passphrase");
+    file::save(b"correct horse battery staple", &passphrase_file).unwrap();
+    let mut encrypted_config = config.clone();
+    encrypted_config.storage_uri = mem_storage();
+    encrypted_config.storage_encryption = Some(StorageEncryptionConfig {
+        passphrase_file: Some(passphrase_file),
+        signer: None,
+    });
+    backup.restore(&encrypted_config).unwrap();
+    let report = verify(&encrypted_config).unwrap();
+    assert!(report.is_ok(), "{}", report);
+
+    info("A backup of encrypted data stays encrypted");
+    let encrypted =
+        KrillBackup::create(&encrypted_config.storage_uri).unwrap();
+    assert!(encrypted.manifest().encrypted());
+    let json = serde_json::to_value(&encrypted).unwrap();
+    for data in json["data"].as_array().unwrap() {
+        if !["encryption", "signers"]
+            .contains(&data["namespace"].as_str().unwrap())
+        {
+            let values = data["values"].as_object().unwrap();
+            assert!(values.values().all(DataKey::is_encrypted));
+        }
+    }
+
+    info("It can only be restored with the same storage encryption");
+    let mut plain_config = config.clone();
+    plain_config.storage_uri = mem_storage();
+    assert!(encrypted.restore(&plain_config).is_err());
+    assert!(KrillBackup::create(&plain_config.storage_uri)
+        .unwrap()
+        .manifest()
+        .namespaces()
+        .iter()
+        .all(|ns| ns.keys() == 0));
+
+    encrypted_config.storage_uri = mem_storage();
+    encrypted.restore(&encrypted_config).unwrap();
+    let report = verify(&encrypted_config).unwrap();
+    assert!(report.is_ok(), "{}", report);
+
+    cleanup();
+}
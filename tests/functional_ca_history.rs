@@ -0,0 +1,60 @@
+//! Perform functional tests on a Krill instance, using the API
+use krill::{
+    commons::api::{PointInTime, RoaConfigurationUpdates},
+    test::*,
+};
+use rpki::repository::x509::Time;
+
+#[tokio::test]
+async fn functional_ca_history() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Test showing a CA as it was at a point in its history.         #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca = ca_handle("CA");
+    let ca_res = resources("", "10.0.0.0/16", "");
+
+    set_up_ca_with_repo(&ca).await;
+    set_up_ca_under_parent_with_resources(&ca, &testbed, &ca_res).await;
+
+    let before = ca_details_at(&ca, PointInTime::Time(Time::now())).await;
+    assert!(before.roas().is_empty());
+
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(roa_configuration("10.0.0.0/16-16 => 64496"));
+    ca_route_authorizations_update(&ca, updates).await;
+
+    // The current state includes the ROA, but the CA as it was at the
+    // earlier version does not.
+    let after = ca_details_at(&ca, PointInTime::Time(Time::now())).await;
+    assert!(after.version() > before.version());
+    assert_eq!(after.roas().len(), 1);
+
+    let at_before =
+        ca_details_at(&ca, PointInTime::Version(before.version())).await;
+    assert_eq!(at_before, before);
+
+    // The testbed CA has the CA as a child, with its entitled resources.
+    let testbed_details =
+        ca_details_at(&testbed, PointInTime::Time(Time::now())).await;
+    assert!(testbed_details.children().contains_key(&ca.convert()));
+
+    cleanup();
+}
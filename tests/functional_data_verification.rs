@@ -0,0 +1,49 @@
+//! Verify the stored data of a Krill instance after using the API
+use krill::{
+    commons::api::RoaConfigurationUpdates, test::*,
+    upgrades::data_verification::verify,
+};
+
+#[tokio::test]
+async fn functional_data_verification() {
+    let (data_dir, cleanup) = tmp_dir();
+    let storage_uri = mem_storage();
+    let config =
+        test_config(&storage_uri, Some(&data_dir), true, false, false, false);
+    start_krill(config.clone()).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Test verifying the stored data for CAs and the repository.     #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca = ca_handle("CA");
+    let ca_res = resources("", "10.0.0.0/16", "");
+
+    set_up_ca_with_repo(&ca).await;
+    set_up_ca_under_parent_with_resources(&ca, &testbed, &ca_res).await;
+
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(roa_configuration("10.0.0.0/16-16 => 64496"));
+    ca_route_authorizations_update(&ca, updates).await;
+
+    let report = verify(&config).unwrap();
+    assert!(report.is_ok(), "{}", report);
+    assert_eq!(Some(&vec![]), report.issues("CA", &ca.convert()));
+    assert_eq!(Some(&vec![]), report.issues("CA", &testbed.convert()));
+
+    cleanup();
+}
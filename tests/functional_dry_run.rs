@@ -0,0 +1,145 @@
+//! Test that changes can be tried as a dry run without being applied.
+use std::str::FromStr;
+
+use rpki::repository::resources::ResourceSet;
+
+use krill::{
+    commons::api::{
+        AspaDefinition, AspaDefinitionList, AspaDefinitionUpdates,
+        CommandDryRun, RoaConfigurationUpdates,
+    },
+    test::*,
+};
+
+fn published_with_extension(dry_run: &CommandDryRun, ext: &str) -> bool {
+    dry_run
+        .published()
+        .iter()
+        .any(|uri| uri.as_str().ends_with(ext))
+}
+
+fn withdrawn_with_extension(dry_run: &CommandDryRun, ext: &str) -> bool {
+    dry_run
+        .withdrawn()
+        .iter()
+        .any(|uri| uri.as_str().ends_with(ext))
+}
+
+#[tokio::test]
+async fn functional_dry_run() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    let testbed = ca_handle("testbed");
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    let route = roa_configuration("10.0.0.0/24 => 64496");
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Dry run a ROA update, it should publish a ROA but not apply it #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(route.clone());
+    let routes_path = format!("api/v1/cas/{}/routes", testbed);
+
+    let dry_run = dry_run_post(&routes_path, &updates).await;
+    assert!(!dry_run.events().is_empty());
+    assert!(published_with_extension(&dry_run, ".roa"));
+    assert!(dry_run.withdrawn().is_empty());
+    expect_configured_roas(&testbed, &[]).await;
+
+    // Nothing was stored, so the CA is still at the same version.
+    assert_eq!(
+        dry_run.version(),
+        dry_run_post(&routes_path, &updates).await.version()
+    );
+
+    ca_route_authorizations_update(&testbed, updates).await;
+    expect_configured_roas(&testbed, &[route.clone()]).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Dry run an ASPA update                                         #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let aspa = AspaDefinition::from_str("AS65000 => AS65001").unwrap();
+    let aspa_updates = AspaDefinitionUpdates::new(vec![aspa], vec![]);
+
+    let dry_run =
+        dry_run_post(&format!("api/v1/cas/{}/aspas", testbed), &aspa_updates)
+            .await;
+    assert!(!dry_run.events().is_empty());
+    assert!(published_with_extension(&dry_run, ".asa"));
+    expect_aspa_definitions(&testbed, AspaDefinitionList::new(vec![])).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Dry run removing the parent, it should withdraw the ROA        #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let dry_run =
+        dry_run_delete(&format!("api/v1/cas/{}/parents/ta", testbed)).await;
+    assert!(!dry_run.events().is_empty());
+    assert!(!dry_run.revocation_requests().is_empty());
+    assert!(withdrawn_with_extension(&dry_run, ".roa"));
+    assert_eq!(1, ca_details(&testbed).await.parents().len());
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Dry run deleting the CA, it should withdraw all objects        #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let dry_run = dry_run_delete(&format!("api/v1/cas/{}", testbed)).await;
+    assert!(!dry_run.revocation_requests().is_empty());
+    assert!(withdrawn_with_extension(&dry_run, ".roa"));
+    assert!(withdrawn_with_extension(&dry_run, ".mft"));
+    expect_configured_roas(&testbed, &[route]).await;
+
+    cleanup();
+}
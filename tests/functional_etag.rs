@@ -0,0 +1,94 @@
+//! Test that updates can be made conditional on the version of a CA.
+use hyper::StatusCode;
+use rpki::repository::resources::ResourceSet;
+
+use krill::{commons::api::RoaConfigurationUpdates, test::*};
+
+#[tokio::test]
+async fn functional_etag() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    let testbed = ca_handle("testbed");
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    let route_1 = roa_configuration("10.0.0.0/24 => 64496");
+    let route_2 = roa_configuration("10.1.0.0/24 => 64496");
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Update routes using the current ETag                           #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let etag = ca_routes_etag(&testbed).await;
+
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(route_1.clone());
+    assert_eq!(
+        StatusCode::OK,
+        ca_routes_update_if_match(&testbed, updates, &etag).await
+    );
+    expect_configured_roas(&testbed, &[route_1.clone()]).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Updates using the old ETag are rejected                        #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    assert_ne!(etag, ca_routes_etag(&testbed).await);
+
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(route_2.clone());
+    assert_eq!(
+        StatusCode::PRECONDITION_FAILED,
+        ca_routes_update_if_match(&testbed, updates.clone(), &etag).await
+    );
+    expect_configured_roas(&testbed, &[route_1.clone()]).await;
+
+    assert_eq!(
+        StatusCode::BAD_REQUEST,
+        ca_routes_update_if_match(&testbed, updates.clone(), "not-a-tag")
+            .await
+    );
+
+    let etag = ca_routes_etag(&testbed).await;
+    assert_eq!(
+        StatusCode::PRECONDITION_FAILED,
+        ca_routes_update_if_match(
+            &testbed,
+            updates.clone(),
+            &format!("W/{}", etag)
+        )
+        .await
+    );
+
+    assert_eq!(
+        StatusCode::OK,
+        ca_routes_update_if_match(&testbed, updates, &etag).await
+    );
+    expect_configured_roas(&testbed, &[route_1, route_2]).await;
+
+    cleanup();
+}
@@ -0,0 +1,61 @@
+//! Perform functional tests on a Krill instance, using the API
+use krill::{
+    commons::api::RoaConfigurationUpdates,
+    daemon::eventstream::StreamedAggregateType, test::*,
+};
+
+#[tokio::test]
+async fn functional_events() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Test the stream of committed events.                           #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca = ca_handle("CA");
+    let ca_res = resources("", "10.0.0.0/16", "");
+
+    set_up_ca_with_repo(&ca).await;
+    set_up_ca_under_parent_with_resources(&ca, &testbed, &ca_res).await;
+
+    // Subscribe before making changes, only the filtered events are
+    // streamed to each subscriber.
+    let mut ca_roas = subscribe_events("ca=CA&type=roas_updated").await;
+    let mut testbed_events = subscribe_events("ca=testbed").await;
+
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(roa_configuration("10.0.0.0/16-16 => 64496"));
+    ca_route_authorizations_update(&ca, updates).await;
+
+    let event = ca_roas.next_event().await;
+    assert_eq!(event.aggregate_type, StreamedAggregateType::Ca);
+    assert_eq!(event.handle.as_str(), "CA");
+    assert_eq!(event.event_type, "roas_updated");
+    assert!(event.version > 0);
+
+    // The subscriber for testbed should have skipped the events for CA.
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(roa_configuration("10.1.0.0/16-16 => 64497"));
+    ca_route_authorizations_update(&testbed, updates).await;
+
+    let event = testbed_events.next_event().await;
+    assert_eq!(event.aggregate_type, StreamedAggregateType::Ca);
+    assert_eq!(event.handle.as_str(), "testbed");
+
+    cleanup();
+}
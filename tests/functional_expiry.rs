@@ -0,0 +1,120 @@
+//! Perform functional tests on a Krill instance, using the API
+use rpki::ca::idexchange::CaHandle;
+
+use krill::{
+    commons::api::{ExpiringObjectType, RoaConfigurationUpdates},
+    test::*,
+};
+
+#[tokio::test]
+async fn functional_expiry() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Test the expiry report for signed objects.                     #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Uses the following lay-out:                                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#                  TA                                            #",
+    );
+    info(
+        "#                   |                                            #",
+    );
+    info(
+        "#                testbed                                         #",
+    );
+    info(
+        "#                   |                                            #",
+    );
+    info(
+        "#                  CA                                            #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ta = ca_handle("ta");
+    let ca = ca_handle("CA");
+    let ca_res = resources("", "10.0.0.0/16", "");
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("#                Set up CA under testbed, with a ROA             #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        set_up_ca_with_repo(&ca).await;
+        set_up_ca_under_parent_with_resources(&ca, &testbed, &ca_res).await;
+
+        let mut updates = RoaConfigurationUpdates::empty();
+        updates.add(roa_configuration("10.0.0.0/16-16 => 64496"));
+        ca_route_authorizations_update(&ca, updates).await;
+    }
+
+    // All objects are reported, ordered by expiry
+    {
+        let report = object_expiry_report(None).await;
+        let objects = report.objects();
+
+        let count = |ca: &CaHandle, object_type: ExpiringObjectType| {
+            objects
+                .iter()
+                .filter(|o| &o.ca == ca && o.object_type == object_type)
+                .count()
+        };
+
+        assert_eq!(1, count(&ca, ExpiringObjectType::ReceivedCert));
+        assert_eq!(1, count(&ca, ExpiringObjectType::Manifest));
+        assert_eq!(1, count(&ca, ExpiringObjectType::Crl));
+        assert_eq!(1, count(&ca, ExpiringObjectType::Roa));
+
+        assert_eq!(1, count(&ta, ExpiringObjectType::TaCert));
+        assert_eq!(1, count(&ta, ExpiringObjectType::Manifest));
+        assert_eq!(1, count(&ta, ExpiringObjectType::ChildCert));
+
+        assert!(objects
+            .iter()
+            .filter(|o| o.object_type == ExpiringObjectType::Manifest)
+            .all(|o| o.next_update.is_some()));
+
+        assert!(objects
+            .windows(2)
+            .all(|pair| pair[0].expires() <= pair[1].expires()));
+    }
+
+    // Only manifests and CRLs are due for an update within a few days
+    {
+        let report = object_expiry_report(Some("1h")).await;
+        assert!(report.is_empty());
+
+        let report = object_expiry_report(Some("3d")).await;
+        assert!(!report.is_empty());
+        assert!(report.objects().iter().all(|o| matches!(
+            o.object_type,
+            ExpiringObjectType::Manifest | ExpiringObjectType::Crl
+        )));
+    }
+
+    cleanup();
+}
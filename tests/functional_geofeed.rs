@@ -0,0 +1,104 @@
+//! Perform functional tests on a Krill instance, using the API
+use rpki::repository::resources::ResourceSet;
+
+use krill::test::*;
+
+#[tokio::test]
+async fn functional_geofeed() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Test geofeed authentication (RFC 9632) support.                #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Uses the following lay-out:                                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#                  TA                                            #",
+    );
+    info(
+        "#                   |                                            #",
+    );
+    info(
+        "#                testbed                                         #",
+    );
+    info(
+        "#                   |                                            #",
+    );
+    info(
+        "#                  CA                                            #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca = ca_handle("CA");
+    let ca_res = resources("", "10.0.0.0/16", "2001:db8::/32");
+
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("#                      Set up CA  under testbed                  #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        set_up_ca_with_repo(&ca).await;
+        set_up_ca_under_parent_with_resources(&ca, &testbed, &ca_res).await;
+    }
+
+    // Sign a geofeed for prefixes held by the CA
+    {
+        let geofeed = "# prefix,country,region,city,postal\n\
+                       10.0.1.0/24,NL,NL-NH,Amsterdam,\n\
+                       2001:db8:1::/48,NL,,,\n";
+
+        let signed = geofeed_sign(ca.clone(), geofeed).await;
+        let signed = signed.geofeed();
+
+        let expected_data = geofeed.replace('\n', "\r\n");
+        assert!(signed.starts_with(&expected_data));
+
+        let block: Vec<_> = signed[expected_data.len()..]
+            .split_terminator("\r\n")
+            .collect();
+        assert_eq!(
+            "# RPKI Signature: 10.0.1.0/24, 2001:db8:1::/48",
+            block[0]
+        );
+        assert_eq!(
+            "# End Signature: 10.0.1.0/24, 2001:db8:1::/48",
+            block[block.len() - 1]
+        );
+        assert!(block[1..block.len() - 1]
+            .iter()
+            .all(|line| line.starts_with("# ") && line.len() <= 66));
+    }
+
+    // Signing a geofeed with a prefix not held by the CA must fail
+    {
+        let geofeed = "10.0.1.0/24,NL,,,\n10.1.0.0/24,NL,,,\n";
+        geofeed_sign_expect_error(ca.clone(), geofeed).await;
+    }
+
+    cleanup();
+}
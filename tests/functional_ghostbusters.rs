@@ -0,0 +1,179 @@
+//! Perform functional tests on a Krill instance, using the API
+use std::str::FromStr;
+
+use rpki::{
+    ca::{idexchange::CaHandle, provisioning::ResourceClassName},
+    repository::resources::ResourceSet,
+};
+
+use krill::{
+    commons::api::{GhostbusterDefinition, GhostbusterName},
+    test::*,
+};
+
+#[tokio::test]
+async fn functional_ghostbusters() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Test Ghostbuster record support.                               #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Uses the following lay-out:                                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#                  TA                                            #",
+    );
+    info(
+        "#                   |                                            #",
+    );
+    info(
+        "#                testbed                                         #",
+    );
+    info(
+        "#                   |                                            #",
+    );
+    info(
+        "#                  CA                                            #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca = ca_handle("CA");
+    let ca_res = resources("AS65000", "10.0.0.0/16", "");
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Wait for the *testbed* CA to get its certificate, this means   #",
+    );
+    info(
+        "# that all CAs which are set up as part of krill_start under the #",
+    );
+    info(
+        "# testbed config have been set up.                               #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("#                      Set up CA  under testbed                  #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        set_up_ca_with_repo(&ca).await;
+        set_up_ca_under_parent_with_resources(&ca, &testbed, &ca_res).await;
+    }
+
+    // short hand to expect published Ghostbuster records under CA
+    async fn expect_ghostbuster_objects(
+        ca: &CaHandle,
+        definitions: &[GhostbusterDefinition],
+    ) {
+        let rcn_0 = ResourceClassName::from(0);
+
+        let mut expected_files = expected_mft_and_crl(ca, &rcn_0).await;
+
+        for definition in definitions {
+            expected_files.push(definition.object_name().to_string());
+        }
+
+        assert!(
+            will_publish_embedded(
+                "published Ghostbuster records do not match expectations",
+                ca,
+                &expected_files
+            )
+            .await
+        );
+    }
+
+    let noc = GhostbusterName::from_str("noc").unwrap();
+
+    let noc_def = GhostbusterDefinition::new(
+        noc.clone(),
+        "Example NOC".to_string(),
+        Some("Example Org".to_string()),
+        None,
+        Some("+31 20 123 4567".to_string()),
+        Some("noc@example.com".to_string()),
+    );
+
+    let noc_def_updated = GhostbusterDefinition::new(
+        noc.clone(),
+        "Example NOC".to_string(),
+        Some("Example Org".to_string()),
+        None,
+        None,
+        Some("rpki@example.com".to_string()),
+    );
+
+    // Add Ghostbuster definition
+    {
+        ca_ghostbusters_add(&ca, noc_def).await;
+
+        // List definitions
+        let definitions = ca_ghostbusters_list(&ca).await.unpack();
+        assert_eq!(1, definitions.len());
+
+        // Expect it's published
+        expect_ghostbuster_objects(&ca, &definitions).await;
+    }
+
+    // Replace Ghostbuster definition
+    {
+        ca_ghostbusters_add(&ca, noc_def_updated.clone()).await;
+
+        // Expect the definition was replaced
+        let definitions = ca_ghostbusters_list(&ca).await.unpack();
+        assert_eq!(vec![noc_def_updated], definitions);
+
+        // Expect it's still published under the same name
+        expect_ghostbuster_objects(&ca, &definitions).await;
+    }
+
+    // Remove Ghostbuster definition
+    {
+        ca_ghostbusters_remove(&ca, noc).await;
+
+        // Expect the definition is removed
+        let definitions = ca_ghostbusters_list(&ca).await.unpack();
+        assert_eq!(0, definitions.len());
+
+        // Expect that the Ghostbuster record is removed.
+        expect_ghostbuster_objects(&ca, &[]).await;
+    }
+
+    cleanup();
+}
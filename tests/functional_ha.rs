@@ -0,0 +1,119 @@
+//! Test an active/passive set up of two Krill nodes sharing storage.
+use hyper::StatusCode;
+
+use krill::{
+    cli::{
+        options::{CaCommand, Command},
+        Error,
+    },
+    commons::{
+        api::{CertAuthInit, NodeRole},
+        util::httpclient,
+    },
+    test::*,
+};
+
+#[tokio::test]
+async fn functional_ha() {
+    let cleanup = start_krill_ha_pair(3).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# The first node is the leader, the second node is a standby     #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let leader = server_info().await;
+    assert_eq!(Some(&"node-a".to_string()), leader.node());
+    assert_eq!(NodeRole::Leader, leader.role());
+
+    let standby = server_info_krill2().await;
+    assert_eq!(Some(&"node-b".to_string()), standby.node());
+    assert_eq!(NodeRole::Standby, standby.role());
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Changes are made on the leader, and can be read on the standby #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let ca = ca_handle("CA");
+    init_ca(&ca).await;
+    let details = ca_details(&ca).await;
+    assert_eq!(details, ca_details_krill2(&ca).await);
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# The standby refuses changes                                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let other = ca_handle("other");
+    let err = krill2_admin_expect_error(Command::CertAuth(CaCommand::Init(
+        CertAuthInit::new(other),
+    )))
+    .await;
+    match err {
+        Error::HttpClientError(httpclient::Error::ErrorResponseWithJson(
+            _,
+            status,
+            res,
+        )) => {
+            assert_eq!(StatusCode::SERVICE_UNAVAILABLE, status);
+            assert_eq!("ha-standby", res.label());
+        }
+        _ => panic!("Expected standby error, got: {}", err),
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# The leader keeps its lease                                     #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    sleep_seconds(5).await;
+    assert_eq!(NodeRole::Leader, server_info().await.role());
+    assert_eq!(NodeRole::Standby, server_info_krill2().await.role());
+
+    cleanup();
+}
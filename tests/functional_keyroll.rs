@@ -13,8 +13,9 @@ use rpki::{
 
 use krill::{
     commons::api::{
-        AspaDefinition, BgpSecDefinition, ObjectName, ReceivedCert,
-        RoaConfiguration, RoaConfigurationUpdates, RoaPayload,
+        AspaDefinition, BgpSecDefinition, KeyRollPolicy, ObjectName,
+        ReceivedCert, RoaConfiguration, RoaConfigurationUpdates, RoaPayload,
+        Timestamp,
     },
     test::*,
 };
@@ -338,9 +339,9 @@ async fn functional_keyroll() {
         let mut expected_files =
             expected_mft_and_crl(&testbed, &dflt_rc_name).await;
         expected_files.push(expected_issued_cer(&ca, &dflt_rc_name).await);
-        expected_files.push(roa_file);
-        expected_files.push(aspa_file);
-        expected_files.push(bgpsec_file);
+        expected_files.push(roa_file.clone());
+        expected_files.push(aspa_file.clone());
+        expected_files.push(bgpsec_file.clone());
 
         let msg = "Testbed should now publish MFT and CRL for the activated key only, and the certificate for CA";
         assert!(will_publish_embedded(msg, &testbed, &expected_files).await);
@@ -355,6 +356,64 @@ async fn functional_keyroll() {
         .await;
     }
 
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("#     testbed replaces its (compromised) key in an emergency     #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+
+        let compromised_key =
+            *ca_key_for_rcn(&testbed, &dflt_rc_name).await.key_id();
+
+        ca_roll_emergency(&testbed, Some(dflt_rc_name.clone())).await;
+
+        // The new key is activated as soon as it is certified, without
+        // staging, and the compromised key is revoked and retired.
+        assert!(state_becomes_active(&testbed).await);
+        let current_key =
+            *ca_key_for_rcn(&testbed, &dflt_rc_name).await.key_id();
+        assert_ne!(compromised_key, current_key);
+
+        let mut expected_files =
+            expected_mft_and_crl(&testbed, &dflt_rc_name).await;
+        expected_files.push(expected_issued_cer(&ca, &dflt_rc_name).await);
+        expected_files.push(roa_file);
+        expected_files.push(aspa_file);
+        expected_files.push(bgpsec_file);
+
+        let msg = "Testbed should publish all objects under the replacement key only";
+        assert!(will_publish_embedded(msg, &testbed, &expected_files).await);
+        assert_manifest_files_current_key(msg, &testbed, &expected_files)
+            .await;
+    }
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("#           CA gets a policy for automatic key rolls             #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+
+        let policy = KeyRollPolicy::new(365, 24);
+        ca_roll_policy_set(&ca, policy).await;
+
+        // The next roll is planned a year after the current key was
+        // certified. Nothing should happen now.
+        let details = ca_details(&ca).await;
+        assert_eq!(Some(&policy), details.keyroll_policy());
+        let next = details.next_keyroll().expect("planned key roll");
+        assert!(next > Timestamp::now_plus_hours(364 * 24));
+        assert!(state_becomes_active(&ca).await);
+
+        ca_roll_policy_remove(&ca).await;
+        let details = ca_details(&ca).await;
+        assert!(details.keyroll_policy().is_none());
+        assert!(details.next_keyroll().is_none());
+    }
+
     cleanup();
 }
 
@@ -0,0 +1,57 @@
+//! Test that Krill works with a PostgreSQL database as storage.
+#![cfg(feature = "postgres-tests")]
+
+use kvx::{Scope, Segment};
+use rpki::repository::resources::ResourceSet;
+
+use krill::{
+    commons::{
+        api::RoaConfigurationUpdates,
+        eventsourcing::{KeyValueStore, SegmentExt},
+    },
+    constants::CASERVER_NS,
+    test::*,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn functional_postgres() {
+    let cleanup = start_krill_with_default_test_config_postgres(
+        true, false, false, false,
+    )
+    .await;
+
+    let testbed = ca_handle("testbed");
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Update ROAs, which are kept in the database                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let route = roa_configuration("10.0.0.0/24 => 64496");
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(route.clone());
+    ca_route_authorizations_update(&testbed, updates).await;
+    expect_configured_roas(&testbed, &[route]).await;
+
+    let cas =
+        KeyValueStore::create(&postgres_storage(), CASERVER_NS).unwrap();
+    assert!(cas
+        .has_scope(&Scope::from_segment(Segment::parse_lossy(
+            testbed.as_str()
+        )))
+        .unwrap());
+
+    cleanup();
+}
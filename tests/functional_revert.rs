@@ -0,0 +1,149 @@
+//! Test that ROA and ASPA changes can be reverted from the history of a CA.
+use std::str::FromStr;
+
+use rpki::repository::resources::ResourceSet;
+
+use krill::{
+    commons::api::{
+        AspaDefinition, AspaDefinitionList, AspaProvidersUpdate, ProviderAsn,
+        RoaConfigurationUpdates,
+    },
+    test::*,
+};
+
+#[tokio::test]
+async fn functional_revert() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    let testbed = ca_handle("testbed");
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    let route_1 = roa_configuration("10.0.0.0/24 => 64496 # first");
+    let route_2 = roa_configuration("10.1.0.0/24 => 64496 # second");
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Remove a ROA by mistake, then revert that removal              #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(route_1.clone());
+    updates.add(route_2.clone());
+    ca_route_authorizations_update(&testbed, updates).await;
+    expect_configured_roas(&testbed, &[route_1.clone(), route_2.clone()])
+        .await;
+
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.remove(route_1.payload());
+    ca_route_authorizations_update(&testbed, updates).await;
+    expect_configured_roas(&testbed, &[route_2.clone()]).await;
+
+    let removal =
+        ca_last_command_version(&testbed, "cmd-ca-roas-updated").await;
+
+    // A dry run shows the ROA that will be published, but changes nothing.
+    let preview = ca_history_revert_dry_run(&testbed, removal).await;
+    assert!(preview
+        .published()
+        .iter()
+        .any(|uri| uri.as_str().ends_with(".roa")));
+    expect_configured_roas(&testbed, &[route_2.clone()]).await;
+
+    ca_history_revert(&testbed, removal).await;
+    expect_configured_roas(&testbed, &[route_1.clone(), route_2.clone()])
+        .await;
+
+    // The revert is a new command which refers to the reverted one.
+    let revert = ca_last_command_version(&testbed, "cmd-ca-revert").await;
+    assert!(revert > removal);
+    let details = ca_command_details(&testbed, revert).await;
+    assert_eq!(
+        details.details().to_string(),
+        format!("Revert command {}", removal)
+    );
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Revert an ASPA update and an ASPA removal                      #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    let aspa = AspaDefinition::from_str("AS65000 => AS65001").unwrap();
+    ca_aspas_add(&testbed, aspa.clone()).await;
+
+    let update = AspaProvidersUpdate::new(
+        vec![ProviderAsn::from_str("AS65002").unwrap()],
+        vec![],
+    );
+    ca_aspas_update(&testbed, aspa.customer(), update).await;
+    expect_aspa_definitions(
+        &testbed,
+        AspaDefinitionList::new(vec![AspaDefinition::from_str(
+            "AS65000 => AS65001, AS65002",
+        )
+        .unwrap()]),
+    )
+    .await;
+
+    let update =
+        ca_last_command_version(&testbed, "cmd-ca-aspas-update-existing")
+            .await;
+    ca_history_revert(&testbed, update).await;
+    expect_aspa_definitions(
+        &testbed,
+        AspaDefinitionList::new(vec![aspa.clone()]),
+    )
+    .await;
+
+    ca_aspas_remove(&testbed, aspa.customer()).await;
+    expect_aspa_definitions(&testbed, AspaDefinitionList::new(vec![])).await;
+
+    let removal =
+        ca_last_command_version(&testbed, "cmd-ca-aspas-update").await;
+    ca_history_revert(&testbed, removal).await;
+    expect_aspa_definitions(&testbed, AspaDefinitionList::new(vec![aspa]))
+        .await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Commands without definition changes cannot be reverted         #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    ca_history_revert_expect_error(&testbed, 0).await;
+    ca_history_revert_expect_error(&testbed, 100_000).await;
+
+    cleanup();
+}
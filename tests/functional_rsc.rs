@@ -0,0 +1,126 @@
+//! Perform functional tests on a Krill instance, using the API
+use bcder::Oid;
+use bytes::Bytes;
+use rpki::{
+    repository::{resources::ResourceSet, sigobj::SignedObject},
+    rrdp::Hash,
+};
+
+use krill::{
+    cli::options::{CaCommand, Command},
+    daemon::ca::RscChecklistItem,
+    test::*,
+};
+
+#[tokio::test]
+async fn functional_rsc() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Test RPKI Signed Checklist (RSC) support.                      #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Uses the following lay-out:                                    #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "#                  TA                                            #",
+    );
+    info(
+        "#                   |                                            #",
+    );
+    info(
+        "#                testbed                                         #",
+    );
+    info(
+        "#                   |                                            #",
+    );
+    info(
+        "#                  CA                                            #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca = ca_handle("CA");
+    let ca_res = resources("AS65000", "10.0.0.0/16", "");
+
+    assert!(ca_contains_resources(&testbed, &ResourceSet::all()).await);
+
+    {
+        info("##################################################################");
+        info("#                                                                #");
+        info("#                      Set up CA  under testbed                  #");
+        info("#                                                                #");
+        info("##################################################################");
+        info("");
+        set_up_ca_with_repo(&ca).await;
+        set_up_ca_under_parent_with_resources(&ca, &testbed, &ca_res).await;
+    }
+
+    let tal = include_bytes!("../test-resources/test.tal");
+    let checklist = vec![
+        RscChecklistItem::for_file("test.tal", tal),
+        RscChecklistItem::new(None, Hash::from_data(b"unnamed")),
+    ];
+
+    // Sign an RSC for a subset of the resources held by the CA
+    {
+        let request = rsc_sign_request(
+            resources("AS65000", "10.0.0.0/24", ""),
+            checklist.clone(),
+        );
+        let rsc = rsc_sign(ca.clone(), request).await;
+
+        // id-ct-signedChecklist
+        let content_type = Oid(Bytes::from_static(&[
+            42, 134, 72, 134, 247, 13, 1, 9, 16, 1, 48,
+        ]));
+
+        let object =
+            SignedObject::decode(rsc.as_ref().clone(), true).unwrap();
+        assert_eq!(&content_type, object.content_type());
+        assert!(object.cert().signed_object().is_none());
+
+        // The checklist hashes are included in the content
+        let content = object.content().to_bytes();
+        let content = content.as_ref();
+        for item in &checklist {
+            let hash = item.hash();
+            assert!(content
+                .windows(hash.as_ref().len())
+                .any(|w| w == hash.as_ref()));
+        }
+    }
+
+    // Signing an RSC for resources not held by the CA must fail
+    {
+        let request =
+            rsc_sign_request(resources("", "10.1.0.0/24", ""), checklist);
+        krill_admin_expect_error(Command::CertAuth(CaCommand::RscSign(
+            ca.clone(),
+            request,
+            None,
+        )))
+        .await;
+    }
+
+    cleanup();
+}
@@ -0,0 +1,183 @@
+//! Perform functional tests on a Krill instance, using the API
+use std::time::Duration;
+
+use hyper::StatusCode;
+use tokio::time::sleep;
+
+use krill::{
+    cli::{
+        options::{Command, TaskCommand},
+        Error,
+    },
+    commons::{api::Timestamp, util::httpclient},
+    daemon::mq::{TaskList, TaskOutcome, TaskRunRequest, TaskState},
+    test::*,
+};
+
+fn last_run_since(
+    list: &TaskList,
+    key: &str,
+    since: Timestamp,
+) -> Option<TaskOutcome> {
+    list.last_runs()
+        .iter()
+        .find(|run| run.key() == key && run.time() >= since)
+        .map(|run| run.outcome().clone())
+}
+
+async fn expect_task_error(
+    request: TaskRunRequest,
+    status: StatusCode,
+    label: &str,
+) {
+    let err =
+        krill_admin_expect_error(Command::Tasks(TaskCommand::Run(request)))
+            .await;
+    match err {
+        Error::HttpClientError(httpclient::Error::ErrorResponseWithJson(
+            _,
+            res_status,
+            res,
+        )) => {
+            assert_eq!(status, res_status);
+            assert_eq!(label, res.label());
+        }
+        _ => panic!("Expected error '{}', got: {}", label, err),
+    }
+}
+
+#[tokio::test]
+async fn functional_tasks() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Test that the task queue can be listed, and that tasks can be  #",
+    );
+    info(
+        "# triggered manually.                                            #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca = ca_handle("CA");
+    let ca_res = resources("", "10.0.0.0/16", "");
+
+    set_up_ca_with_repo(&ca).await;
+    set_up_ca_under_parent_with_resources(&ca, &testbed, &ca_res).await;
+
+    // After synchronising with its parent the CA will do so again later.
+    let sync_key = format!("sync_{}_with_parent_{}", ca, testbed);
+    let list = tasks_list().await;
+    let pending = list
+        .tasks()
+        .iter()
+        .find(|task| task.key() == sync_key)
+        .expect("parent sync should be queued");
+    assert_eq!(pending.state(), TaskState::Pending);
+    assert!(list.last_runs().iter().any(|run| run.key() == sync_key));
+    assert!(list.last_runs_since() <= Timestamp::now());
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Trigger the parent sync now, it should run and be scheduled    #",
+    );
+    info(
+        "# again as a follow-up.                                          #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    sleep(Duration::from_secs(1)).await;
+    let triggered = Timestamp::now();
+    task_run(TaskRunRequest::new(
+        "sync_parent".to_string(),
+        Some(ca.clone()),
+        Some(testbed.convert()),
+    ))
+    .await;
+
+    let mut outcome = None;
+    for _ in 0..30 {
+        outcome = last_run_since(&tasks_list().await, &sync_key, triggered);
+        if outcome.is_some() {
+            break;
+        }
+        sleep(Duration::from_secs(1)).await;
+    }
+    match outcome {
+        Some(TaskOutcome::FollowUp { task, .. }) => {
+            assert_eq!(task, sync_key)
+        }
+        other => panic!("Expected parent sync follow-up, got: {:?}", other),
+    }
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Tasks for unknown parents, or tasks that Krill only creates    #",
+    );
+    info(
+        "# itself, are refused.                                           #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+    expect_task_error(
+        TaskRunRequest::new(
+            "sync_parent".to_string(),
+            Some(ca.clone()),
+            Some(ca_handle("unknown").convert()),
+        ),
+        StatusCode::NOT_FOUND,
+        "ca-parent-unknown",
+    )
+    .await;
+    expect_task_error(
+        TaskRunRequest::new(
+            "sync_parent".to_string(),
+            Some(ca.clone()),
+            None,
+        ),
+        StatusCode::BAD_REQUEST,
+        "task-run-invalid",
+    )
+    .await;
+    expect_task_error(
+        TaskRunRequest::new("queue_start_tasks".to_string(), None, None),
+        StatusCode::BAD_REQUEST,
+        "task-not-runnable",
+    )
+    .await;
+
+    cleanup();
+}
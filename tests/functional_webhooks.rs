@@ -0,0 +1,115 @@
+//! Perform functional tests on a Krill instance, using the API
+use std::{str::FromStr, time::Duration};
+
+use tokio::{net::TcpListener, time::timeout};
+use url::Url;
+
+use krill::{
+    commons::api::{
+        RoaConfigurationUpdates, WebhookDefinition, WebhookEventType,
+        WebhookName, WebhookNotification,
+    },
+    daemon::webhooks::{
+        WebhookManager, WEBHOOK_EVENT_HEADER, WEBHOOK_SIGNATURE_HEADER,
+    },
+    test::*,
+};
+
+/// Starts a server that accepts connections, but never responds.
+async fn start_unresponsive_server() -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!(
+        "http://{}/hook",
+        listener.local_addr().unwrap()
+    ))
+    .unwrap();
+    tokio::spawn(async move {
+        let mut connections = vec![];
+        while let Ok((stream, _)) = listener.accept().await {
+            connections.push(stream);
+        }
+    });
+    url
+}
+
+#[tokio::test]
+async fn functional_webhooks() {
+    let cleanup =
+        start_krill_with_default_test_config(true, false, false, false).await;
+
+    info(
+        "##################################################################",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "# Test that webhooks are notified of CA events.                  #",
+    );
+    info(
+        "#                                                                #",
+    );
+    info(
+        "##################################################################",
+    );
+    info("");
+
+    let testbed = ca_handle("testbed");
+    let ca = ca_handle("CA");
+    let ca_res = resources("", "10.0.0.0/16", "");
+
+    set_up_ca_with_repo(&ca).await;
+    set_up_ca_under_parent_with_resources(&ca, &testbed, &ca_res).await;
+
+    // A webhook that does not respond must not hold up the delivery to
+    // other webhooks.
+    webhook_add(WebhookDefinition::new(
+        WebhookName::from_str("unresponsive").unwrap(),
+        start_unresponsive_server().await,
+        "other-secret".to_string(),
+        vec![WebhookEventType::RoasUpdated],
+    ))
+    .await;
+
+    let mut receiver = WebhookReceiver::start().await;
+    let name = WebhookName::from_str("ops").unwrap();
+    let secret = "shared-secret";
+
+    webhook_add(WebhookDefinition::new(
+        name.clone(),
+        receiver.url().clone(),
+        secret.to_string(),
+        vec![WebhookEventType::RoasUpdated],
+    ))
+    .await;
+    assert_eq!(webhooks_list().await.unpack().len(), 2);
+
+    let mut updates = RoaConfigurationUpdates::empty();
+    updates.add(roa_configuration("10.0.0.0/16-16 => 64496"));
+    ca_route_authorizations_update(&ca, updates).await;
+
+    let delivery = timeout(Duration::from_secs(10), receiver.next_delivery())
+        .await
+        .expect("delivery was held up by the unresponsive webhook");
+    assert_eq!(delivery.headers[WEBHOOK_EVENT_HEADER], "roas_updated");
+
+    // The receiver must be able to verify the signature using the secret
+    let expected = format!(
+        "sha256={}",
+        WebhookManager::signature(secret, &delivery.body).unwrap()
+    );
+    assert_eq!(
+        delivery.headers[WEBHOOK_SIGNATURE_HEADER],
+        expected.as_str()
+    );
+
+    let notification: WebhookNotification =
+        serde_json::from_slice(&delivery.body).unwrap();
+    assert_eq!(notification.event, WebhookEventType::RoasUpdated);
+    assert_eq!(notification.ca, Some(ca.clone()));
+
+    webhook_remove(&name).await;
+    assert_eq!(webhooks_list().await.unpack().len(), 1);
+
+    cleanup();
+}